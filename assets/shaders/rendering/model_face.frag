@@ -1,3 +1,11 @@
+#include "model_face_vars.frag"
+#include "model_face_lights.frag"
+#include "model_face_mapping.frag"
+#include "model_face_effects.frag"
+#include "model_face_pbr.frag"
+#include "model_face_shadow_mapping.frag"
+#include "model_face_misc.frag"
+
 // =================================================
 //
 // main()
@@ -96,9 +96,15 @@ struct Effect_Bloom {
 };
 
 // lights & mats
+#ifndef NR_DIRECTIONAL_LIGHTS
 #define NR_DIRECTIONAL_LIGHTS 8
+#endif
+#ifndef NR_POINT_LIGHTS
 #define NR_POINT_LIGHTS 4
+#endif
+#ifndef NR_SPOT_LIGHTS
 #define NR_SPOT_LIGHTS 4
+#endif
 uniform LightSource_Directional directionalLights[NR_DIRECTIONAL_LIGHTS];
 uniform LightSource_Point pointLights[NR_POINT_LIGHTS];
 uniform LightSource_Spot spotLights[NR_SPOT_LIGHTS];
@@ -0,0 +1,130 @@
+#version 410 core
+
+struct SolidLightSkin {
+  vec3 materialColor;
+  vec3 ambient;
+  vec3 diffuse;
+  vec3 specular;
+  float strengthAmbient;
+  float strengthDiffuse;
+  float strengthSpecular;
+};
+
+uniform bool u_hasTexture;
+uniform bool u_hasVertexColors;
+uniform sampler2D u_samplerDiffuse;
+uniform vec3 u_ambientColor;
+uniform vec3 u_diffuseColor;
+uniform vec3 u_specularColor;
+uniform float u_specularExp;
+uniform float u_alpha;
+uniform vec3 u_cameraPosition;
+uniform vec3 u_lightDirection;
+uniform vec3 u_lightColor;
+uniform float u_gamma;
+// 0 solid, 1 material, 2 texture, 3 wireframe, 4 rendered, 5 normals, 6 depth
+uniform int u_viewSkin;
+uniform bool u_celShading;
+uniform SolidLightSkin u_solidLight;
+// the distances from the camera to the nearest and the farthest point of the scene
+uniform vec2 u_depthRange;
+
+in vec3 v_vertexPosition;
+in vec3 v_vertexNormal;
+in vec2 v_textureCoord;
+in vec4 v_vertexColor;
+
+out vec4 fragColor;
+
+float stepmix(float edge0, float edge1, float E, float x) {
+  float T = clamp(0.5 * (x - edge0 + E) / E, 0.0, 1.0);
+  return mix(edge0, edge1, T);
+}
+
+// the diffuse factor in four bands, smoothed over a pixel at their edges
+float celDiffuse(float df) {
+  const float A = 0.1;
+  const float B = 0.3;
+  const float C = 0.6;
+  const float D = 1.0;
+  float E = fwidth(df);
+  if (df > A - E && df < A + E) return stepmix(A, B, E, df);
+  else if (df > B - E && df < B + E) return stepmix(B, C, E, df);
+  else if (df > C - E && df < C + E) return stepmix(C, D, E, df);
+  else if (df < A) return 0.0;
+  else if (df < B) return B;
+  else if (df < C) return C;
+  return D;
+}
+
+float celSpecular(float sf) {
+  float E = fwidth(sf);
+  if (sf > 0.5 - E && sf < 0.5 + E)
+    return smoothstep(0.5 - E, 0.5 + E, sf);
+  return step(0.5, sf);
+}
+
+// the colors lit by the solid light, which shines from the camera
+vec3 solidLight(vec3 normal, vec3 directionView, vec3 ambient, vec3 diffuse, vec3 specular, float specularExp) {
+  float lambertFactor = max(dot(normal, directionView), 0.0);
+  float specularFactor = pow(lambertFactor, max(specularExp, 1.0));
+  if (u_celShading) {
+    lambertFactor = celDiffuse(lambertFactor);
+    specularFactor = celSpecular(specularFactor);
+  }
+  return ambient * u_solidLight.ambient * u_solidLight.strengthAmbient
+    + diffuse * u_solidLight.diffuse * u_solidLight.strengthDiffuse * lambertFactor
+    + specular * u_solidLight.specular * u_solidLight.strengthSpecular * specularFactor;
+}
+
+void main(void) {
+  vec3 normal = normalize(v_vertexNormal);
+  if (!gl_FrontFacing)
+    normal = -normal;
+  vec3 directionView = normalize(u_cameraPosition - v_vertexPosition);
+
+  if (u_viewSkin == 0) {
+    vec3 color = u_solidLight.materialColor;
+    fragColor = vec4(solidLight(normal, directionView, color, color, color, 32.0), u_alpha);
+    return;
+  }
+  if (u_viewSkin == 1) {
+    fragColor = vec4(solidLight(normal, directionView, u_ambientColor, u_diffuseColor, u_specularColor, u_specularExp), u_alpha);
+    return;
+  }
+  if (u_viewSkin == 2) {
+    vec3 color = u_hasTexture ? texture(u_samplerDiffuse, v_textureCoord).rgb : u_solidLight.materialColor;
+    fragColor = vec4(solidLight(normal, directionView, color, color, color, 32.0), u_alpha);
+    return;
+  }
+  if (u_viewSkin == 3) {
+    fragColor = vec4(1.0);
+    return;
+  }
+  if (u_viewSkin == 5) {
+    fragColor = vec4(normal * 0.5 + 0.5, 1.0);
+    return;
+  }
+  if (u_viewSkin == 6) {
+    float depth = (distance(u_cameraPosition, v_vertexPosition) - u_depthRange.x) / max(u_depthRange.y - u_depthRange.x, 0.0001);
+    fragColor = vec4(vec3(1.0 - clamp(depth, 0.0, 1.0)), 1.0);
+    return;
+  }
+
+  vec4 baseColor = vec4(u_diffuseColor, u_alpha);
+  if (u_hasTexture)
+    baseColor *= texture(u_samplerDiffuse, v_textureCoord);
+  if (u_hasVertexColors)
+    baseColor *= v_vertexColor;
+
+  vec3 directionLight = normalize(-u_lightDirection);
+  float lambertFactor = max(dot(normal, directionLight), 0.0);
+  float specularFactor = pow(max(dot(normal, normalize(directionLight + directionView)), 0.0), max(u_specularExp, 1.0));
+  if (u_celShading) {
+    lambertFactor = celDiffuse(lambertFactor);
+    specularFactor = celSpecular(specularFactor);
+  }
+
+  vec3 color = baseColor.rgb * (0.25 + 0.75 * lambertFactor * u_lightColor) + 0.2 * u_lightColor * specularFactor;
+  fragColor = vec4(pow(color, vec3(1.0 / u_gamma)), baseColor.a);
+}
\ No newline at end of file
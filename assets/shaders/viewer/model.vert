@@ -0,0 +1,23 @@
+#version 410 core
+
+layout (location = 0) in vec3 a_vertexPosition;
+layout (location = 1) in vec3 a_vertexNormal;
+layout (location = 2) in vec2 a_textureCoord;
+layout (location = 3) in vec4 a_vertexColor;
+
+uniform mat4 u_MVPMatrix;
+uniform mat4 u_WorldMatrix;
+uniform mat3 u_NormalMatrix;
+
+out vec3 v_vertexPosition;
+out vec3 v_vertexNormal;
+out vec2 v_textureCoord;
+out vec4 v_vertexColor;
+
+void main(void) {
+  v_vertexPosition = (u_WorldMatrix * vec4(a_vertexPosition, 1.0)).xyz;
+  v_vertexNormal = normalize(u_NormalMatrix * a_vertexNormal);
+  v_textureCoord = a_textureCoord;
+  v_vertexColor = a_vertexColor;
+  gl_Position = u_MVPMatrix * vec4(a_vertexPosition, 1.0);
+}
\ No newline at end of file
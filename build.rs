@@ -21,6 +21,9 @@ fn main()-> io::Result<()> {
   fs::create_dir(&assets_ref).unwrap();
   copy_dir(ASSETS_DIR, &assets_ref);
 
+  #[cfg(feature = "embedded_assets")]
+  bundle_assets(&env::var("OUT_DIR").unwrap())?;
+
   setup_internals();
   Ok(())
 }
@@ -47,6 +50,50 @@ fn copy_dir<P, Q>(from: P, to: Q) where P: AsRef<Path>, Q: AsRef<Path>, {
   }
 }
 
+/// Packs the assets folder into a single deflate-compressed bundle and generates its index -
+/// (path relative to the assets folder, offset and length in the bundle, uncompressed size).
+#[cfg(feature = "embedded_assets")]
+fn bundle_assets(out_dir: &str) -> io::Result<()> {
+  use std::io::Write;
+  println!("cargo:rerun-if-changed={}", ASSETS_DIR);
+
+  let mut files = Vec::new();
+  list_files(Path::new(ASSETS_DIR), &mut files);
+  files.sort();
+
+  let mut bundle: Vec<u8> = Vec::new();
+  let mut index = String::from("pub static ASSETS_INDEX: &[(&str, usize, usize, usize)] = &[\n");
+  for file in &files {
+    let data = fs::read(file)?;
+    let mut encoder = flate2::write::DeflateEncoder::new(Vec::new(), flate2::Compression::best());
+    encoder.write_all(&data)?;
+    let compressed = encoder.finish()?;
+    let name = file.strip_prefix(ASSETS_DIR).unwrap().to_string_lossy().replace('\\', "/");
+    index.push_str(&format!("  ({:?}, {}, {}, {}),\n", name, bundle.len(), compressed.len(), data.len()));
+    bundle.extend_from_slice(&compressed);
+  }
+  index.push_str("];\n");
+  index.push_str("pub static ASSETS_BUNDLE: &[u8] = include_bytes!(concat!(env!(\"OUT_DIR\"), \"/assets.bundle\"));\n");
+
+  fs::write(format!("{}/assets.bundle", out_dir), &bundle)?;
+  fs::write(format!("{}/assets_index.rs", out_dir), index)?;
+  println!("[Kuplung-BUILD] Embedded {} assets ({} bytes compressed).", files.len(), bundle.len());
+  Ok(())
+}
+
+#[cfg(feature = "embedded_assets")]
+fn list_files(folder: &Path, files: &mut Vec<PathBuf>) {
+  for path in fs::read_dir(folder).unwrap() {
+    let path = path.unwrap().path();
+    if path.is_file() {
+      files.push(path);
+    }
+    else if path.is_dir() {
+      list_files(&path, files);
+    }
+  }
+}
+
 /// set platform-specific resources
 #[cfg(target_os = "windows")]
 fn setup_internals() {
@@ -14,14 +14,17 @@ extern crate strum;
 use crate::fractals::julia::Julia;
 use crate::fractals::mandelbrot::Mandelbrot;
 use crate::do_log;
+use crate::rendering::gl_error::GLError;
+use crate::rendering::shader_registry;
 use crate::settings::{configuration, kuplung_logger};
+use crate::ui::components::error_panel;
 
 pub struct FractalsManager {
   pub show_fractals: bool,
   show_mandelbrot: bool,
   show_julia: bool,
-  fractal_mandelbrot: Arc<Mutex<Mandelbrot>>,
-  fractal_julia: Arc<Mutex<Julia>>,
+  fractal_mandelbrot: Result<Arc<Mutex<Mandelbrot>>, GLError>,
+  fractal_julia: Result<Arc<Mutex<Julia>>, GLError>,
   zoom_center: nalgebra_glm::Vec2,
   zoom_center_target: nalgebra_glm::Vec2,
   zoom_size: f32,
@@ -31,16 +34,16 @@ pub struct FractalsManager {
 }
 
 impl FractalsManager {
-  pub fn new<'a>(cc: &'a eframe::CreationContext<'a>) -> Option<Self> {
+  pub fn new<'a>(cc: &'a eframe::CreationContext<'a>) -> Result<Self, GLError> {
     do_log!("[Kuplung] New FractalsManager...");
 
-    let gl = cc.gl.as_ref()?;
+    let gl = cc.gl.as_ref().ok_or(GLError::NoContext)?;
     let this = Self {
       show_fractals: false,
       show_mandelbrot: true,
       show_julia: false,
-      fractal_mandelbrot: Arc::new(Mutex::new(Mandelbrot::new(gl)?)),
-      fractal_julia: Arc::new(Mutex::new(Julia::new(gl)?)),
+      fractal_mandelbrot: Mandelbrot::new(gl).map(|m| Arc::new(Mutex::new(m))),
+      fractal_julia: Julia::new(gl).map(|j| Arc::new(Mutex::new(j))),
       zoom_center: nalgebra_glm::Vec2::new(0.0, 0.0),
       zoom_center_target: nalgebra_glm::Vec2::new(0.0, 0.0),
       zoom_size: 1.0,
@@ -50,11 +53,26 @@ impl FractalsManager {
     };
 
     do_log!("[Kuplung] New FractalsManager finished.");
-    Some(this)
+    Ok(this)
+  }
+
+  /// Builds the fractals again when a retry of their failed programs was requested from the Shaders panel or their files changed.
+  fn retry_failed_programs(&mut self, frame: &eframe::Frame) {
+    let Some(gl) = frame.gl() else { return; };
+    if self.fractal_mandelbrot.is_err() && shader_registry::retry_pending("Mandelbrot") {
+      self.fractal_mandelbrot = Mandelbrot::new(gl).map(|m| Arc::new(Mutex::new(m)));
+    }
+    if self.fractal_julia.is_err() && shader_registry::retry_pending("Julia") {
+      self.fractal_julia = Julia::new(gl).map(|j| Arc::new(Mutex::new(j)));
+    }
   }
 
   fn paint_mandelbrot(&mut self, ui: &mut Ui) {
-    self.fractal_mandelbrot.lock().draw_ui(ui);
+    let fractal_mandelbrot = match &self.fractal_mandelbrot {
+      Ok(fractal_mandelbrot) => fractal_mandelbrot.clone(),
+      Err(e) => return error_panel::render_error_panel(ui, "Mandelbrot", e),
+    };
+    fractal_mandelbrot.lock().draw_ui(ui);
 
     if !self.zoom_stop {
       self.zoom_max_iterations -= 10;
@@ -92,7 +110,6 @@ impl FractalsManager {
           do_log!("{} x {}", canvas_pos.x, canvas_pos.y);
         }
       }
-      let fractal_mandelbrot = self.fractal_mandelbrot.clone();
       let cb = egui_glow::CallbackFn::new(move |_, painter| {
         fractal_mandelbrot.lock().paint(painter.gl(), window_width, window_height, zoom_max_iterations, zoom_center, zoom_size);
       });
@@ -105,12 +122,15 @@ impl FractalsManager {
   }
 
   fn paint_julia(&mut self, ui: &mut Ui) {
-    self.fractal_julia.lock().draw_ui(ui);
+    let fractal_julia = match &self.fractal_julia {
+      Ok(fractal_julia) => fractal_julia.clone(),
+      Err(e) => return error_panel::render_error_panel(ui, "Julia", e),
+    };
+    fractal_julia.lock().draw_ui(ui);
     egui::Frame::canvas(ui.style()).show(ui, |ui| {
       let window_width: f32 = ui.available_size().x;
       let window_height: f32 = ui.available_size().y;
       let (rect, _) = ui.allocate_exact_size(egui::Vec2::from([window_width, window_height]), egui::Sense::drag());
-      let fractal_julia = self.fractal_julia.clone();
       let cb = egui_glow::CallbackFn::new(move |_, painter| {
         fractal_julia.lock().paint(painter.gl(), window_width, window_height);
       });
@@ -124,8 +144,9 @@ impl FractalsManager {
 }
 
 impl eframe::App for FractalsManager {
-  fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+  fn update(&mut self, ctx: &egui::Context, frame: &mut eframe::Frame) {
     self.show_fractals = true;
+    self.retry_failed_programs(frame);
     egui::Window::new("Fractals")
       .id(egui::Id::new("window_fractals"))
       .resizable(true)
@@ -161,8 +182,8 @@ impl eframe::App for FractalsManager {
 
   fn on_exit(&mut self, gl: Option<&glow::Context>) {
     if let Some(gl) = gl {
-      self.fractal_mandelbrot.lock().destroy(gl);
-      self.fractal_julia.lock().destroy(gl);
+      if let Ok(fractal_mandelbrot) = &self.fractal_mandelbrot { fractal_mandelbrot.lock().destroy(gl); }
+      if let Ok(fractal_julia) = &self.fractal_julia { fractal_julia.lock().destroy(gl); }
     }
   }
 }
\ No newline at end of file
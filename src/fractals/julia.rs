@@ -4,8 +4,8 @@ use eframe::egui_glow;
 use eframe::glow::HasContext;
 use egui::Ui;
 use egui_glow::glow;
-use log::error;
-use crate::rendering::gl_utils;
+use crate::rendering::gl_error::GLError;
+use crate::rendering::shader_program::ShaderProgram;
 extern crate nalgebra_glm as glm;
 
 #[rustfmt::skip]
@@ -21,7 +21,7 @@ pub static JULIA_INDICES: [i32; 6] = [ 0, 1, 3, 1, 2, 3 ];
 
 pub struct Julia {
   pub option_iterations: i32,
-  gl_Program: glow::Program,
+  gl_Program: ShaderProgram,
   gl_VAO: glow::VertexArray,
   vbo_Vertices: glow::Buffer,
   vbo_Indices: glow::Buffer,
@@ -29,33 +29,22 @@ pub struct Julia {
 
 #[allow(unsafe_code)]
 impl Julia {
-  pub fn new(gl: &glow::Context) -> Option<Self> {
+  pub fn new(gl: &glow::Context) -> Result<Self, GLError> {
     use glow::HasContext as _;
     unsafe {
-      let gl_Program = gl.create_program().expect("[Kuplung] [Julia] Cannot create program!");
+      let gl_Program = ShaderProgram::from_files(gl, "Julia", &[
+        (glow::VERTEX_SHADER, "assets/shaders/fractals/julia.vert"),
+        (glow::FRAGMENT_SHADER, "assets/shaders/fractals/julia.frag"),
+      ])?;
 
-      let shader_vertex = gl_utils::create_shader(&gl_Program, &gl, glow::VERTEX_SHADER, "assets/shaders/fractals/julia.vert");
-      let shader_fragment = gl_utils::create_shader(&gl_Program, &gl, glow::FRAGMENT_SHADER, "assets/shaders/fractals/julia.frag");
-
-      gl.link_program(gl_Program);
-      if !gl.get_program_link_status(gl_Program) {
-        error!("[Kuplung] [Julia] Program cannot be linked! {}", gl.get_program_info_log(gl_Program));
-        panic!("[Kuplung] [Julia] Program cannot be linked! {}", gl.get_program_info_log(gl_Program));
-      }
-
-      gl.detach_shader(gl_Program, shader_vertex);
-      gl.delete_shader(shader_vertex);
-      gl.detach_shader(gl_Program, shader_fragment);
-      gl.delete_shader(shader_fragment);
-
-      let gl_VAO = gl.create_vertex_array().expect("[Kuplung] [Julia] Cannot create vertex array!");
+      let gl_VAO = gl.create_vertex_array().map_err(GLError::resource("vertex array"))?;
       gl.bind_vertex_array(Some(gl_VAO));
 
-      let vbo_Vertices = gl.create_buffer().expect("[Kuplung] [Julia] Cannot create vertex buffer!");
+      let vbo_Vertices = gl.create_buffer().map_err(GLError::resource("vertex buffer"))?;
       gl.bind_buffer(glow::ARRAY_BUFFER, Some(vbo_Vertices));
       gl.buffer_data_u8_slice(glow::ARRAY_BUFFER, bytemuck::cast_slice(&JULIA_VERTICES[..]), glow::STATIC_DRAW);
 
-      let vbo_Indices = gl.create_buffer().expect("[Kuplung] [Julia] Cannot create indices buffer!");
+      let vbo_Indices = gl.create_buffer().map_err(GLError::resource("indices buffer"))?;
       gl.bind_buffer(glow::ELEMENT_ARRAY_BUFFER, Some(vbo_Indices));
       gl.buffer_data_u8_slice(glow::ELEMENT_ARRAY_BUFFER, bytemuck::cast_slice(&JULIA_INDICES[..]), glow::STATIC_DRAW);
 
@@ -64,7 +53,7 @@ impl Julia {
 
       gl.bind_vertex_array(None);
 
-      Some(Self {
+      Ok(Self {
         option_iterations: 256,
         gl_Program,
         gl_VAO,
@@ -85,17 +74,19 @@ impl Julia {
     ui.end_row();
   }
 
-  pub fn paint(&self, gl: &glow::Context, screen_width: f32, screen_height: f32) {
+  pub fn paint(&mut self, gl: &glow::Context, screen_width: f32, screen_height: f32) {
+    self.gl_Program.reload_if_changed(gl);
     unsafe {
-      gl.use_program(Some(self.gl_Program));
+      self.gl_Program.begin_frame(gl);
       gl.bind_vertex_array(Some(self.gl_VAO));
 
-      gl.uniform_1_f32(gl.get_uniform_location(self.gl_Program, "u_window_width").as_ref(), screen_width);
-      gl.uniform_1_f32(gl.get_uniform_location(self.gl_Program, "u_window_height").as_ref(), screen_height);
-      gl.uniform_1_i32(gl.get_uniform_location(self.gl_Program, "u_iterations").as_ref(), self.option_iterations);
-      gl.uniform_matrix_3_f32_slice(gl.get_uniform_location(self.gl_Program, "u_world_view").as_ref(), false, glm::Mat3x3::identity().as_slice().try_into().unwrap());
+      self.gl_Program.set_f32(gl, "u_window_width", screen_width);
+      self.gl_Program.set_f32(gl, "u_window_height", screen_height);
+      self.gl_Program.set_i32(gl, "u_iterations", self.option_iterations);
+      self.gl_Program.set_mat3(gl, "u_world_view", &glm::Mat3::identity());
 
       gl.draw_elements(glow::TRIANGLES, JULIA_INDICES.len() as i32, glow::UNSIGNED_INT, 0);
+      self.gl_Program.end_frame();
 
       gl.bind_vertex_array(None);
     }
@@ -104,7 +95,7 @@ impl Julia {
   pub fn destroy(&self, gl: &glow::Context) {
     use glow::HasContext as _;
     unsafe {
-      gl.delete_program(self.gl_Program);
+      self.gl_Program.destroy(gl);
       gl.delete_vertex_array(self.gl_VAO);
       gl.delete_buffer(self.vbo_Vertices);
       gl.delete_buffer(self.vbo_Indices);
@@ -4,10 +4,10 @@ use eframe::egui_glow;
 use eframe::glow::HasContext;
 use egui::{TextBuffer, Ui};
 use egui_glow::glow;
-use log::error;
 use strum::IntoEnumIterator;
 use strum_macros::{AsRefStr, EnumIter};
-use crate::rendering::gl_utils;
+use crate::rendering::gl_error::GLError;
+use crate::rendering::shader_program::ShaderProgram;
 
 #[rustfmt::skip]
 pub static MANDELBROT_VERTICES:[f32; 12] = [
@@ -24,7 +24,7 @@ pub struct Mandelbrot {
   pub option_iterations: i32,
   pub option_blackandwhite: bool,
   pub option_colorpalette: i32,
-  gl_Program: glow::Program,
+  gl_Program: ShaderProgram,
   gl_VAO: glow::VertexArray,
   vbo_Vertices: glow::Buffer,
   vbo_Indices: glow::Buffer,
@@ -32,33 +32,22 @@ pub struct Mandelbrot {
 
 #[allow(unsafe_code)]
 impl Mandelbrot {
-  pub fn new(gl: &glow::Context) -> Option<Self> {
+  pub fn new(gl: &glow::Context) -> Result<Self, GLError> {
     use glow::HasContext as _;
     unsafe {
-      let gl_Program = gl.create_program().expect("[Kuplung] [Mandelbrot] Cannot create program!");
+      let gl_Program = ShaderProgram::from_files(gl, "Mandelbrot", &[
+        (glow::VERTEX_SHADER, "assets/shaders/fractals/mandelbrot.vert"),
+        (glow::FRAGMENT_SHADER, "assets/shaders/fractals/mandelbrot.frag"),
+      ])?;
 
-      let shader_vertex = gl_utils::create_shader(&gl_Program, &gl, glow::VERTEX_SHADER, "assets/shaders/fractals/mandelbrot.vert");
-      let shader_fragment = gl_utils::create_shader(&gl_Program, &gl, glow::FRAGMENT_SHADER, "assets/shaders/fractals/mandelbrot.frag");
-
-      gl.link_program(gl_Program);
-      if !gl.get_program_link_status(gl_Program) {
-        error!("[Kuplung] [Mandelbrot] Program cannot be linked! {}", gl.get_program_info_log(gl_Program));
-        panic!("[Kuplung] [Mandelbrot] Program cannot be linked! {}", gl.get_program_info_log(gl_Program));
-      }
-
-      gl.detach_shader(gl_Program, shader_vertex);
-      gl.delete_shader(shader_vertex);
-      gl.detach_shader(gl_Program, shader_fragment);
-      gl.delete_shader(shader_fragment);
-
-      let gl_VAO = gl.create_vertex_array().expect("[Kuplung] [Mandelbrot] Cannot create vertex array!");
+      let gl_VAO = gl.create_vertex_array().map_err(GLError::resource("vertex array"))?;
       gl.bind_vertex_array(Some(gl_VAO));
 
-      let vbo_Vertices = gl.create_buffer().expect("[Kuplung] [Mandelbrot] Cannot create vertex buffer!");
+      let vbo_Vertices = gl.create_buffer().map_err(GLError::resource("vertex buffer"))?;
       gl.bind_buffer(glow::ARRAY_BUFFER, Some(vbo_Vertices));
       gl.buffer_data_u8_slice(glow::ARRAY_BUFFER, bytemuck::cast_slice(&MANDELBROT_VERTICES[..]), glow::STATIC_DRAW);
 
-      let vbo_Indices = gl.create_buffer().expect("[Kuplung] [Mandelbrot] Cannot create indices buffer!");
+      let vbo_Indices = gl.create_buffer().map_err(GLError::resource("indices buffer"))?;
       gl.bind_buffer(glow::ELEMENT_ARRAY_BUFFER, Some(vbo_Indices));
       gl.buffer_data_u8_slice(glow::ELEMENT_ARRAY_BUFFER, bytemuck::cast_slice(&MANDELBROT_INDICES[..]), glow::STATIC_DRAW);
 
@@ -67,7 +56,7 @@ impl Mandelbrot {
 
       gl.bind_vertex_array(None);
 
-      Some(Self {
+      Ok(Self {
         option_iterations: 500,
         option_blackandwhite: false,
         option_colorpalette: 0,
@@ -98,28 +87,25 @@ impl Mandelbrot {
     ui.end_row();
   }
 
-  pub fn paint(&self, gl: &glow::Context, screen_width: f32, screen_height: f32, zoom_max_iterations: i32, zoom_center: nalgebra_glm::Vec2, zoom_size: f32) {
+  pub fn paint(&mut self, gl: &glow::Context, screen_width: f32, screen_height: f32, zoom_max_iterations: i32, zoom_center: nalgebra_glm::Vec2, zoom_size: f32) {
+    self.gl_Program.reload_if_changed(gl);
     unsafe {
-      gl.use_program(Some(self.gl_Program));
+      self.gl_Program.begin_frame(gl);
       gl.bind_vertex_array(Some(self.gl_VAO));
 
-      gl.uniform_1_f32(gl.get_uniform_location(self.gl_Program, "u_window_width").as_ref(), screen_width);
-      gl.uniform_1_f32(gl.get_uniform_location(self.gl_Program, "u_window_height").as_ref(), screen_height);
-      gl.uniform_1_i32(gl.get_uniform_location(self.gl_Program, "u_iterations").as_ref(), self.option_iterations);
-      gl.uniform_1_i32(gl.get_uniform_location(self.gl_Program, "u_zoom_iterations").as_ref(), zoom_max_iterations);
-      if self.option_blackandwhite {
-        gl.uniform_1_i32(gl.get_uniform_location(self.gl_Program, "u_black_and_white").as_ref(), 1);
-      }
-      else {
-        gl.uniform_1_i32(gl.get_uniform_location(self.gl_Program, "u_black_and_white").as_ref(), 0);
-      }
-      gl.uniform_1_i32(gl.get_uniform_location(self.gl_Program, "u_color_palette").as_ref(), self.option_colorpalette);
-      gl.uniform_2_f32(gl.get_uniform_location(self.gl_Program, "u_zoomCenter").as_ref(), zoom_center.x, zoom_center.y);
-      gl.uniform_1_f32(gl.get_uniform_location(self.gl_Program, "u_zoomSize").as_ref(), zoom_size);
+      self.gl_Program.set_f32(gl, "u_window_width", screen_width);
+      self.gl_Program.set_f32(gl, "u_window_height", screen_height);
+      self.gl_Program.set_i32(gl, "u_iterations", self.option_iterations);
+      self.gl_Program.set_i32(gl, "u_zoom_iterations", zoom_max_iterations);
+      self.gl_Program.set_bool(gl, "u_black_and_white", self.option_blackandwhite);
+      self.gl_Program.set_i32(gl, "u_color_palette", self.option_colorpalette);
+      self.gl_Program.set_vec2(gl, "u_zoomCenter", &zoom_center);
+      self.gl_Program.set_f32(gl, "u_zoomSize", zoom_size);
 
       //do_log!("{} x {} = {} / {}", zoom_center.x, zoom_center.y, zoom_size, zoom_max_iterations);
 
       gl.draw_elements(glow::TRIANGLES, MANDELBROT_INDICES.len() as i32, glow::UNSIGNED_INT, 0);
+      self.gl_Program.end_frame();
 
       gl.bind_vertex_array(None);
     }
@@ -128,7 +114,7 @@ impl Mandelbrot {
   pub fn destroy(&self, gl: &glow::Context) {
     use glow::HasContext as _;
     unsafe {
-      gl.delete_program(self.gl_Program);
+      self.gl_Program.destroy(gl);
       gl.delete_vertex_array(self.gl_VAO);
       gl.delete_buffer(self.vbo_Vertices);
       gl.delete_buffer(self.vbo_Indices);
@@ -5,10 +5,13 @@ use egui::ViewportBuilder;
 use env_logger::Env;
 use crate::fractals::fractals_manager;
 use crate::do_log;
+use crate::rendering::gl_error::GLError;
 use crate::rendering::rendering_manager;
 use crate::shadertoy::shadertoy_manager;
-use crate::settings::{configuration, kuplung_logger};
+use crate::settings::{configuration, kuplung_logger, kuplung_settings};
+use crate::ui::components::error_panel;
 use crate::ui::ui_manager;
+use crate::utils::asset_locator;
 
 pub fn main() -> eframe::Result {
   let env = Env::default()
@@ -17,6 +20,9 @@ pub fn main() -> eframe::Result {
   env_logger::init_from_env(env);
 
   do_log!("[Kuplung] Initializing Kuplung...");
+  asset_locator::asset_folders();
+  kuplung_settings::load();
+  let settings = kuplung_settings::get();
 
   let icon = include_bytes!(concat!(env!("OUT_DIR"), "/assets/Kuplung.png"));
   let image = image::load_from_memory(icon).expect("[Kuplung] Failed to open icon path!").to_rgba8();
@@ -32,7 +38,7 @@ pub fn main() -> eframe::Result {
     renderer: Renderer::Glow,
     shader_version: Option::from(ShaderVersion::Gl140),
     viewport: ViewportBuilder::default()
-      .with_inner_size([configuration::WINDOW_WIDTH, configuration::WINDOW_HEIGHT])
+      .with_inner_size([settings.app_window.window_width, settings.app_window.window_height])
       .with_resizable(true)
       .with_visible(true)
       .with_icon(egui::IconData {
@@ -50,15 +56,17 @@ pub fn main() -> eframe::Result {
 #[derive(Default)]
 pub struct KuplungApp {
   manager_ui: ui_manager::UIManager,
-  manager_rendering: Option<rendering_manager::RenderingManager>,
-  manager_fractals: Option<fractals_manager::FractalsManager>,
-  manager_shadertoy: Option<shadertoy_manager::ShaderToy>,
+  manager_rendering: Option<Result<rendering_manager::RenderingManager, GLError>>,
+  manager_fractals: Option<Result<fractals_manager::FractalsManager, GLError>>,
+  manager_shadertoy: Option<Result<shadertoy_manager::ShaderToy, GLError>>,
 }
 
 impl KuplungApp {
   fn new(cc: &eframe::CreationContext<'_>) -> Self {
-    // set light mode as initial theme
-    cc.egui_ctx.set_visuals(egui::Visuals::light());
+    // theme from the settings, light mode by default
+    let settings = kuplung_settings::get();
+    cc.egui_ctx.set_visuals(if settings.app_gui.dark_mode { egui::Visuals::dark() } else { egui::Visuals::light() });
+    cc.egui_ctx.set_zoom_factor(settings.app_gui.ui_scale);
 
     // load fonts
     let mut fonts = egui::FontDefinitions::default();
@@ -68,9 +76,9 @@ impl KuplungApp {
 
     // initialize sub-systems
     let manager_ui = ui_manager::UIManager::new();
-    let manager_rendering = rendering_manager::RenderingManager::new(cc);
-    let manager_fractals = fractals_manager::FractalsManager::new(cc);
-    let manager_shadertoy = shadertoy_manager::ShaderToy::new(cc);
+    let manager_rendering = Some(rendering_manager::RenderingManager::new(cc));
+    let manager_fractals = Some(fractals_manager::FractalsManager::new(cc));
+    let manager_shadertoy = Some(shadertoy_manager::ShaderToy::new(cc));
     let this = Self {
       manager_ui,
       manager_rendering,
@@ -86,30 +94,81 @@ impl KuplungApp {
 impl eframe::App for KuplungApp {
   fn update(&mut self, ctx: &egui::Context, frame: &mut eframe::Frame) {
     self.manager_ui.render(ctx, frame);
-    if self.manager_ui.show_viewer { self.manager_rendering.as_mut().unwrap().update(ctx, frame); }
+    if self.manager_ui.take_settings_applied() {
+      if let Some(Ok(manager_rendering)) = self.manager_rendering.as_mut() { manager_rendering.apply_settings(); }
+    }
+    match self.manager_ui.take_file_request() {
+      Some(ui_manager::FileRequest::OpenScene(file)) => match self.manager_rendering.as_mut() {
+        Some(Ok(manager_rendering)) => match manager_rendering.open_scene(&file) {
+          Ok(()) => self.manager_ui.show_viewer = true,
+          Err(e) => {
+            do_log!("[Kuplung] {}", e);
+            self.manager_ui.show_error(e);
+          }
+        },
+        _ => self.manager_ui.show_error(format!("Cannot open {} - the renderer is not available.", file)),
+      },
+      Some(ui_manager::FileRequest::SaveScene(file)) => match self.manager_rendering.as_mut() {
+        Some(Ok(manager_rendering)) => if let Err(e) = manager_rendering.save_scene(&file) {
+          do_log!("[Kuplung] {}", e);
+          self.manager_ui.show_error(e);
+        },
+        _ => self.manager_ui.show_error(format!("Cannot save {} - the renderer is not available.", file)),
+      },
+      Some(ui_manager::FileRequest::ImportModel(file)) => match self.manager_rendering.as_mut() {
+        Some(Ok(manager_rendering)) => {
+          manager_rendering.import_model(&file);
+          self.manager_ui.show_viewer = true;
+        }
+        _ => do_log!("[Kuplung] Cannot import {} - the renderer is not available.", file),
+      },
+      Some(ui_manager::FileRequest::ExportScene(file)) => match self.manager_rendering.as_mut() {
+        Some(Ok(manager_rendering)) => {
+          manager_rendering.export_scene(&file);
+          self.manager_ui.show_viewer = true;
+        }
+        _ => do_log!("[Kuplung] Cannot export {} - the renderer is not available.", file),
+      },
+      None => {}
+    }
+    if self.manager_ui.show_viewer {
+      match self.manager_rendering.as_mut() {
+        Some(Ok(manager_rendering)) => manager_rendering.update(ctx, frame),
+        Some(Err(e)) => self.manager_ui.show_viewer = error_panel::render_error_window(ctx, "Viewer", e),
+        None => {}
+      }
+    }
     if self.manager_ui.show_fractals {
-      self.manager_fractals.as_mut().unwrap().update(ctx, frame);
-      if !self.manager_fractals.as_mut().unwrap().show_fractals {
-        self.manager_ui.show_fractals = false;
+      match self.manager_fractals.as_mut() {
+        Some(Ok(manager_fractals)) => {
+          manager_fractals.update(ctx, frame);
+          if !manager_fractals.show_fractals { self.manager_ui.show_fractals = false; }
+        }
+        Some(Err(e)) => self.manager_ui.show_fractals = error_panel::render_error_window(ctx, "Fractals", e),
+        None => {}
       }
     }
     if self.manager_ui.show_shadertoy {
-      self.manager_shadertoy.as_mut().unwrap().update(ctx, frame);
-      if !self.manager_shadertoy.as_mut().unwrap().show_shadertoy {
-        self.manager_ui.show_shadertoy = false;
+      match self.manager_shadertoy.as_mut() {
+        Some(Ok(manager_shadertoy)) => {
+          manager_shadertoy.update(ctx, frame);
+          if !manager_shadertoy.show_shadertoy { self.manager_ui.show_shadertoy = false; }
+        }
+        Some(Err(e)) => self.manager_ui.show_shadertoy = error_panel::render_error_window(ctx, "ShaderToy", e),
+        None => {}
       }
     }
   }
 
   fn on_exit(&mut self, gl: Option<&glow::Context>) {
     self.manager_ui.on_exit();
-    if let Some(manager_rendering) = &mut self.manager_rendering {
+    if let Some(Ok(manager_rendering)) = &mut self.manager_rendering {
       manager_rendering.on_exit(gl);
     }
-    if let Some(manager_fractals) = &mut self.manager_fractals {
+    if let Some(Ok(manager_fractals)) = &mut self.manager_fractals {
       manager_fractals.on_exit(gl);
     }
-    if let Some(manager_shadertoy) = &mut self.manager_shadertoy {
+    if let Some(Ok(manager_shadertoy)) = &mut self.manager_shadertoy {
       manager_shadertoy.on_exit(gl);
     }
   }
@@ -10,6 +10,8 @@ mod settings;
 mod rendering;
 mod ui;
 mod fractals;
+mod meshes;
+mod parsers;
 mod shadertoy;
 mod utils;
 
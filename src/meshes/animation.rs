@@ -0,0 +1,359 @@
+use nalgebra_glm as glm;
+use serde::{Deserialize, Serialize};
+use strum_macros::{AsRefStr, EnumIter};
+use crate::meshes::mesh_model::{MeshModel, ModelTransform};
+
+// Animated model properties - the ObjectCoordinate { animate, point } and MaterialColor { animate, ... } values
+// of assets/protobuf/KuplungAppScene.proto. A property can move on its own (a motion that swings or turns it)
+// and follow keyframes on the timeline, the motion is added on top of the keyframes.
+
+/// The values of a model that can be animated. Rotations are in degrees, the material strengths multiply the colors.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, EnumIter, AsRefStr, Serialize, Deserialize)]
+pub enum AnimatedProperty {
+  #[strum(serialize = "Position X")]
+  PositionX,
+  #[strum(serialize = "Position Y")]
+  PositionY,
+  #[strum(serialize = "Position Z")]
+  PositionZ,
+  #[strum(serialize = "Rotate X")]
+  RotateX,
+  #[strum(serialize = "Rotate Y")]
+  RotateY,
+  #[strum(serialize = "Rotate Z")]
+  RotateZ,
+  #[strum(serialize = "Scale X")]
+  ScaleX,
+  #[strum(serialize = "Scale Y")]
+  ScaleY,
+  #[strum(serialize = "Scale Z")]
+  ScaleZ,
+  Transparency,
+  #[strum(serialize = "Specular exponent")]
+  SpecularExp,
+  #[strum(serialize = "Ambient strength")]
+  AmbientStrength,
+  #[strum(serialize = "Diffuse strength")]
+  DiffuseStrength,
+  #[strum(serialize = "Specular strength")]
+  SpecularStrength,
+}
+
+impl AnimatedProperty {
+  pub fn is_rotation(&self) -> bool {
+    matches!(self, AnimatedProperty::RotateX | AnimatedProperty::RotateY | AnimatedProperty::RotateZ)
+  }
+
+  pub fn is_transform(&self) -> bool {
+    (*self as usize) <= AnimatedProperty::ScaleZ as usize
+  }
+
+  /// The value of the model, without animation.
+  pub fn value(&self, model: &MeshModel) -> f32 {
+    let transform = &model.transform;
+    match self {
+      AnimatedProperty::PositionX => transform.position.x,
+      AnimatedProperty::PositionY => transform.position.y,
+      AnimatedProperty::PositionZ => transform.position.z,
+      AnimatedProperty::RotateX => transform.rotation.x,
+      AnimatedProperty::RotateY => transform.rotation.y,
+      AnimatedProperty::RotateZ => transform.rotation.z,
+      AnimatedProperty::ScaleX => transform.scale.x,
+      AnimatedProperty::ScaleY => transform.scale.y,
+      AnimatedProperty::ScaleZ => transform.scale.z,
+      AnimatedProperty::Transparency => model.model_material.transparency,
+      AnimatedProperty::SpecularExp => model.model_material.specular_exp,
+      AnimatedProperty::AmbientStrength | AnimatedProperty::DiffuseStrength | AnimatedProperty::SpecularStrength => 1.0,
+    }
+  }
+
+  /// A motion that shows the property moving.
+  pub fn default_motion(&self) -> Motion {
+    let range = match self {
+      AnimatedProperty::PositionX | AnimatedProperty::PositionY | AnimatedProperty::PositionZ => 1.0,
+      AnimatedProperty::RotateX | AnimatedProperty::RotateY | AnimatedProperty::RotateZ => 0.0,
+      AnimatedProperty::SpecularExp => 16.0,
+      _ => 0.5,
+    };
+    Motion { property: *self, animate: true, speed: 0.25, range }
+  }
+}
+
+/// The curve from a keyframe to the next one.
+#[derive(Clone, Copy, Debug, Default, PartialEq, EnumIter, AsRefStr, Serialize, Deserialize)]
+pub enum Interpolation {
+  /// Keeps the value until the next keyframe.
+  Step,
+  #[default]
+  Linear,
+  #[strum(serialize = "Ease in")]
+  EaseIn,
+  #[strum(serialize = "Ease out")]
+  EaseOut,
+  #[strum(serialize = "Ease in-out")]
+  EaseInOut,
+}
+
+impl Interpolation {
+  /// How far along the way to the next keyframe the value is, `t` going from 0 to 1.
+  pub fn curve(&self, t: f32) -> f32 {
+    match self {
+      Interpolation::Step => 0.0,
+      Interpolation::Linear => t,
+      Interpolation::EaseIn => t * t,
+      Interpolation::EaseOut => t * (2.0 - t),
+      Interpolation::EaseInOut => t * t * (3.0 - 2.0 * t),
+    }
+  }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Keyframe {
+  /// Seconds from the start of the timeline.
+  pub time: f32,
+  pub value: f32,
+  pub interpolation: Interpolation,
+}
+
+/// The keyframes of one property, in time order.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Track {
+  pub property: AnimatedProperty,
+  pub keyframes: Vec<Keyframe>,
+}
+
+impl Track {
+  /// The value at the time - the first keyframe before it, the last one after it.
+  pub fn value(&self, time: f32) -> Option<f32> {
+    let next = self.keyframes.iter().position(|k| k.time > time);
+    match next {
+      Some(0) => self.keyframes.first().map(|k| k.value),
+      Some(index) => {
+        let (from, to) = (&self.keyframes[index - 1], &self.keyframes[index]);
+        let t = from.interpolation.curve((time - from.time) / (to.time - from.time));
+        Some(from.value + (to.value - from.value) * t)
+      }
+      None => self.keyframes.last().map(|k| k.value),
+    }
+  }
+}
+
+/// A property swinging around its value, or turning all the way around for rotations with no range.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Motion {
+  pub property: AnimatedProperty,
+  /// Off keeps the speed and the range for later.
+  pub animate: bool,
+  /// Swings or turns per second.
+  pub speed: f32,
+  /// How far the value goes to each side.
+  pub range: f32,
+}
+
+impl Motion {
+  pub fn offset(&self, time: f32) -> f32 {
+    if !self.animate { return 0.0; }
+    if self.property.is_rotation() && self.range == 0.0 { return (360.0 * self.speed * time) % 360.0; }
+    self.range * (std::f32::consts::TAU * self.speed * time).sin()
+  }
+}
+
+/// The motions and the keyframes of a model.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ModelAnimation {
+  pub motions: Vec<Motion>,
+  pub tracks: Vec<Track>,
+}
+
+/// What a model looks like at one time on the timeline.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ModelPose {
+  pub transform: ModelTransform,
+  pub transparency: f32,
+  pub specular_exp: f32,
+  pub ambient_strength: f32,
+  pub diffuse_strength: f32,
+  pub specular_strength: f32,
+}
+
+impl ModelAnimation {
+  /// Nothing moves - no motion is on and no track has keyframes.
+  pub fn is_empty(&self) -> bool {
+    !self.motions.iter().any(|m| m.animate) && self.tracks.iter().all(|t| t.keyframes.is_empty())
+  }
+
+  pub fn animates_transform(&self) -> bool {
+    self.motions.iter().any(|m| m.animate && m.property.is_transform()) || self.tracks.iter().any(|t| !t.keyframes.is_empty() && t.property.is_transform())
+  }
+
+  pub fn motion(&self, property: AnimatedProperty) -> Option<&Motion> {
+    self.motions.iter().find(|m| m.property == property)
+  }
+
+  pub fn track(&self, property: AnimatedProperty) -> Option<&Track> {
+    self.tracks.iter().find(|t| t.property == property)
+  }
+
+  /// Turns the motion of the property on or off, a new one starts with the defaults of the property.
+  pub fn set_animate(&mut self, property: AnimatedProperty, animate: bool) {
+    match self.motions.iter_mut().find(|m| m.property == property) {
+      Some(motion) => motion.animate = animate,
+      None if animate => self.motions.push(property.default_motion()),
+      None => {}
+    }
+  }
+
+  /// Adds a keyframe, or changes the value of the one at the same time.
+  pub fn set_keyframe(&mut self, property: AnimatedProperty, time: f32, value: f32) {
+    let index = match self.tracks.iter().position(|t| t.property == property) {
+      Some(index) => index,
+      None => {
+        self.tracks.push(Track { property, keyframes: Vec::new() });
+        self.tracks.len() - 1
+      }
+    };
+    let keyframes = &mut self.tracks[index].keyframes;
+    match keyframes.iter_mut().find(|k| (k.time - time).abs() < 1e-3) {
+      Some(keyframe) => keyframe.value = value,
+      None => {
+        let at = keyframes.partition_point(|k| k.time < time);
+        let interpolation = keyframes.get(at.wrapping_sub(1)).map_or(Interpolation::default(), |k| k.interpolation);
+        keyframes.insert(at, Keyframe { time, value, interpolation });
+      }
+    }
+  }
+
+  /// Puts the keyframes of every track back in time order, after their times were edited.
+  pub fn sort_keyframes(&mut self) {
+    for track in self.tracks.iter_mut() { track.keyframes.sort_by(|a, b| a.time.total_cmp(&b.time)); }
+    self.tracks.retain(|t| !t.keyframes.is_empty());
+  }
+
+  /// The value of the property at the time, `base` is the value of the model when there are no keyframes.
+  pub fn value(&self, property: AnimatedProperty, base: f32, time: f32) -> f32 {
+    let keyed = self.track(property).and_then(|t| t.value(time)).unwrap_or(base);
+    keyed + self.motion(property).map_or(0.0, |m| m.offset(time))
+  }
+
+  pub fn pose(&self, model: &MeshModel, time: f32) -> ModelPose {
+    let value = |property: AnimatedProperty| self.value(property, property.value(model), time);
+    ModelPose {
+      transform: ModelTransform {
+        position: glm::vec3(value(AnimatedProperty::PositionX), value(AnimatedProperty::PositionY), value(AnimatedProperty::PositionZ)),
+        rotation: glm::vec3(value(AnimatedProperty::RotateX), value(AnimatedProperty::RotateY), value(AnimatedProperty::RotateZ)),
+        scale: glm::vec3(value(AnimatedProperty::ScaleX), value(AnimatedProperty::ScaleY), value(AnimatedProperty::ScaleZ)),
+      },
+      transparency: value(AnimatedProperty::Transparency).clamp(0.0, 1.0),
+      specular_exp: value(AnimatedProperty::SpecularExp).max(0.0),
+      ambient_strength: value(AnimatedProperty::AmbientStrength).max(0.0),
+      diffuse_strength: value(AnimatedProperty::DiffuseStrength).max(0.0),
+      specular_strength: value(AnimatedProperty::SpecularStrength).max(0.0),
+    }
+  }
+}
+
+/// The playback of the animations of a scene. Only the length, the looping and the speed are saved.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Timeline {
+  /// Seconds.
+  pub duration: f32,
+  pub looping: bool,
+  pub speed: f32,
+  #[serde(skip)]
+  pub time: f32,
+  #[serde(skip)]
+  pub playing: bool,
+}
+
+impl Default for Timeline {
+  fn default() -> Self {
+    Self { duration: 5.0, looping: true, speed: 1.0, time: 0.0, playing: false }
+  }
+}
+
+impl Timeline {
+  /// Moves the time on by `seconds` while playing. At the end it starts over or stops.
+  pub fn advance(&mut self, seconds: f32) {
+    if !self.playing { return; }
+    self.time += seconds * self.speed;
+    if self.time >= self.duration {
+      if self.looping && self.duration > 0.0 { self.time %= self.duration; }
+      else {
+        self.time = self.duration;
+        self.playing = false;
+      }
+    }
+  }
+
+  /// Plays or pauses, playing from the end of a timeline that doesn't loop starts over.
+  pub fn toggle(&mut self) {
+    if !self.playing && self.time >= self.duration { self.time = 0.0; }
+    self.playing = !self.playing;
+  }
+
+  pub fn stop(&mut self) {
+    self.playing = false;
+    self.time = 0.0;
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn keyframes_interpolate_along_their_curves() {
+    let mut animation = ModelAnimation::default();
+    animation.set_keyframe(AnimatedProperty::PositionX, 2.0, 10.0);
+    animation.set_keyframe(AnimatedProperty::PositionX, 0.0, 0.0);
+    animation.set_keyframe(AnimatedProperty::PositionX, 4.0, 0.0);
+    let track = animation.track(AnimatedProperty::PositionX).unwrap();
+    assert_eq!(track.keyframes.iter().map(|k| k.time).collect::<Vec<_>>(), vec![0.0, 2.0, 4.0]);
+    assert_eq!(animation.value(AnimatedProperty::PositionX, 5.0, -1.0), 0.0);
+    assert_eq!(animation.value(AnimatedProperty::PositionX, 5.0, 1.0), 5.0);
+    assert_eq!(animation.value(AnimatedProperty::PositionX, 5.0, 9.0), 0.0);
+    // the other properties keep the value of the model
+    assert_eq!(animation.value(AnimatedProperty::PositionY, 5.0, 1.0), 5.0);
+
+    animation.tracks[0].keyframes[0].interpolation = Interpolation::Step;
+    animation.tracks[0].keyframes[1].interpolation = Interpolation::EaseIn;
+    assert_eq!(animation.value(AnimatedProperty::PositionX, 0.0, 1.9), 0.0);
+    assert_eq!(animation.value(AnimatedProperty::PositionX, 0.0, 3.0), 7.5);
+    assert_eq!(Interpolation::EaseInOut.curve(0.5), 0.5);
+    assert_eq!(Interpolation::EaseOut.curve(0.5), 0.75);
+  }
+
+  #[test]
+  fn motions_swing_and_turn_on_top_of_the_keyframes() {
+    let mut animation = ModelAnimation::default();
+    animation.set_animate(AnimatedProperty::RotateY, true);
+    animation.motions[0].speed = 0.5;
+    assert!((animation.value(AnimatedProperty::RotateY, 10.0, 1.0) - 190.0).abs() < 1e-3);
+    animation.set_animate(AnimatedProperty::PositionY, true);
+    animation.set_keyframe(AnimatedProperty::PositionY, 0.0, 2.0);
+    // a quarter of a swing at 0.25 swings per second
+    assert!((animation.value(AnimatedProperty::PositionY, 0.0, 1.0) - 3.0).abs() < 1e-5);
+    assert!(animation.animates_transform());
+
+    animation.set_animate(AnimatedProperty::RotateY, false);
+    assert_eq!(animation.value(AnimatedProperty::RotateY, 10.0, 1.0), 10.0);
+    assert_eq!(animation.motion(AnimatedProperty::RotateY).unwrap().speed, 0.5);
+  }
+
+  #[test]
+  fn timeline_loops_or_stops_at_the_end() {
+    let mut timeline = Timeline { duration: 2.0, ..Default::default() };
+    timeline.advance(1.0);
+    assert_eq!(timeline.time, 0.0);
+    timeline.toggle();
+    timeline.advance(2.5);
+    assert!((timeline.time - 0.5).abs() < 1e-6 && timeline.playing);
+    timeline.looping = false;
+    timeline.advance(2.0);
+    assert_eq!((timeline.time, timeline.playing), (2.0, false));
+    timeline.toggle();
+    assert_eq!((timeline.time, timeline.playing), (0.0, true));
+  }
+}
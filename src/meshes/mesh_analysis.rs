@@ -0,0 +1,105 @@
+use std::collections::HashMap;
+use crate::meshes::mesh_model::MeshModel;
+use crate::meshes::mesh_processing;
+
+/// How printable a mesh is. Corners at the same position are one vertex here, whatever the normals and
+/// texture coordinates, so meshes with split vertices (STL, hard edges, UV seams) are checked as one surface.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct MeshReport {
+  pub vertices: usize,
+  pub edges: usize,
+  /// Edges with a single triangle - holes in the surface.
+  pub boundary_edges: usize,
+  /// Edges shared by more than two triangles.
+  pub non_manifold_edges: usize,
+  /// Edges whose two triangles are wound the same way, one of them faces the wrong side.
+  pub flipped_edges: usize,
+  /// Triangles with two corners at the same position.
+  pub degenerate_triangles: usize,
+}
+
+impl MeshReport {
+  /// Closed with every edge between exactly two triangles.
+  pub fn is_watertight(&self) -> bool {
+    self.edges > 0 && self.boundary_edges == 0 && self.non_manifold_edges == 0
+  }
+}
+
+pub fn analyze(model: &MeshModel) -> MeshReport {
+  let (ids, positions) = mesh_processing::position_ids(model);
+  let mut report = MeshReport { vertices: positions.len(), ..Default::default() };
+  // per edge, the number of triangles and the sum of the directions they go along it
+  let mut edges: HashMap<(u32, u32), (usize, i32)> = HashMap::new();
+  for triangle in model.indices.chunks_exact(3) {
+    let [a, b, c] = [0, 1, 2].map(|i| ids[triangle[i] as usize]);
+    if a == b || b == c || c == a {
+      report.degenerate_triangles += 1;
+      continue;
+    }
+    for (from, to) in [(a, b), (b, c), (c, a)] {
+      let edge = edges.entry((from.min(to), from.max(to))).or_default();
+      edge.0 += 1;
+      edge.1 += if from < to { 1 } else { -1 };
+    }
+  }
+  report.edges = edges.len();
+  for (triangles, direction) in edges.into_values() {
+    match triangles {
+      1 => report.boundary_edges += 1,
+      2 if direction != 0 => report.flipped_edges += 1,
+      2 => {}
+      _ => report.non_manifold_edges += 1,
+    }
+  }
+  report
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use nalgebra_glm as glm;
+
+  /// A unit cube with split corners like an STL file, one triangle is left out when `open`.
+  fn cube(open: bool) -> MeshModel {
+    let corner = |i: u32| glm::vec3((i & 1) as f32, ((i >> 1) & 1) as f32, ((i >> 2) & 1) as f32);
+    let quads = [[0, 2, 3, 1], [4, 5, 7, 6], [0, 1, 5, 4], [2, 6, 7, 3], [0, 4, 6, 2], [1, 3, 7, 5]];
+    let mut model = MeshModel::default();
+    for quad in quads {
+      for triangle in [[quad[0], quad[1], quad[2]], [quad[0], quad[2], quad[3]]] {
+        for i in triangle {
+          model.indices.push(model.vertices.len() as u32);
+          model.vertices.push(corner(i));
+        }
+      }
+    }
+    if open { model.indices.truncate(model.indices.len() - 3); }
+    model
+  }
+
+  #[test]
+  fn closed_cube_is_watertight() {
+    let report = analyze(&cube(false));
+    assert_eq!((report.vertices, report.edges), (8, 18));
+    assert!(report.is_watertight());
+    assert_eq!(report.flipped_edges, 0);
+  }
+
+  #[test]
+  fn finds_holes_non_manifold_edges_and_flips() {
+    let report = analyze(&cube(true));
+    assert_eq!(report.boundary_edges, 3);
+    assert!(!report.is_watertight());
+
+    // three triangles around one edge, the last one wound like the first
+    let fin = MeshModel {
+      vertices: vec![glm::vec3(0.0, 0.0, 0.0), glm::vec3(1.0, 0.0, 0.0), glm::vec3(0.0, 1.0, 0.0), glm::vec3(0.0, -1.0, 0.0), glm::vec3(0.0, 0.0, 1.0)],
+      indices: vec![0, 1, 2, 1, 0, 3, 0, 1, 4, 0, 0, 1],
+      ..Default::default()
+    };
+    let report = analyze(&fin);
+    assert_eq!((report.non_manifold_edges, report.boundary_edges, report.degenerate_triangles), (1, 6, 1));
+
+    let flipped = MeshModel { indices: vec![0, 1, 2, 0, 1, 3], ..fin };
+    assert_eq!(analyze(&flipped).flipped_edges, 1);
+  }
+}
\ No newline at end of file
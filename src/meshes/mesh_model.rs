@@ -0,0 +1,225 @@
+use std::sync::Arc;
+use image::RgbaImage;
+use nalgebra_glm as glm;
+use crate::meshes::animation::ModelAnimation;
+
+// Naming follows the Mesh messages in assets/protobuf/KuplungDefinitions.proto.
+
+/// A texture of a material - the file as written in the material file, where it was found and the decoded image.
+#[derive(Clone, Debug, Default)]
+pub struct MeshMaterialTextureImage {
+  pub filename: String,
+  pub image: String,
+  pub width: u32,
+  pub height: u32,
+  pub use_texture: bool,
+  /// Decoded by the importer, dropped after the GL upload.
+  pub pixels: Option<Arc<RgbaImage>>,
+  /// The encoded image of a texture stored inside the model file, there is no image file then.
+  pub encoded: Option<Arc<Vec<u8>>>,
+}
+
+#[derive(Clone, Debug)]
+pub struct MeshModelMaterial {
+  pub material_title: String,
+  pub ambient_color: glm::Vec3,
+  pub diffuse_color: glm::Vec3,
+  pub specular_color: glm::Vec3,
+  pub emission_color: glm::Vec3,
+  pub specular_exp: f32,
+  pub transparency: f32,
+  pub illumination_mode: u32,
+  pub optical_density: f32,
+  pub metallic: f32,
+  pub roughness: f32,
+  pub texture_ambient: MeshMaterialTextureImage,
+  pub texture_diffuse: MeshMaterialTextureImage,
+  pub texture_specular: MeshMaterialTextureImage,
+  pub texture_specular_exp: MeshMaterialTextureImage,
+  pub texture_dissolve: MeshMaterialTextureImage,
+  pub texture_bump: MeshMaterialTextureImage,
+  pub texture_displacement: MeshMaterialTextureImage,
+  pub texture_emission: MeshMaterialTextureImage,
+  /// glTF packs both - roughness in the green channel, metallic in the blue one.
+  pub texture_metallic_roughness: MeshMaterialTextureImage,
+}
+
+impl Default for MeshModelMaterial {
+  fn default() -> Self {
+    Self {
+      material_title: "Default".to_string(),
+      ambient_color: glm::vec3(0.0, 0.0, 0.0),
+      diffuse_color: glm::vec3(0.8, 0.8, 0.8),
+      specular_color: glm::vec3(0.5, 0.5, 0.5),
+      emission_color: glm::vec3(0.0, 0.0, 0.0),
+      specular_exp: 10.0,
+      transparency: 1.0,
+      illumination_mode: 2,
+      optical_density: 1.0,
+      metallic: 0.0,
+      roughness: 0.5,
+      texture_ambient: Default::default(),
+      texture_diffuse: Default::default(),
+      texture_specular: Default::default(),
+      texture_specular_exp: Default::default(),
+      texture_dissolve: Default::default(),
+      texture_bump: Default::default(),
+      texture_displacement: Default::default(),
+      texture_emission: Default::default(),
+      texture_metallic_roughness: Default::default(),
+    }
+  }
+}
+
+impl MeshModelMaterial {
+  /// The texture slots with their names as in the MTL files.
+  pub fn textures(&self) -> [(&'static str, &MeshMaterialTextureImage); 8] {
+    [
+      ("map_Ka", &self.texture_ambient),
+      ("map_Kd", &self.texture_diffuse),
+      ("map_Ks", &self.texture_specular),
+      ("map_Ns", &self.texture_specular_exp),
+      ("map_d", &self.texture_dissolve),
+      ("map_bump", &self.texture_bump),
+      ("disp", &self.texture_displacement),
+      ("map_Ke", &self.texture_emission),
+    ]
+  }
+}
+
+/// Where a model is in the scene. The rotation is in degrees, applied around X, then Y, then Z.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ModelTransform {
+  pub position: glm::Vec3,
+  pub rotation: glm::Vec3,
+  pub scale: glm::Vec3,
+}
+
+impl Default for ModelTransform {
+  fn default() -> Self {
+    Self {
+      position: glm::Vec3::zeros(),
+      rotation: glm::Vec3::zeros(),
+      scale: glm::vec3(1.0, 1.0, 1.0),
+    }
+  }
+}
+
+impl ModelTransform {
+  pub fn rotation_matrix(&self) -> glm::Mat4 {
+    let r = self.rotation.map(f32::to_radians);
+    glm::rotation(r.z, &glm::Vec3::z()) * glm::rotation(r.y, &glm::Vec3::y()) * glm::rotation(r.x, &glm::Vec3::x())
+  }
+
+  pub fn matrix(&self) -> glm::Mat4 {
+    glm::translation(&self.position) * self.rotation_matrix() * glm::scaling(&self.scale)
+  }
+
+  /// Splits a matrix into position, rotation and scale. Shear, which only comes from non-uniform scaling
+  /// of a rotated parent, is lost.
+  pub fn from_matrix(matrix: &glm::Mat4) -> Self {
+    let column = |i: usize| glm::vec3(matrix[(0, i)], matrix[(1, i)], matrix[(2, i)]);
+    let mut scale = glm::vec3(column(0).norm(), column(1).norm(), column(2).norm());
+    // a mirroring matrix flips one axis
+    if glm::dot(&glm::cross(&column(0), &column(1)), &column(2)) < 0.0 { scale.x = -scale.x; }
+    let axis = |i: usize| if scale[i] != 0.0 { column(i) / scale[i] } else { glm::Vec3::zeros() };
+    let (x, y, z) = (axis(0), axis(1), axis(2));
+    // R = Rz * Ry * Rx, so R[2][0] = -sin(y)
+    let rotation = if x.z.abs() < 0.99999 {
+      glm::vec3(y.z.atan2(z.z), (-x.z).asin(), x.y.atan2(x.x))
+    }
+    else {
+      glm::vec3(0.0, (-x.z).signum() * std::f32::consts::FRAC_PI_2, (-y.x).atan2(y.y))
+    };
+    Self { position: column(3), rotation: rotation.map(f32::to_degrees), scale }
+  }
+}
+
+/// Triangles with one material. Vertices, texture coordinates and normals are indexed together.
+#[derive(Clone, Debug, Default)]
+pub struct MeshModel {
+  pub id: usize,
+  pub file: String,
+  pub file_path: String,
+  pub model_title: String,
+  pub model_material: MeshModelMaterial,
+  pub transform: ModelTransform,
+  pub vertices: Vec<glm::Vec3>,
+  pub texture_coordinates: Vec<glm::Vec2>,
+  pub normals: Vec<glm::Vec3>,
+  /// RGBA per vertex, empty when the file has no vertex colors.
+  pub colors: Vec<glm::Vec4>,
+  /// Along the +U texture direction, w is the handedness of the bitangent. Empty when not known.
+  pub tangents: Vec<glm::Vec4>,
+  pub indices: Vec<u32>,
+  /// The corner count of each face as it was in the file, the triangles are fans of them in order.
+  /// Empty when the faces are not known, then every triangle is a face.
+  pub polygons: Vec<u32>,
+  pub animation: ModelAnimation,
+}
+
+impl MeshModel {
+  pub fn count_triangles(&self) -> usize {
+    self.indices.len() / 3
+  }
+
+  /// The faces as corner lists - the polygons put back together from their fans, or the triangles.
+  pub fn faces(&self) -> Vec<Vec<u32>> {
+    let fans: usize = self.polygons.iter().map(|n| n.saturating_sub(2) as usize * 3).sum();
+    if self.polygons.is_empty() || fans != self.indices.len() || self.polygons.iter().any(|n| *n < 3) {
+      return self.indices.chunks_exact(3).map(|t| t.to_vec()).collect();
+    }
+    let mut faces = Vec::with_capacity(self.polygons.len());
+    let mut offset = 0;
+    for count in self.polygons.iter().map(|n| *n as usize) {
+      let fan = &self.indices[offset..offset + (count - 2) * 3];
+      faces.push([fan[0], fan[1]].into_iter().chain(fan.chunks_exact(3).map(|t| t[2])).collect());
+      offset += fan.len();
+    }
+    faces
+  }
+
+  /// The (min, max) corners of the vertices, None for an empty mesh.
+  pub fn bounds(&self) -> Option<(glm::Vec3, glm::Vec3)> {
+    let first = *self.vertices.first()?;
+    Some(self.vertices.iter().fold((first, first), |(min, max), v| (glm::min2(&min, v), glm::max2(&max, v))))
+  }
+
+  /// A copy placed in the scene - the transform applied to the vertices, normals and tangents, and the positions scaled.
+  pub fn baked(&self, scale: f32) -> MeshModel {
+    let matrix = glm::scaling(&glm::vec3(scale, scale, scale)) * self.transform.matrix();
+    let normal_matrix = glm::transpose(&glm::inverse(&glm::mat4_to_mat3(&matrix)));
+    MeshModel {
+      transform: ModelTransform::default(),
+      vertices: self.vertices.iter().map(|v| (matrix * v.push(1.0)).xyz()).collect(),
+      normals: self.normals.iter().map(|n| (normal_matrix * n).try_normalize(1e-12).unwrap_or(*n)).collect(),
+      tangents: self.tangents.iter().map(|t| (glm::mat4_to_mat3(&matrix) * t.xyz()).try_normalize(1e-12).unwrap_or(t.xyz()).push(t.w)).collect(),
+      ..self.clone()
+    }
+  }
+
+  /// The bounds in the scene, around the transformed corners of the mesh bounds.
+  pub fn world_bounds(&self) -> Option<(glm::Vec3, glm::Vec3)> {
+    let (min, max) = self.bounds()?;
+    let matrix = self.transform.matrix();
+    let corners = (0..8).map(|i| {
+      let corner = glm::vec3(if i & 1 == 0 { min.x } else { max.x }, if i & 2 == 0 { min.y } else { max.y }, if i & 4 == 0 { min.z } else { max.z });
+      (matrix * corner.push(1.0)).xyz()
+    });
+    corners.fold(None, |bounds, c| Some(bounds.map_or((c, c), |(min, max)| (glm::min2(&min, &c), glm::max2(&max, &c)))))
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn transform_matrix_round_trip() {
+    let transform = ModelTransform { position: glm::vec3(1.0, -2.0, 3.0), rotation: glm::vec3(30.0, -45.0, 120.0), scale: glm::vec3(2.0, 0.5, 1.5) };
+    let decomposed = ModelTransform::from_matrix(&transform.matrix());
+    assert!(glm::distance(&decomposed.position, &transform.position) < 1e-5);
+    assert!(glm::distance(&decomposed.rotation, &transform.rotation) < 1e-3);
+    assert!(glm::distance(&decomposed.scale, &transform.scale) < 1e-5);
+  }
+}
@@ -0,0 +1,562 @@
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, HashMap, HashSet};
+use nalgebra_glm as glm;
+use strum_macros::{AsRefStr, EnumIter};
+use crate::meshes::mesh_model::MeshModel;
+
+// Geometry operations on the meshes in memory, each gives a new mesh. The topology comes from the positions,
+// so vertices split for texture coordinates or normals still join up, while the split itself - a UV seam - is kept.
+
+/// Attributes closer than this are the same when welding.
+const ATTRIBUTE_TOLERANCE: f32 = 1e-4;
+
+#[derive(Clone, Copy, Debug, PartialEq, EnumIter, AsRefStr)]
+pub enum MeshOperation {
+  #[strum(serialize = "Flat normals")]
+  FlatNormals,
+  #[strum(serialize = "Smooth normals")]
+  SmoothNormals,
+  #[strum(serialize = "Weld vertices")]
+  Weld,
+  #[strum(serialize = "Loop subdivision")]
+  LoopSubdivision,
+  #[strum(serialize = "Catmull-Clark subdivision")]
+  CatmullClark,
+  Decimate,
+}
+
+#[derive(Clone, Debug)]
+pub struct ProcessingOptions {
+  /// Faces further apart than this, in degrees, keep a hard edge between them when smoothing.
+  pub smoothing_angle: f32,
+  /// Vertices closer than this are welded.
+  pub weld_distance: f32,
+  /// The part of the triangles kept when decimating.
+  pub decimate_ratio: f32,
+}
+
+impl Default for ProcessingOptions {
+  fn default() -> Self {
+    Self {
+      smoothing_angle: 30.0,
+      weld_distance: 1e-4,
+      decimate_ratio: 0.5,
+    }
+  }
+}
+
+pub fn apply(model: &MeshModel, operation: MeshOperation, options: &ProcessingOptions) -> MeshModel {
+  match operation {
+    MeshOperation::FlatNormals => flat_normals(model),
+    MeshOperation::SmoothNormals => smooth_normals(model, options.smoothing_angle),
+    MeshOperation::Weld => weld(model, options.weld_distance),
+    MeshOperation::LoopSubdivision => loop_subdivision(model),
+    MeshOperation::CatmullClark => catmull_clark(model),
+    MeshOperation::Decimate => decimate(model, (model.count_triangles() as f32 * options.decimate_ratio.clamp(0.0, 1.0)) as usize),
+  }
+}
+
+/// An id per vertex, the same for the vertices at the same position, and the position of each id.
+pub fn position_ids(model: &MeshModel) -> (Vec<u32>, Vec<glm::Vec3>) {
+  // adding 0 turns -0 into 0, so both weld
+  let mut ids: HashMap<[u32; 3], u32> = HashMap::new();
+  let mut positions = Vec::new();
+  let per_vertex = model.vertices.iter().map(|v| {
+    *ids.entry([v.x, v.y, v.z].map(|c| (c + 0.0).to_bits())).or_insert_with(|| {
+      positions.push(*v);
+      (positions.len() - 1) as u32
+    })
+  }).collect();
+  (per_vertex, positions)
+}
+
+/// Twice the area along the normal, the corners wound counter-clockwise.
+fn face_normal(vertices: &[glm::Vec3], triangle: &[u32]) -> glm::Vec3 {
+  let [a, b, c] = [0, 1, 2].map(|i| vertices[triangle[i] as usize]);
+  glm::cross(&(b - a), &(c - a))
+}
+
+/// Vertices made as weighted blends of the vertices of a mesh, with the attributes the mesh has for all of them.
+struct Blender<'a> {
+  source: &'a MeshModel,
+  target: MeshModel,
+  has_uvs: bool,
+  has_normals: bool,
+  has_colors: bool,
+  has_tangents: bool,
+}
+
+impl<'a> Blender<'a> {
+  fn new(source: &'a MeshModel) -> Self {
+    let count = source.vertices.len();
+    Self {
+      source,
+      target: MeshModel {
+        id: source.id,
+        file: source.file.clone(),
+        file_path: source.file_path.clone(),
+        model_title: source.model_title.clone(),
+        model_material: source.model_material.clone(),
+        transform: source.transform,
+        ..Default::default()
+      },
+      has_uvs: source.texture_coordinates.len() == count,
+      has_normals: source.normals.len() == count,
+      has_colors: source.colors.len() == count,
+      has_tangents: source.tangents.len() == count,
+    }
+  }
+
+  fn push(&mut self, position: glm::Vec3, corners: &[(u32, f32)]) -> u32 {
+    let source = self.source;
+    let target = &mut self.target;
+    target.vertices.push(position);
+    if self.has_uvs { target.texture_coordinates.push(corners.iter().map(|(i, w)| source.texture_coordinates[*i as usize] * *w).sum()); }
+    if self.has_normals {
+      let normal: glm::Vec3 = corners.iter().map(|(i, w)| source.normals[*i as usize] * *w).sum();
+      target.normals.push(normal.try_normalize(1e-12).unwrap_or(source.normals[corners[0].0 as usize]));
+    }
+    if self.has_colors { target.colors.push(corners.iter().map(|(i, w)| source.colors[*i as usize] * *w).sum()); }
+    if self.has_tangents {
+      let first = source.tangents[corners[0].0 as usize];
+      let tangent: glm::Vec3 = corners.iter().map(|(i, w)| source.tangents[*i as usize].xyz() * *w).sum();
+      target.tangents.push(tangent.try_normalize(1e-12).unwrap_or(first.xyz()).push(first.w));
+    }
+    (target.vertices.len() - 1) as u32
+  }
+
+  fn copy(&mut self, vertex: u32) -> u32 {
+    self.push(self.source.vertices[vertex as usize], &[(vertex, 1.0)])
+  }
+}
+
+/// Every triangle with its own corners, facing the way it is wound.
+pub fn flat_normals(model: &MeshModel) -> MeshModel {
+  let mut blender = Blender::new(model);
+  blender.has_normals = false;
+  for triangle in model.indices.chunks_exact(3) {
+    let normal = face_normal(&model.vertices, triangle).try_normalize(1e-12).unwrap_or(glm::vec3(0.0, 1.0, 0.0));
+    for vertex in triangle {
+      let index = blender.copy(*vertex);
+      blender.target.normals.push(normal);
+      blender.target.indices.push(index);
+    }
+  }
+  blender.target.polygons = model.polygons.clone();
+  blender.target
+}
+
+/// Normals averaged over the triangles around each position, weighted by their area. Triangles more than
+/// the angle (in degrees) apart from the one of the corner are left out, which keeps the hard edges.
+pub fn smooth_normals(model: &MeshModel, angle: f32) -> MeshModel {
+  let (ids, positions) = position_ids(model);
+  let faces: Vec<glm::Vec3> = model.indices.chunks_exact(3).map(|t| face_normal(&model.vertices, t)).collect();
+  let units: Vec<Option<glm::Vec3>> = faces.iter().map(|f| f.try_normalize(1e-12)).collect();
+  let mut around = vec![Vec::new(); positions.len()];
+  for (face, triangle) in model.indices.chunks_exact(3).enumerate() {
+    for vertex in triangle { around[ids[*vertex as usize] as usize].push(face); }
+  }
+  let limit = angle.to_radians().cos();
+  let mut blender = Blender::new(model);
+  blender.has_normals = false;
+  // a vertex is split when its corners get different normals
+  let mut split: HashMap<(u32, [u32; 3]), u32> = HashMap::new();
+  for (face, triangle) in model.indices.chunks_exact(3).enumerate() {
+    for vertex in triangle {
+      let normal: glm::Vec3 = around[ids[*vertex as usize] as usize].iter()
+        .filter(|other| matches!((units[face], units[**other]), (Some(a), Some(b)) if glm::dot(&a, &b) >= limit))
+        .map(|other| faces[*other])
+        .sum();
+      let normal = normal.try_normalize(1e-12).unwrap_or(units[face].unwrap_or(glm::vec3(0.0, 1.0, 0.0)));
+      let index = *split.entry((*vertex, [normal.x, normal.y, normal.z].map(f32::to_bits))).or_insert_with(|| {
+        let index = blender.copy(*vertex);
+        blender.target.normals.push(normal);
+        index
+      });
+      blender.target.indices.push(index);
+    }
+  }
+  blender.target.polygons = model.polygons.clone();
+  blender.target
+}
+
+/// Joins the vertices closer than the distance - snapped to a grid that size - which have the same texture
+/// coordinates, normals and colors, so UV seams and hard edges stay. Triangles that collapse are removed.
+pub fn weld(model: &MeshModel, distance: f32) -> MeshModel {
+  let quantize = |values: &[f32], step: f32| values.iter().map(|v| (v / step).round() as i64).collect::<Vec<i64>>();
+  let distance = distance.max(f32::EPSILON);
+  let mut blender = Blender::new(model);
+  let mut welded: HashMap<Vec<i64>, u32> = HashMap::new();
+  let remap: Vec<u32> = (0..model.vertices.len()).map(|i| {
+    let mut key = quantize(model.vertices[i].as_slice(), distance);
+    if blender.has_uvs { key.extend(quantize(model.texture_coordinates[i].as_slice(), ATTRIBUTE_TOLERANCE)); }
+    if blender.has_normals { key.extend(quantize(model.normals[i].as_slice(), ATTRIBUTE_TOLERANCE)); }
+    if blender.has_colors { key.extend(quantize(model.colors[i].as_slice(), ATTRIBUTE_TOLERANCE)); }
+    *welded.entry(key).or_insert_with(|| blender.copy(i as u32))
+  }).collect();
+  for triangle in model.indices.chunks_exact(3) {
+    let [a, b, c] = [0, 1, 2].map(|i| remap[triangle[i] as usize]);
+    if a != b && b != c && c != a { blender.target.indices.extend([a, b, c]); }
+  }
+  // the polygons only fit while no triangle is gone
+  if blender.target.indices.len() == model.indices.len() { blender.target.polygons = model.polygons.clone(); }
+  blender.target
+}
+
+/// The edges between positions, smaller id first, with the faces along each.
+fn position_edges(faces: &[Vec<u32>], ids: &[u32]) -> HashMap<(u32, u32), Vec<usize>> {
+  let mut edges: HashMap<(u32, u32), Vec<usize>> = HashMap::new();
+  for (index, face) in faces.iter().enumerate() {
+    for i in 0..face.len() {
+      let (a, b) = (ids[face[i] as usize], ids[face[(i + 1) % face.len()] as usize]);
+      edges.entry((a.min(b), a.max(b))).or_default().push(index);
+    }
+  }
+  edges
+}
+
+/// The new position of a corner on a border between its two neighbours there, None when the border is not simple.
+fn border_position(position: &glm::Vec3, neighbours: &[glm::Vec3]) -> Option<glm::Vec3> {
+  match neighbours {
+    [a, b] => Some(position * 0.75 + (a + b) * 0.125),
+    _ => None,
+  }
+}
+
+/// Splits every triangle into four and smooths with the Loop weights, borders with the cubic B-spline ones.
+/// Texture coordinates and colors are interpolated, the normals are computed again.
+pub fn loop_subdivision(model: &MeshModel) -> MeshModel {
+  let (ids, positions) = position_ids(model);
+  let triangles: Vec<Vec<u32>> = model.indices.chunks_exact(3).map(|t| t.to_vec()).collect();
+  let edges = position_edges(&triangles, &ids);
+  let mut neighbours: Vec<HashSet<u32>> = vec![HashSet::new(); positions.len()];
+  let mut borders: Vec<Vec<glm::Vec3>> = vec![Vec::new(); positions.len()];
+  let mut non_manifold = vec![false; positions.len()];
+  for ((a, b), faces) in edges.iter() {
+    neighbours[*a as usize].insert(*b);
+    neighbours[*b as usize].insert(*a);
+    match faces.len() {
+      1 => {
+        borders[*a as usize].push(positions[*b as usize]);
+        borders[*b as usize].push(positions[*a as usize]);
+      }
+      2 => {}
+      _ => { non_manifold[*a as usize] = true; non_manifold[*b as usize] = true; }
+    }
+  }
+  let moved: Vec<glm::Vec3> = positions.iter().enumerate().map(|(p, position)| {
+    if non_manifold[p] { return *position; }
+    if !borders[p].is_empty() { return border_position(position, &borders[p]).unwrap_or(*position); }
+    let count = neighbours[p].len() as f32;
+    let beta = if neighbours[p].len() == 3 { 3.0 / 16.0 } else { 3.0 / (8.0 * count) };
+    position * (1.0 - count * beta) + neighbours[p].iter().map(|n| positions[*n as usize]).sum::<glm::Vec3>() * beta
+  }).collect();
+  let edge_point = |a: u32, b: u32| {
+    let (pa, pb) = (positions[a as usize], positions[b as usize]);
+    match edges[&(a.min(b), a.max(b))].as_slice() {
+      [f, g] => {
+        // the corners of the two triangles across the edge
+        let opposite = |face: usize| triangles[face].iter().map(|v| ids[*v as usize]).find(|p| *p != a && *p != b).map_or(glm::Vec3::zeros(), |p| positions[p as usize]);
+        (pa + pb) * 0.375 + (opposite(*f) + opposite(*g)) * 0.125
+      }
+      _ => (pa + pb) * 0.5,
+    }
+  };
+  let mut blender = Blender::new(model);
+  blender.has_tangents = false;
+  let mut corners: HashMap<u32, u32> = HashMap::new();
+  let mut middles: HashMap<(u32, u32), u32> = HashMap::new();
+  for triangle in triangles.iter() {
+    let corner = [0, 1, 2].map(|i| {
+      let vertex = triangle[i];
+      *corners.entry(vertex).or_insert_with(|| blender.push(moved[ids[vertex as usize] as usize], &[(vertex, 1.0)]))
+    });
+    let middle = [0, 1, 2].map(|i| {
+      let (a, b) = (triangle[i], triangle[(i + 1) % 3]);
+      *middles.entry((a.min(b), a.max(b))).or_insert_with(|| blender.push(edge_point(ids[a as usize], ids[b as usize]), &[(a, 0.5), (b, 0.5)]))
+    });
+    blender.target.indices.extend([corner[0], middle[0], middle[2], middle[0], corner[1], middle[1], middle[2], middle[1], corner[2], middle[0], middle[1], middle[2]]);
+  }
+  let has_normals = blender.has_normals;
+  if has_normals { smooth_normals(&blender.target, 180.0) } else { blender.target }
+}
+
+/// Splits every face into quads - one per corner - and smooths with the Catmull-Clark rules, borders with the
+/// cubic B-spline ones. Texture coordinates and colors are interpolated, the normals are computed again.
+pub fn catmull_clark(model: &MeshModel) -> MeshModel {
+  let (ids, positions) = position_ids(model);
+  let faces = model.faces();
+  let face_points: Vec<glm::Vec3> = faces.iter().map(|f| f.iter().map(|v| positions[ids[*v as usize] as usize]).sum::<glm::Vec3>() / f.len() as f32).collect();
+  let edges = position_edges(&faces, &ids);
+  let mut around_faces: Vec<HashSet<usize>> = vec![HashSet::new(); positions.len()];
+  let mut middles: Vec<Vec<glm::Vec3>> = vec![Vec::new(); positions.len()];
+  let mut borders: Vec<Vec<glm::Vec3>> = vec![Vec::new(); positions.len()];
+  let mut non_manifold = vec![false; positions.len()];
+  for ((a, b), edge_faces) in edges.iter() {
+    let (pa, pb) = (positions[*a as usize], positions[*b as usize]);
+    for (p, other) in [(*a, pb), (*b, pa)] {
+      middles[p as usize].push((positions[p as usize] + other) * 0.5);
+      around_faces[p as usize].extend(edge_faces.iter().copied());
+      match edge_faces.len() {
+        1 => borders[p as usize].push(other),
+        2 => {}
+        _ => non_manifold[p as usize] = true,
+      }
+    }
+  }
+  let moved: Vec<glm::Vec3> = positions.iter().enumerate().map(|(p, position)| {
+    if non_manifold[p] || around_faces[p].is_empty() { return *position; }
+    if !borders[p].is_empty() { return border_position(position, &borders[p]).unwrap_or(*position); }
+    let count = around_faces[p].len() as f32;
+    let faces_average = around_faces[p].iter().map(|f| face_points[*f]).sum::<glm::Vec3>() / count;
+    let middles_average = middles[p].iter().sum::<glm::Vec3>() / middles[p].len() as f32;
+    (faces_average + middles_average * 2.0 + position * (count - 3.0)) / count
+  }).collect();
+  let edge_point = |a: u32, b: u32| {
+    let (pa, pb) = (positions[a as usize], positions[b as usize]);
+    match edges[&(a.min(b), a.max(b))].as_slice() {
+      [f, g] => (pa + pb + face_points[*f] + face_points[*g]) * 0.25,
+      _ => (pa + pb) * 0.5,
+    }
+  };
+  let mut blender = Blender::new(model);
+  blender.has_tangents = false;
+  let mut corners: HashMap<u32, u32> = HashMap::new();
+  let mut edge_vertices: HashMap<(u32, u32), u32> = HashMap::new();
+  for (index, face) in faces.iter().enumerate() {
+    let count = face.len();
+    let weights: Vec<(u32, f32)> = face.iter().map(|v| (*v, 1.0 / count as f32)).collect();
+    let center = blender.push(face_points[index], &weights);
+    let corner: Vec<u32> = face.iter().map(|v| *corners.entry(*v).or_insert_with(|| blender.push(moved[ids[*v as usize] as usize], &[(*v, 1.0)]))).collect();
+    let edge: Vec<u32> = (0..count).map(|i| {
+      let (a, b) = (face[i], face[(i + 1) % count]);
+      *edge_vertices.entry((a.min(b), a.max(b))).or_insert_with(|| blender.push(edge_point(ids[a as usize], ids[b as usize]), &[(a, 0.5), (b, 0.5)]))
+    }).collect();
+    for i in 0..count {
+      let quad = [corner[i], edge[i], center, edge[(i + count - 1) % count]];
+      blender.target.indices.extend([quad[0], quad[1], quad[2], quad[0], quad[2], quad[3]]);
+      blender.target.polygons.push(4);
+    }
+  }
+  let has_normals = blender.has_normals;
+  if has_normals { smooth_normals(&blender.target, 180.0) } else { blender.target }
+}
+
+/// An edge collapse waiting in the queue, the cheapest comes out first.
+struct Collapse {
+  cost: f32,
+  from: u32,
+  to: u32,
+  /// The versions of both positions when queued, a collapse next to them since makes it stale.
+  versions: (u32, u32),
+}
+
+impl PartialEq for Collapse {
+  fn eq(&self, other: &Self) -> bool {
+    self.cmp(other) == Ordering::Equal
+  }
+}
+
+impl Eq for Collapse {}
+
+impl PartialOrd for Collapse {
+  fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+    Some(self.cmp(other))
+  }
+}
+
+impl Ord for Collapse {
+  fn cmp(&self, other: &Self) -> Ordering {
+    other.cost.total_cmp(&self.cost)
+  }
+}
+
+/// Collapses edges by the quadric error of their planes until the triangle count is down to the target or
+/// nothing else can go. A position moves onto a neighbour, so the kept vertices don't move. Positions on borders,
+/// on UV seams or hard edges - those with more than one vertex - stay, so seams keep matching on both sides.
+pub fn decimate(model: &MeshModel, target: usize) -> MeshModel {
+  let (ids, positions) = position_ids(model);
+  let mut triangles: Vec<[u32; 3]> = model.indices.chunks_exact(3).map(|t| [t[0], t[1], t[2]]).collect();
+  let mut alive = vec![true; triangles.len()];
+  let mut around: Vec<Vec<usize>> = vec![Vec::new(); positions.len()];
+  let mut quadrics = vec![glm::Mat4::zeros(); positions.len()];
+  let mut vertices_at: Vec<HashSet<u32>> = vec![HashSet::new(); positions.len()];
+  for (index, triangle) in triangles.iter().enumerate() {
+    let normal = face_normal(&model.vertices, triangle);
+    let area = normal.norm() * 0.5;
+    let plane_quadric = normal.try_normalize(1e-12).map_or(glm::Mat4::zeros(), |n| {
+      let plane = n.push(-glm::dot(&n, &model.vertices[triangle[0] as usize]));
+      plane * plane.transpose() * area
+    });
+    for vertex in triangle {
+      let p = ids[*vertex as usize] as usize;
+      around[p].push(index);
+      quadrics[p] += plane_quadric;
+      vertices_at[p].insert(*vertex);
+    }
+  }
+  let faces: Vec<Vec<u32>> = triangles.iter().map(|t| t.to_vec()).collect();
+  let edges = position_edges(&faces, &ids);
+  let mut locked: Vec<bool> = vertices_at.iter().map(|v| v.len() > 1).collect();
+  for ((a, b), edge_faces) in edges.iter() {
+    if edge_faces.len() != 2 { locked[*a as usize] = true; locked[*b as usize] = true; }
+  }
+  let position_of = |triangle: &[u32; 3]| triangle.map(|v| ids[v as usize]);
+  let cost = |quadrics: &[glm::Mat4], from: u32, to: u32| {
+    let point = positions[to as usize].push(1.0);
+    glm::dot(&point, &((quadrics[from as usize] + quadrics[to as usize]) * point))
+  };
+  let mut versions = vec![0u32; positions.len()];
+  let mut queue = BinaryHeap::new();
+  for (a, b) in edges.keys() {
+    for (from, to) in [(*a, *b), (*b, *a)] {
+      if !locked[from as usize] { queue.push(Collapse { cost: cost(&quadrics, from, to), from, to, versions: (0, 0) }); }
+    }
+  }
+  let mut removed = vec![false; positions.len()];
+  let mut count = triangles.len();
+  while count > target {
+    let Some(collapse) = queue.pop() else { break; };
+    let (from, to) = (collapse.from as usize, collapse.to as usize);
+    if removed[from] || removed[to] || collapse.versions != (versions[from], versions[to]) { continue; }
+    let shared: Vec<usize> = around[from].iter().copied().filter(|t| alive[*t] && position_of(&triangles[*t]).contains(&collapse.to)).collect();
+    // an edge between two triangles whose neighbourhoods only meet at them, or the surface would fold
+    let neighbours = |p: usize| -> HashSet<u32> {
+      around[p].iter().filter(|t| alive[**t]).flat_map(|t| position_of(&triangles[*t])).filter(|n| *n as usize != p).collect()
+    };
+    if shared.len() != 2 || neighbours(from).intersection(&neighbours(to)).count() != 2 { continue; }
+    // the vertex the corners of the removed position take, the same on both sides of the edge
+    let target_vertex = |t: usize| triangles[t].iter().copied().find(|v| ids[*v as usize] == collapse.to);
+    let (Some(vertex), Some(other)) = (target_vertex(shared[0]), target_vertex(shared[1])) else { continue; };
+    if vertex != other { continue; }
+    let flips = around[from].iter().filter(|t| alive[**t] && !shared.contains(t)).any(|t| {
+      let before = face_normal(&model.vertices, &triangles[*t]);
+      let moved = triangles[*t].map(|v| if ids[v as usize] == collapse.from { vertex } else { v });
+      let after = face_normal(&model.vertices, &moved);
+      after.norm() < 1e-12 || glm::dot(&before, &after) <= 0.0
+    });
+    if flips { continue; }
+    for t in shared {
+      alive[t] = false;
+      count -= 1;
+    }
+    let moving: Vec<usize> = around[from].iter().copied().filter(|t| alive[*t]).collect();
+    for t in moving {
+      for corner in triangles[t].iter_mut() {
+        if ids[*corner as usize] == collapse.from { *corner = vertex; }
+      }
+      around[to].push(t);
+    }
+    around[to].retain(|t| alive[*t]);
+    let quadric = quadrics[from];
+    quadrics[to] += quadric;
+    removed[from] = true;
+    versions[to] += 1;
+    let neighbours: HashSet<u32> = around[to].iter().flat_map(|t| position_of(&triangles[*t])).filter(|n| *n != collapse.to).collect();
+    for neighbour in neighbours {
+      for (from, to) in [(neighbour, collapse.to), (collapse.to, neighbour)] {
+        if !locked[from as usize] {
+          queue.push(Collapse { cost: cost(&quadrics, from, to), from, to, versions: (versions[from as usize], versions[to as usize]) });
+        }
+      }
+    }
+  }
+  let mut blender = Blender::new(model);
+  let mut remap: HashMap<u32, u32> = HashMap::new();
+  for (triangle, _) in triangles.iter().zip(alive.iter()).filter(|(_, alive)| **alive) {
+    for vertex in triangle {
+      let index = *remap.entry(*vertex).or_insert_with(|| blender.copy(*vertex));
+      blender.target.indices.push(index);
+    }
+  }
+  blender.target
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::meshes::mesh_analysis;
+  use crate::rendering::primitives::Primitive;
+
+  /// A unit cube of quads sharing its 8 corners.
+  fn cube() -> MeshModel {
+    let corner = |i: u32| glm::vec3((i & 1) as f32, ((i >> 1) & 1) as f32, ((i >> 2) & 1) as f32) * 2.0 - glm::vec3(1.0, 1.0, 1.0);
+    let quads = [[0, 2, 3, 1], [4, 5, 7, 6], [0, 1, 5, 4], [2, 6, 7, 3], [0, 4, 6, 2], [1, 3, 7, 5]];
+    MeshModel {
+      vertices: (0..8).map(corner).collect(),
+      indices: quads.iter().flat_map(|q| [q[0], q[1], q[2], q[0], q[2], q[3]]).collect(),
+      polygons: vec![4; 6],
+      ..Default::default()
+    }
+  }
+
+  #[test]
+  fn flat_and_smooth_normals() {
+    let flat = flat_normals(&cube());
+    assert_eq!((flat.vertices.len(), flat.polygons.len()), (36, 6));
+    assert_eq!(flat.normals[0], glm::vec3(0.0, 0.0, -1.0));
+
+    // the faces of a cube meet at 90 degrees
+    let hard = smooth_normals(&cube(), 30.0);
+    assert_eq!(hard.vertices.len(), 24);
+    assert!(hard.normals.iter().all(|n| n.abs().max() == 1.0));
+    let soft = smooth_normals(&cube(), 100.0);
+    assert_eq!(soft.vertices.len(), 8);
+    let corner = soft.vertices.iter().position(|v| *v == glm::vec3(1.0, 1.0, 1.0)).unwrap();
+    assert!((soft.normals[corner] - glm::vec3(1.0, 1.0, 1.0).normalize()).norm() < 1e-6);
+  }
+
+  #[test]
+  fn welding_keeps_uv_seams() {
+    let split = flat_normals(&cube());
+    let welded = weld(&MeshModel { normals: Vec::new(), ..split.clone() }, 1e-4);
+    assert_eq!((welded.vertices.len(), welded.count_triangles(), welded.polygons.len()), (8, 12, 6));
+    // the same position with other texture coordinates stays apart
+    let seam = MeshModel { texture_coordinates: (0..36).map(|i| glm::vec2((i / 6) as f32, 0.0)).collect(), normals: Vec::new(), ..split };
+    assert_eq!(weld(&seam, 1e-4).vertices.len(), 6 * 4);
+    // triangles that collapse are gone
+    let tiny = MeshModel { vertices: vec![glm::vec3(0.0, 0.0, 0.0), glm::vec3(1e-6, 0.0, 0.0), glm::vec3(0.0, 1.0, 0.0)], indices: vec![0, 1, 2], ..Default::default() };
+    assert!(weld(&tiny, 1e-3).indices.is_empty());
+  }
+
+  #[test]
+  fn subdivision_keeps_closed_shapes_closed() {
+    let cube = catmull_clark(&cube());
+    assert_eq!((cube.polygons.len(), cube.count_triangles()), (24, 48));
+    assert!(mesh_analysis::analyze(&cube).is_watertight());
+    // a corner of a cube moves to 5/9 of the way out
+    assert!(cube.vertices.iter().any(|v| (v - glm::vec3(5.0, 5.0, 5.0) / 9.0).norm() < 1e-6));
+
+    let sphere = Primitive::Sphere { radius: 1.0, segments: 8, rings: 4 }.mesh();
+    let smooth = loop_subdivision(&sphere);
+    assert_eq!(smooth.count_triangles(), sphere.count_triangles() * 4);
+    let report = mesh_analysis::analyze(&smooth);
+    assert!(report.is_watertight() && report.flipped_edges == 0);
+    // the seam of the texture is still split
+    assert!(smooth.vertices.len() > mesh_analysis::analyze(&smooth).vertices);
+
+    // a flat border stays flat
+    let plane = catmull_clark(&Primitive::Plane { size: 2.0, segments: 2 }.mesh());
+    assert!(plane.vertices.iter().all(|v| v.y == 0.0));
+    assert_eq!(mesh_analysis::analyze(&plane).boundary_edges, 16);
+  }
+
+  #[test]
+  fn decimation_keeps_borders_and_seams() {
+    let sphere = Primitive::Sphere { radius: 1.0, segments: 32, rings: 16 }.mesh();
+    let decimated = decimate(&sphere, 200);
+    assert!(decimated.count_triangles() <= 200);
+    let report = mesh_analysis::analyze(&decimated);
+    assert!(report.is_watertight() && report.flipped_edges == 0);
+    // no triangle reaches across the seam of the texture
+    for triangle in decimated.indices.chunks_exact(3) {
+      let u: Vec<f32> = triangle.iter().map(|i| decimated.texture_coordinates[*i as usize].x).collect();
+      assert!(u.iter().fold(0.0f32, |a, b| a.max(*b)) - u.iter().fold(1.0f32, |a, b| a.min(*b)) < 0.5);
+    }
+
+    let plane = Primitive::Plane { size: 2.0, segments: 8 }.mesh();
+    let decimated = decimate(&plane, 10);
+    assert!(decimated.count_triangles() < plane.count_triangles());
+    assert_eq!(mesh_analysis::analyze(&decimated).boundary_edges, 32);
+  }
+}
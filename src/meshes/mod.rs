@@ -0,0 +1,7 @@
+pub mod animation;
+pub mod mesh_analysis;
+pub mod mesh_model;
+pub mod mesh_processing;
+pub mod model_export;
+pub mod model_import;
+pub mod scene;
\ No newline at end of file
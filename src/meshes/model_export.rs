@@ -0,0 +1,53 @@
+use std::error::Error;
+use std::path::Path;
+use crate::meshes::model_import::ModelUnit;
+use crate::meshes::scene::{Scene, SCENE_FILE_EXTENSIONS};
+use crate::parsers::{gltf_exporter, obj_exporter, ply_exporter, stl_exporter};
+
+#[derive(Clone, Debug)]
+pub struct ExportOptions {
+  /// The unit of STL, PLY and OBJ files, glTF is always in meters.
+  pub unit: ModelUnit,
+  /// Binary STL and PLY instead of text.
+  pub binary: bool,
+  /// OBJ models placed in the scene, otherwise they keep their own space and the transform is written as a comment.
+  pub bake_transforms: bool,
+  /// OBJ faces as triangles instead of the polygons of the imported files.
+  pub triangulate: bool,
+  /// Copies the textures of OBJ models next to the MTL file instead of referring to them where they are.
+  pub copy_textures: bool,
+}
+
+impl Default for ExportOptions {
+  fn default() -> Self {
+    Self {
+      unit: ModelUnit::Meters,
+      binary: true,
+      bake_transforms: true,
+      triangulate: false,
+      copy_textures: false,
+    }
+  }
+}
+
+/// Writes the scene in the format of the file extension.
+pub fn export(scene: &Scene, file: &Path, options: &ExportOptions) -> Result<(), Box<dyn Error>> {
+  match extension(file).as_str() {
+    "gltf" | "glb" => gltf_exporter::export(scene, file),
+    "stl" => stl_exporter::export(scene, file, options),
+    "ply" => ply_exporter::export(scene, file, options),
+    "obj" => obj_exporter::export(scene, file, options),
+    other => Err(format!("Cannot export to .{} files.", other).into()),
+  }
+}
+
+/// Saves the whole scene, with the animations and the timeline, to be opened again.
+pub fn save_scene(scene: &Scene, file: &Path) -> Result<(), Box<dyn Error>> {
+  if !SCENE_FILE_EXTENSIONS.contains(&extension(file).as_str()) { return Err("Scenes are saved as glTF, use .gltf or .glb.".into()); }
+  gltf_exporter::export(scene, file)
+}
+
+/// The lowercase extension of the file, which picks the format.
+pub fn extension(file: &Path) -> String {
+  file.extension().map(|e| e.to_string_lossy().to_lowercase()).unwrap_or_default()
+}
\ No newline at end of file
@@ -0,0 +1,387 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use std::time::Instant;
+use nalgebra_glm as glm;
+use strum_macros::{AsRefStr, EnumIter};
+use crate::do_log;
+use crate::meshes::mesh_model::{MeshModel, ModelTransform};
+use crate::meshes::scene::Scene;
+use crate::parsers::{gltf_parser, obj_parser, ply_parser, stl_parser};
+use crate::settings::kuplung_logger;
+
+/// The axis pointing up in the imported file, converted to Kuplung's Y up.
+#[derive(Clone, Copy, Debug, PartialEq, EnumIter, AsRefStr)]
+pub enum UpAxis {
+  Y,
+  Z,
+  X,
+}
+
+impl UpAxis {
+  fn convert(self, v: &glm::Vec3) -> glm::Vec3 {
+    match self {
+      Self::Y => *v,
+      Self::Z => glm::vec3(v.x, v.z, -v.y),
+      Self::X => glm::vec3(-v.y, v.x, v.z),
+    }
+  }
+
+  fn matrix(self) -> glm::Mat4 {
+    let column = |axis: glm::Vec3| self.convert(&axis).push(0.0);
+    glm::Mat4::from_columns(&[column(glm::Vec3::x()), column(glm::Vec3::y()), column(glm::Vec3::z()), glm::vec4(0.0, 0.0, 0.0, 1.0)])
+  }
+}
+
+/// The length unit of a model file. The Viewer works in meters, like glTF.
+#[derive(Clone, Copy, Debug, PartialEq, EnumIter, AsRefStr)]
+pub enum ModelUnit {
+  Meters,
+  Centimeters,
+  Millimeters,
+  Inches,
+  Feet,
+}
+
+impl ModelUnit {
+  pub fn meters(self) -> f32 {
+    match self {
+      Self::Meters => 1.0,
+      Self::Centimeters => 0.01,
+      Self::Millimeters => 0.001,
+      Self::Inches => 0.0254,
+      Self::Feet => 0.3048,
+    }
+  }
+
+  /// What files of this kind are usually in - STL has no units and comes from CAD tools that work in millimeters.
+  pub fn for_file(file: &str) -> Self {
+    let is_stl = Path::new(file).extension().is_some_and(|e| e.eq_ignore_ascii_case("stl"));
+    if is_stl { Self::Millimeters } else { Self::Meters }
+  }
+}
+
+#[derive(Clone, Debug)]
+pub struct ImportOptions {
+  pub scale: f32,
+  pub unit: ModelUnit,
+  pub up_axis: UpAxis,
+  pub flip_uvs: bool,
+  pub recompute_normals: bool,
+  pub merge_by_material: bool,
+}
+
+impl Default for ImportOptions {
+  fn default() -> Self {
+    Self {
+      scale: 1.0,
+      unit: ModelUnit::Meters,
+      up_axis: UpAxis::Y,
+      flip_uvs: false,
+      recompute_normals: false,
+      merge_by_material: false,
+    }
+  }
+}
+
+/// Shared between the import thread and the UI - what is being done, how far it is and whether to stop.
+#[derive(Default)]
+pub struct ImportProgress {
+  stage: Mutex<String>,
+  fraction: AtomicU32,
+  cancelled: AtomicBool,
+}
+
+impl ImportProgress {
+  pub fn set_stage(&self, stage: &str) {
+    *self.stage.lock().unwrap() = stage.to_string();
+    self.set_fraction(0.0);
+  }
+
+  pub fn set_fraction(&self, fraction: f32) {
+    self.fraction.store(fraction.clamp(0.0, 1.0).to_bits(), Ordering::Relaxed);
+  }
+
+  pub fn stage(&self) -> String {
+    self.stage.lock().unwrap().clone()
+  }
+
+  pub fn fraction(&self) -> f32 {
+    f32::from_bits(self.fraction.load(Ordering::Relaxed))
+  }
+
+  pub fn cancel(&self) {
+    self.cancelled.store(true, Ordering::Relaxed);
+  }
+
+  /// For the parsers to bail out between chunks of work.
+  pub fn check_cancelled(&self) -> Result<(), Box<dyn Error>> {
+    if self.cancelled.load(Ordering::Relaxed) { Err("Import cancelled.".into()) } else { Ok(()) }
+  }
+}
+
+/// A model file being parsed on its own thread. The scene comes out of `poll` once it's done, ready for the GL upload.
+pub struct ModelImportJob {
+  pub file: String,
+  pub started: Instant,
+  progress: Arc<ImportProgress>,
+  handle: Option<JoinHandle<Result<Scene, String>>>,
+}
+
+impl ModelImportJob {
+  /// Imports the file on a thread of its own - fails when the thread cannot be started.
+  pub fn start(file: &str, options: &ImportOptions) -> Result<Self, String> {
+    do_log!("[Kuplung] [Import] Importing {} with {:?}...", file, options);
+    let progress = Arc::new(ImportProgress::default());
+    let handle = {
+      let (file, options, progress) = (file.to_string(), options.clone(), progress.clone());
+      std::thread::Builder::new()
+        .name("kuplung-import".to_string())
+        .spawn(move || import(Path::new(&file), &options, &progress).map_err(|e| e.to_string()))
+    };
+    let handle = handle
+      .map_err(|e| format!("Cannot import {} - the import thread cannot be started: {}", file, e))
+      .inspect_err(|e| do_log!("[Kuplung] [Import] {}", e))?;
+    Ok(Self { file: file.to_string(), started: Instant::now(), progress, handle: Some(handle) })
+  }
+
+  pub fn progress(&self) -> &ImportProgress {
+    &self.progress
+  }
+
+  pub fn cancel(&self) {
+    self.progress.cancel();
+  }
+
+  /// The imported scene or the error, once the thread finished.
+  pub fn poll(&mut self) -> Option<Result<Scene, String>> {
+    if self.handle.as_ref().is_some_and(|h| !h.is_finished()) { return None; }
+    let result = match self.handle.take()?.join() {
+      Ok(result) => result,
+      Err(_) => Err("The import thread crashed.".to_string()),
+    };
+    match &result {
+      Ok(scene) => do_log!("[Kuplung] [Import] Imported {} meshes, {} cameras and {} lights from {} in {:.2}s.", scene.models.len(), scene.cameras.len(), scene.lights.len(), self.file, self.started.elapsed().as_secs_f32()),
+      Err(e) => do_log!("[Kuplung] [Import] Cannot import {}: {}", self.file, e),
+    }
+    Some(result)
+  }
+}
+
+fn import(file: &Path, options: &ImportOptions, progress: &ImportProgress) -> Result<Scene, Box<dyn Error>> {
+  let extension = file.extension().map(|e| e.to_string_lossy().to_lowercase()).unwrap_or_default();
+  let mut scene = match extension.as_str() {
+    "obj" => Scene { models: obj_parser::parse(file, progress)?, ..Default::default() },
+    "gltf" | "glb" => gltf_parser::parse(file, progress)?,
+    "stl" => Scene { models: stl_parser::parse(file, progress)?, ..Default::default() },
+    "ply" => Scene { models: ply_parser::parse(file, progress)?, ..Default::default() },
+    _ => return Err(format!("Cannot import {} - .{} files are not supported.", file.display(), extension).into()),
+  };
+  load_textures(&mut scene.models, progress)?;
+  if options.merge_by_material { scene.models = merge_by_material(scene.models); }
+  progress.set_stage("Applying options");
+  let count = scene.models.len();
+  for (index, model) in scene.models.iter_mut().enumerate() {
+    progress.check_cancelled()?;
+    apply_options(model, options);
+    progress.set_fraction((index + 1) as f32 / count as f32);
+  }
+  let conversion = |v: &glm::Vec3| options.up_axis.convert(v) * options.scale * options.unit.meters();
+  for camera in scene.cameras.iter_mut() {
+    camera.position = conversion(&camera.position);
+    camera.direction = options.up_axis.convert(&camera.direction);
+  }
+  for light in scene.lights.iter_mut() {
+    light.position = conversion(&light.position);
+    light.direction = options.up_axis.convert(&light.direction);
+  }
+  Ok(scene)
+}
+
+/// Decodes the diffuse textures, each image once - from the file or from the bytes stored in the model file.
+fn load_textures(models: &mut [MeshModel], progress: &ImportProgress) -> Result<(), Box<dyn Error>> {
+  progress.set_stage("Loading textures");
+  let mut images = HashMap::new();
+  let count = models.len();
+  for (index, model) in models.iter_mut().enumerate() {
+    progress.check_cancelled()?;
+    let texture = &mut model.model_material.texture_diffuse;
+    if texture.use_texture {
+      let key = match &texture.encoded {
+        Some(encoded) => format!("embedded:{:p}", Arc::as_ptr(encoded)),
+        None => texture.image.clone(),
+      };
+      let pixels = images.entry(key).or_insert_with(|| {
+        let decoded = match &texture.encoded {
+          Some(encoded) => image::load_from_memory(encoded),
+          None => image::open(&texture.image),
+        };
+        match decoded {
+          Ok(image) => Some(Arc::new(image.to_rgba8())),
+          Err(e) => {
+            do_log!("[Kuplung] [Import] Cannot load texture {}: {}", texture.filename, e);
+            None
+          }
+        }
+      });
+      if let Some(pixels) = pixels {
+        texture.width = pixels.width();
+        texture.height = pixels.height();
+        texture.pixels = Some(pixels.clone());
+      }
+    }
+    progress.set_fraction((index + 1) as f32 / count as f32);
+  }
+  Ok(())
+}
+
+/// One mesh per material, in the order the materials first appear.
+fn merge_by_material(models: Vec<MeshModel>) -> Vec<MeshModel> {
+  let mut merged: Vec<MeshModel> = Vec::new();
+  for model in models {
+    let Some(target) = merged.iter_mut().find(|m| m.model_material.material_title == model.model_material.material_title) else {
+      merged.push(MeshModel { model_title: model.model_material.material_title.clone(), ..model });
+      continue;
+    };
+    // the meshes keep their placement in the vertices, the merged one has no transform of its own
+    if target.transform != ModelTransform::default() { *target = target.baked(1.0); }
+    let model = if model.transform != ModelTransform::default() { model.baked(1.0) } else { model };
+    // meshes without vertex colors are white next to ones with them
+    let has_colors = !target.colors.is_empty() || !model.colors.is_empty();
+    if has_colors {
+      target.colors.resize(target.vertices.len(), glm::vec4(1.0, 1.0, 1.0, 1.0));
+      let mut colors = model.colors;
+      colors.resize(model.vertices.len(), glm::vec4(1.0, 1.0, 1.0, 1.0));
+      target.colors.extend(colors);
+    }
+    let offset = target.vertices.len() as u32;
+    target.vertices.extend(model.vertices);
+    target.texture_coordinates.extend(model.texture_coordinates);
+    target.normals.extend(model.normals);
+    if target.tangents.is_empty() || model.tangents.is_empty() { target.tangents.clear(); } else { target.tangents.extend(model.tangents); }
+    target.indices.extend(model.indices.iter().map(|i| i + offset));
+    // the faces stay known only when both meshes know theirs
+    if target.polygons.is_empty() || model.polygons.is_empty() { target.polygons.clear(); } else { target.polygons.extend(model.polygons); }
+  }
+  merged
+}
+
+fn apply_options(model: &mut MeshModel, options: &ImportOptions) {
+  // the conversion goes around the placement too, so the model ends up where the converted scene has it
+  if model.transform != ModelTransform::default() {
+    let scale = options.scale * options.unit.meters();
+    let conversion = glm::scaling(&glm::vec3(scale, scale, scale)) * options.up_axis.matrix();
+    let inverse = glm::inverse(&conversion);
+    model.transform = ModelTransform::from_matrix(&(conversion * model.transform.matrix() * inverse));
+  }
+  for vertex in model.vertices.iter_mut() {
+    *vertex = options.up_axis.convert(vertex) * options.scale * options.unit.meters();
+  }
+  for normal in model.normals.iter_mut() {
+    *normal = options.up_axis.convert(normal);
+  }
+  if options.flip_uvs {
+    for uv in model.texture_coordinates.iter_mut() { uv.y = 1.0 - uv.y; }
+  }
+  if options.recompute_normals || model.normals.len() != model.vertices.len() { recompute_normals(model); }
+}
+
+/// Smooth normals, each triangle weighted by its area.
+fn recompute_normals(model: &mut MeshModel) {
+  let mut normals = vec![glm::Vec3::zeros(); model.vertices.len()];
+  for triangle in model.indices.chunks_exact(3) {
+    let [a, b, c] = [triangle[0] as usize, triangle[1] as usize, triangle[2] as usize];
+    let normal = glm::cross(&(model.vertices[b] - model.vertices[a]), &(model.vertices[c] - model.vertices[a]));
+    for index in [a, b, c] { normals[index] += normal; }
+  }
+  model.normals = normals.into_iter().map(|n| if n.norm() > 0.0 { n.normalize() } else { glm::vec3(0.0, 1.0, 0.0) }).collect();
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::meshes::animation::{AnimatedProperty, Timeline};
+  use crate::meshes::mesh_model::MeshModelMaterial;
+  use crate::meshes::model_export;
+
+  fn triangle(material: &str) -> MeshModel {
+    MeshModel {
+      model_material: MeshModelMaterial { material_title: material.to_string(), ..Default::default() },
+      vertices: vec![glm::vec3(0.0, 0.0, 0.0), glm::vec3(1.0, 0.0, 0.0), glm::vec3(0.0, 0.0, -1.0)],
+      texture_coordinates: vec![glm::vec2(0.0, 0.0), glm::vec2(1.0, 0.0), glm::vec2(0.0, 1.0)],
+      indices: vec![0, 1, 2],
+      ..Default::default()
+    }
+  }
+
+  #[test]
+  fn options_convert_axis_scale_uvs_and_normals() {
+    let mut model = triangle("A");
+    apply_options(&mut model, &ImportOptions { scale: 2.0, up_axis: UpAxis::Z, flip_uvs: true, ..Default::default() });
+    assert_eq!(model.vertices[2], glm::vec3(0.0, -2.0, 0.0));
+    assert_eq!(model.texture_coordinates[2], glm::vec2(0.0, 0.0));
+    // the triangle faced +Y before the conversion, Z up makes that -Z
+    assert_eq!(model.normals, vec![glm::vec3(0.0, 0.0, -1.0); 3]);
+  }
+
+  #[test]
+  fn saved_scenes_open_with_their_animations() {
+    let mut model = MeshModel { model_title: "Spinner".to_string(), normals: vec![glm::vec3(0.0, 1.0, 0.0); 3], ..triangle("A") };
+    model.animation.set_animate(AnimatedProperty::RotateY, true);
+    model.animation.set_keyframe(AnimatedProperty::PositionX, 1.5, 2.0);
+    let scene = Scene { models: vec![model.clone()], timeline: Timeline { duration: 3.0, looping: false, ..Default::default() }, ..Default::default() };
+    let folder = std::env::temp_dir().join(format!("kuplung_scene_{}", std::process::id()));
+    std::fs::create_dir_all(&folder).unwrap();
+    for name in ["scene.glb", "scene.gltf"] {
+      let file = folder.join(name);
+      model_export::save_scene(&scene, &file).unwrap();
+      let mut job = ModelImportJob::start(&file.display().to_string(), &ImportOptions::default()).unwrap();
+      let opened = loop {
+        if let Some(result) = job.poll() { break result.unwrap(); }
+        std::thread::sleep(std::time::Duration::from_millis(5));
+      };
+      assert_eq!(opened.models.len(), 1, "{}", name);
+      assert_eq!(opened.models[0].model_title, "Spinner");
+      assert_eq!(opened.models[0].animation, model.animation, "{}", name);
+      assert_eq!((opened.timeline.duration, opened.timeline.looping), (3.0, false), "{}", name);
+    }
+    assert!(model_export::save_scene(&scene, &folder.join("scene.obj")).is_err());
+    std::fs::remove_dir_all(&folder).unwrap();
+  }
+
+  #[test]
+  fn merging_joins_meshes_with_the_same_material() {
+    let merged = merge_by_material(vec![triangle("A"), triangle("B"), triangle("A")]);
+    assert_eq!(merged.iter().map(|m| m.model_title.as_str()).collect::<Vec<_>>(), vec!["A", "B"]);
+    assert_eq!(merged[0].vertices.len(), 6);
+    assert_eq!(merged[0].indices, vec![0, 1, 2, 3, 4, 5]);
+  }
+
+  #[test]
+  fn merging_keeps_the_placement_of_the_nodes() {
+    // the same triangle in two nodes moved apart, sharing the material
+    let gltf = r#"{
+      "asset": { "version": "2.0" },
+      "scenes": [{ "nodes": [0, 1] }],
+      "nodes": [{ "name": "Right", "mesh": 0, "translation": [5, 0, 0] }, { "name": "Left", "mesh": 0, "translation": [-5, 0, 0] }],
+      "meshes": [{ "primitives": [{ "attributes": { "POSITION": 0 }, "indices": 1, "material": 0 }] }],
+      "materials": [{ "name": "Shared" }],
+      "accessors": [
+        { "bufferView": 0, "componentType": 5126, "count": 3, "type": "VEC3", "min": [0, 0, 0], "max": [1, 1, 0] },
+        { "bufferView": 1, "componentType": 5123, "count": 3, "type": "SCALAR" }
+      ],
+      "bufferViews": [{ "buffer": 0, "byteLength": 36 }, { "buffer": 0, "byteOffset": 36, "byteLength": 6 }],
+      "buffers": [{ "byteLength": 44, "uri": "data:application/octet-stream;base64,AAAAAAAAAAAAAAAAAACAPwAAAAAAAAAAAAAAAAAAgD8AAAAAAAABAAIAAAA=" }]
+    }"#;
+    let scene = gltf_parser::parse_bytes(gltf.as_bytes(), Path::new("/nowhere"), &ImportProgress::default()).unwrap();
+    assert_eq!(scene.models.len(), 2);
+    let merged = merge_by_material(scene.models);
+    assert_eq!(merged.len(), 1);
+    assert_eq!(merged[0].transform, ModelTransform::default());
+    assert_eq!(merged[0].vertices[..3], [glm::vec3(5.0, 0.0, 0.0), glm::vec3(6.0, 0.0, 0.0), glm::vec3(5.0, 1.0, 0.0)]);
+    assert_eq!(merged[0].vertices[3..], [glm::vec3(-5.0, 0.0, 0.0), glm::vec3(-4.0, 0.0, 0.0), glm::vec3(-5.0, 1.0, 0.0)]);
+  }
+}
@@ -0,0 +1,52 @@
+use nalgebra_glm as glm;
+use strum_macros::AsRefStr;
+use crate::meshes::animation::Timeline;
+use crate::meshes::mesh_model::MeshModel;
+
+#[derive(Clone, Copy, Debug, PartialEq, AsRefStr)]
+pub enum LightType {
+  Directional,
+  Point,
+  Spot,
+}
+
+/// A light brought in with a model - glTF punctual lights. Angles are in radians.
+#[derive(Clone, Debug)]
+pub struct SceneLight {
+  pub name: String,
+  pub light_type: LightType,
+  pub color: glm::Vec3,
+  pub intensity: f32,
+  /// 0 is no limit.
+  pub range: f32,
+  pub position: glm::Vec3,
+  pub direction: glm::Vec3,
+  pub inner_cone_angle: f32,
+  pub outer_cone_angle: f32,
+}
+
+/// A perspective camera brought in with a model. The field of view is vertical, in radians.
+#[derive(Clone, Debug)]
+pub struct SceneCamera {
+  pub name: String,
+  pub position: glm::Vec3,
+  pub direction: glm::Vec3,
+  pub yfov: f32,
+  pub znear: f32,
+  /// 0 is an infinite projection.
+  pub zfar: f32,
+}
+
+/// The files a scene is opened from and saved to - glTF, with the animations in the extras. The Kuplung .kuplung
+/// scenes are not read yet.
+pub const SCENE_FILE_EXTENSIONS: [&str; 2] = ["gltf", "glb"];
+
+/// What an import brings into the Viewer and an export writes out. Models are flat - nested nodes
+/// are resolved to the transform of each model in the scene.
+#[derive(Clone, Debug, Default)]
+pub struct Scene {
+  pub models: Vec<MeshModel>,
+  pub cameras: Vec<SceneCamera>,
+  pub lights: Vec<SceneLight>,
+  pub timeline: Timeline,
+}
@@ -0,0 +1,400 @@
+use std::collections::BTreeMap;
+use std::error::Error;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+// The parts of the glTF 2.0 JSON that Kuplung reads and writes, and the GLB container around it.
+// https://registry.khronos.org/glTF/specs/2.0/glTF-2.0.html
+
+pub const GLB_MAGIC: &[u8; 4] = b"glTF";
+const GLB_VERSION: u32 = 2;
+const GLB_CHUNK_JSON: u32 = 0x4E4F534A;
+const GLB_CHUNK_BIN: u32 = 0x004E4942;
+
+pub const COMPONENT_BYTE: u32 = 5120;
+pub const COMPONENT_UNSIGNED_BYTE: u32 = 5121;
+pub const COMPONENT_SHORT: u32 = 5122;
+pub const COMPONENT_UNSIGNED_SHORT: u32 = 5123;
+pub const COMPONENT_UNSIGNED_INT: u32 = 5125;
+pub const COMPONENT_FLOAT: u32 = 5126;
+
+pub const TARGET_ARRAY_BUFFER: u32 = 34962;
+pub const TARGET_ELEMENT_ARRAY_BUFFER: u32 = 34963;
+
+pub const MODE_TRIANGLES: u32 = 4;
+pub const MODE_TRIANGLE_STRIP: u32 = 5;
+pub const MODE_TRIANGLE_FAN: u32 = 6;
+
+pub const KHR_LIGHTS_PUNCTUAL: &str = "KHR_lights_punctual";
+
+/// The keys of the Kuplung settings in the extras of the nodes and the scenes, the rest of the extras is left alone.
+pub const EXTRAS_ANIMATION: &str = "kuplungAnimation";
+pub const EXTRAS_TIMELINE: &str = "kuplungTimeline";
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct GltfDocument {
+  pub asset: GltfAsset,
+  pub scene: Option<usize>,
+  pub scenes: Vec<GltfScene>,
+  pub nodes: Vec<GltfNode>,
+  pub meshes: Vec<GltfMesh>,
+  pub materials: Vec<GltfMaterial>,
+  pub textures: Vec<GltfTexture>,
+  pub images: Vec<GltfImage>,
+  pub accessors: Vec<GltfAccessor>,
+  pub buffer_views: Vec<GltfBufferView>,
+  pub buffers: Vec<GltfBuffer>,
+  pub cameras: Vec<GltfCamera>,
+  pub animations: Vec<GltfAnimation>,
+  pub extensions_used: Vec<String>,
+  pub extensions: Option<GltfExtensions>,
+}
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct GltfAsset {
+  pub version: String,
+  pub generator: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct GltfScene {
+  pub name: Option<String>,
+  pub nodes: Vec<usize>,
+  pub extras: Option<Value>,
+}
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct GltfNode {
+  pub name: Option<String>,
+  pub children: Vec<usize>,
+  pub mesh: Option<usize>,
+  pub camera: Option<usize>,
+  /// Column-major.
+  pub matrix: Option<[f32; 16]>,
+  pub translation: Option<[f32; 3]>,
+  /// Quaternion as x, y, z, w.
+  pub rotation: Option<[f32; 4]>,
+  pub scale: Option<[f32; 3]>,
+  pub extensions: Option<GltfNodeExtensions>,
+  pub extras: Option<Value>,
+}
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct GltfNodeExtensions {
+  #[serde(rename = "KHR_lights_punctual")]
+  pub lights_punctual: Option<GltfLightReference>,
+}
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct GltfLightReference {
+  pub light: usize,
+}
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct GltfExtensions {
+  #[serde(rename = "KHR_lights_punctual")]
+  pub lights_punctual: Option<GltfLights>,
+}
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct GltfLights {
+  pub lights: Vec<GltfLight>,
+}
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct GltfLight {
+  pub name: Option<String>,
+  #[serde(rename = "type")]
+  pub light_type: String,
+  pub color: Option<[f32; 3]>,
+  pub intensity: Option<f32>,
+  pub range: Option<f32>,
+  pub spot: Option<GltfSpot>,
+}
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct GltfSpot {
+  pub inner_cone_angle: Option<f32>,
+  pub outer_cone_angle: Option<f32>,
+}
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct GltfMesh {
+  pub name: Option<String>,
+  pub primitives: Vec<GltfPrimitive>,
+}
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct GltfPrimitive {
+  pub attributes: BTreeMap<String, usize>,
+  pub indices: Option<usize>,
+  pub material: Option<usize>,
+  pub mode: Option<u32>,
+}
+
+#[derive(Debug, Default, PartialEq, Deserialize, Serialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct GltfMaterial {
+  pub name: Option<String>,
+  pub pbr_metallic_roughness: Option<GltfPbrMetallicRoughness>,
+  pub normal_texture: Option<GltfTextureInfo>,
+  pub occlusion_texture: Option<GltfTextureInfo>,
+  pub emissive_texture: Option<GltfTextureInfo>,
+  pub emissive_factor: Option<[f32; 3]>,
+  pub alpha_mode: Option<String>,
+}
+
+#[derive(Debug, Default, PartialEq, Deserialize, Serialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct GltfPbrMetallicRoughness {
+  pub base_color_factor: Option<[f32; 4]>,
+  pub base_color_texture: Option<GltfTextureInfo>,
+  pub metallic_factor: Option<f32>,
+  pub roughness_factor: Option<f32>,
+  pub metallic_roughness_texture: Option<GltfTextureInfo>,
+}
+
+#[derive(Debug, Default, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct GltfTextureInfo {
+  pub index: usize,
+}
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct GltfTexture {
+  pub source: Option<usize>,
+}
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct GltfImage {
+  pub name: Option<String>,
+  pub uri: Option<String>,
+  pub mime_type: Option<String>,
+  pub buffer_view: Option<usize>,
+}
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct GltfAccessor {
+  pub buffer_view: Option<usize>,
+  pub byte_offset: usize,
+  pub component_type: u32,
+  pub normalized: bool,
+  pub count: usize,
+  #[serde(rename = "type")]
+  pub accessor_type: String,
+  pub min: Option<Vec<f32>>,
+  pub max: Option<Vec<f32>>,
+  pub sparse: Option<Value>,
+}
+
+impl GltfAccessor {
+  pub fn components(&self) -> Option<usize> {
+    match self.accessor_type.as_str() {
+      "SCALAR" => Some(1),
+      "VEC2" => Some(2),
+      "VEC3" => Some(3),
+      "VEC4" => Some(4),
+      "MAT4" => Some(16),
+      _ => None,
+    }
+  }
+}
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct GltfBufferView {
+  pub buffer: usize,
+  pub byte_offset: usize,
+  pub byte_length: usize,
+  pub byte_stride: Option<usize>,
+  pub target: Option<u32>,
+}
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct GltfBuffer {
+  pub uri: Option<String>,
+  pub byte_length: usize,
+}
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct GltfCamera {
+  pub name: Option<String>,
+  #[serde(rename = "type")]
+  pub camera_type: String,
+  pub perspective: Option<GltfPerspective>,
+}
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct GltfPerspective {
+  pub aspect_ratio: Option<f32>,
+  pub yfov: f32,
+  pub znear: f32,
+  pub zfar: Option<f32>,
+}
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct GltfAnimation {
+  pub name: Option<String>,
+  pub channels: Vec<GltfChannel>,
+  pub samplers: Vec<GltfAnimationSampler>,
+}
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct GltfChannel {
+  pub sampler: usize,
+  pub target: GltfChannelTarget,
+}
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct GltfChannelTarget {
+  pub node: Option<usize>,
+  /// translation, rotation, scale or weights.
+  pub path: String,
+}
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct GltfAnimationSampler {
+  /// The keyframe times in seconds.
+  pub input: usize,
+  pub output: usize,
+  pub interpolation: Option<String>,
+}
+
+/// The JSON of the document, without the unset optional properties and empty arrays (glTF doesn't allow those).
+pub fn to_json(document: &GltfDocument) -> Result<String, Box<dyn Error>> {
+  fn strip(value: Value) -> Option<Value> {
+    match value {
+      Value::Null => None,
+      Value::Array(items) if items.is_empty() => None,
+      Value::Array(items) => Some(Value::Array(items.into_iter().filter_map(strip).collect())),
+      Value::Object(fields) => Some(Value::Object(fields.into_iter().filter_map(|(k, v)| strip(v).map(|v| (k, v))).collect())),
+      value => Some(value),
+    }
+  }
+  let value = strip(serde_json::to_value(document)?).unwrap_or(Value::Null);
+  Ok(serde_json::to_string_pretty(&value)?)
+}
+
+/// The document and the binary chunk of a .glb file.
+pub type Glb = (GltfDocument, Option<Vec<u8>>);
+
+pub fn read_glb(bytes: &[u8]) -> Result<Glb, Box<dyn Error>> {
+  let u32_at = |offset: usize| -> Result<u32, Box<dyn Error>> {
+    let word = bytes.get(offset..offset + 4).ok_or("The GLB file is truncated.")?;
+    Ok(u32::from_le_bytes(word.try_into()?))
+  };
+  if bytes.get(0..4) != Some(&GLB_MAGIC[..]) { return Err("Not a GLB file.".into()); }
+  if u32_at(4)? != GLB_VERSION { return Err(format!("GLB version {} is not supported.", u32_at(4)?).into()); }
+  let length = (u32_at(8)? as usize).min(bytes.len());
+  let mut offset = 12;
+  let (mut json, mut bin) = (None, None);
+  while offset + 8 <= length {
+    let (chunk_length, chunk_type) = (u32_at(offset)? as usize, u32_at(offset + 4)?);
+    let chunk = bytes.get(offset + 8..offset + 8 + chunk_length).ok_or("A GLB chunk is truncated.")?;
+    match chunk_type {
+      GLB_CHUNK_JSON => json = Some(chunk),
+      GLB_CHUNK_BIN => bin = Some(chunk.to_vec()),
+      _ => {}
+    }
+    offset += 8 + chunk_length;
+  }
+  let document = serde_json::from_slice(json.ok_or("The GLB file has no JSON chunk.")?)?;
+  Ok((document, bin))
+}
+
+pub fn write_glb(document: &GltfDocument, bin: &[u8]) -> Result<Vec<u8>, Box<dyn Error>> {
+  let mut json = to_json(document)?.into_bytes();
+  while json.len() % 4 != 0 { json.push(b' '); }
+  let bin_length = bin.len().next_multiple_of(4);
+  let length = 12 + 8 + json.len() + if bin.is_empty() { 0 } else { 8 + bin_length };
+  let mut glb = Vec::with_capacity(length);
+  glb.extend_from_slice(GLB_MAGIC);
+  glb.extend_from_slice(&GLB_VERSION.to_le_bytes());
+  glb.extend_from_slice(&(length as u32).to_le_bytes());
+  glb.extend_from_slice(&(json.len() as u32).to_le_bytes());
+  glb.extend_from_slice(&GLB_CHUNK_JSON.to_le_bytes());
+  glb.extend_from_slice(&json);
+  if !bin.is_empty() {
+    glb.extend_from_slice(&(bin_length as u32).to_le_bytes());
+    glb.extend_from_slice(&GLB_CHUNK_BIN.to_le_bytes());
+    glb.extend_from_slice(bin);
+    glb.resize(length, 0);
+  }
+  Ok(glb)
+}
+
+/// The media type and the contents of a `data:` URI.
+pub type DataUri = (String, Vec<u8>);
+
+/// Decodes a `data:` URI with base64 data, None when the URI points to a file.
+pub fn decode_data_uri(uri: &str) -> Option<Result<DataUri, Box<dyn Error>>> {
+  let rest = uri.strip_prefix("data:")?;
+  let Some((header, data)) = rest.split_once(',') else { return Some(Err("Invalid data URI.".into())); };
+  let Some(mime) = header.strip_suffix(";base64") else { return Some(Err("Only base64 data URIs are supported.".into())); };
+  Some(decode_base64(data).map(|bytes| (mime.to_string(), bytes)))
+}
+
+fn decode_base64(data: &str) -> Result<Vec<u8>, Box<dyn Error>> {
+  let value = |c: u8| -> Result<u32, Box<dyn Error>> {
+    Ok(match c {
+      b'A'..=b'Z' => c - b'A',
+      b'a'..=b'z' => c - b'a' + 26,
+      b'0'..=b'9' => c - b'0' + 52,
+      b'+' | b'-' => 62,
+      b'/' | b'_' => 63,
+      _ => return Err(format!("Invalid base64 character '{}'.", c as char).into()),
+    } as u32)
+  };
+  let symbols: Vec<u8> = data.bytes().filter(|c| !c.is_ascii_whitespace() && *c != b'=').collect();
+  let mut bytes = Vec::with_capacity(symbols.len() * 3 / 4);
+  for chunk in symbols.chunks(4) {
+    let mut word = 0;
+    for (i, c) in chunk.iter().enumerate() { word |= value(*c)? << (18 - 6 * i); }
+    let decoded = [(word >> 16) as u8, (word >> 8) as u8, word as u8];
+    bytes.extend_from_slice(&decoded[..chunk.len().saturating_sub(1)]);
+  }
+  Ok(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn glb_round_trip_and_data_uris() {
+    let document = GltfDocument { asset: GltfAsset { version: "2.0".to_string(), generator: None }, ..Default::default() };
+    let json = to_json(&document).unwrap();
+    assert_eq!(serde_json::from_str::<Value>(&json).unwrap(), serde_json::json!({ "asset": { "version": "2.0" } }));
+    let glb = write_glb(&document, &[1, 2, 3]).unwrap();
+    assert_eq!(glb.len() % 4, 0);
+    let (read, bin) = read_glb(&glb).unwrap();
+    assert_eq!(read.asset.version, "2.0");
+    assert_eq!(bin, Some(vec![1, 2, 3, 0]));
+
+    let (mime, bytes) = decode_data_uri("data:application/octet-stream;base64,S3VwbHVuZw==").unwrap().unwrap();
+    assert_eq!((mime.as_str(), bytes.as_slice()), ("application/octet-stream", &b"Kuplung"[..]));
+    assert!(decode_data_uri("model.bin").is_none());
+  }
+}
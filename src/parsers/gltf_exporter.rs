@@ -0,0 +1,414 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::io::Cursor;
+use std::path::Path;
+use std::sync::Arc;
+use image::ImageFormat;
+use nalgebra_glm as glm;
+use serde_json::Value;
+use crate::do_log;
+use crate::meshes::animation::Timeline;
+use crate::meshes::mesh_model::{MeshMaterialTextureImage, MeshModel, MeshModelMaterial, ModelTransform};
+use crate::meshes::scene::{LightType, Scene};
+use crate::parsers::gltf_document::{
+  to_json, write_glb, GltfAccessor, GltfAnimation, GltfAnimationSampler, GltfAsset, GltfBuffer, GltfBufferView, GltfCamera, GltfChannel,
+  GltfChannelTarget, GltfDocument, GltfExtensions, GltfImage, GltfLight, GltfLightReference, GltfLights, GltfMaterial, GltfMesh, GltfNode,
+  GltfNodeExtensions, GltfPbrMetallicRoughness, GltfPerspective, GltfPrimitive, GltfScene, GltfSpot, GltfTexture, GltfTextureInfo,
+  COMPONENT_FLOAT, COMPONENT_UNSIGNED_INT, EXTRAS_ANIMATION, EXTRAS_TIMELINE, KHR_LIGHTS_PUNCTUAL, TARGET_ARRAY_BUFFER,
+  TARGET_ELEMENT_ARRAY_BUFFER,
+};
+use crate::settings::kuplung_logger;
+
+// Writes the scene as glTF 2.0 - a .gltf with the buffer in a .bin next to it, or a single .glb.
+// Every model is a node with its own mesh, textures are stored in the buffer, lights use KHR_lights_punctual.
+// Animations are kept in the extras for Kuplung and their transforms sampled into glTF animations.
+
+/// How often the animated transforms are sampled.
+const ANIMATION_SAMPLES_PER_SECOND: f32 = 30.0;
+
+pub fn export(scene: &Scene, file: &Path) -> Result<(), Box<dyn Error>> {
+  let extension = file.extension().map(|e| e.to_string_lossy().to_lowercase()).unwrap_or_default();
+  match extension.as_str() {
+    "glb" => std::fs::write(file, to_glb(scene)?)?,
+    "gltf" => {
+      let (mut document, bin) = build_document(scene)?;
+      if let Some(buffer) = document.buffers.first_mut() {
+        let bin_name = format!("{}.bin", file.file_stem().map_or("scene".to_string(), |s| s.to_string_lossy().to_string()));
+        std::fs::write(file.with_file_name(&bin_name), &bin)?;
+        buffer.uri = Some(bin_name.replace('%', "%25").replace(' ', "%20"));
+      }
+      std::fs::write(file, to_json(&document)?)?;
+    }
+    _ => return Err(format!("Cannot export to .{} files, use .gltf or .glb.", extension).into()),
+  }
+  do_log!("[Kuplung] [glTF] Exported {} models, {} cameras and {} lights to {}.", scene.models.len(), scene.cameras.len(), scene.lights.len(), file.display());
+  Ok(())
+}
+
+pub fn to_glb(scene: &Scene) -> Result<Vec<u8>, Box<dyn Error>> {
+  let (document, bin) = build_document(scene)?;
+  write_glb(&document, &bin)
+}
+
+fn build_document(scene: &Scene) -> Result<(GltfDocument, Vec<u8>), Box<dyn Error>> {
+  let mut writer = GltfWriter {
+    document: GltfDocument {
+      asset: GltfAsset { version: "2.0".to_string(), generator: Some("Kuplung".to_string()) },
+      scene: Some(0),
+      ..Default::default()
+    },
+    bin: Vec::new(),
+    materials: HashMap::new(),
+    textures: HashMap::new(),
+  };
+  for model in scene.models.iter() {
+    writer.add_model(model)?;
+    writer.add_animation(writer.document.nodes.len() - 1, model, &scene.timeline)?;
+  }
+  for camera in scene.cameras.iter() {
+    writer.document.cameras.push(GltfCamera {
+      name: Some(camera.name.clone()),
+      camera_type: "perspective".to_string(),
+      perspective: Some(GltfPerspective { aspect_ratio: None, yfov: camera.yfov, znear: camera.znear, zfar: (camera.zfar > 0.0).then_some(camera.zfar) }),
+    });
+    writer.document.nodes.push(GltfNode {
+      name: Some(camera.name.clone()),
+      camera: Some(writer.document.cameras.len() - 1),
+      translation: Some(camera.position.into()),
+      rotation: Some(look_rotation(&camera.direction)),
+      ..Default::default()
+    });
+  }
+  if !scene.lights.is_empty() {
+    let lights = scene.lights.iter().map(|light| GltfLight {
+      name: Some(light.name.clone()),
+      light_type: light.light_type.as_ref().to_lowercase(),
+      color: Some(light.color.into()),
+      intensity: Some(light.intensity),
+      range: (light.range > 0.0 && light.light_type != LightType::Directional).then_some(light.range),
+      spot: (light.light_type == LightType::Spot).then_some(GltfSpot { inner_cone_angle: Some(light.inner_cone_angle), outer_cone_angle: Some(light.outer_cone_angle) }),
+    });
+    writer.document.extensions = Some(GltfExtensions { lights_punctual: Some(GltfLights { lights: lights.collect() }) });
+    writer.document.extensions_used.push(KHR_LIGHTS_PUNCTUAL.to_string());
+    for (index, light) in scene.lights.iter().enumerate() {
+      writer.document.nodes.push(GltfNode {
+        name: Some(light.name.clone()),
+        translation: Some(light.position.into()),
+        rotation: Some(look_rotation(&light.direction)),
+        extensions: Some(GltfNodeExtensions { lights_punctual: Some(GltfLightReference { light: index }) }),
+        ..Default::default()
+      });
+    }
+  }
+  let timeline = scene.models.iter().any(|m| !m.animation.is_empty()).then(|| extras(EXTRAS_TIMELINE, &scene.timeline)).transpose()?;
+  writer.document.scenes.push(GltfScene { name: Some("Scene".to_string()), nodes: (0..writer.document.nodes.len()).collect(), extras: timeline });
+  if !writer.bin.is_empty() { writer.document.buffers.push(GltfBuffer { uri: None, byte_length: writer.bin.len() }); }
+  Ok((writer.document, writer.bin))
+}
+
+/// The extras object with a Kuplung setting.
+fn extras<T: serde::Serialize>(key: &str, value: &T) -> Result<Value, Box<dyn Error>> {
+  let mut extras = serde_json::Map::new();
+  extras.insert(key.to_string(), serde_json::to_value(value)?);
+  Ok(Value::Object(extras))
+}
+
+/// The node rotation that turns the -Z a glTF camera and light look along to `direction`.
+fn look_rotation(direction: &glm::Vec3) -> [f32; 4] {
+  let forward = direction.try_normalize(1e-6).unwrap_or(-glm::Vec3::z());
+  let up = if forward.y.abs() > 0.999 { glm::Vec3::z() } else { glm::Vec3::y() };
+  let right = glm::cross(&forward, &up).normalize();
+  let up = glm::cross(&right, &forward);
+  let rotation = glm::mat3(right.x, up.x, -forward.x, right.y, up.y, -forward.y, right.z, up.z, -forward.z);
+  glm::mat3_to_quat(&rotation).coords.into()
+}
+
+/// The image as PNG or JPEG bytes, the only formats glTF allows. Anything else is converted to PNG.
+fn encode_texture(texture: &MeshMaterialTextureImage) -> Result<(&'static str, Vec<u8>), Box<dyn Error>> {
+  let bytes = match &texture.encoded {
+    Some(encoded) => encoded.to_vec(),
+    None => std::fs::read(&texture.image).map_err(|e| format!("Cannot read {}: {}", texture.image, e))?,
+  };
+  Ok(match image::guess_format(&bytes)? {
+    ImageFormat::Png => ("image/png", bytes),
+    ImageFormat::Jpeg => ("image/jpeg", bytes),
+    _ => {
+      let mut png = Vec::new();
+      image::load_from_memory(&bytes)?.write_to(&mut Cursor::new(&mut png), ImageFormat::Png)?;
+      ("image/png", png)
+    }
+  })
+}
+
+struct GltfWriter {
+  document: GltfDocument,
+  bin: Vec<u8>,
+  materials: HashMap<String, usize>,
+  textures: HashMap<String, usize>,
+}
+
+impl GltfWriter {
+  fn add_view(&mut self, bytes: &[u8], target: Option<u32>) -> usize {
+    self.bin.resize(self.bin.len().next_multiple_of(4), 0);
+    self.document.buffer_views.push(GltfBufferView { buffer: 0, byte_offset: self.bin.len(), byte_length: bytes.len(), byte_stride: None, target });
+    self.bin.extend_from_slice(bytes);
+    self.document.buffer_views.len() - 1
+  }
+
+  fn add_floats(&mut self, values: &[f32], accessor_type: &str, components: usize, with_bounds: bool, target: Option<u32>) -> usize {
+    let bytes: Vec<u8> = values.iter().flat_map(|v| v.to_le_bytes()).collect();
+    let view = self.add_view(&bytes, target);
+    let bound = |pick: fn(f32, f32) -> f32| (0..components).map(|c| values.iter().skip(c).step_by(components).copied().reduce(pick).unwrap_or(0.0)).collect();
+    self.document.accessors.push(GltfAccessor {
+      buffer_view: Some(view),
+      component_type: COMPONENT_FLOAT,
+      count: values.len() / components,
+      accessor_type: accessor_type.to_string(),
+      min: with_bounds.then(|| bound(f32::min)),
+      max: with_bounds.then(|| bound(f32::max)),
+      ..Default::default()
+    });
+    self.document.accessors.len() - 1
+  }
+
+  fn add_model(&mut self, model: &MeshModel) -> Result<(), Box<dyn Error>> {
+    let count = model.vertices.len();
+    let mut attributes = std::collections::BTreeMap::new();
+    let positions: Vec<f32> = model.vertices.iter().flat_map(|v| [v.x, v.y, v.z]).collect();
+    attributes.insert("POSITION".to_string(), self.add_floats(&positions, "VEC3", 3, true, Some(TARGET_ARRAY_BUFFER)));
+    if model.normals.len() == count {
+      let normals: Vec<f32> = model.normals.iter().flat_map(|n| [n.x, n.y, n.z]).collect();
+      attributes.insert("NORMAL".to_string(), self.add_floats(&normals, "VEC3", 3, false, Some(TARGET_ARRAY_BUFFER)));
+    }
+    if model.texture_coordinates.len() == count {
+      let uvs: Vec<f32> = model.texture_coordinates.iter().flat_map(|uv| [uv.x, 1.0 - uv.y]).collect();
+      attributes.insert("TEXCOORD_0".to_string(), self.add_floats(&uvs, "VEC2", 2, false, Some(TARGET_ARRAY_BUFFER)));
+    }
+    if model.colors.len() == count {
+      let colors: Vec<f32> = model.colors.iter().flat_map(|c| [c.x, c.y, c.z, c.w]).collect();
+      attributes.insert("COLOR_0".to_string(), self.add_floats(&colors, "VEC4", 4, false, Some(TARGET_ARRAY_BUFFER)));
+    }
+    let indices: Vec<u8> = model.indices.iter().flat_map(|i| i.to_le_bytes()).collect();
+    let view = self.add_view(&indices, Some(TARGET_ELEMENT_ARRAY_BUFFER));
+    self.document.accessors.push(GltfAccessor {
+      buffer_view: Some(view),
+      component_type: COMPONENT_UNSIGNED_INT,
+      count: model.indices.len(),
+      accessor_type: "SCALAR".to_string(),
+      ..Default::default()
+    });
+    let primitive = GltfPrimitive {
+      attributes,
+      indices: Some(self.document.accessors.len() - 1),
+      material: Some(self.add_material(&model.model_material)?),
+      mode: None,
+    };
+    self.document.meshes.push(GltfMesh { name: Some(model.model_title.clone()), primitives: vec![primitive] });
+    let transform = &model.transform;
+    self.document.nodes.push(GltfNode {
+      name: Some(model.model_title.clone()),
+      mesh: Some(self.document.meshes.len() - 1),
+      translation: Some(transform.position.into()),
+      rotation: Some(glm::to_quat(&transform.rotation_matrix()).coords.into()),
+      scale: Some(transform.scale.into()),
+      ..Default::default()
+    });
+    Ok(())
+  }
+
+  /// The animation of the model in the extras of its node for Kuplung, and the transform sampled over the timeline
+  /// as a glTF animation for the other tools.
+  fn add_animation(&mut self, node: usize, model: &MeshModel, timeline: &Timeline) -> Result<(), Box<dyn Error>> {
+    let animation = &model.animation;
+    if animation.is_empty() { return Ok(()); }
+    self.document.nodes[node].extras = Some(extras(EXTRAS_ANIMATION, animation)?);
+    if !animation.animates_transform() || timeline.duration <= 0.0 { return Ok(()); }
+    let count = (timeline.duration * ANIMATION_SAMPLES_PER_SECOND).ceil() as usize + 1;
+    let times: Vec<f32> = (0..count).map(|i| (i as f32 / ANIMATION_SAMPLES_PER_SECOND).min(timeline.duration)).collect();
+    let transforms: Vec<ModelTransform> = times.iter().map(|time| animation.pose(model, *time).transform).collect();
+    let mut rotations: Vec<glm::Quat> = transforms.iter().map(|t| glm::to_quat(&t.rotation_matrix())).collect();
+    // the shorter way between the samples
+    for i in 1..rotations.len() {
+      if rotations[i - 1].dot(&rotations[i]) < 0.0 { rotations[i] = -rotations[i]; }
+    }
+    let input = self.add_floats(&times, "SCALAR", 1, true, None);
+    let translations: Vec<f32> = transforms.iter().flat_map(|t| [t.position.x, t.position.y, t.position.z]).collect();
+    let rotations: Vec<f32> = rotations.iter().flat_map(|q| <[f32; 4]>::from(q.coords)).collect();
+    let scales: Vec<f32> = transforms.iter().flat_map(|t| [t.scale.x, t.scale.y, t.scale.z]).collect();
+    let mut gltf_animation = GltfAnimation { name: Some(model.model_title.clone()), ..Default::default() };
+    for (path, values, accessor_type, components) in [("translation", translations, "VEC3", 3), ("rotation", rotations, "VEC4", 4), ("scale", scales, "VEC3", 3)] {
+      let output = self.add_floats(&values, accessor_type, components, false, None);
+      gltf_animation.samplers.push(GltfAnimationSampler { input, output, interpolation: Some("LINEAR".to_string()) });
+      gltf_animation.channels.push(GltfChannel { sampler: gltf_animation.samplers.len() - 1, target: GltfChannelTarget { node: Some(node), path: path.to_string() } });
+    }
+    self.document.animations.push(gltf_animation);
+    Ok(())
+  }
+
+  /// Materials with the same title and contents are written once, a different material with a taken title gets a numbered one.
+  fn add_material(&mut self, material: &MeshModelMaterial) -> Result<usize, Box<dyn Error>> {
+    let color = material.diffuse_color;
+    let mut gltf = GltfMaterial {
+      name: None,
+      pbr_metallic_roughness: Some(GltfPbrMetallicRoughness {
+        base_color_factor: Some([color.x, color.y, color.z, material.transparency]),
+        base_color_texture: self.add_texture(&material.texture_diffuse)?,
+        metallic_factor: Some(material.metallic),
+        roughness_factor: Some(material.roughness),
+        metallic_roughness_texture: self.add_texture(&material.texture_metallic_roughness)?,
+      }),
+      normal_texture: self.add_texture(&material.texture_bump)?,
+      occlusion_texture: self.add_texture(&material.texture_ambient)?,
+      emissive_texture: self.add_texture(&material.texture_emission)?,
+      emissive_factor: (material.emission_color != glm::Vec3::zeros()).then(|| material.emission_color.into()),
+      alpha_mode: (material.transparency < 1.0).then(|| "BLEND".to_string()),
+    };
+    let mut title = material.material_title.clone();
+    let mut copy = 1;
+    while let Some(index) = self.materials.get(&title) {
+      let written = &self.document.materials[*index];
+      gltf.name = written.name.clone();
+      if *written == gltf { return Ok(*index); }
+      copy += 1;
+      title = format!("{} ({})", material.material_title, copy);
+    }
+    gltf.name = Some(title.clone());
+    self.document.materials.push(gltf);
+    self.materials.insert(title, self.document.materials.len() - 1);
+    Ok(self.document.materials.len() - 1)
+  }
+
+  /// Stores the image in the buffer once, even when several materials use it. Textures that can't be read are left out.
+  fn add_texture(&mut self, texture: &MeshMaterialTextureImage) -> Result<Option<GltfTextureInfo>, Box<dyn Error>> {
+    if !texture.use_texture { return Ok(None); }
+    let key = match &texture.encoded {
+      Some(encoded) => format!("embedded:{:p}", Arc::as_ptr(encoded)),
+      None => texture.image.clone(),
+    };
+    if let Some(index) = self.textures.get(&key) { return Ok(Some(GltfTextureInfo { index: *index })); }
+    let (mime, bytes) = match encode_texture(texture) {
+      Ok(encoded) => encoded,
+      Err(e) => {
+        do_log!("[Kuplung] [glTF] Left out the texture {}: {}", texture.filename, e);
+        return Ok(None);
+      }
+    };
+    let view = self.add_view(&bytes, None);
+    let extension = if mime == "image/png" { "png" } else { "jpg" };
+    let name = Path::new(&texture.filename).with_extension(extension).display().to_string();
+    self.document.images.push(GltfImage { name: Some(name), uri: None, mime_type: Some(mime.to_string()), buffer_view: Some(view) });
+    self.document.textures.push(GltfTexture { source: Some(self.document.images.len() - 1) });
+    self.textures.insert(key, self.document.textures.len() - 1);
+    Ok(Some(GltfTextureInfo { index: self.document.textures.len() - 1 }))
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::meshes::animation::{AnimatedProperty, ModelAnimation};
+  use crate::meshes::model_import::ImportProgress;
+  use crate::meshes::scene::{SceneCamera, SceneLight};
+  use crate::parsers::gltf_parser;
+
+  fn sample_scene() -> Scene {
+    let mut png = Vec::new();
+    image::RgbaImage::from_pixel(2, 2, image::Rgba([255, 0, 0, 255])).write_to(&mut Cursor::new(&mut png), ImageFormat::Png).unwrap();
+    let material = MeshModelMaterial {
+      material_title: "Brick".to_string(),
+      diffuse_color: glm::vec3(0.5, 0.25, 0.125),
+      metallic: 0.5,
+      roughness: 0.75,
+      texture_diffuse: MeshMaterialTextureImage { filename: "brick.png".to_string(), use_texture: true, encoded: Some(Arc::new(png)), ..Default::default() },
+      ..Default::default()
+    };
+    let model = MeshModel {
+      model_title: "Wall".to_string(),
+      model_material: material,
+      transform: ModelTransform { position: glm::vec3(1.0, 2.0, 3.0), rotation: glm::vec3(10.0, 20.0, 30.0), scale: glm::vec3(1.0, 2.0, 3.0) },
+      vertices: vec![glm::vec3(0.0, 0.0, 0.0), glm::vec3(1.0, 0.0, 0.0), glm::vec3(0.0, 1.0, 0.0)],
+      texture_coordinates: vec![glm::vec2(0.0, 0.0), glm::vec2(1.0, 0.0), glm::vec2(0.0, 1.0)],
+      normals: vec![glm::vec3(0.0, 0.0, 1.0); 3],
+      indices: vec![0, 1, 2],
+      ..Default::default()
+    };
+    let mut animation = ModelAnimation::default();
+    animation.set_animate(AnimatedProperty::RotateY, true);
+    animation.set_keyframe(AnimatedProperty::Transparency, 1.0, 0.5);
+    Scene {
+      models: vec![MeshModel { animation, ..model.clone() }, MeshModel { model_title: "Wall copy".to_string(), ..model }],
+      cameras: vec![SceneCamera { name: "Eye".to_string(), position: glm::vec3(0.0, 1.0, 5.0), direction: glm::vec3(0.0, 0.0, -1.0), yfov: 0.8, znear: 0.1, zfar: 100.0 }],
+      lights: vec![SceneLight {
+        name: "Lamp".to_string(),
+        light_type: LightType::Spot,
+        color: glm::vec3(1.0, 0.9, 0.8),
+        intensity: 20.0,
+        range: 10.0,
+        position: glm::vec3(0.0, 4.0, 0.0),
+        direction: glm::vec3(0.0, -1.0, 0.0),
+        inner_cone_angle: 0.2,
+        outer_cone_angle: 0.6,
+      }],
+      timeline: Timeline { duration: 2.0, looping: false, ..Default::default() },
+    }
+  }
+
+  #[test]
+  fn glb_export_imports_back_the_same_scene() {
+    let scene = sample_scene();
+    let glb = to_glb(&scene).unwrap();
+    let imported = gltf_parser::parse_bytes(&glb, Path::new(""), &ImportProgress::default()).unwrap();
+    let (document, _) = crate::parsers::gltf_document::read_glb(&glb).unwrap();
+
+    assert_eq!(imported.models.len(), 2);
+    for (original, model) in scene.models.iter().zip(imported.models.iter()) {
+      assert_eq!(model.model_title, original.model_title);
+      assert_eq!(model.vertices, original.vertices);
+      assert_eq!(model.texture_coordinates, original.texture_coordinates);
+      assert_eq!(model.normals, original.normals);
+      assert_eq!(model.indices, original.indices);
+      assert!(glm::distance(&model.transform.position, &original.transform.position) < 1e-5);
+      assert!(glm::distance(&model.transform.rotation, &original.transform.rotation) < 1e-3);
+      assert!(glm::distance(&model.transform.scale, &original.transform.scale) < 1e-5);
+      let material = &model.model_material;
+      assert_eq!(material.material_title, "Brick");
+      assert_eq!((material.diffuse_color, material.metallic, material.roughness), (glm::vec3(0.5, 0.25, 0.125), 0.5, 0.75));
+      assert_eq!(material.texture_diffuse.filename, "brick.png");
+      assert!(image::load_from_memory(material.texture_diffuse.encoded.as_ref().unwrap()).is_ok());
+    }
+    // both models share one material and one image
+    assert!(Arc::ptr_eq(imported.models[0].model_material.texture_diffuse.encoded.as_ref().unwrap(), imported.models[1].model_material.texture_diffuse.encoded.as_ref().unwrap()));
+
+    let camera = &imported.cameras[0];
+    assert_eq!((camera.name.as_str(), camera.yfov, camera.znear, camera.zfar), ("Eye", 0.8, 0.1, 100.0));
+    assert!(glm::distance(&camera.position, &glm::vec3(0.0, 1.0, 5.0)) < 1e-5);
+    assert!(glm::distance(&camera.direction, &glm::vec3(0.0, 0.0, -1.0)) < 1e-5);
+    let light = &imported.lights[0];
+    assert_eq!((light.light_type, light.intensity, light.range, light.outer_cone_angle), (LightType::Spot, 20.0, 10.0, 0.6));
+    assert!(glm::distance(&light.direction, &glm::vec3(0.0, -1.0, 0.0)) < 1e-5);
+
+    // the animation comes back from the extras, the other tools get the turning sampled over the timeline
+    assert_eq!(imported.models[0].animation, scene.models[0].animation);
+    assert!(imported.models[1].animation.is_empty());
+    assert_eq!((imported.timeline.duration, imported.timeline.looping), (2.0, false));
+    assert_eq!(document.animations.len(), 1);
+    let channels = &document.animations[0].channels;
+    assert_eq!(channels.iter().map(|c| c.target.path.as_str()).collect::<Vec<_>>(), vec!["translation", "rotation", "scale"]);
+    let input = &document.accessors[document.animations[0].samplers[0].input];
+    assert_eq!((input.count, input.max.clone()), (61, Some(vec![2.0])));
+  }
+
+  #[test]
+  fn glb_export_keeps_clashing_materials_and_vertex_colors() {
+    let mut scene = sample_scene();
+    scene.models[1].model_material.diffuse_color = glm::vec3(0.0, 1.0, 0.0);
+    scene.models[1].colors = vec![glm::vec4(1.0, 0.0, 0.0, 1.0), glm::vec4(0.0, 1.0, 0.0, 0.5), glm::vec4(0.0, 0.0, 1.0, 0.25)];
+    let imported = gltf_parser::parse_bytes(&to_glb(&scene).unwrap(), Path::new(""), &ImportProgress::default()).unwrap();
+
+    assert_eq!(imported.models[0].model_material.material_title, "Brick");
+    assert_eq!(imported.models[1].model_material.material_title, "Brick (2)");
+    assert_eq!(imported.models[1].model_material.diffuse_color, glm::vec3(0.0, 1.0, 0.0));
+    assert!(imported.models[0].colors.is_empty());
+    assert_eq!(imported.models[1].colors, scene.models[1].colors);
+  }
+}
@@ -0,0 +1,479 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::path::Path;
+use std::sync::Arc;
+use nalgebra_glm as glm;
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+use crate::do_log;
+use crate::meshes::animation::ModelAnimation;
+use crate::meshes::mesh_model::{MeshMaterialTextureImage, MeshModel, MeshModelMaterial, ModelTransform};
+use crate::meshes::model_import::ImportProgress;
+use crate::meshes::scene::{LightType, Scene, SceneCamera, SceneLight};
+use crate::parsers::gltf_document::{
+  decode_data_uri, read_glb, GltfAccessor, GltfDocument, GltfNode, GltfPbrMetallicRoughness, COMPONENT_BYTE, COMPONENT_FLOAT,
+  COMPONENT_SHORT, COMPONENT_UNSIGNED_BYTE, COMPONENT_UNSIGNED_INT, COMPONENT_UNSIGNED_SHORT, EXTRAS_ANIMATION, EXTRAS_TIMELINE,
+  GLB_MAGIC, MODE_TRIANGLES, MODE_TRIANGLE_FAN, MODE_TRIANGLE_STRIP,
+};
+use crate::settings::kuplung_logger;
+
+// glTF 2.0 - the .gltf JSON with its buffers and images next to it or inlined as data URIs, and the binary .glb.
+// Every primitive of a mesh becomes a model placed with the world transform of its node.
+// Animations are read back from the Kuplung extras only, the glTF animation channels are skipped.
+
+pub fn parse(file: &Path, progress: &ImportProgress) -> Result<Scene, Box<dyn Error>> {
+  let bytes = std::fs::read(file).map_err(|e| format!("Cannot read {}: {}", file.display(), e))?;
+  let folder = file.parent().unwrap_or(Path::new(""));
+  let mut scene = parse_bytes(&bytes, folder, progress)?;
+  let file_name = file.file_name().map_or("".to_string(), |f| f.to_string_lossy().to_string());
+  for model in scene.models.iter_mut() {
+    model.file = file_name.clone();
+    model.file_path = file.display().to_string();
+  }
+  Ok(scene)
+}
+
+/// A .gltf or .glb file already in memory, external buffers and images are looked up in `folder`.
+pub fn parse_bytes(bytes: &[u8], folder: &Path, progress: &ImportProgress) -> Result<Scene, Box<dyn Error>> {
+  progress.set_stage("Reading buffers");
+  let (document, mut glb_bin): (GltfDocument, _) = if bytes.starts_with(GLB_MAGIC) { read_glb(bytes)? } else { (serde_json::from_slice(bytes)?, None) };
+  if !document.asset.version.starts_with("2.") {
+    return Err(format!("glTF version {} is not supported.", document.asset.version).into());
+  }
+  let mut buffers = Vec::with_capacity(document.buffers.len());
+  for buffer in document.buffers.iter() {
+    let data = match &buffer.uri {
+      Some(uri) => read_uri(uri, folder)?,
+      None => glb_bin.take().ok_or("A buffer without an URI needs the binary chunk of a GLB file.")?,
+    };
+    if data.len() < buffer.byte_length { return Err(format!("A buffer has {} bytes, expected {}.", data.len(), buffer.byte_length).into()); }
+    buffers.push(data);
+  }
+  let mut timeline = None;
+  let roots: Vec<usize> = match document.scene.or(if document.scenes.is_empty() { None } else { Some(0) }) {
+    Some(index) => {
+      let scene = document.scenes.get(index).ok_or_else(|| format!("Scene {} doesn't exist.", index))?;
+      timeline = read_extras(&scene.extras, EXTRAS_TIMELINE);
+      scene.nodes.clone()
+    }
+    // without scenes every node that isn't a child is a root
+    None => (0..document.nodes.len()).filter(|i| !document.nodes.iter().any(|n| n.children.contains(i))).collect(),
+  };
+  let mut reader = GltfReader { document: &document, buffers, folder, materials: HashMap::new(), textures: HashMap::new(), scene: Scene::default() };
+  progress.set_stage("Parsing meshes");
+  for (index, root) in roots.iter().enumerate() {
+    progress.check_cancelled()?;
+    reader.read_node(*root, &glm::Mat4::identity(), 0)?;
+    progress.set_fraction((index + 1) as f32 / roots.len() as f32);
+  }
+  if !document.animations.is_empty() && reader.scene.models.iter().all(|m| m.animation.is_empty()) {
+    do_log!("[Kuplung] [glTF] Skipped {} animations, only the ones saved by Kuplung are read.", document.animations.len());
+  }
+  reader.scene.timeline = timeline.unwrap_or_default();
+  Ok(reader.scene)
+}
+
+fn read_uri(uri: &str, folder: &Path) -> Result<Vec<u8>, Box<dyn Error>> {
+  match decode_data_uri(uri) {
+    Some(decoded) => Ok(decoded?.1),
+    None => {
+      let file = folder.join(percent_decode(uri));
+      std::fs::read(&file).map_err(|e| format!("Cannot read {}: {}", file.display(), e).into())
+    }
+  }
+}
+
+fn percent_decode(uri: &str) -> String {
+  let bytes = uri.as_bytes();
+  let mut decoded = Vec::with_capacity(bytes.len());
+  let mut index = 0;
+  while index < bytes.len() {
+    let escaped = bytes.get(index + 1..index + 3).and_then(|hex| u8::from_str_radix(std::str::from_utf8(hex).ok()?, 16).ok());
+    match (bytes[index], escaped) {
+      (b'%', Some(byte)) => {
+        decoded.push(byte);
+        index += 3;
+      }
+      (byte, _) => {
+        decoded.push(byte);
+        index += 1;
+      }
+    }
+  }
+  String::from_utf8_lossy(&decoded).to_string()
+}
+
+/// A Kuplung setting from the extras, None when it isn't there or cannot be read.
+fn read_extras<T: DeserializeOwned>(extras: &Option<Value>, key: &str) -> Option<T> {
+  let value = extras.as_ref()?.get(key)?;
+  match serde_json::from_value(value.clone()) {
+    Ok(value) => Some(value),
+    Err(e) => {
+      do_log!("[Kuplung] [glTF] Cannot read {}: {}", key, e);
+      None
+    }
+  }
+}
+
+fn node_matrix(node: &GltfNode) -> glm::Mat4 {
+  if let Some(matrix) = &node.matrix { return glm::make_mat4(matrix); }
+  let [x, y, z, w] = node.rotation.unwrap_or([0.0, 0.0, 0.0, 1.0]);
+  glm::translation(&glm::make_vec3(&node.translation.unwrap_or([0.0; 3])))
+    * glm::quat_to_mat4(&glm::quat(x, y, z, w))
+    * glm::scaling(&glm::make_vec3(&node.scale.unwrap_or([1.0; 3])))
+}
+
+/// Strips and fans to a list of triangles.
+fn triangulate(mode: u32, indices: Vec<u32>) -> Vec<u32> {
+  let count = indices.len().saturating_sub(2);
+  match mode {
+    MODE_TRIANGLE_STRIP => (0..count).flat_map(|i| if i % 2 == 0 { [indices[i], indices[i + 1], indices[i + 2]] } else { [indices[i + 1], indices[i], indices[i + 2]] }).collect(),
+    MODE_TRIANGLE_FAN => (0..count).flat_map(|i| [indices[0], indices[i + 1], indices[i + 2]]).collect(),
+    _ => indices,
+  }
+}
+
+fn component_to_f32(bytes: &[u8], component_type: u32, normalized: bool) -> f32 {
+  match component_type {
+    COMPONENT_BYTE if normalized => (bytes[0] as i8 as f32 / 127.0).max(-1.0),
+    COMPONENT_BYTE => bytes[0] as i8 as f32,
+    COMPONENT_UNSIGNED_BYTE if normalized => bytes[0] as f32 / 255.0,
+    COMPONENT_UNSIGNED_BYTE => bytes[0] as f32,
+    COMPONENT_SHORT if normalized => (i16::from_le_bytes([bytes[0], bytes[1]]) as f32 / 32767.0).max(-1.0),
+    COMPONENT_SHORT => i16::from_le_bytes([bytes[0], bytes[1]]) as f32,
+    COMPONENT_UNSIGNED_SHORT if normalized => u16::from_le_bytes([bytes[0], bytes[1]]) as f32 / 65535.0,
+    COMPONENT_UNSIGNED_SHORT => u16::from_le_bytes([bytes[0], bytes[1]]) as f32,
+    COMPONENT_UNSIGNED_INT => u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as f32,
+    _ => f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]),
+  }
+}
+
+struct GltfReader<'a> {
+  document: &'a GltfDocument,
+  buffers: Vec<Vec<u8>>,
+  folder: &'a Path,
+  materials: HashMap<usize, MeshModelMaterial>,
+  textures: HashMap<usize, MeshMaterialTextureImage>,
+  scene: Scene,
+}
+
+impl GltfReader<'_> {
+  fn read_node(&mut self, index: usize, parent: &glm::Mat4, depth: usize) -> Result<(), Box<dyn Error>> {
+    let document = self.document;
+    let node = document.nodes.get(index).ok_or_else(|| format!("Node {} doesn't exist.", index))?;
+    if depth > document.nodes.len() { return Err("The node hierarchy has a cycle.".into()); }
+    let world = parent * node_matrix(node);
+    let name = node.name.clone().unwrap_or_else(|| format!("Node {}", index));
+    if let Some(mesh) = node.mesh {
+      let first = self.scene.models.len();
+      self.read_mesh(mesh, &name, &world)?;
+      if let Some(animation) = read_extras::<ModelAnimation>(&node.extras, EXTRAS_ANIMATION) {
+        for model in self.scene.models[first..].iter_mut() { model.animation = animation.clone(); }
+      }
+    }
+    let position = (world * glm::vec4(0.0, 0.0, 0.0, 1.0)).xyz();
+    let direction = (world * glm::vec4(0.0, 0.0, -1.0, 0.0)).xyz().try_normalize(1e-6).unwrap_or(-glm::Vec3::z());
+    if let Some(camera) = node.camera {
+      let camera = document.cameras.get(camera).ok_or_else(|| format!("Camera {} doesn't exist.", camera))?;
+      match &camera.perspective {
+        Some(perspective) => self.scene.cameras.push(SceneCamera {
+          name: camera.name.clone().unwrap_or_else(|| name.clone()),
+          position,
+          direction,
+          yfov: perspective.yfov,
+          znear: perspective.znear,
+          zfar: perspective.zfar.unwrap_or(0.0),
+        }),
+        None => do_log!("[Kuplung] [glTF] Skipped the {} camera {}, only perspective cameras are supported.", camera.camera_type, name),
+      }
+    }
+    if let Some(reference) = node.extensions.as_ref().and_then(|e| e.lights_punctual.as_ref()) {
+      let lights = document.extensions.as_ref().and_then(|e| e.lights_punctual.as_ref()).map_or(&[][..], |l| &l.lights[..]);
+      let light = lights.get(reference.light).ok_or_else(|| format!("Light {} doesn't exist.", reference.light))?;
+      let light_type = match light.light_type.as_str() {
+        "directional" => Some(LightType::Directional),
+        "point" => Some(LightType::Point),
+        "spot" => Some(LightType::Spot),
+        other => {
+          do_log!("[Kuplung] [glTF] Skipped the light {} of unknown type {}.", name, other);
+          None
+        }
+      };
+      if let Some(light_type) = light_type {
+        let spot = light.spot.as_ref();
+        self.scene.lights.push(SceneLight {
+          name: light.name.clone().unwrap_or_else(|| name.clone()),
+          light_type,
+          color: glm::make_vec3(&light.color.unwrap_or([1.0; 3])),
+          intensity: light.intensity.unwrap_or(1.0),
+          range: light.range.unwrap_or(0.0),
+          position,
+          direction,
+          inner_cone_angle: spot.and_then(|s| s.inner_cone_angle).unwrap_or(0.0),
+          outer_cone_angle: spot.and_then(|s| s.outer_cone_angle).unwrap_or(std::f32::consts::FRAC_PI_4),
+        });
+      }
+    }
+    for child in node.children.iter() {
+      self.read_node(*child, &world, depth + 1)?;
+    }
+    Ok(())
+  }
+
+  fn read_mesh(&mut self, index: usize, name: &str, world: &glm::Mat4) -> Result<(), Box<dyn Error>> {
+    let mesh = self.document.meshes.get(index).ok_or_else(|| format!("Mesh {} doesn't exist.", index))?;
+    for (number, primitive) in mesh.primitives.iter().enumerate() {
+      let mode = primitive.mode.unwrap_or(MODE_TRIANGLES);
+      if ![MODE_TRIANGLES, MODE_TRIANGLE_STRIP, MODE_TRIANGLE_FAN].contains(&mode) {
+        do_log!("[Kuplung] [glTF] Skipped a primitive of {} with mode {}, only triangles are supported.", name, mode);
+        continue;
+      }
+      let position = *primitive.attributes.get("POSITION").ok_or_else(|| format!("A primitive of {} has no positions.", name))?;
+      let vertices: Vec<glm::Vec3> = self.read_floats(position, 3)?.chunks_exact(3).map(glm::make_vec3).collect();
+      let normals = match primitive.attributes.get("NORMAL") {
+        Some(normal) => self.read_floats(*normal, 3)?.chunks_exact(3).map(glm::make_vec3).collect(),
+        None => Vec::new(),
+      };
+      // glTF puts the texture origin top left, Kuplung bottom left like OBJ
+      let texture_coordinates = match primitive.attributes.get("TEXCOORD_0") {
+        Some(uv) => self.read_floats(*uv, 2)?.chunks_exact(2).map(|uv| glm::vec2(uv[0], 1.0 - uv[1])).collect(),
+        None => vec![glm::Vec2::zeros(); vertices.len()],
+      };
+      let colors = match primitive.attributes.get("COLOR_0") {
+        Some(color) => match self.accessor(*color)?.components() {
+          Some(3) => self.read_floats(*color, 3)?.chunks_exact(3).map(|c| glm::vec4(c[0], c[1], c[2], 1.0)).collect(),
+          _ => self.read_floats(*color, 4)?.chunks_exact(4).map(glm::make_vec4).collect(),
+        },
+        None => Vec::new(),
+      };
+      let indices = match primitive.indices {
+        Some(indices) => self.read_indices(indices)?,
+        None => (0..vertices.len() as u32).collect(),
+      };
+      let indices = triangulate(mode, indices);
+      if indices.iter().any(|i| *i as usize >= vertices.len()) { return Err(format!("A primitive of {} has indices out of its vertices.", name).into()); }
+      if texture_coordinates.len() != vertices.len() { return Err(format!("A primitive of {} has {} texture coordinates for {} vertices.", name, texture_coordinates.len(), vertices.len()).into()); }
+      if !colors.is_empty() && colors.len() != vertices.len() { return Err(format!("A primitive of {} has {} colors for {} vertices.", name, colors.len(), vertices.len()).into()); }
+      let model_material = match primitive.material {
+        Some(material) => self.material(material)?,
+        None => MeshModelMaterial::default(),
+      };
+      self.scene.models.push(MeshModel {
+        model_title: if mesh.primitives.len() > 1 { format!("{} #{}", name, number + 1) } else { name.to_string() },
+        model_material,
+        transform: ModelTransform::from_matrix(world),
+        vertices,
+        texture_coordinates,
+        normals,
+        colors,
+        indices,
+        ..Default::default()
+      });
+    }
+    Ok(())
+  }
+
+  fn accessor(&self, index: usize) -> Result<&GltfAccessor, Box<dyn Error>> {
+    self.document.accessors.get(index).ok_or_else(|| format!("Accessor {} doesn't exist.", index).into())
+  }
+
+  /// The bytes of every component, in order.
+  fn read_accessor<T>(&self, index: usize, components: usize, read: impl Fn(&[u8]) -> T) -> Result<Vec<T>, Box<dyn Error>> {
+    let accessor = self.accessor(index)?;
+    if accessor.sparse.is_some() { return Err(format!("Accessor {} is sparse, that is not supported.", index).into()); }
+    if accessor.components() != Some(components) { return Err(format!("Accessor {} is {}, expected {} components.", index, accessor.accessor_type, components).into()); }
+    let size = match accessor.component_type {
+      COMPONENT_BYTE | COMPONENT_UNSIGNED_BYTE => 1,
+      COMPONENT_SHORT | COMPONENT_UNSIGNED_SHORT => 2,
+      COMPONENT_UNSIGNED_INT | COMPONENT_FLOAT => 4,
+      other => return Err(format!("Accessor {} has the unknown component type {}.", index, other).into()),
+    };
+    let out_of_buffer = || format!("Accessor {} reaches out of its buffer.", index);
+    let Some(view) = accessor.buffer_view else {
+      // no data is all zeros, no more of them than the buffers could hold - the count alone could ask for any amount of memory
+      let buffers: usize = self.buffers.iter().map(|b| b.len()).sum();
+      let length = accessor.count.checked_mul(components * size).filter(|length| *length <= buffers).ok_or_else(out_of_buffer)?;
+      return Ok((0..length / size).map(|_| read(&[0; 4][..size])).collect());
+    };
+    let view = self.document.buffer_views.get(view).ok_or_else(|| format!("Buffer view {} doesn't exist.", view))?;
+    let buffer = self.buffers.get(view.buffer).ok_or_else(|| format!("Buffer {} doesn't exist.", view.buffer))?;
+    let stride = view.byte_stride.unwrap_or(size * components);
+    // with a shorter stride the elements would overlap and the count would not be bounded by the buffer
+    if stride < size * components { return Err(format!("Buffer view of accessor {} has the stride {}, shorter than its elements.", index, stride).into()); }
+    let start = view.byte_offset.checked_add(accessor.byte_offset).ok_or_else(out_of_buffer)?;
+    let end = view.byte_offset.checked_add(view.byte_length).ok_or_else(out_of_buffer)?.min(buffer.len());
+    if accessor.count > 0 {
+      let last = (accessor.count - 1).checked_mul(stride).and_then(|offset| offset.checked_add(start)).and_then(|offset| offset.checked_add(size * components));
+      if last.is_none_or(|last| last > end) { return Err(out_of_buffer().into()); }
+    }
+    let mut values = Vec::with_capacity(accessor.count * components);
+    for element in 0..accessor.count {
+      for component in 0..components {
+        let offset = start + element * stride + component * size;
+        values.push(read(&buffer[offset..offset + size]));
+      }
+    }
+    Ok(values)
+  }
+
+  fn read_floats(&self, index: usize, components: usize) -> Result<Vec<f32>, Box<dyn Error>> {
+    let accessor = self.accessor(index)?;
+    let (component_type, normalized) = (accessor.component_type, accessor.normalized);
+    self.read_accessor(index, components, |bytes| component_to_f32(bytes, component_type, normalized))
+  }
+
+  fn read_indices(&self, index: usize) -> Result<Vec<u32>, Box<dyn Error>> {
+    match self.accessor(index)?.component_type {
+      COMPONENT_UNSIGNED_BYTE => self.read_accessor(index, 1, |b| b[0] as u32),
+      COMPONENT_UNSIGNED_SHORT => self.read_accessor(index, 1, |b| u16::from_le_bytes([b[0], b[1]]) as u32),
+      COMPONENT_UNSIGNED_INT => self.read_accessor(index, 1, |b| u32::from_le_bytes([b[0], b[1], b[2], b[3]])),
+      other => Err(format!("Accessor {} has indices of type {}, expected unsigned integers.", index, other).into()),
+    }
+  }
+
+  fn view_bytes(&self, index: usize) -> Result<&[u8], Box<dyn Error>> {
+    let view = self.document.buffer_views.get(index).ok_or_else(|| format!("Buffer view {} doesn't exist.", index))?;
+    let buffer = self.buffers.get(view.buffer).ok_or_else(|| format!("Buffer {} doesn't exist.", view.buffer))?;
+    buffer.get(view.byte_offset..view.byte_offset + view.byte_length).ok_or_else(|| format!("Buffer view {} reaches out of its buffer.", index).into())
+  }
+
+  fn material(&mut self, index: usize) -> Result<MeshModelMaterial, Box<dyn Error>> {
+    if let Some(material) = self.materials.get(&index) { return Ok(material.clone()); }
+    let gltf = self.document.materials.get(index).ok_or_else(|| format!("Material {} doesn't exist.", index))?;
+    let default_pbr = GltfPbrMetallicRoughness::default();
+    let pbr = gltf.pbr_metallic_roughness.as_ref().unwrap_or(&default_pbr);
+    let color = pbr.base_color_factor.unwrap_or([1.0; 4]);
+    let mut material = MeshModelMaterial {
+      material_title: gltf.name.clone().unwrap_or_else(|| format!("Material {}", index)),
+      diffuse_color: glm::vec3(color[0], color[1], color[2]),
+      // the alpha only counts when blending
+      transparency: if gltf.alpha_mode.as_deref() == Some("BLEND") { color[3] } else { 1.0 },
+      emission_color: glm::make_vec3(&gltf.emissive_factor.unwrap_or([0.0; 3])),
+      metallic: pbr.metallic_factor.unwrap_or(1.0),
+      roughness: pbr.roughness_factor.unwrap_or(1.0),
+      ..Default::default()
+    };
+    if let Some(info) = &pbr.base_color_texture { material.texture_diffuse = self.texture(info.index)?; }
+    if let Some(info) = &pbr.metallic_roughness_texture { material.texture_metallic_roughness = self.texture(info.index)?; }
+    if let Some(info) = &gltf.normal_texture { material.texture_bump = self.texture(info.index)?; }
+    if let Some(info) = &gltf.occlusion_texture { material.texture_ambient = self.texture(info.index)?; }
+    if let Some(info) = &gltf.emissive_texture { material.texture_emission = self.texture(info.index)?; }
+    self.materials.insert(index, material.clone());
+    Ok(material)
+  }
+
+  fn texture(&mut self, index: usize) -> Result<MeshMaterialTextureImage, Box<dyn Error>> {
+    if let Some(texture) = self.textures.get(&index) { return Ok(texture.clone()); }
+    let gltf = self.document.textures.get(index).ok_or_else(|| format!("Texture {} doesn't exist.", index))?;
+    // textures can have their image only in an extension, like KHR_texture_basisu
+    let Some(source) = gltf.source else { return Ok(MeshMaterialTextureImage::default()); };
+    let image = self.document.images.get(source).ok_or_else(|| format!("Image {} doesn't exist.", source))?;
+    let embedded = |mime: &str, bytes: Vec<u8>| {
+      let name = image.name.clone().unwrap_or_else(|| format!("Image {}", source));
+      let extension = if mime == "image/jpeg" { "jpg" } else { mime.trim_start_matches("image/") };
+      MeshMaterialTextureImage {
+        filename: if Path::new(&name).extension().is_some() { name } else { format!("{}.{}", name, extension) },
+        use_texture: true,
+        encoded: Some(Arc::new(bytes)),
+        ..Default::default()
+      }
+    };
+    let texture = match (&image.uri, image.buffer_view) {
+      (Some(uri), _) => match decode_data_uri(uri) {
+        Some(decoded) => {
+          let (mime, bytes) = decoded?;
+          embedded(&mime, bytes)
+        }
+        None => {
+          let filename = percent_decode(uri);
+          let file = self.folder.join(&filename);
+          if !file.is_file() { do_log!("[Kuplung] [glTF] Texture {} was not found in {}.", filename, self.folder.display()); }
+          MeshMaterialTextureImage { use_texture: file.is_file(), image: file.display().to_string(), filename, ..Default::default() }
+        }
+      },
+      (None, Some(view)) => embedded(image.mime_type.as_deref().unwrap_or("image/png"), self.view_bytes(view)?.to_vec()),
+      (None, None) => return Err(format!("Image {} has neither an URI nor a buffer view.", source).into()),
+    };
+    self.textures.insert(index, texture.clone());
+    Ok(texture)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  // one triangle in a child node, a camera and a directional light, the buffer inlined as a data URI
+  const HIERARCHY: &str = r#"{
+    "asset": { "version": "2.0" },
+    "scene": 0,
+    "scenes": [{ "nodes": [0] }],
+    "nodes": [
+      { "name": "Parent", "translation": [0, 0, 5], "children": [1, 2, 3] },
+      { "name": "Child", "mesh": 0, "rotation": [0, 0.70710677, 0, 0.70710677], "scale": [2, 2, 2] },
+      { "name": "Eye", "camera": 0, "translation": [0, 1, 0] },
+      { "name": "Sun", "rotation": [-0.70710677, 0, 0, 0.70710677], "extensions": { "KHR_lights_punctual": { "light": 0 } } }
+    ],
+    "meshes": [{ "name": "Triangle", "primitives": [{ "attributes": { "POSITION": 0 }, "indices": 1, "material": 0 }] }],
+    "materials": [{
+      "name": "Red",
+      "alphaMode": "BLEND",
+      "pbrMetallicRoughness": { "baseColorFactor": [1, 0, 0, 0.5], "metallicFactor": 0.25, "baseColorTexture": { "index": 0 } }
+    }],
+    "textures": [{ "source": 0 }],
+    "images": [{ "uri": "missing%20texture.png" }],
+    "accessors": [
+      { "bufferView": 0, "componentType": 5126, "count": 3, "type": "VEC3", "min": [0, 0, 0], "max": [1, 1, 0] },
+      { "bufferView": 1, "componentType": 5123, "count": 3, "type": "SCALAR" }
+    ],
+    "bufferViews": [{ "buffer": 0, "byteLength": 36 }, { "buffer": 0, "byteOffset": 36, "byteLength": 6 }],
+    "buffers": [{ "byteLength": 44, "uri": "data:application/octet-stream;base64,AAAAAAAAAAAAAAAAAACAPwAAAAAAAAAAAAAAAAAAgD8AAAAAAAABAAIAAAA=" }],
+    "cameras": [{ "type": "perspective", "perspective": { "yfov": 0.8, "znear": 0.1 } }],
+    "extensions": { "KHR_lights_punctual": { "lights": [{ "type": "directional", "color": [1, 0.5, 0], "intensity": 3 }] } }
+  }"#;
+
+  #[test]
+  fn reads_the_node_hierarchy_materials_cameras_and_lights() {
+    let scene = parse_bytes(HIERARCHY.as_bytes(), Path::new("/nowhere"), &ImportProgress::default()).unwrap();
+    assert_eq!(scene.models.len(), 1);
+    let model = &scene.models[0];
+    assert_eq!(model.model_title, "Child");
+    assert_eq!(model.vertices, vec![glm::vec3(0.0, 0.0, 0.0), glm::vec3(1.0, 0.0, 0.0), glm::vec3(0.0, 1.0, 0.0)]);
+    assert_eq!(model.indices, vec![0, 1, 2]);
+    assert_eq!(model.texture_coordinates.len(), 3);
+    assert!(model.normals.is_empty());
+    // +X turned a quarter around Y, doubled and moved with the parent
+    let corner = (model.transform.matrix() * glm::vec4(1.0, 0.0, 0.0, 1.0)).xyz();
+    assert!(glm::distance(&corner, &glm::vec3(0.0, 0.0, 3.0)) < 1e-5);
+
+    let material = &model.model_material;
+    assert_eq!((material.material_title.as_str(), material.diffuse_color, material.transparency), ("Red", glm::vec3(1.0, 0.0, 0.0), 0.5));
+    assert_eq!((material.metallic, material.roughness), (0.25, 1.0));
+    assert_eq!(material.texture_diffuse.filename, "missing texture.png");
+    assert!(!material.texture_diffuse.use_texture);
+
+    assert_eq!(scene.cameras.len(), 1);
+    assert_eq!((scene.cameras[0].name.as_str(), scene.cameras[0].position, scene.cameras[0].zfar), ("Eye", glm::vec3(0.0, 1.0, 5.0), 0.0));
+    assert_eq!(scene.lights.len(), 1);
+    let light = &scene.lights[0];
+    assert_eq!((light.light_type, light.color, light.intensity), (LightType::Directional, glm::vec3(1.0, 0.5, 0.0), 3.0));
+    // the sun is tilted to shine straight down
+    assert!(glm::distance(&light.direction, &glm::vec3(0.0, -1.0, 0.0)) < 1e-5);
+  }
+
+  #[test]
+  fn rejects_accessors_out_of_their_buffer() {
+    let parse = |from: &str, to: &str| parse_bytes(HIERARCHY.replacen(from, to, 1).as_bytes(), Path::new("/nowhere"), &ImportProgress::default()).map(|_| ()).unwrap_err().to_string();
+    let huge = format!(r#""count": {}"#, usize::MAX / 4);
+    assert!(parse(r#""count": 3"#, &huge).contains("reaches out of its buffer"));
+    assert!(parse(r#""bufferView": 0, "componentType": 5126, "count": 3"#, &format!(r#""componentType": 5126, {}"#, huge)).contains("reaches out of its buffer"));
+    assert!(parse(r#""byteLength": 36 }"#, &format!(r#""byteLength": 36, "byteOffset": {} }}"#, usize::MAX)).contains("reaches out of its buffer"));
+    assert!(parse(r#""byteLength": 36 }"#, r#""byteLength": 36, "byteStride": 4 }"#).contains("shorter than its elements"));
+  }
+
+  #[test]
+  fn strips_and_fans_become_triangles() {
+    assert_eq!(triangulate(MODE_TRIANGLE_STRIP, vec![0, 1, 2, 3]), vec![0, 1, 2, 2, 1, 3]);
+    assert_eq!(triangulate(MODE_TRIANGLE_FAN, vec![0, 1, 2, 3]), vec![0, 1, 2, 0, 2, 3]);
+  }
+}
@@ -0,0 +1,9 @@
+pub mod gltf_document;
+pub mod gltf_exporter;
+pub mod gltf_parser;
+pub mod obj_exporter;
+pub mod obj_parser;
+pub mod ply_exporter;
+pub mod ply_parser;
+pub mod stl_exporter;
+pub mod stl_parser;
\ No newline at end of file
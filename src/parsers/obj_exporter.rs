@@ -0,0 +1,293 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use crate::do_log;
+use crate::meshes::mesh_model::{MeshMaterialTextureImage, MeshModel, MeshModelMaterial};
+use crate::meshes::model_export::ExportOptions;
+use crate::meshes::scene::Scene;
+use crate::parsers::obj_parser::TRANSFORM_COMMENT;
+use crate::settings::kuplung_logger;
+
+// Wavefront OBJ and MTL, written so the OBJ parser reads back the same meshes. Every model is an object
+// with one material, the faces keep their polygons unless triangulated. Baked models are placed in the scene,
+// the others keep their local vertices and get a "# Kuplung transform" comment. Cameras and lights are not written.
+
+/// An image the export has to put next to the MTL file.
+#[derive(Clone, Debug)]
+pub enum TextureCopy {
+  File(PathBuf, String),
+  Encoded(Arc<Vec<u8>>, String),
+}
+
+/// The text of the OBJ and the MTL files and the images to put in their folder.
+pub struct ObjFiles {
+  pub obj: String,
+  pub mtl: String,
+  pub textures: Vec<TextureCopy>,
+}
+
+pub fn export(scene: &Scene, file: &Path, options: &ExportOptions) -> Result<(), Box<dyn Error>> {
+  let folder = file.parent().unwrap_or(Path::new(""));
+  let mtl_file = file.with_extension("mtl");
+  let mtl_name = mtl_file.file_name().map_or("".to_string(), |f| f.to_string_lossy().to_string());
+  let files = to_obj(scene, &mtl_name, folder, options);
+  std::fs::write(file, &files.obj)?;
+  std::fs::write(&mtl_file, &files.mtl)?;
+  for texture in files.textures.iter() {
+    match texture {
+      TextureCopy::File(source, name) => {
+        let target = folder.join(name);
+        // copying a file onto itself empties it
+        if source.canonicalize().ok() != target.canonicalize().ok() { std::fs::copy(source, &target)?; }
+      }
+      TextureCopy::Encoded(bytes, name) => std::fs::write(folder.join(name), bytes.as_slice())?,
+    }
+  }
+  do_log!("[Kuplung] [OBJ] Exported {} models and {} textures to {}.", scene.models.len(), files.textures.len(), file.display());
+  Ok(())
+}
+
+/// The vertex data pools shared by all objects, each value is written once.
+#[derive(Default)]
+struct ObjPools {
+  obj: String,
+  positions: HashMap<[u32; 3], usize>,
+  uvs: HashMap<[u32; 2], usize>,
+  normals: HashMap<[u32; 3], usize>,
+  count_v: usize,
+}
+
+impl ObjPools {
+  fn position(&mut self, value: [f32; 3]) -> usize {
+    let next = self.positions.len() + 1;
+    let index = *self.positions.entry(value.map(f32::to_bits)).or_insert(next);
+    if index == next { let _ = writeln!(self.obj, "v {} {} {}", value[0], value[1], value[2]); self.count_v = next; }
+    index
+  }
+
+  /// A position of its own, for two vertices of a mesh that only differ by their index.
+  fn duplicate_position(&mut self, value: [f32; 3]) -> usize {
+    self.count_v += 1;
+    let _ = writeln!(self.obj, "v {} {} {}", value[0], value[1], value[2]);
+    self.count_v
+  }
+
+  fn uv(&mut self, value: [f32; 2]) -> usize {
+    let next = self.uvs.len() + 1;
+    let index = *self.uvs.entry(value.map(f32::to_bits)).or_insert(next);
+    if index == next { let _ = writeln!(self.obj, "vt {} {}", value[0], value[1]); }
+    index
+  }
+
+  fn normal(&mut self, value: [f32; 3]) -> usize {
+    let next = self.normals.len() + 1;
+    let index = *self.normals.entry(value.map(f32::to_bits)).or_insert(next);
+    if index == next { let _ = writeln!(self.obj, "vn {} {} {}", value[0], value[1], value[2]); }
+    index
+  }
+}
+
+pub fn to_obj(scene: &Scene, mtl_name: &str, folder: &Path, options: &ExportOptions) -> ObjFiles {
+  let scale = 1.0 / options.unit.meters();
+  let mut pools = ObjPools::default();
+  pools.obj.push_str("# Kuplung\n");
+  let _ = writeln!(pools.obj, "mtllib {}", mtl_name);
+  let mut materials = MaterialLibrary::new(folder, options.copy_textures);
+  let mut objects: HashMap<(String, String), usize> = HashMap::new();
+  for model in scene.models.iter() {
+    let model = if options.bake_transforms { model.baked(scale) } else { local(model, scale) };
+    let material = materials.add(&model.model_material);
+    // an object and material pair seen before would continue that mesh
+    let clashes = objects.entry((model.model_title.clone(), material.clone())).or_default();
+    *clashes += 1;
+    let title = if *clashes == 1 { model.model_title.clone() } else { format!("{} ({})", model.model_title, clashes) };
+    let _ = writeln!(pools.obj, "o {}", title);
+    if !options.bake_transforms {
+      let t = &model.transform;
+      let _ = writeln!(pools.obj, "{} {} {} {} {} {} {} {} {} {}", TRANSFORM_COMMENT, t.position.x, t.position.y, t.position.z, t.rotation.x, t.rotation.y, t.rotation.z, t.scale.x, t.scale.y, t.scale.z);
+    }
+    let count = model.vertices.len();
+    let has_uvs = model.texture_coordinates.len() == count && model.texture_coordinates.iter().any(|uv| uv.x != 0.0 || uv.y != 0.0);
+    let has_normals = model.normals.len() == count;
+    // the corners of every vertex, the first vertex with a triplet keeps it
+    let mut triplets: HashMap<(usize, usize, usize), u32> = HashMap::new();
+    let corners: Vec<String> = (0..count).map(|i| {
+      let position = [model.vertices[i].x, model.vertices[i].y, model.vertices[i].z];
+      let mut v = pools.position(position);
+      let vt = if has_uvs { pools.uv([model.texture_coordinates[i].x, model.texture_coordinates[i].y]) } else { 0 };
+      let vn = if has_normals { pools.normal([model.normals[i].x, model.normals[i].y, model.normals[i].z]) } else { 0 };
+      if *triplets.entry((v, vt, vn)).or_insert(i as u32) != i as u32 { v = pools.duplicate_position(position); }
+      match (has_uvs, has_normals) {
+        (true, true) => format!("{}/{}/{}", v, vt, vn),
+        (true, false) => format!("{}/{}", v, vt),
+        (false, true) => format!("{}//{}", v, vn),
+        (false, false) => v.to_string(),
+      }
+    }).collect();
+    let _ = writeln!(pools.obj, "usemtl {}", material);
+    let faces = if options.triangulate { model.indices.chunks_exact(3).map(|t| t.to_vec()).collect() } else { model.faces() };
+    for face in faces {
+      let face: Vec<&str> = face.iter().map(|i| corners[*i as usize].as_str()).collect();
+      let _ = writeln!(pools.obj, "f {}", face.join(" "));
+    }
+  }
+  ObjFiles { obj: pools.obj, mtl: materials.mtl, textures: materials.textures }
+}
+
+/// The model in its own space, scaled to the unit of the export with its position.
+fn local(model: &MeshModel, scale: f32) -> MeshModel {
+  let mut local = model.clone();
+  local.vertices.iter_mut().for_each(|v| *v *= scale);
+  local.transform.position *= scale;
+  local
+}
+
+struct MaterialLibrary<'a> {
+  folder: &'a Path,
+  copy_textures: bool,
+  mtl: String,
+  /// The written materials by title, with their statements to tell apart materials of the same name.
+  written: HashMap<String, String>,
+  textures: Vec<TextureCopy>,
+  /// The names of the copied images by their source.
+  copied: HashMap<String, String>,
+}
+
+impl<'a> MaterialLibrary<'a> {
+  fn new(folder: &'a Path, copy_textures: bool) -> Self {
+    Self { folder, copy_textures, mtl: "# Kuplung\n".to_string(), written: HashMap::new(), textures: Vec::new(), copied: HashMap::new() }
+  }
+
+  /// Writes the material once and gives the title the OBJ file refers to it with.
+  fn add(&mut self, material: &MeshModelMaterial) -> String {
+    let statements = self.statements(material);
+    let mut title = material.material_title.clone();
+    let mut copy = 1;
+    while let Some(written) = self.written.get(&title) {
+      if *written == statements { return title; }
+      copy += 1;
+      title = format!("{} ({})", material.material_title, copy);
+    }
+    let _ = write!(self.mtl, "\nnewmtl {}\n{}", title, statements);
+    self.written.insert(title.clone(), statements);
+    title
+  }
+
+  fn statements(&mut self, material: &MeshModelMaterial) -> String {
+    let mut mtl = String::new();
+    for (keyword, color) in [("Ka", material.ambient_color), ("Kd", material.diffuse_color), ("Ks", material.specular_color), ("Ke", material.emission_color)] {
+      let _ = writeln!(mtl, "{} {} {} {}", keyword, color.x, color.y, color.z);
+    }
+    let _ = writeln!(mtl, "Ns {}\nNi {}\nd {}\nillum {}\nPm {}\nPr {}", material.specular_exp, material.optical_density, material.transparency, material.illumination_mode, material.metallic, material.roughness);
+    for (keyword, texture) in material.textures() {
+      if let Some(file) = self.texture(texture) { let _ = writeln!(mtl, "{} {}", keyword, file); }
+    }
+    mtl
+  }
+
+  /// The file name of the texture in the MTL file - copied next to it, relative to it, or as it was when the image is missing.
+  fn texture(&mut self, texture: &MeshMaterialTextureImage) -> Option<String> {
+    let file_name = |path: &str| Path::new(&path.replace('\\', "/")).file_name().map(|f| f.to_string_lossy().to_string());
+    if let Some(encoded) = &texture.encoded {
+      let name = file_name(&texture.filename).unwrap_or_else(|| "texture.png".to_string());
+      return Some(self.copy(format!("embedded:{:p}", Arc::as_ptr(encoded)), name, |name| TextureCopy::Encoded(encoded.clone(), name)));
+    }
+    if texture.use_texture && !texture.image.is_empty() {
+      let image = PathBuf::from(&texture.image);
+      if !self.copy_textures { return Some(relative_path(&image, self.folder)); }
+      let name = file_name(&texture.image).unwrap_or_default();
+      return Some(self.copy(texture.image.clone(), name, |name| TextureCopy::File(image, name)));
+    }
+    (!texture.filename.is_empty()).then(|| texture.filename.clone())
+  }
+
+  /// A name in the export folder for the source, images with the same name get a number.
+  fn copy(&mut self, source: String, name: String, texture: impl FnOnce(String) -> TextureCopy) -> String {
+    if let Some(name) = self.copied.get(&source) { return name.clone(); }
+    let path = Path::new(&name);
+    let (stem, extension) = (path.file_stem().map_or("texture".to_string(), |s| s.to_string_lossy().to_string()), path.extension().map(|e| e.to_string_lossy().to_string()));
+    let mut unique = name.clone();
+    let mut copy = 1;
+    while self.copied.values().any(|n| *n == unique) {
+      copy += 1;
+      unique = match &extension {
+        Some(extension) => format!("{}_{}.{}", stem, copy, extension),
+        None => format!("{}_{}", stem, copy),
+      };
+    }
+    self.copied.insert(source, unique.clone());
+    self.textures.push(texture(unique.clone()));
+    unique
+  }
+}
+
+/// The path from the folder to the file with '/' separators, the full path when there is no common root.
+fn relative_path(file: &Path, folder: &Path) -> String {
+  let (Ok(file), Ok(folder)) = (file.canonicalize(), folder.canonicalize()) else { return file.display().to_string(); };
+  let common = file.components().zip(folder.components()).take_while(|(a, b)| a == b).count();
+  if common == 0 { return file.display().to_string(); }
+  let mut relative = PathBuf::new();
+  for _ in folder.components().skip(common) { relative.push(".."); }
+  for component in file.components().skip(common) { relative.push(component); }
+  relative.to_string_lossy().replace('\\', "/")
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::meshes::model_import::ImportProgress;
+  use crate::parsers::obj_parser;
+
+  fn read_back(scene: &Scene, options: &ExportOptions, name: &str) -> Vec<MeshModel> {
+    let folder = std::env::temp_dir().join(format!("kuplung_obj_{}_{}", std::process::id(), name));
+    std::fs::create_dir_all(&folder).unwrap();
+    let file = folder.join(format!("{}.obj", name));
+    export(scene, &file, options).unwrap();
+    let models = obj_parser::parse(&file, &ImportProgress::default()).unwrap();
+    std::fs::remove_dir_all(&folder).unwrap();
+    models
+  }
+
+  #[test]
+  fn shapes_read_back_the_same() {
+    let options = ExportOptions { bake_transforms: false, ..Default::default() };
+    for shape in ["cube", "cone", "torus", "uv_sphere", "monkey_head", "plane_objects", "brick_wall"] {
+      let mut models = obj_parser::parse(Path::new(&format!("assets/shapes/{}.obj", shape)), &ImportProgress::default()).unwrap();
+      models[0].transform.position = nalgebra_glm::vec3(1.0, 2.0, 3.0);
+      models[0].transform.rotation.y = 45.0;
+      let scene = Scene { models, ..Default::default() };
+      let exported = read_back(&scene, &options, shape);
+      assert_eq!(exported.len(), scene.models.len(), "{}", shape);
+      for (a, b) in scene.models.iter().zip(exported.iter()) {
+        assert_eq!((&a.model_title, a.transform), (&b.model_title, b.transform), "{}", shape);
+        assert_eq!((&a.vertices, &a.texture_coordinates, &a.normals), (&b.vertices, &b.texture_coordinates, &b.normals), "{}", shape);
+        assert_eq!((&a.indices, &a.polygons), (&b.indices, &b.polygons), "{}", shape);
+        let (a, b) = (&a.model_material, &b.model_material);
+        assert_eq!((&a.material_title, a.diffuse_color, a.specular_exp, a.transparency), (&b.material_title, b.diffuse_color, b.specular_exp, b.transparency), "{}", shape);
+        assert_eq!((a.texture_diffuse.use_texture, a.texture_bump.use_texture), (b.texture_diffuse.use_texture, b.texture_bump.use_texture), "{}", shape);
+      }
+    }
+  }
+
+  #[test]
+  fn bakes_and_triangulates() {
+    let quad = MeshModel {
+      model_title: "Quad".to_string(),
+      vertices: vec![nalgebra_glm::vec3(0.0, 0.0, 0.0), nalgebra_glm::vec3(1.0, 0.0, 0.0), nalgebra_glm::vec3(1.0, 1.0, 0.0), nalgebra_glm::vec3(0.0, 1.0, 0.0)],
+      indices: vec![0, 1, 2, 0, 2, 3],
+      polygons: vec![4],
+      ..Default::default()
+    };
+    let mut moved = quad.clone();
+    moved.transform.position.x = 2.0;
+    let scene = Scene { models: vec![quad, moved], ..Default::default() };
+    let files = to_obj(&scene, "quads.mtl", Path::new(""), &ExportOptions::default());
+    assert!(files.obj.contains("f 1 2 3 4\n"));
+    assert!(files.obj.contains("o Quad (2)\n") && files.obj.contains("v 3 1 0\n"));
+    assert_eq!(files.mtl.matches("newmtl Default").count(), 1);
+    let files = to_obj(&scene, "quads.mtl", Path::new(""), &ExportOptions { triangulate: true, ..Default::default() });
+    assert!(files.obj.contains("f 1 2 3\nf 1 3 4\n"));
+  }
+}
@@ -0,0 +1,284 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::path::Path;
+use nalgebra_glm as glm;
+use crate::do_log;
+use crate::meshes::mesh_model::{MeshMaterialTextureImage, MeshModel, MeshModelMaterial, ModelTransform};
+use crate::meshes::model_import::ImportProgress;
+use crate::settings::kuplung_logger;
+
+// Wavefront OBJ and MTL. Every object (o/g) becomes one mesh per material used in it,
+// polygons are triangulated as fans and v/vt/vn triplets are shared within a mesh.
+// A "# Kuplung transform" comment after an object places it, the OBJ exporter writes one for unbaked models.
+
+pub const TRANSFORM_COMMENT: &str = "# Kuplung transform";
+
+const LINES_PER_PROGRESS: usize = 4096;
+
+struct ObjMesh {
+  model: MeshModel,
+  material: String,
+  triplets: HashMap<(usize, Option<usize>, Option<usize>), u32>,
+  has_normals: bool,
+}
+
+impl ObjMesh {
+  fn new(title: &str, material: &str, transform: ModelTransform) -> Self {
+    Self {
+      model: MeshModel { model_title: title.to_string(), transform, ..Default::default() },
+      material: material.to_string(),
+      triplets: HashMap::new(),
+      has_normals: true,
+    }
+  }
+}
+
+pub fn parse(file: &Path, progress: &ImportProgress) -> Result<Vec<MeshModel>, Box<dyn Error>> {
+  let source = std::fs::read_to_string(file).map_err(|e| format!("Cannot read {}: {}", file.display(), e))?;
+  let folder = file.parent().unwrap_or(Path::new(""));
+  let mut models = parse_obj(&source, folder, progress)?;
+  let file_name = file.file_name().map_or("".to_string(), |f| f.to_string_lossy().to_string());
+  for model in models.iter_mut() {
+    model.file = file_name.clone();
+    model.file_path = file.display().to_string();
+  }
+  Ok(models)
+}
+
+fn parse_obj(source: &str, folder: &Path, progress: &ImportProgress) -> Result<Vec<MeshModel>, Box<dyn Error>> {
+  progress.set_stage("Parsing geometry");
+  let mut positions: Vec<glm::Vec3> = Vec::new();
+  let mut uvs: Vec<glm::Vec2> = Vec::new();
+  let mut normals: Vec<glm::Vec3> = Vec::new();
+  let mut materials: HashMap<String, MeshModelMaterial> = HashMap::new();
+  let mut meshes: Vec<ObjMesh> = Vec::new();
+  let mut object = "Object".to_string();
+  let mut material = "".to_string();
+  let mut transform = ModelTransform::default();
+  let mut current: Option<usize> = None;
+  let mut parsed_bytes = 0;
+  for (line_number, line) in source.lines().enumerate() {
+    parsed_bytes += line.len() + 1;
+    if line_number % LINES_PER_PROGRESS == 0 {
+      progress.check_cancelled()?;
+      progress.set_fraction(parsed_bytes as f32 / source.len() as f32);
+    }
+    let error = |what: &str| format!("Line {}: {} in '{}'.", line_number + 1, what, line.trim());
+    if let Some(values) = line.strip_prefix(TRANSFORM_COMMENT) {
+      let values: Vec<f32> = values.split_whitespace().map(|p| p.parse::<f32>()).collect::<Result<_, _>>().map_err(|_| error("Invalid transform"))?;
+      if values.len() != 9 { return Err(error("A transform needs position, rotation and scale").into()); }
+      let vector = |i: usize| glm::vec3(values[i], values[i + 1], values[i + 2]);
+      transform = ModelTransform { position: vector(0), rotation: vector(3), scale: vector(6) };
+      continue;
+    }
+    let mut parts = line.split_whitespace();
+    let Some(keyword) = parts.next() else { continue; };
+    match keyword {
+      "v" => positions.push(parse_vec3(parts).ok_or_else(|| error("Invalid vertex"))?),
+      "vn" => normals.push(parse_vec3(parts).ok_or_else(|| error("Invalid normal"))?),
+      "vt" => {
+        let values: Vec<f32> = parts.map(|p| p.parse::<f32>()).collect::<Result<_, _>>().map_err(|_| error("Invalid texture coordinate"))?;
+        uvs.push(glm::vec2(values.first().copied().unwrap_or(0.0), values.get(1).copied().unwrap_or(0.0)));
+      }
+      "o" | "g" => {
+        let name = parts.collect::<Vec<_>>().join(" ");
+        object = if name.is_empty() { "Object".to_string() } else { name };
+        transform = ModelTransform::default();
+        current = None;
+      }
+      "usemtl" => {
+        material = parts.collect::<Vec<_>>().join(" ");
+        current = None;
+      }
+      "mtllib" => {
+        let library = parts.collect::<Vec<_>>().join(" ");
+        match parse_mtl_file(&folder.join(&library)) {
+          Ok(library_materials) => materials.extend(library_materials),
+          Err(e) => do_log!("[Kuplung] [OBJ] Cannot read the material library {}: {}", library, e),
+        }
+      }
+      "f" => {
+        let index = *current.get_or_insert_with(|| {
+          // a group name repeated later continues its mesh
+          meshes.iter().position(|m| m.model.model_title == object && m.material == material).unwrap_or_else(|| {
+            meshes.push(ObjMesh::new(&object, &material, transform));
+            meshes.len() - 1
+          })
+        });
+        let mesh = &mut meshes[index];
+        let mut polygon = Vec::new();
+        for corner in parts {
+          let triplet = parse_triplet(corner, positions.len(), uvs.len(), normals.len()).ok_or_else(|| error("Invalid face"))?;
+          let vertex = *mesh.triplets.entry(triplet).or_insert_with(|| {
+            let (v, vt, vn) = triplet;
+            mesh.model.vertices.push(positions[v]);
+            mesh.model.texture_coordinates.push(vt.map_or(glm::vec2(0.0, 0.0), |vt| uvs[vt]));
+            mesh.model.normals.push(vn.map_or(glm::Vec3::zeros(), |vn| normals[vn]));
+            mesh.has_normals &= vn.is_some();
+            (mesh.model.vertices.len() - 1) as u32
+          });
+          polygon.push(vertex);
+        }
+        if polygon.len() < 3 { return Err(error("A face needs at least 3 vertices").into()); }
+        for i in 1..polygon.len() - 1 {
+          mesh.model.indices.extend([polygon[0], polygon[i], polygon[i + 1]]);
+        }
+        mesh.model.polygons.push(polygon.len() as u32);
+      }
+      _ => {}
+    }
+  }
+  let models: Vec<MeshModel> = meshes.into_iter().map(|mut mesh| {
+    // missing normals are recomputed after the import options are applied
+    if !mesh.has_normals { mesh.model.normals.clear(); }
+    if !mesh.material.is_empty() {
+      match materials.get(&mesh.material) {
+        Some(material) => mesh.model.model_material = material.clone(),
+        None => {
+          do_log!("[Kuplung] [OBJ] Material {} of {} is not defined.", mesh.material, mesh.model.model_title);
+          mesh.model.model_material.material_title = mesh.material.clone();
+        }
+      }
+    }
+    mesh.model
+  }).collect();
+  if models.is_empty() { return Err("The file has no faces.".into()); }
+  Ok(models)
+}
+
+fn parse_vec3<'a>(mut parts: impl Iterator<Item = &'a str>) -> Option<glm::Vec3> {
+  let mut next = || parts.next()?.parse::<f32>().ok();
+  Some(glm::vec3(next()?, next()?, next()?))
+}
+
+/// "v", "v/vt", "v//vn" or "v/vt/vn", 1-based or negative from the end, to 0-based indices.
+fn parse_triplet(corner: &str, count_v: usize, count_vt: usize, count_vn: usize) -> Option<(usize, Option<usize>, Option<usize>)> {
+  let mut parts = corner.split('/');
+  let index = |part: Option<&str>, count: usize| -> Option<Option<usize>> {
+    match part {
+      None | Some("") => Some(None),
+      Some(part) => {
+        let index = part.parse::<i64>().ok()?;
+        let index = if index < 0 { count as i64 + index } else { index - 1 };
+        (0..count as i64).contains(&index).then_some(Some(index as usize))
+      }
+    }
+  };
+  let v = index(parts.next(), count_v)??;
+  let vt = index(parts.next(), count_vt)?;
+  let vn = index(parts.next(), count_vn)?;
+  Some((v, vt, vn))
+}
+
+fn parse_mtl_file(file: &Path) -> Result<HashMap<String, MeshModelMaterial>, Box<dyn Error>> {
+  let source = std::fs::read_to_string(file)?;
+  Ok(parse_mtl(&source, file.parent().unwrap_or(Path::new(""))))
+}
+
+fn parse_mtl(source: &str, folder: &Path) -> HashMap<String, MeshModelMaterial> {
+  let mut materials = HashMap::new();
+  let mut current: Option<MeshModelMaterial> = None;
+  for line in source.lines() {
+    let mut parts = line.split_whitespace();
+    let Some(keyword) = parts.next() else { continue; };
+    if keyword == "newmtl" {
+      if let Some(material) = current.take() { materials.insert(material.material_title.clone(), material); }
+      current = Some(MeshModelMaterial { material_title: parts.collect::<Vec<_>>().join(" "), ..Default::default() });
+      continue;
+    }
+    let Some(material) = current.as_mut() else { continue; };
+    let value = || parts.clone().next().and_then(|p| p.parse::<f32>().ok());
+    match keyword {
+      "Ka" => if let Some(color) = parse_vec3(parts.clone()) { material.ambient_color = color; },
+      "Kd" => if let Some(color) = parse_vec3(parts.clone()) { material.diffuse_color = color; },
+      "Ks" => if let Some(color) = parse_vec3(parts.clone()) { material.specular_color = color; },
+      "Ke" => if let Some(color) = parse_vec3(parts.clone()) { material.emission_color = color; },
+      "Ns" => if let Some(value) = value() { material.specular_exp = value; },
+      "d" => if let Some(value) = value() { material.transparency = value; },
+      "Tr" => if let Some(value) = value() { material.transparency = 1.0 - value; },
+      "Ni" => if let Some(value) = value() { material.optical_density = value; },
+      "illum" => if let Some(value) = value() { material.illumination_mode = value as u32; },
+      "Pm" => if let Some(value) = value() { material.metallic = value; },
+      "Pr" => if let Some(value) = value() { material.roughness = value; },
+      _ => {
+        let slot = match keyword {
+          "map_Ka" => &mut material.texture_ambient,
+          "map_Kd" => &mut material.texture_diffuse,
+          "map_Ks" => &mut material.texture_specular,
+          "map_Ns" => &mut material.texture_specular_exp,
+          "map_d" => &mut material.texture_dissolve,
+          "map_bump" | "map_Bump" | "bump" | "norm" => &mut material.texture_bump,
+          "disp" | "map_disp" => &mut material.texture_displacement,
+          "map_Ke" => &mut material.texture_emission,
+          _ => continue,
+        };
+        *slot = parse_texture(parts, folder);
+      }
+    }
+  }
+  if let Some(material) = current { materials.insert(material.material_title.clone(), material); }
+  materials
+}
+
+/// The file name after the options of a texture statement, looked up next to the material file.
+fn parse_texture<'a>(parts: impl Iterator<Item = &'a str>, folder: &Path) -> MeshMaterialTextureImage {
+  let parts: Vec<&str> = parts.collect();
+  let mut index = 0;
+  while index < parts.len() && parts[index].starts_with('-') {
+    // -o, -s and -t take up to 3 numbers, -mm 2, the rest 1
+    let arguments = match parts[index] {
+      "-o" | "-s" | "-t" => parts[index + 1..].iter().take(3).take_while(|p| p.parse::<f32>().is_ok()).count(),
+      "-mm" => 2,
+      _ => 1,
+    };
+    index += 1 + arguments;
+  }
+  let filename = parts.get(index..).unwrap_or_default().join(" ");
+  if filename.is_empty() { return MeshMaterialTextureImage::default(); }
+  let name_only = Path::new(&filename.replace('\\', "/")).file_name().map(|f| f.to_owned()).unwrap_or_default();
+  let image = [folder.join(&filename), folder.join(&name_only)].into_iter().find(|p| p.is_file());
+  if image.is_none() { do_log!("[Kuplung] [OBJ] Texture {} was not found in {}.", filename, folder.display()); }
+  MeshMaterialTextureImage {
+    use_texture: image.is_some(),
+    image: image.map_or("".to_string(), |p| p.display().to_string()),
+    filename,
+    ..Default::default()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn parses_the_cube_shape() {
+    let models = parse_obj(include_str!("../../assets/shapes/cube.obj"), Path::new("assets/shapes"), &ImportProgress::default()).unwrap();
+    assert_eq!(models.len(), 1);
+    let cube = &models[0];
+    assert_eq!(cube.model_title, "Cube");
+    assert_eq!(cube.model_material.material_title, "None");
+    assert_eq!(cube.model_material.diffuse_color, glm::vec3(0.8, 0.8, 0.8));
+    assert_eq!((cube.vertices.len(), cube.normals.len(), cube.count_triangles()), (24, 24, 12));
+  }
+
+  #[test]
+  fn splits_objects_and_materials_and_handles_negative_indices() {
+    let obj = "v 0 0 0\nv 1 0 0\nv 1 1 0\nv 0 1 0\nvt 0 0\nvt 1 1\no A\nusemtl red\nf 1/1 2/1 3/2\nusemtl blue\nf -4 -3 -2 -1\no B\nf 1 2 3\n";
+    let models = parse_obj(obj, Path::new(""), &ImportProgress::default()).unwrap();
+    let summary: Vec<(&str, &str, usize, usize)> = models.iter().map(|m| (m.model_title.as_str(), m.model_material.material_title.as_str(), m.vertices.len(), m.indices.len())).collect();
+    assert_eq!(summary, vec![("A", "red", 3, 3), ("A", "blue", 4, 6), ("B", "blue", 3, 3)]);
+    assert!(models[0].normals.is_empty());
+    assert_eq!(models[0].texture_coordinates[2], glm::vec2(1.0, 1.0));
+    assert!(parse_obj("v 0 0 0\nf 1 2 3\n", Path::new(""), &ImportProgress::default()).is_err());
+  }
+
+  #[test]
+  fn parses_materials_and_texture_options() {
+    let materials = parse_mtl("newmtl Brick\nKd 0.5 0.25 1\nd 0.5\nmap_Kd -s 2 2 1 -bm 0.5 brick wall.png\n", Path::new(""));
+    let brick = &materials["Brick"];
+    assert_eq!(brick.diffuse_color, glm::vec3(0.5, 0.25, 1.0));
+    assert_eq!(brick.transparency, 0.5);
+    assert_eq!(brick.texture_diffuse.filename, "brick wall.png");
+    assert!(!brick.texture_diffuse.use_texture);
+  }
+}
@@ -0,0 +1,100 @@
+use std::error::Error;
+use std::fmt::Write;
+use std::path::Path;
+use nalgebra_glm as glm;
+use crate::do_log;
+use crate::meshes::mesh_model::MeshModel;
+use crate::meshes::model_export::ExportOptions;
+use crate::meshes::scene::Scene;
+use crate::parsers::ply_parser::PlyFormat;
+use crate::settings::kuplung_logger;
+
+// PLY, text or binary little endian. The models are placed in the scene, scaled to the unit of the export
+// and written as one mesh. With vertex colors on any model, the others get the diffuse color of their material.
+
+pub fn export(scene: &Scene, file: &Path, options: &ExportOptions) -> Result<(), Box<dyn Error>> {
+  let model = merge(scene, 1.0 / options.unit.meters());
+  let format = if options.binary { PlyFormat::BinaryLittleEndian } else { PlyFormat::Ascii };
+  std::fs::write(file, to_bytes(&model, format))?;
+  do_log!("[Kuplung] [PLY] Exported {} vertices and {} triangles to {}.", model.vertices.len(), model.count_triangles(), file.display());
+  Ok(())
+}
+
+fn merge(scene: &Scene, scale: f32) -> MeshModel {
+  let has_colors = scene.models.iter().any(|m| !m.colors.is_empty());
+  let mut merged = MeshModel::default();
+  for model in scene.models.iter() {
+    let model = model.baked(scale);
+    let offset = merged.vertices.len() as u32;
+    merged.indices.extend(model.indices.iter().map(|i| i + offset));
+    let count = model.vertices.len();
+    merged.vertices.extend(model.vertices);
+    merged.normals.extend(if model.normals.len() == count { model.normals } else { vec![glm::Vec3::zeros(); count] });
+    merged.texture_coordinates.extend(if model.texture_coordinates.len() == count { model.texture_coordinates } else { vec![glm::Vec2::zeros(); count] });
+    if has_colors {
+      let material = &model.model_material;
+      let diffuse = material.diffuse_color.push(material.transparency);
+      merged.colors.extend(if model.colors.len() == count { model.colors } else { vec![diffuse; count] });
+    }
+  }
+  merged
+}
+
+/// Normals, texture coordinates and colors are written when the model has them for every vertex.
+pub fn to_bytes(model: &MeshModel, format: PlyFormat) -> Vec<u8> {
+  let count = model.vertices.len();
+  let has_normals = model.normals.len() == count;
+  let has_uvs = model.texture_coordinates.len() == count;
+  let has_colors = model.colors.len() == count;
+  let mut header = String::from("ply\n");
+  let _ = writeln!(header, "format {} 1.0", match format {
+    PlyFormat::Ascii => "ascii",
+    PlyFormat::BinaryLittleEndian => "binary_little_endian",
+    PlyFormat::BinaryBigEndian => "binary_big_endian",
+  });
+  header.push_str("comment Kuplung\n");
+  let _ = writeln!(header, "element vertex {}", count);
+  header.push_str("property float x\nproperty float y\nproperty float z\n");
+  if has_normals { header.push_str("property float nx\nproperty float ny\nproperty float nz\n"); }
+  if has_uvs { header.push_str("property float s\nproperty float t\n"); }
+  if has_colors { header.push_str("property uchar red\nproperty uchar green\nproperty uchar blue\nproperty uchar alpha\n"); }
+  let _ = writeln!(header, "element face {}", model.count_triangles());
+  header.push_str("property list uchar int vertex_indices\nend_header\n");
+
+  let mut vertices: Vec<(Vec<f32>, [u8; 4])> = Vec::with_capacity(count);
+  for index in 0..count {
+    let mut floats = model.vertices[index].as_slice().to_vec();
+    if has_normals { floats.extend_from_slice(model.normals[index].as_slice()); }
+    if has_uvs { floats.extend_from_slice(model.texture_coordinates[index].as_slice()); }
+    let color = if has_colors { model.colors[index].map(|c| (c.clamp(0.0, 1.0) * 255.0).round() as u8).into() } else { [0; 4] };
+    vertices.push((floats, color));
+  }
+  let color_count = if has_colors { 4 } else { 0 };
+  match format {
+    PlyFormat::Ascii => {
+      let mut body = header;
+      for (floats, color) in vertices.iter() {
+        let values: Vec<String> = floats.iter().map(|f| f.to_string()).chain(color[..color_count].iter().map(|c| c.to_string())).collect();
+        let _ = writeln!(body, "{}", values.join(" "));
+      }
+      for triangle in model.indices.chunks_exact(3) {
+        let _ = writeln!(body, "3 {} {} {}", triangle[0], triangle[1], triangle[2]);
+      }
+      body.into_bytes()
+    }
+    PlyFormat::BinaryLittleEndian | PlyFormat::BinaryBigEndian => {
+      let big_endian = format == PlyFormat::BinaryBigEndian;
+      let mut body = header.into_bytes();
+      let word = |body: &mut Vec<u8>, bytes: [u8; 4]| body.extend(if big_endian { [bytes[3], bytes[2], bytes[1], bytes[0]] } else { bytes });
+      for (floats, color) in vertices.iter() {
+        for value in floats { word(&mut body, value.to_le_bytes()); }
+        body.extend_from_slice(&color[..color_count]);
+      }
+      for triangle in model.indices.chunks_exact(3) {
+        body.push(3);
+        for index in triangle { word(&mut body, (*index as i32).to_le_bytes()); }
+      }
+      body
+    }
+  }
+}
\ No newline at end of file
@@ -0,0 +1,273 @@
+use std::error::Error;
+use std::path::Path;
+use nalgebra_glm as glm;
+use crate::meshes::mesh_model::MeshModel;
+use crate::meshes::model_import::ImportProgress;
+
+// PLY, text and binary in both byte orders. The vertex element gives positions, normals, texture coordinates
+// and colors, the face element polygons that are triangulated as fans. Other elements are read past.
+
+const ELEMENTS_PER_PROGRESS: usize = 4096;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PlyFormat {
+  Ascii,
+  BinaryLittleEndian,
+  BinaryBigEndian,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum PlyType {
+  Int8,
+  UInt8,
+  Int16,
+  UInt16,
+  Int32,
+  UInt32,
+  Float32,
+  Float64,
+}
+
+impl PlyType {
+  fn parse(name: &str) -> Option<Self> {
+    Some(match name {
+      "char" | "int8" => Self::Int8,
+      "uchar" | "uint8" => Self::UInt8,
+      "short" | "int16" => Self::Int16,
+      "ushort" | "uint16" => Self::UInt16,
+      "int" | "int32" => Self::Int32,
+      "uint" | "uint32" => Self::UInt32,
+      "float" | "float32" => Self::Float32,
+      "double" | "float64" => Self::Float64,
+      _ => return None,
+    })
+  }
+
+  fn size(self) -> usize {
+    match self {
+      Self::Int8 | Self::UInt8 => 1,
+      Self::Int16 | Self::UInt16 => 2,
+      Self::Int32 | Self::UInt32 | Self::Float32 => 4,
+      Self::Float64 => 8,
+    }
+  }
+
+  /// What a color component of this type is divided by.
+  fn color_range(self) -> f64 {
+    match self {
+      Self::Float32 | Self::Float64 => 1.0,
+      Self::UInt16 | Self::Int16 => 65535.0,
+      _ => 255.0,
+    }
+  }
+}
+
+#[derive(Debug)]
+enum PlyProperty {
+  Scalar { name: String, kind: PlyType },
+  List { name: String, count: PlyType, item: PlyType },
+}
+
+#[derive(Debug)]
+struct PlyElement {
+  name: String,
+  count: usize,
+  properties: Vec<PlyProperty>,
+}
+
+/// The values after the header, as text tokens or bytes.
+enum PlyBody<'a> {
+  Ascii(std::str::SplitAsciiWhitespace<'a>),
+  Binary { data: &'a [u8], offset: usize, big_endian: bool },
+}
+
+impl PlyBody<'_> {
+  fn read(&mut self, kind: PlyType) -> Result<f64, Box<dyn Error>> {
+    match self {
+      Self::Ascii(tokens) => {
+        let token = tokens.next().ok_or("The PLY file ends too early.")?;
+        Ok(token.parse::<f64>().map_err(|_| format!("Invalid PLY value {}.", token))?)
+      }
+      Self::Binary { data, offset, big_endian } => {
+        let size = kind.size();
+        let bytes = data.get(*offset..*offset + size).ok_or("The PLY file ends too early.")?;
+        *offset += size;
+        let mut word = [0u8; 8];
+        word[..size].copy_from_slice(bytes);
+        if *big_endian { word[..size].reverse(); }
+        Ok(match kind {
+          PlyType::Int8 => word[0] as i8 as f64,
+          PlyType::UInt8 => word[0] as f64,
+          PlyType::Int16 => i16::from_le_bytes([word[0], word[1]]) as f64,
+          PlyType::UInt16 => u16::from_le_bytes([word[0], word[1]]) as f64,
+          PlyType::Int32 => i32::from_le_bytes([word[0], word[1], word[2], word[3]]) as f64,
+          PlyType::UInt32 => u32::from_le_bytes([word[0], word[1], word[2], word[3]]) as f64,
+          PlyType::Float32 => f32::from_le_bytes([word[0], word[1], word[2], word[3]]) as f64,
+          PlyType::Float64 => f64::from_le_bytes(word),
+        })
+      }
+    }
+  }
+}
+
+pub fn parse(file: &Path, progress: &ImportProgress) -> Result<Vec<MeshModel>, Box<dyn Error>> {
+  let bytes = std::fs::read(file).map_err(|e| format!("Cannot read {}: {}", file.display(), e))?;
+  let mut model = parse_bytes(&bytes, progress)?;
+  model.model_title = file.file_stem().map_or("".to_string(), |f| f.to_string_lossy().to_string());
+  model.file = file.file_name().map_or("".to_string(), |f| f.to_string_lossy().to_string());
+  model.file_path = file.display().to_string();
+  Ok(vec![model])
+}
+
+pub fn parse_bytes(bytes: &[u8], progress: &ImportProgress) -> Result<MeshModel, Box<dyn Error>> {
+  progress.set_stage("Parsing elements");
+  let header_end = bytes.windows(10).position(|w| w == b"end_header").ok_or("Not a PLY file - there is no end_header.")?;
+  let body_start = bytes[header_end..].iter().position(|b| *b == b'\n').map_or(bytes.len(), |p| header_end + p + 1);
+  let (format, elements) = parse_header(std::str::from_utf8(&bytes[..header_end])?)?;
+  let mut body = match format {
+    PlyFormat::Ascii => PlyBody::Ascii(std::str::from_utf8(&bytes[body_start..])?.split_ascii_whitespace()),
+    PlyFormat::BinaryLittleEndian => PlyBody::Binary { data: bytes, offset: body_start, big_endian: false },
+    PlyFormat::BinaryBigEndian => PlyBody::Binary { data: bytes, offset: body_start, big_endian: true },
+  };
+  let mut model = MeshModel::default();
+  let total: usize = elements.iter().map(|e| e.count).sum::<usize>().max(1);
+  let mut done = 0;
+  for element in elements.iter() {
+    let mut values = vec![0.0; element.properties.len()];
+    let mut list = Vec::new();
+    for _ in 0..element.count {
+      if done % ELEMENTS_PER_PROGRESS == 0 {
+        progress.check_cancelled()?;
+        progress.set_fraction(done as f32 / total as f32);
+      }
+      done += 1;
+      for (index, property) in element.properties.iter().enumerate() {
+        match property {
+          PlyProperty::Scalar { kind, .. } => values[index] = body.read(*kind)?,
+          PlyProperty::List { name, count, item } => {
+            let count = body.read(*count)? as usize;
+            let items = (0..count).map(|_| body.read(*item)).collect::<Result<Vec<_>, _>>()?;
+            if name == "vertex_indices" || name == "vertex_index" { list = items; }
+          }
+        }
+      }
+      match element.name.as_str() {
+        "vertex" => add_vertex(&mut model, element, &values),
+        "face" => {
+          // the vertices can come after the faces, the indices are checked once everything is read
+          for index in 1..list.len().saturating_sub(1) {
+            model.indices.extend([list[0], list[index], list[index + 1]].map(|i| i as u32));
+          }
+        }
+        _ => {}
+      }
+    }
+  }
+  if model.indices.iter().any(|i| *i as usize >= model.vertices.len()) { return Err("A PLY face uses a vertex that doesn't exist.".into()); }
+  Ok(model)
+}
+
+fn parse_header(header: &str) -> Result<(PlyFormat, Vec<PlyElement>), Box<dyn Error>> {
+  let mut lines = header.lines();
+  if lines.next().map(str::trim) != Some("ply") { return Err("Not a PLY file.".into()); }
+  let mut format = None;
+  let mut elements: Vec<PlyElement> = Vec::new();
+  for line in lines {
+    let parts: Vec<&str> = line.split_whitespace().collect();
+    let error = || format!("Invalid PLY header line: {}", line.trim());
+    match parts.as_slice() {
+      ["format", name, _version] => {
+        format = Some(match *name {
+          "ascii" => PlyFormat::Ascii,
+          "binary_little_endian" => PlyFormat::BinaryLittleEndian,
+          "binary_big_endian" => PlyFormat::BinaryBigEndian,
+          _ => return Err(format!("Unknown PLY format {}.", name).into()),
+        });
+      }
+      ["element", name, count] => elements.push(PlyElement { name: name.to_string(), count: count.parse().map_err(|_| error())?, properties: Vec::new() }),
+      ["property", "list", count, item, name] => {
+        let (Some(count), Some(item)) = (PlyType::parse(count), PlyType::parse(item)) else { return Err(error().into()); };
+        elements.last_mut().ok_or_else(error)?.properties.push(PlyProperty::List { name: name.to_string(), count, item });
+      }
+      ["property", kind, name] => {
+        let kind = PlyType::parse(kind).ok_or_else(error)?;
+        elements.last_mut().ok_or_else(error)?.properties.push(PlyProperty::Scalar { name: name.to_string(), kind });
+      }
+      ["comment", ..] | ["obj_info", ..] | [] => {}
+      _ => return Err(error().into()),
+    }
+  }
+  Ok((format.ok_or("The PLY header has no format.")?, elements))
+}
+
+fn add_vertex(model: &mut MeshModel, element: &PlyElement, values: &[f64]) {
+  let mut position = glm::Vec3::zeros();
+  let mut normal = None;
+  let mut uv = glm::Vec2::zeros();
+  let mut color = None;
+  for (property, value) in element.properties.iter().zip(values) {
+    let PlyProperty::Scalar { name, kind } = property else { continue; };
+    let value = *value as f32;
+    let channel = |color: &mut Option<glm::Vec4>, channel: usize| color.get_or_insert(glm::vec4(1.0, 1.0, 1.0, 1.0))[channel] = value / kind.color_range() as f32;
+    match name.as_str() {
+      "x" => position.x = value,
+      "y" => position.y = value,
+      "z" => position.z = value,
+      "nx" => normal.get_or_insert(glm::Vec3::zeros()).x = value,
+      "ny" => normal.get_or_insert(glm::Vec3::zeros()).y = value,
+      "nz" => normal.get_or_insert(glm::Vec3::zeros()).z = value,
+      "s" | "u" | "texture_u" => uv.x = value,
+      "t" | "v" | "texture_v" => uv.y = value,
+      "red" | "diffuse_red" => channel(&mut color, 0),
+      "green" | "diffuse_green" => channel(&mut color, 1),
+      "blue" | "diffuse_blue" => channel(&mut color, 2),
+      "alpha" => channel(&mut color, 3),
+      _ => {}
+    }
+  }
+  model.vertices.push(position);
+  model.texture_coordinates.push(uv);
+  // normals and colors only count when every vertex has them, the missing normals are recomputed
+  if let Some(normal) = normal { model.normals.push(normal); }
+  if let Some(color) = color { model.colors.push(color); }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::parsers::ply_exporter;
+
+  #[test]
+  fn reads_colors_quads_and_unknown_elements() {
+    let ply = "ply\nformat ascii 1.0\ncomment made by hand\nelement vertex 4\nproperty float x\nproperty float y\nproperty float z\n\
+      property uchar red\nproperty uchar green\nproperty uchar blue\nelement face 1\nproperty list uchar int vertex_indices\n\
+      element edge 1\nproperty int vertex1\nproperty int vertex2\nend_header\n\
+      0 0 0 255 0 0\n1 0 0 0 255 0\n1 1 0 0 0 255\n0 1 0 51 51 51\n4 0 1 2 3\n0 1\n";
+    let model = parse_bytes(ply.as_bytes(), &ImportProgress::default()).unwrap();
+    assert_eq!(model.vertices.len(), 4);
+    assert_eq!(model.indices, vec![0, 1, 2, 0, 2, 3]);
+    assert!(model.normals.is_empty());
+    assert_eq!(model.colors[0], glm::vec4(1.0, 0.0, 0.0, 1.0));
+    assert_eq!(model.colors[3], glm::vec4(0.2, 0.2, 0.2, 1.0));
+  }
+
+  #[test]
+  fn text_and_binary_round_trip() {
+    let model = MeshModel {
+      vertices: vec![glm::vec3(0.0, 0.0, 0.0), glm::vec3(1.5, 0.0, 0.0), glm::vec3(0.0, -2.0, 0.25)],
+      normals: vec![glm::vec3(0.0, 0.0, 1.0); 3],
+      texture_coordinates: vec![glm::vec2(0.0, 0.0), glm::vec2(1.0, 0.0), glm::vec2(0.0, 1.0)],
+      colors: vec![glm::vec4(1.0, 0.0, 0.0, 1.0), glm::vec4(0.0, 1.0, 0.0, 1.0), glm::vec4(0.0, 0.0, 1.0, 0.2)],
+      indices: vec![0, 1, 2],
+      ..Default::default()
+    };
+    for format in [PlyFormat::Ascii, PlyFormat::BinaryLittleEndian, PlyFormat::BinaryBigEndian] {
+      let parsed = parse_bytes(&ply_exporter::to_bytes(&model, format), &ImportProgress::default()).unwrap();
+      assert_eq!(parsed.vertices, model.vertices);
+      assert_eq!(parsed.normals, model.normals);
+      assert_eq!(parsed.texture_coordinates, model.texture_coordinates);
+      assert_eq!(parsed.colors, model.colors);
+      assert_eq!(parsed.indices, model.indices);
+    }
+  }
+}
\ No newline at end of file
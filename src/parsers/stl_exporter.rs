@@ -0,0 +1,61 @@
+use std::error::Error;
+use std::fmt::Write;
+use std::path::Path;
+use nalgebra_glm as glm;
+use crate::do_log;
+use crate::meshes::mesh_model::MeshModel;
+use crate::meshes::model_export::ExportOptions;
+use crate::meshes::scene::Scene;
+use crate::settings::kuplung_logger;
+
+// STL, text or binary. STL has no transforms and no units, so the models are placed in the scene
+// and scaled to the unit of the export. The text format keeps a solid per model, the binary one has a single solid.
+
+pub fn export(scene: &Scene, file: &Path, options: &ExportOptions) -> Result<(), Box<dyn Error>> {
+  let models: Vec<MeshModel> = scene.models.iter().map(|m| m.baked(1.0 / options.unit.meters())).collect();
+  let bytes = if options.binary { to_binary(&models) } else { to_ascii(&models).into_bytes() };
+  std::fs::write(file, bytes)?;
+  do_log!("[Kuplung] [STL] Exported {} triangles to {}.", models.iter().map(|m| m.count_triangles()).sum::<usize>(), file.display());
+  Ok(())
+}
+
+fn facets(model: &MeshModel) -> impl Iterator<Item = (glm::Vec3, [glm::Vec3; 3])> + '_ {
+  model.indices.chunks_exact(3).map(|triangle| {
+    let corners = [0, 1, 2].map(|i| model.vertices[triangle[i] as usize]);
+    let normal = glm::cross(&(corners[1] - corners[0]), &(corners[2] - corners[0])).try_normalize(1e-12).unwrap_or_else(glm::Vec3::zeros);
+    (normal, corners)
+  })
+}
+
+pub fn to_ascii(models: &[MeshModel]) -> String {
+  let mut stl = String::new();
+  for model in models {
+    let _ = writeln!(stl, "solid {}", model.model_title);
+    for (normal, corners) in facets(model) {
+      let _ = writeln!(stl, "  facet normal {} {} {}", normal.x, normal.y, normal.z);
+      stl.push_str("    outer loop\n");
+      for corner in corners {
+        let _ = writeln!(stl, "      vertex {} {} {}", corner.x, corner.y, corner.z);
+      }
+      stl.push_str("    endloop\n  endfacet\n");
+    }
+    let _ = writeln!(stl, "endsolid {}", model.model_title);
+  }
+  stl
+}
+
+pub fn to_binary(models: &[MeshModel]) -> Vec<u8> {
+  let count: usize = models.iter().map(|m| m.count_triangles()).sum();
+  let mut stl = Vec::with_capacity(84 + count * 50);
+  let mut header = b"Kuplung STL".to_vec();
+  header.resize(80, b' ');
+  stl.extend_from_slice(&header);
+  stl.extend_from_slice(&(count as u32).to_le_bytes());
+  for (normal, corners) in models.iter().flat_map(facets) {
+    for vector in [normal, corners[0], corners[1], corners[2]] {
+      for value in [vector.x, vector.y, vector.z] { stl.extend_from_slice(&value.to_le_bytes()); }
+    }
+    stl.extend_from_slice(&0u16.to_le_bytes());
+  }
+  stl
+}
\ No newline at end of file
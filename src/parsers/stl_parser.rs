@@ -0,0 +1,155 @@
+use std::error::Error;
+use std::path::Path;
+use nalgebra_glm as glm;
+use crate::meshes::mesh_model::MeshModel;
+use crate::meshes::model_import::ImportProgress;
+
+// STL, text and binary. Every solid becomes a mesh with the facet normals on its corners,
+// so nothing is shared between triangles - the mesh report welds the corners when it needs the topology.
+
+const TRIANGLES_PER_PROGRESS: usize = 4096;
+const BINARY_HEADER: usize = 80;
+const BINARY_TRIANGLE: usize = 50;
+
+pub fn parse(file: &Path, progress: &ImportProgress) -> Result<Vec<MeshModel>, Box<dyn Error>> {
+  let bytes = std::fs::read(file).map_err(|e| format!("Cannot read {}: {}", file.display(), e))?;
+  let mut models = parse_bytes(&bytes, progress)?;
+  let file_name = file.file_name().map_or("".to_string(), |f| f.to_string_lossy().to_string());
+  let file_stem = file.file_stem().map_or("".to_string(), |f| f.to_string_lossy().to_string());
+  for model in models.iter_mut() {
+    if model.model_title.is_empty() { model.model_title = file_stem.clone(); }
+    model.file = file_name.clone();
+    model.file_path = file.display().to_string();
+  }
+  Ok(models)
+}
+
+/// Meshes without a solid name have an empty title.
+pub fn parse_bytes(bytes: &[u8], progress: &ImportProgress) -> Result<Vec<MeshModel>, Box<dyn Error>> {
+  progress.set_stage("Parsing triangles");
+  if is_binary(bytes) { parse_binary(bytes, progress).map(|model| vec![model]) } else { parse_ascii(std::str::from_utf8(bytes)?, progress) }
+}
+
+/// Binary files can start with "solid" too, the size tells them apart.
+fn is_binary(bytes: &[u8]) -> bool {
+  if let Some(count) = bytes.get(BINARY_HEADER..BINARY_HEADER + 4) {
+    let count = u32::from_le_bytes([count[0], count[1], count[2], count[3]]) as usize;
+    if BINARY_HEADER + 4 + count * BINARY_TRIANGLE == bytes.len() { return true; }
+  }
+  !bytes.trim_ascii_start().starts_with(b"solid")
+}
+
+fn add_triangle(model: &mut MeshModel, normal: glm::Vec3, corners: [glm::Vec3; 3]) {
+  let normal = normal.try_normalize(1e-12).unwrap_or_else(|| {
+    glm::cross(&(corners[1] - corners[0]), &(corners[2] - corners[0])).try_normalize(1e-12).unwrap_or(glm::vec3(0.0, 1.0, 0.0))
+  });
+  for corner in corners {
+    model.indices.push(model.vertices.len() as u32);
+    model.vertices.push(corner);
+    model.normals.push(normal);
+    model.texture_coordinates.push(glm::Vec2::zeros());
+  }
+}
+
+fn parse_binary(bytes: &[u8], progress: &ImportProgress) -> Result<MeshModel, Box<dyn Error>> {
+  let count = bytes.get(BINARY_HEADER..BINARY_HEADER + 4).ok_or("The STL file is truncated.")?;
+  let count = u32::from_le_bytes([count[0], count[1], count[2], count[3]]) as usize;
+  if bytes.len() < BINARY_HEADER + 4 + count * BINARY_TRIANGLE { return Err(format!("The STL file is truncated, it should have {} triangles.", count).into()); }
+  let mut model = MeshModel::default();
+  for index in 0..count {
+    if index % TRIANGLES_PER_PROGRESS == 0 {
+      progress.check_cancelled()?;
+      progress.set_fraction(index as f32 / count as f32);
+    }
+    let triangle = &bytes[BINARY_HEADER + 4 + index * BINARY_TRIANGLE..];
+    let vector = |i: usize| {
+      let float = |j: usize| f32::from_le_bytes([triangle[i * 12 + j * 4], triangle[i * 12 + j * 4 + 1], triangle[i * 12 + j * 4 + 2], triangle[i * 12 + j * 4 + 3]]);
+      glm::vec3(float(0), float(1), float(2))
+    };
+    add_triangle(&mut model, vector(0), [vector(1), vector(2), vector(3)]);
+  }
+  Ok(model)
+}
+
+fn parse_ascii(source: &str, progress: &ImportProgress) -> Result<Vec<MeshModel>, Box<dyn Error>> {
+  let mut models = Vec::new();
+  let mut model: Option<MeshModel> = None;
+  let mut normal = glm::Vec3::zeros();
+  let mut corners = Vec::with_capacity(3);
+  let mut parsed_bytes = 0;
+  for (line_number, line) in source.lines().enumerate() {
+    parsed_bytes += line.len() + 1;
+    if line_number % (TRIANGLES_PER_PROGRESS * 7) == 0 {
+      progress.check_cancelled()?;
+      progress.set_fraction(parsed_bytes as f32 / source.len() as f32);
+    }
+    let error = |message: &str| format!("{} at line {}: {}", message, line_number + 1, line.trim());
+    let mut parts = line.split_whitespace();
+    let vector = |parts: std::str::SplitWhitespace| -> Option<glm::Vec3> {
+      let values: Vec<f32> = parts.map(|p| p.parse().ok()).collect::<Option<_>>()?;
+      (values.len() == 3).then(|| glm::vec3(values[0], values[1], values[2]))
+    };
+    match parts.next() {
+      Some("solid") => {
+        let title = line.trim_start().trim_start_matches("solid").trim();
+        models.extend(model.replace(MeshModel { model_title: title.to_string(), ..Default::default() }));
+      }
+      Some("facet") => {
+        if parts.next() != Some("normal") { return Err(error("Expected a facet normal").into()); }
+        normal = vector(parts).ok_or_else(|| error("Invalid normal"))?;
+        corners.clear();
+      }
+      Some("vertex") => corners.push(vector(parts).ok_or_else(|| error("Invalid vertex"))?),
+      Some("endloop") => {
+        let model = model.as_mut().ok_or_else(|| error("Facet outside of a solid"))?;
+        // some writers put polygons in a loop
+        for index in 1..corners.len().saturating_sub(1) {
+          add_triangle(model, normal, [corners[0], corners[index], corners[index + 1]]);
+        }
+      }
+      Some("endsolid") => models.extend(model.take()),
+      _ => {}
+    }
+  }
+  models.extend(model);
+  Ok(models)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::parsers::stl_exporter;
+
+  fn tetrahedron() -> MeshModel {
+    MeshModel {
+      model_title: "Tetra".to_string(),
+      vertices: vec![glm::vec3(0.0, 0.0, 0.0), glm::vec3(1.0, 0.0, 0.0), glm::vec3(0.0, 1.0, 0.0), glm::vec3(0.0, 0.0, 1.0)],
+      indices: vec![0, 2, 1, 0, 1, 3, 0, 3, 2, 1, 2, 3],
+      ..Default::default()
+    }
+  }
+
+  #[test]
+  fn text_and_binary_round_trip() {
+    let models = vec![tetrahedron()];
+    for bytes in [stl_exporter::to_ascii(&models).into_bytes(), stl_exporter::to_binary(&models)] {
+      let parsed = parse_bytes(&bytes, &ImportProgress::default()).unwrap();
+      assert_eq!(parsed.len(), 1);
+      assert_eq!(parsed[0].count_triangles(), 4);
+      assert_eq!(parsed[0].vertices[0..3], [glm::vec3(0.0, 0.0, 0.0), glm::vec3(0.0, 1.0, 0.0), glm::vec3(1.0, 0.0, 0.0)]);
+      assert_eq!(parsed[0].normals[0], glm::vec3(0.0, 0.0, -1.0));
+    }
+    assert_eq!(parse_bytes(stl_exporter::to_ascii(&models).as_bytes(), &ImportProgress::default()).unwrap()[0].model_title, "Tetra");
+  }
+
+  #[test]
+  fn binary_files_starting_with_solid_are_binary() {
+    let mut bytes = stl_exporter::to_binary(&[tetrahedron()]);
+    bytes[..5].copy_from_slice(b"solid");
+    assert!(is_binary(&bytes));
+    let ascii = "solid cube\nfacet normal 0 0 0\nouter loop\nvertex 0 0 0\nvertex 1 0 0\nvertex 1 1 0\nvertex 0 1 0\nendloop\nendfacet\nendsolid cube\n";
+    let parsed = parse_bytes(ascii.as_bytes(), &ImportProgress::default()).unwrap();
+    // the quad is split in two and the missing normal computed
+    assert_eq!((parsed[0].count_triangles(), parsed[0].normals[0]), (2, glm::vec3(0.0, 0.0, 1.0)));
+  }
+}
\ No newline at end of file
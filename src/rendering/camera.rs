@@ -0,0 +1,67 @@
+use nalgebra_glm as glm;
+use crate::settings::kuplung_settings;
+
+const PITCH_LIMIT: f32 = 89.0;
+const DISTANCE_MIN: f32 = 0.1;
+
+/// Orbits around a target - dragging turns it, scrolling moves it closer.
+pub struct Camera {
+  pub target: glm::Vec3,
+  pub yaw: f32,
+  pub pitch: f32,
+  pub distance: f32,
+}
+
+impl Default for Camera {
+  fn default() -> Self {
+    Self {
+      target: glm::Vec3::zeros(),
+      yaw: 45.0,
+      pitch: 25.0,
+      distance: 10.0,
+    }
+  }
+}
+
+impl Camera {
+  pub fn position(&self) -> glm::Vec3 {
+    let (yaw, pitch) = (self.yaw.to_radians(), self.pitch.to_radians());
+    self.target + glm::vec3(pitch.cos() * yaw.sin(), pitch.sin(), pitch.cos() * yaw.cos()) * self.distance
+  }
+
+  pub fn view_matrix(&self) -> glm::Mat4 {
+    glm::look_at(&self.position(), &self.target, &glm::vec3(0.0, 1.0, 0.0))
+  }
+
+  /// Field of view and clipping planes from the rendering settings.
+  pub fn projection_matrix(&self, aspect: f32) -> glm::Mat4 {
+    let general = kuplung_settings::get_rendering().general;
+    glm::perspective(aspect.max(0.01), general.fov.to_radians(), general.plane_close, general.plane_far)
+  }
+
+  /// Drag in points - a full window width is about a half turn.
+  pub fn orbit(&mut self, delta: egui::Vec2) {
+    self.yaw = (self.yaw - delta.x * 0.5) % 360.0;
+    self.pitch = (self.pitch + delta.y * 0.5).clamp(-PITCH_LIMIT, PITCH_LIMIT);
+  }
+
+  pub fn zoom(&mut self, scroll: f32) {
+    self.distance = (self.distance * (1.0 - scroll * 0.001)).max(DISTANCE_MIN);
+  }
+
+  /// Moves the orbit to look from `position` along `direction`, keeping the distance to the target.
+  pub fn look_along(&mut self, position: &glm::Vec3, direction: &glm::Vec3) {
+    let Some(direction) = direction.try_normalize(1e-6) else { return; };
+    self.target = position + direction * self.distance;
+    self.pitch = (-direction.y).asin().to_degrees().clamp(-PITCH_LIMIT, PITCH_LIMIT);
+    self.yaw = (-direction.x).atan2(-direction.z).to_degrees();
+  }
+
+  /// Looks at the center of the bounds from far enough to see all of it.
+  pub fn frame(&mut self, (min, max): (glm::Vec3, glm::Vec3)) {
+    let fov = kuplung_settings::get_rendering().general.fov.to_radians();
+    self.target = (min + max) * 0.5;
+    let radius = (glm::distance(&min, &max) * 0.5).max(DISTANCE_MIN);
+    self.distance = radius / (fov * 0.5).sin() * 1.1;
+  }
+}
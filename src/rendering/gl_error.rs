@@ -0,0 +1,34 @@
+use std::fmt;
+
+/// Why a GL program or resource could not be created.
+#[derive(Clone, Debug)]
+pub enum GLError {
+  NoContext,
+  ShaderLoad { file: String, reason: String },
+  ShaderCompile { shader: String, log: String },
+  ProgramLink { program: String, log: String },
+  MissingUniform { program: String, uniform: String },
+  ResourceCreation { resource: String, reason: String },
+}
+
+impl GLError {
+  /// For mapping the `Err(String)` of the glow create_* functions.
+  pub fn resource(resource: &str) -> impl FnOnce(String) -> GLError + '_ {
+    move |reason| GLError::ResourceCreation { resource: resource.to_string(), reason }
+  }
+}
+
+impl fmt::Display for GLError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      GLError::NoContext => write!(f, "No OpenGL context is available."),
+      GLError::ShaderLoad { file, reason } => write!(f, "Cannot load shader {}: {}", file, reason),
+      GLError::ShaderCompile { shader, log } => write!(f, "Cannot compile shader {}:\n{}", shader, log),
+      GLError::ProgramLink { program, log } => write!(f, "Cannot link program {}:\n{}", program, log),
+      GLError::MissingUniform { program, uniform } => write!(f, "Program {} has no active uniform {}.", program, uniform),
+      GLError::ResourceCreation { resource, reason } => write!(f, "Cannot create {}: {}", resource, reason),
+    }
+  }
+}
+
+impl std::error::Error for GLError {}
@@ -1,56 +1,64 @@
-use std::ffi::CString;
-use std::io::Read;
 use eframe::glow;
 use eframe::glow::HasContext;
-use egui::TextBuffer;
 use crate::do_log;
+use crate::rendering::gl_error::GLError;
+use crate::rendering::shader_preprocessor::{ShaderPreprocessor, ShaderSource};
 use crate::settings::kuplung_logger;
 
-pub unsafe fn create_shader(program: &glow::Program, gl: &glow::Context, shader_type: u32, shader_filepath: &str) -> glow::Shader {
-  do_log!("[Kuplung] [GLUtils] Loading shader file {}", shader_filepath);
-
-  let mut shader_file = std::fs::File::open(shader_filepath).expect(format!("[Kuplung] [GLUtils] Cannot find the shader file specified = {}!", stringify!(shader_filepath)).as_str());
-  let mut shader_buffer: Vec<u8> = Vec::new();
-  shader_file.read_to_end(&mut shader_buffer).unwrap();
-  let shader_source = CString::new(shader_buffer).unwrap();
-
-  let shader = gl.create_shader(shader_type).expect("[Kuplung] [GLUtils] Cannot create shader");
-  gl.shader_source(shader, shader_source.to_str().unwrap());
-  gl.compile_shader(shader);
-  if !gl.get_shader_compile_status(shader) {
-    do_log!("[Kuplung] [GLUtils] Failed to compile shader {shader_filepath} {shader_type}: {}", gl.get_shader_info_log(shader));
+/// Reads shader files through the preprocessor, as (type, file, source) for `create_program_from_sources`.
+pub fn load_shader_sources(shader_files: &[(u32, &str)], preprocessor: &ShaderPreprocessor) -> Result<Vec<(u32, String, ShaderSource)>, GLError> {
+  let mut sources = Vec::new();
+  for (shader_type, shader_filepath) in shader_files {
+    do_log!("[Kuplung] [GLUtils] Loading shader file {}", shader_filepath);
+    let source = preprocessor.process_file(shader_filepath).map_err(|e| GLError::ShaderLoad { file: shader_filepath.to_string(), reason: e.to_string() })?;
+    sources.push((*shader_type, shader_filepath.to_string(), source));
   }
-  gl.attach_shader(*program, shader);
-  shader
+  Ok(sources)
 }
 
-pub unsafe fn create_shader_from_string(program: &glow::Program, gl: &glow::Context, shader_type: u32, shader_source: &str) -> glow::Shader {
-  do_log!("[Kuplung] [GLUtils] Loading shader source.");
-
-  let shader = gl.create_shader(shader_type).expect("[Kuplung] [GLUtils] Cannot create shader");
-  gl.shader_source(shader, shader_source);
-  gl.compile_shader(shader);
-  if !gl.get_shader_compile_status(shader) {
-    do_log!("[Kuplung] [GLUtils] Failed to compile shader from source {shader_type}: {}", gl.get_shader_info_log(shader));
+/// Compiles and links already preprocessed shaders - (type, name for the messages, source).
+/// Nothing is left behind on failure.
+pub unsafe fn create_program_from_sources(gl: &glow::Context, program_name: &str, shaders: &[(u32, String, ShaderSource)]) -> Result<glow::Program, GLError> {
+  let program = gl.create_program().map_err(GLError::resource("program"))?;
+  let mut compiled = Vec::new();
+  for (shader_type, shader_name, source) in shaders {
+    match compile_shader(gl, *shader_type, shader_name, source) {
+      Ok(shader) => {
+        gl.attach_shader(program, shader);
+        compiled.push(shader);
+      }
+      Err(e) => {
+        do_log!("[Kuplung] [GLUtils] {}", e);
+        for shader in compiled { gl.delete_shader(shader); }
+        gl.delete_program(program);
+        return Err(e);
+      }
+    }
   }
-  gl.attach_shader(*program, shader);
-  shader
-}
-
-pub unsafe fn get_uniform(program: &glow::Program, gl: &glow::Context, var_name: &str) -> glow::UniformLocation {
-  let ul = gl.get_uniform_location(*program, var_name);
-  if ul.is_none() {
-    do_log!("[Kuplung] [GLUtils] Cannot get uniform for {}. {}", var_name.as_str(), gl.get_program_info_log(*program));
+  gl.link_program(program);
+  for shader in compiled {
+    gl.detach_shader(program, shader);
+    gl.delete_shader(shader);
+  }
+  if !gl.get_program_link_status(program) {
+    let e = GLError::ProgramLink { program: program_name.to_string(), log: gl.get_program_info_log(program) };
+    do_log!("[Kuplung] [GLUtils] {}", e);
+    gl.delete_program(program);
+    return Err(e);
   }
-  ul.expect(format!("[Kuplung] [GLUtils] Cannot get uniform for {}. {}", var_name.as_str(), gl.get_program_info_log(*program)).as_str())
+  Ok(program)
 }
 
-pub unsafe fn get_uniform_no_warning(program: &glow::Program, gl: &glow::Context, uniform_name: &str) -> glow::UniformLocation {
-  let ul = gl.get_uniform_location(*program, uniform_name);
-  if ul.is_none() {
-    do_log!("[Kuplung] [GLUtils] Cannot get uniform for {}. {}", uniform_name.as_str(), gl.get_program_info_log(*program));
+unsafe fn compile_shader(gl: &glow::Context, shader_type: u32, shader_name: &str, source: &ShaderSource) -> Result<glow::Shader, GLError> {
+  let shader = gl.create_shader(shader_type).map_err(GLError::resource("shader"))?;
+  gl.shader_source(shader, &source.code);
+  gl.compile_shader(shader);
+  if !gl.get_shader_compile_status(shader) {
+    let log = source.map_log(&gl.get_shader_info_log(shader));
+    gl.delete_shader(shader);
+    return Err(GLError::ShaderCompile { shader: shader_name.to_string(), log });
   }
-  ul.unwrap()
+  Ok(shader)
 }
 
 pub unsafe fn print_program_uniforms(program: &glow::Program, gl: &glow::Context) {
@@ -61,4 +69,4 @@ pub unsafe fn print_program_uniforms(program: &glow::Program, gl: &glow::Context
     let unif = gl.get_active_uniform(*program, i).unwrap();
     do_log!("[Kuplung] [STOY] {} - {} : {}", unif.name, unif.utype, unif.size);
   }
-}
\ No newline at end of file
+}
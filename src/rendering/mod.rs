@@ -1,3 +1,12 @@
 pub mod rendering_manager;
+pub mod camera;
+pub mod gl_error;
 pub mod gl_utils;
-mod triangler;
+pub mod model_face;
+pub mod primitives;
+pub mod rendering_simple;
+pub mod shader_preprocessor;
+pub mod shader_program;
+pub mod shader_registry;
+pub mod view_skin;
+mod triangler;
\ No newline at end of file
@@ -0,0 +1,159 @@
+#![allow(non_snake_case)]
+
+use eframe::egui_glow;
+use eframe::glow::HasContext;
+use egui_glow::glow;
+use crate::meshes::animation::ModelPose;
+use crate::meshes::mesh_model::{MeshMaterialTextureImage, MeshModel};
+use crate::rendering::gl_error::GLError;
+use crate::rendering::shader_program::ShaderProgram;
+use crate::rendering::view_skin::ModelViewSettings;
+
+/// A mesh uploaded to the GPU - one buffer per attribute and the diffuse texture.
+pub struct ModelFace {
+  pub mesh: MeshModel,
+  buffers: MeshBuffers,
+  texture_Diffuse: Option<glow::Texture>,
+  pub view: ModelViewSettings,
+}
+
+/// The geometry of a mesh on the GPU.
+struct MeshBuffers {
+  gl_VAO: glow::VertexArray,
+  vbo_Vertices: glow::Buffer,
+  vbo_Normals: glow::Buffer,
+  vbo_TextureCoordinates: glow::Buffer,
+  vbo_Colors: Option<glow::Buffer>,
+  vbo_Indices: glow::Buffer,
+}
+
+#[allow(unsafe_code)]
+impl MeshBuffers {
+  unsafe fn new(gl: &glow::Context, mesh: &MeshModel) -> Result<Self, GLError> {
+    let gl_VAO = gl.create_vertex_array().map_err(GLError::resource("vertex array"))?;
+    gl.bind_vertex_array(Some(gl_VAO));
+
+    let vertices: Vec<f32> = mesh.vertices.iter().flat_map(|v| [v.x, v.y, v.z]).collect();
+    let normals: Vec<f32> = mesh.normals.iter().flat_map(|n| [n.x, n.y, n.z]).collect();
+    let texture_coordinates: Vec<f32> = mesh.texture_coordinates.iter().flat_map(|uv| [uv.x, uv.y]).collect();
+    let vbo_Vertices = Self::create_attribute(gl, 0, 3, &vertices)?;
+    let vbo_Normals = Self::create_attribute(gl, 1, 3, &normals)?;
+    let vbo_TextureCoordinates = Self::create_attribute(gl, 2, 2, &texture_coordinates)?;
+    let vbo_Colors = if !mesh.colors.is_empty() && mesh.colors.len() == mesh.vertices.len() {
+      let colors: Vec<f32> = mesh.colors.iter().flat_map(|c| [c.x, c.y, c.z, c.w]).collect();
+      Some(Self::create_attribute(gl, 3, 4, &colors)?)
+    }
+    else {
+      None
+    };
+
+    let vbo_Indices = gl.create_buffer().map_err(GLError::resource("indices buffer"))?;
+    gl.bind_buffer(glow::ELEMENT_ARRAY_BUFFER, Some(vbo_Indices));
+    gl.buffer_data_u8_slice(glow::ELEMENT_ARRAY_BUFFER, bytemuck::cast_slice(&mesh.indices), glow::STATIC_DRAW);
+
+    gl.bind_vertex_array(None);
+    Ok(Self { gl_VAO, vbo_Vertices, vbo_Normals, vbo_TextureCoordinates, vbo_Colors, vbo_Indices })
+  }
+
+  unsafe fn create_attribute(gl: &glow::Context, location: u32, size: i32, data: &[f32]) -> Result<glow::Buffer, GLError> {
+    let buffer = gl.create_buffer().map_err(GLError::resource("vertex buffer"))?;
+    gl.bind_buffer(glow::ARRAY_BUFFER, Some(buffer));
+    gl.buffer_data_u8_slice(glow::ARRAY_BUFFER, bytemuck::cast_slice(data), glow::STATIC_DRAW);
+    gl.vertex_attrib_pointer_f32(location, size, glow::FLOAT, false, size * size_of::<f32>() as i32, 0);
+    gl.enable_vertex_attrib_array(location);
+    Ok(buffer)
+  }
+
+  unsafe fn destroy(&self, gl: &glow::Context) {
+    gl.delete_vertex_array(self.gl_VAO);
+    gl.delete_buffer(self.vbo_Vertices);
+    gl.delete_buffer(self.vbo_Normals);
+    gl.delete_buffer(self.vbo_TextureCoordinates);
+    if let Some(buffer) = self.vbo_Colors { gl.delete_buffer(buffer); }
+    gl.delete_buffer(self.vbo_Indices);
+  }
+}
+
+#[allow(unsafe_code)]
+impl ModelFace {
+  /// Has to be called on the thread with the GL context. The decoded texture is dropped once uploaded.
+  pub fn new(gl: &glow::Context, mut mesh: MeshModel) -> Result<Self, GLError> {
+    unsafe {
+      let buffers = MeshBuffers::new(gl, &mesh)?;
+
+      let texture_Diffuse = Self::upload_texture(gl, &mut mesh.model_material.texture_diffuse)?;
+
+      Ok(Self {
+        mesh,
+        buffers,
+        texture_Diffuse,
+        view: ModelViewSettings::default(),
+      })
+    }
+  }
+
+  /// Replaces the geometry with the one of the mesh, the texture stays.
+  pub fn set_mesh(&mut self, gl: &glow::Context, mesh: MeshModel) -> Result<(), GLError> {
+    unsafe {
+      let buffers = MeshBuffers::new(gl, &mesh)?;
+      std::mem::replace(&mut self.buffers, buffers).destroy(gl);
+    }
+    self.mesh = mesh;
+    Ok(())
+  }
+
+  /// Replaces the diffuse texture with the decoded one, the old texture is deleted once the new one is uploaded.
+  pub fn set_diffuse_texture(&mut self, gl: &glow::Context, mut texture: MeshMaterialTextureImage) -> Result<(), GLError> {
+    unsafe {
+      let texture_Diffuse = Self::upload_texture(gl, &mut texture)?;
+      if let Some(old) = std::mem::replace(&mut self.texture_Diffuse, texture_Diffuse) { gl.delete_texture(old); }
+    }
+    self.mesh.model_material.texture_diffuse = texture;
+    Ok(())
+  }
+
+  /// The GL texture of the decoded image, which is dropped once uploaded.
+  unsafe fn upload_texture(gl: &glow::Context, texture: &mut MeshMaterialTextureImage) -> Result<Option<glow::Texture>, GLError> {
+    let Some(image) = texture.pixels.take() else { return Ok(None); };
+    let gl_texture = gl.create_texture().map_err(GLError::resource("diffuse texture"))?;
+    gl.bind_texture(glow::TEXTURE_2D, Some(gl_texture));
+    gl.tex_parameter_i32(glow::TEXTURE_2D, glow::TEXTURE_WRAP_S, glow::REPEAT as i32);
+    gl.tex_parameter_i32(glow::TEXTURE_2D, glow::TEXTURE_WRAP_T, glow::REPEAT as i32);
+    gl.tex_parameter_i32(glow::TEXTURE_2D, glow::TEXTURE_MIN_FILTER, glow::LINEAR_MIPMAP_LINEAR as i32);
+    gl.tex_parameter_i32(glow::TEXTURE_2D, glow::TEXTURE_MAG_FILTER, glow::LINEAR as i32);
+    // images have their first row at the top, OBJ texture coordinates start at the bottom
+    let flipped = image::imageops::flip_vertical(image.as_ref());
+    gl.tex_image_2d(glow::TEXTURE_2D, 0, glow::RGBA as i32, image.width() as i32, image.height() as i32, 0, glow::RGBA, glow::UNSIGNED_BYTE, Some(flipped.as_raw()));
+    gl.generate_mipmap(glow::TEXTURE_2D);
+    gl.bind_texture(glow::TEXTURE_2D, None);
+    Ok(Some(gl_texture))
+  }
+
+  /// Sets the material uniforms of the pose and draws - the program and the matrices are set by the renderer.
+  pub fn paint(&self, gl: &glow::Context, program: &ShaderProgram, pose: &ModelPose) {
+    let material = &self.mesh.model_material;
+    program.set_vec3(gl, "u_ambientColor", &(material.ambient_color * pose.ambient_strength));
+    program.set_vec3(gl, "u_diffuseColor", &(material.diffuse_color * pose.diffuse_strength));
+    program.set_vec3(gl, "u_specularColor", &(material.specular_color * pose.specular_strength));
+    program.set_f32(gl, "u_specularExp", pose.specular_exp);
+    program.set_f32(gl, "u_alpha", pose.transparency);
+    program.set_bool(gl, "u_hasTexture", self.texture_Diffuse.is_some());
+    program.set_bool(gl, "u_hasVertexColors", self.buffers.vbo_Colors.is_some());
+    program.set_sampler(gl, "u_samplerDiffuse", 0);
+    unsafe {
+      gl.active_texture(glow::TEXTURE0);
+      gl.bind_texture(glow::TEXTURE_2D, self.texture_Diffuse);
+      gl.bind_vertex_array(Some(self.buffers.gl_VAO));
+      gl.draw_elements(glow::TRIANGLES, self.mesh.indices.len() as i32, glow::UNSIGNED_INT, 0);
+      gl.bind_vertex_array(None);
+      gl.bind_texture(glow::TEXTURE_2D, None);
+    }
+  }
+
+  pub fn destroy(&self, gl: &glow::Context) {
+    unsafe {
+      self.buffers.destroy(gl);
+      if let Some(texture) = self.texture_Diffuse { gl.delete_texture(texture); }
+    }
+  }
+}
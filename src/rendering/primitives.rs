@@ -0,0 +1,334 @@
+use std::collections::HashMap;
+use std::f32::consts::PI;
+use nalgebra_glm as glm;
+use strum_macros::AsRefStr;
+use crate::meshes::mesh_model::MeshModel;
+
+// Procedural shapes around the origin with Y up. Vertices at seams and poles are split for the texture
+// coordinates but sit at exactly the same positions, so the closed shapes are watertight once welded.
+// The faces are wound counter-clockwise seen from outside.
+
+#[derive(Clone, Copy, Debug, PartialEq, AsRefStr)]
+pub enum Primitive {
+  Cube { size: f32, segments: u32 },
+  Sphere { radius: f32, segments: u32, rings: u32 },
+  IcoSphere { radius: f32, subdivisions: u32 },
+  Torus { radius: f32, tube_radius: f32, segments: u32, tube_segments: u32 },
+  Cylinder { radius: f32, height: f32, segments: u32 },
+  Cone { radius: f32, height: f32, segments: u32 },
+  Tube { outer: f32, inner: f32, height: f32, segments: u32 },
+  Plane { size: f32, segments: u32 },
+}
+
+impl Primitive {
+  /// Every shape with its default parameters.
+  pub fn all() -> [Primitive; 8] {
+    [
+      Primitive::Cube { size: 2.0, segments: 1 },
+      Primitive::Sphere { radius: 1.0, segments: 32, rings: 16 },
+      Primitive::IcoSphere { radius: 1.0, subdivisions: 2 },
+      Primitive::Torus { radius: 1.0, tube_radius: 0.25, segments: 48, tube_segments: 12 },
+      Primitive::Cylinder { radius: 1.0, height: 2.0, segments: 32 },
+      Primitive::Cone { radius: 1.0, height: 2.0, segments: 32 },
+      Primitive::Tube { outer: 1.0, inner: 0.75, height: 2.0, segments: 32 },
+      Primitive::Plane { size: 2.0, segments: 1 },
+    ]
+  }
+
+  /// The mesh with normals, texture coordinates and tangents. Too few segments are raised to the least the shape needs,
+  /// the ico sphere subdivisions are capped at `MAX_ICO_SUBDIVISIONS`.
+  pub fn mesh(&self) -> MeshModel {
+    let mut model = MeshModel { model_title: self.as_ref().to_string(), ..Default::default() };
+    match *self {
+      Primitive::Cube { size, segments } => {
+        let segments = segments.max(1);
+        let coordinate = |k: i32| size * (k as f32 / segments as f32 - 0.5);
+        let s = segments as i32;
+        // per face the lattice corner at (0, 0) and the lattice steps along U and V, U x V points outside
+        let faces: [([i32; 3], [i32; 3], [i32; 3]); 6] = [
+          ([s, 0, s], [0, 0, -1], [0, 1, 0]),
+          ([0, 0, 0], [0, 0, 1], [0, 1, 0]),
+          ([0, s, s], [1, 0, 0], [0, 0, -1]),
+          ([0, 0, 0], [1, 0, 0], [0, 0, 1]),
+          ([0, 0, s], [1, 0, 0], [0, 1, 0]),
+          ([s, 0, 0], [-1, 0, 0], [0, 1, 0]),
+        ];
+        for (corner, u, v) in faces {
+          let normal = glm::cross(&glm::vec3(u[0] as f32, u[1] as f32, u[2] as f32), &glm::vec3(v[0] as f32, v[1] as f32, v[2] as f32));
+          grid(&mut model, segments, segments, |i, j| {
+            let lattice = [0, 1, 2].map(|a| corner[a] + i as i32 * u[a] + j as i32 * v[a]);
+            let position = glm::vec3(coordinate(lattice[0]), coordinate(lattice[1]), coordinate(lattice[2]));
+            (position, normal, glm::vec2(i as f32 / segments as f32, j as f32 / segments as f32))
+          });
+        }
+      }
+      Primitive::Sphere { radius, segments, rings } => {
+        let (segments, rings) = (segments.max(3), rings.max(2));
+        grid(&mut model, segments, rings, |i, j| {
+          // from the bottom pole up, the poles exactly on the axis
+          let (sin, cos) = if j == 0 { (0.0, 1.0) } else if j == rings { (0.0, -1.0) } else { (PI * j as f32 / rings as f32).sin_cos() };
+          let normal = ring(i, segments, sin, -cos);
+          (normal * radius, normal, glm::vec2(i as f32 / segments as f32, j as f32 / rings as f32))
+        });
+      }
+      Primitive::IcoSphere { radius, subdivisions } => {
+        // every triangle has vertices of its own, so the texture can wrap around without a seam vertex
+        let (points, triangles) = icosahedron(subdivisions.min(MAX_ICO_SUBDIVISIONS));
+        for triangle in triangles {
+          let mut uvs = triangle.map(|k| sphere_uv(&points[k as usize]));
+          if uvs.iter().any(|uv| uv.x > 0.75) && uvs.iter().any(|uv| uv.x < 0.25) {
+            for uv in uvs.iter_mut().filter(|uv| uv.x < 0.5) { uv.x += 1.0; }
+          }
+          // a pole has no U of its own, it takes the middle of the other two
+          for pole in 0..3 {
+            let point = points[triangle[pole] as usize];
+            if point.x == 0.0 && point.z == 0.0 { uvs[pole].x = (uvs[(pole + 1) % 3].x + uvs[(pole + 2) % 3].x) * 0.5; }
+          }
+          for (k, uv) in triangle.iter().zip(uvs) {
+            model.indices.push(model.vertices.len() as u32);
+            model.vertices.push(points[*k as usize] * radius);
+            model.normals.push(points[*k as usize]);
+            model.texture_coordinates.push(uv);
+          }
+          model.polygons.push(3);
+        }
+      }
+      Primitive::Torus { radius, tube_radius, segments, tube_segments } => {
+        let (segments, tube_segments) = (segments.max(3), tube_segments.max(3));
+        grid(&mut model, segments, tube_segments, |i, j| {
+          let (sin, cos) = angle(j, tube_segments).sin_cos();
+          let normal = ring(i, segments, cos, sin);
+          let center = ring(i, segments, radius, 0.0);
+          (center + normal * tube_radius, normal, glm::vec2(i as f32 / segments as f32, j as f32 / tube_segments as f32))
+        });
+      }
+      Primitive::Cylinder { radius, height, segments } => {
+        let segments = segments.max(3);
+        grid(&mut model, segments, 1, |i, j| {
+          let y = height * (j as f32 - 0.5);
+          (ring(i, segments, radius, y), ring(i, segments, 1.0, 0.0), glm::vec2(i as f32 / segments as f32, j as f32))
+        });
+        cap(&mut model, segments, radius, 0.0, height * 0.5, true);
+        cap(&mut model, segments, radius, 0.0, -height * 0.5, false);
+      }
+      Primitive::Cone { radius, height, segments } => {
+        let segments = segments.max(3);
+        // the slope tilts the normals up by the ratio of the radius to the height
+        let slope = radius / height.max(1e-6);
+        grid(&mut model, segments, 1, |i, j| {
+          let normal = ring(i, segments, 1.0, slope).normalize();
+          (ring(i, segments, radius * (1 - j) as f32, height * (j as f32 - 0.5)), normal, glm::vec2(i as f32 / segments as f32, j as f32))
+        });
+        cap(&mut model, segments, radius, 0.0, -height * 0.5, false);
+      }
+      Primitive::Tube { outer, inner, height, segments } => {
+        let segments = segments.max(3);
+        let inner = inner.clamp(0.0, outer * 0.99);
+        grid(&mut model, segments, 1, |i, j| {
+          let y = height * (j as f32 - 0.5);
+          (ring(i, segments, outer, y), ring(i, segments, 1.0, 0.0), glm::vec2(i as f32 / segments as f32, j as f32))
+        });
+        // the inner wall goes from the top down, so its faces point to the axis
+        grid(&mut model, segments, 1, |i, j| {
+          let y = height * (0.5 - j as f32);
+          (ring(i, segments, inner, y), ring(i, segments, -1.0, 0.0), glm::vec2(i as f32 / segments as f32, 1.0 - j as f32))
+        });
+        cap(&mut model, segments, outer, inner, height * 0.5, true);
+        cap(&mut model, segments, outer, inner, -height * 0.5, false);
+      }
+      Primitive::Plane { size, segments } => {
+        let segments = segments.max(1);
+        grid(&mut model, segments, segments, |i, j| {
+          let (u, v) = (i as f32 / segments as f32, j as f32 / segments as f32);
+          (glm::vec3(size * (u - 0.5), 0.0, size * (0.5 - v)), glm::vec3(0.0, 1.0, 0.0), glm::vec2(u, v))
+        });
+      }
+    }
+    model.tangents = tangents(&model);
+    model
+  }
+}
+
+/// More subdivisions than this make meshes of hundreds of thousands of triangles.
+pub const MAX_ICO_SUBDIVISIONS: u32 = 6;
+
+/// The points of an icosahedron on the unit sphere and its triangles, each split in four per subdivision.
+fn icosahedron(subdivisions: u32) -> (Vec<glm::Vec3>, Vec<[u32; 3]>) {
+  let t = (1.0 + 5f32.sqrt()) / 2.0;
+  let mut points: Vec<glm::Vec3> = [
+    (-1.0, t, 0.0), (1.0, t, 0.0), (-1.0, -t, 0.0), (1.0, -t, 0.0),
+    (0.0, -1.0, t), (0.0, 1.0, t), (0.0, -1.0, -t), (0.0, 1.0, -t),
+    (t, 0.0, -1.0), (t, 0.0, 1.0), (-t, 0.0, -1.0), (-t, 0.0, 1.0),
+  ].iter().map(|(x, y, z)| glm::vec3(*x, *y, *z).normalize()).collect();
+  let mut triangles = vec![
+    [0, 11, 5], [0, 5, 1], [0, 1, 7], [0, 7, 10], [0, 10, 11],
+    [1, 5, 9], [5, 11, 4], [11, 10, 2], [10, 7, 6], [7, 1, 8],
+    [3, 9, 4], [3, 4, 2], [3, 2, 6], [3, 6, 8], [3, 8, 9],
+    [4, 9, 5], [2, 4, 11], [6, 2, 10], [8, 6, 7], [9, 8, 1],
+  ];
+  for _ in 0..subdivisions {
+    // the middle of an edge is made once, for both of its triangles
+    let mut middles: HashMap<(u32, u32), u32> = HashMap::new();
+    let mut middle = |a: u32, b: u32, points: &mut Vec<glm::Vec3>| *middles.entry((a.min(b), a.max(b))).or_insert_with(|| {
+      points.push(((points[a as usize] + points[b as usize]) * 0.5).normalize());
+      points.len() as u32 - 1
+    });
+    triangles = triangles.iter().flat_map(|&[a, b, c]| {
+      let (ab, bc, ca) = (middle(a, b, &mut points), middle(b, c, &mut points), middle(c, a, &mut points));
+      [[a, ab, ca], [b, bc, ab], [c, ca, bc], [ab, bc, ca]]
+    }).collect();
+  }
+  (points, triangles)
+}
+
+/// The texture coordinates of a point on the unit sphere, matching the UV sphere.
+fn sphere_uv(point: &glm::Vec3) -> glm::Vec2 {
+  let u = (-point.z).atan2(point.x) / (2.0 * PI);
+  glm::vec2(if u < 0.0 { u + 1.0 } else { u }, (-point.y).clamp(-1.0, 1.0).acos() / PI)
+}
+
+/// The angle of a step around a circle, the last step lands exactly on the first.
+fn angle(step: u32, steps: u32) -> f32 {
+  2.0 * PI * (step % steps) as f32 / steps as f32
+}
+
+/// The point of a step around the Y axis at that distance from it, turning counter-clockwise seen from above.
+fn ring(step: u32, steps: u32, distance: f32, y: f32) -> glm::Vec3 {
+  let (sin, cos) = angle(step, steps).sin_cos();
+  glm::vec3(distance * cos, y, -distance * sin)
+}
+
+/// A (columns + 1) x (rows + 1) grid of vertices with a quad per cell. The triangles that collapse
+/// at poles and apexes are left out, the cell is a triangle then.
+fn grid(model: &mut MeshModel, columns: u32, rows: u32, vertex: impl Fn(u32, u32) -> (glm::Vec3, glm::Vec3, glm::Vec2)) {
+  let first = model.vertices.len() as u32;
+  for j in 0..=rows {
+    for i in 0..=columns {
+      let (position, normal, uv) = vertex(i, j);
+      model.vertices.push(position);
+      model.normals.push(normal);
+      model.texture_coordinates.push(uv);
+    }
+  }
+  let index = |i: u32, j: u32| first + j * (columns + 1) + i;
+  for j in 0..rows {
+    for i in 0..columns {
+      let [a, b, c, d] = [index(i, j), index(i + 1, j), index(i + 1, j + 1), index(i, j + 1)];
+      let mut corners = 2;
+      for triangle in [[a, b, c], [a, c, d]] {
+        let [p, q, r] = triangle.map(|k| model.vertices[k as usize]);
+        if p != q && q != r && r != p {
+          model.indices.extend(triangle);
+          corners += 1;
+        }
+      }
+      if corners > 2 { model.polygons.push(corners); }
+    }
+  }
+}
+
+/// A disc closing a cylinder or a cone, or a ring closing a tube, with the texture mapped from above.
+fn cap(model: &mut MeshModel, segments: u32, radius: f32, inner_radius: f32, y: f32, top: bool) {
+  let normal = glm::vec3(0.0, if top { 1.0 } else { -1.0 }, 0.0);
+  grid(model, segments, 1, |i, j| {
+    // from the rim inwards on top, the other way below, so the faces point outside
+    let distance = if (j == 0) == top { radius } else { inner_radius };
+    let position = ring(i, segments, distance, y);
+    let uv = glm::vec2(0.5 + position.x / (2.0 * radius), 0.5 + normal.y * position.z / (2.0 * radius));
+    (position, normal, uv)
+  });
+}
+
+/// Per vertex the direction the U coordinate grows along the surface, from the triangles around it.
+fn tangents(model: &MeshModel) -> Vec<glm::Vec4> {
+  let mut tangents = vec![glm::Vec3::zeros(); model.vertices.len()];
+  let mut bitangents = vec![glm::Vec3::zeros(); model.vertices.len()];
+  for triangle in model.indices.chunks_exact(3) {
+    let [a, b, c] = [0, 1, 2].map(|i| triangle[i] as usize);
+    let (edge_1, edge_2) = (model.vertices[b] - model.vertices[a], model.vertices[c] - model.vertices[a]);
+    let (uv_1, uv_2) = (model.texture_coordinates[b] - model.texture_coordinates[a], model.texture_coordinates[c] - model.texture_coordinates[a]);
+    let determinant = uv_1.x * uv_2.y - uv_2.x * uv_1.y;
+    if determinant.abs() < 1e-12 { continue; }
+    let tangent = (edge_1 * uv_2.y - edge_2 * uv_1.y) / determinant;
+    let bitangent = (edge_2 * uv_1.x - edge_1 * uv_2.x) / determinant;
+    for index in [a, b, c] {
+      tangents[index] += tangent;
+      bitangents[index] += bitangent;
+    }
+  }
+  model.normals.iter().zip(tangents.iter().zip(bitangents.iter())).map(|(normal, (tangent, bitangent))| {
+    // perpendicular to the normal, any such direction when the texture doesn't give one
+    let tangent = (tangent - normal * glm::dot(normal, tangent)).try_normalize(1e-12).unwrap_or_else(|| {
+      let axis = if normal.x.abs() < 0.9 { glm::vec3(1.0, 0.0, 0.0) } else { glm::vec3(0.0, 1.0, 0.0) };
+      glm::cross(&axis, normal).normalize()
+    });
+    let handedness = if glm::dot(&glm::cross(normal, &tangent), bitangent) < 0.0 { -1.0 } else { 1.0 };
+    tangent.push(handedness)
+  }).collect()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::meshes::mesh_analysis;
+
+  #[test]
+  fn vertex_and_triangle_counts() {
+    let counts = |primitive: Primitive| {
+      let mesh = primitive.mesh();
+      (mesh.vertices.len(), mesh.count_triangles())
+    };
+    assert_eq!(counts(Primitive::Cube { size: 1.0, segments: 2 }), (6 * 9, 6 * 8));
+    assert_eq!(counts(Primitive::Sphere { radius: 1.0, segments: 8, rings: 4 }), (9 * 5, 8 * 2 * 3));
+    assert_eq!(counts(Primitive::Torus { radius: 1.0, tube_radius: 0.5, segments: 8, tube_segments: 4 }), (9 * 5, 8 * 4 * 2));
+    assert_eq!(counts(Primitive::Cylinder { radius: 1.0, height: 1.0, segments: 8 }), (3 * 9 * 2, 8 * 2 + 8 * 2));
+    assert_eq!(counts(Primitive::Cone { radius: 1.0, height: 1.0, segments: 8 }), (2 * 9 * 2, 8 + 8));
+    assert_eq!(counts(Primitive::IcoSphere { radius: 1.0, subdivisions: 0 }), (20 * 3, 20));
+    assert_eq!(counts(Primitive::IcoSphere { radius: 1.0, subdivisions: 2 }), (320 * 3, 320));
+    assert_eq!(counts(Primitive::Tube { outer: 1.0, inner: 0.5, height: 1.0, segments: 8 }), (4 * 9 * 2, 4 * 8 * 2));
+    assert_eq!(counts(Primitive::Plane { size: 1.0, segments: 3 }), (16, 18));
+    // quads, with triangles around the poles
+    let sphere = Primitive::Sphere { radius: 1.0, segments: 8, rings: 4 }.mesh();
+    assert_eq!(sphere.faces().iter().map(|f| f.len()).filter(|n| *n == 4).count(), 8 * 2);
+    // too few segments for the shape
+    assert_eq!(counts(Primitive::Sphere { radius: 1.0, segments: 0, rings: 0 }), (4 * 3, 3 * 2));
+    assert_eq!(counts(Primitive::IcoSphere { radius: 1.0, subdivisions: 100 }), counts(Primitive::IcoSphere { radius: 1.0, subdivisions: MAX_ICO_SUBDIVISIONS }));
+  }
+
+  #[test]
+  fn closed_shapes_are_watertight() {
+    for primitive in Primitive::all() {
+      let report = mesh_analysis::analyze(&primitive.mesh());
+      assert_eq!((report.flipped_edges, report.non_manifold_edges, report.degenerate_triangles), (0, 0, 0), "{:?}", primitive);
+      match primitive {
+        Primitive::Plane { .. } => assert_eq!(report.boundary_edges, 4),
+        _ => assert!(report.is_watertight(), "{:?}", primitive),
+      }
+    }
+  }
+
+  #[test]
+  fn normals_and_tangents_follow_the_surface() {
+    for primitive in Primitive::all() {
+      let mesh = primitive.mesh();
+      assert_eq!((mesh.normals.len(), mesh.texture_coordinates.len(), mesh.tangents.len()), (mesh.vertices.len(), mesh.vertices.len(), mesh.vertices.len()));
+      for triangle in mesh.indices.chunks_exact(3) {
+        let [a, b, c] = [0, 1, 2].map(|i| triangle[i] as usize);
+        let face = glm::cross(&(mesh.vertices[b] - mesh.vertices[a]), &(mesh.vertices[c] - mesh.vertices[a]));
+        for index in [a, b, c] {
+          // the normals point the way the triangles are wound
+          assert!(glm::dot(&face, &mesh.normals[index]) > 0.0, "{:?}", primitive);
+          // the tangent points along the U coordinate
+          let (tangent, edge) = (mesh.tangents[index].xyz(), mesh.texture_coordinates[b] - mesh.texture_coordinates[a]);
+          let along = glm::dot(&tangent, &(mesh.vertices[b] - mesh.vertices[a]));
+          if edge.y == 0.0 && edge.x != 0.0 { assert!(along * edge.x > 0.0, "{:?}", primitive); }
+        }
+      }
+      for (normal, tangent) in mesh.normals.iter().zip(mesh.tangents.iter()) {
+        assert!((normal.norm() - 1.0).abs() < 1e-5 && (tangent.xyz().norm() - 1.0).abs() < 1e-5);
+        assert!(glm::dot(normal, &tangent.xyz()).abs() < 1e-5);
+        assert_eq!(tangent.w.abs(), 1.0);
+      }
+    }
+  }
+}
@@ -1,38 +1,550 @@
+use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
 
 use eframe::egui_glow;
 use egui::mutex::Mutex;
 use egui_glow::glow;
+use strum::IntoEnumIterator;
 use crate::do_log;
+use crate::meshes::animation::{AnimatedProperty, Interpolation, ModelAnimation};
+use crate::meshes::mesh_analysis::{self, MeshReport};
+use crate::meshes::mesh_model::{MeshMaterialTextureImage, MeshModel};
+use crate::meshes::mesh_processing::{self, MeshOperation, ProcessingOptions};
+use crate::meshes::model_export::{self, ExportOptions};
+use crate::meshes::model_import::{ImportOptions, ModelImportJob, ModelUnit, UpAxis};
+use crate::meshes::scene::{Scene, SCENE_FILE_EXTENSIONS};
+use crate::rendering::camera::Camera;
+use crate::rendering::gl_error::GLError;
+use crate::rendering::primitives::{self, Primitive};
+use crate::rendering::rendering_simple::{RenderingSimple, SceneView};
+use crate::rendering::shader_registry;
 use crate::rendering::triangler::Triangler;
-use crate::settings::{configuration, kuplung_logger};
+use crate::rendering::view_skin::{ModelViewSettings, SolidLightSkin, ViewModelSkin};
+use crate::settings::recent_files::{self, RecentList};
+use crate::settings::{configuration, kuplung_logger, kuplung_settings};
+use crate::ui::components::error_panel;
+use crate::ui::components::file_browser::{self, FileBrowser};
 
 pub struct RenderingManager {
   show_triangler: bool,
-  triangler: Arc<Mutex<Triangler>>,
+  triangler: Result<Arc<Mutex<Triangler>>, GLError>,
   angle: f32,
+  rendering_simple: Result<Arc<Mutex<RenderingSimple>>, GLError>,
+  camera: Camera,
+  show_import: bool,
+  import_file: String,
+  import_options: ImportOptions,
+  import_job: Option<ModelImportJob>,
+  import_status: String,
+  /// Opening a scene replaces the models in the Viewer, importing adds to them.
+  import_replaces: bool,
+  show_export: bool,
+  export_file: String,
+  export_options: ExportOptions,
+  export_status: String,
+  /// Writes only the models picked in the models list.
+  export_selected: bool,
+  /// The ids of the models picked in the models list.
+  selected: HashSet<usize>,
+  /// The parameters of the geometry operations in the model info.
+  processing: ProcessingOptions,
+  /// The last parameters of each shape in Add > Shape.
+  primitives: [Primitive; 8],
+  /// Computed when the info of a model is first opened.
+  mesh_reports: HashMap<usize, MeshReport>,
+  /// Picks the diffuse texture of the model with the id.
+  browser_texture: FileBrowser,
+  texture_model: Option<usize>,
+  /// Picked in the toolbar, saved with the rendering settings when the options are applied.
+  view_skin: ViewModelSkin,
+  /// Height over width of the scene view, from the rendering settings.
+  view_ratio: f32,
 }
 
 impl RenderingManager {
-  pub fn new<'a>(cc: &'a eframe::CreationContext<'a>) -> Option<Self> {
+  pub fn new<'a>(cc: &'a eframe::CreationContext<'a>) -> Result<Self, GLError> {
     do_log!("[Kuplung] New RenderingManager...");
 
-    let gl = cc.gl.as_ref()?;
+    let gl = cc.gl.as_ref().ok_or(GLError::NoContext)?;
+    let rendering = kuplung_settings::get_rendering();
     let this = Self {
       show_triangler: true,
-      triangler: Arc::new(Mutex::new(Triangler::new(gl)?)),
+      triangler: Triangler::new(gl).map(|t| Arc::new(Mutex::new(t))),
       angle: 0.0,
+      rendering_simple: RenderingSimple::new(gl).map(|r| Arc::new(Mutex::new(r))),
+      camera: Camera::default(),
+      show_import: false,
+      import_file: "".to_string(),
+      import_options: ImportOptions::default(),
+      import_job: None,
+      import_status: "".to_string(),
+      import_replaces: false,
+      show_export: false,
+      export_file: "".to_string(),
+      export_options: ExportOptions::default(),
+      export_status: "".to_string(),
+      export_selected: false,
+      selected: HashSet::new(),
+      processing: ProcessingOptions::default(),
+      primitives: Primitive::all(),
+      mesh_reports: HashMap::new(),
+      browser_texture: FileBrowser::new("browser_model_texture", "Diffuse Texture").filter("Images", &file_browser::IMAGE_EXTENSIONS),
+      texture_model: None,
+      view_skin: ViewModelSkin::from_index(rendering.general.selected_view_model_skin),
+      view_ratio: rendering.general.ratio_height / rendering.general.ratio_width,
     };
 
     do_log!("[Kuplung] New RenderingManager finished.");
-    Some(this)
+    Ok(this)
+  }
+
+  /// Opens the import dialog for the file, the import starts once the options are confirmed.
+  pub fn import_model(&mut self, file: &str) {
+    if self.import_job.is_some() {
+      do_log!("[Kuplung] Cannot import {} - another import is running.", file);
+      return;
+    }
+    self.import_file = file.to_string();
+    self.import_status = "".to_string();
+    self.import_options.unit = ModelUnit::for_file(file);
+    self.import_replaces = false;
+    self.show_import = true;
+  }
+
+  /// Replaces the models, cameras and lights in the Viewer with the ones of the scene file, read without import options.
+  pub fn open_scene(&mut self, file: &str) -> Result<(), String> {
+    let extension = model_export::extension(std::path::Path::new(file));
+    if !SCENE_FILE_EXTENSIONS.contains(&extension.as_str()) {
+      return Err(format!("Cannot open {} - .{} scenes are not supported yet, open a glTF scene (.gltf or .glb).", file, extension));
+    }
+    if self.import_job.is_some() { return Err(format!("Cannot open {} - another import is running.", file)); }
+    self.import_file = file.to_string();
+    self.import_status = "".to_string();
+    self.import_replaces = true;
+    self.import_job = Some(ModelImportJob::start(file, &ImportOptions::default())?);
+    self.show_import = true;
+    Ok(())
+  }
+
+  /// Writes the models, cameras, lights and animations in the Viewer to the scene file.
+  pub fn save_scene(&mut self, file: &str) -> Result<(), String> {
+    let Ok(rendering_simple) = &self.rendering_simple else { return Err(format!("Cannot save {} - the renderer is not available.", file)); };
+    let scene = rendering_simple.lock().scene();
+    model_export::save_scene(&scene, std::path::Path::new(file)).map_err(|e| format!("Cannot save {}: {}", file, e))?;
+    recent_files::add(RecentList::Scenes, file);
+    do_log!("[Kuplung] Saved the scene to {}.", file);
+    Ok(())
+  }
+
+  /// Opens the export dialog for the file, the models, cameras and lights in the Viewer are written once the options are confirmed.
+  pub fn export_scene(&mut self, file: &str) {
+    self.export_file = file.to_string();
+    self.export_status = "".to_string();
+    self.export_options.unit = ModelUnit::for_file(file);
+    self.show_export = true;
+  }
+
+  fn render_export(&mut self, ctx: &egui::Context) {
+    let mut open = self.show_export;
+    egui::Window::new("Export Scene")
+      .id(egui::Id::new("window_export_scene"))
+      .open(&mut open)
+      .resizable(false)
+      .collapsible(false)
+      .show(ctx, |ui| {
+        ui.label(&self.export_file);
+        ui.separator();
+        let options = &mut self.export_options;
+        let extension = model_export::extension(std::path::Path::new(&self.export_file));
+        if ["stl", "ply", "obj"].contains(&extension.as_str()) {
+          egui::Grid::new("export_scene_grid").num_columns(2).show(ui, |ui| {
+            ui.label("Units:");
+            unit_combo_box(ui, "export_scene_unit", &mut options.unit);
+            ui.end_row();
+          });
+        }
+        if extension == "obj" {
+          ui.checkbox(&mut options.bake_transforms, "Bake transforms").on_hover_text("Place the models in the scene, otherwise the transforms are kept for Kuplung");
+          ui.checkbox(&mut options.triangulate, "Triangulate faces");
+          ui.checkbox(&mut options.copy_textures, "Copy textures").on_hover_text("Copy the textures next to the MTL file, otherwise they are referred to where they are");
+        }
+        else if extension == "stl" || extension == "ply" {
+          ui.checkbox(&mut options.binary, "Binary file");
+        }
+        else {
+          ui.label("glTF files are in meters, with the transforms and the animations of the models.");
+        }
+        ui.add_enabled(!self.selected.is_empty(), egui::Checkbox::new(&mut self.export_selected, format!("Selected models only ({})", self.selected.len())));
+        ui.separator();
+        ui.horizontal(|ui| {
+          if ui.button("Export").clicked() {
+            let Ok(rendering_simple) = &self.rendering_simple else { return; };
+            let mut scene = rendering_simple.lock().scene();
+            if self.export_selected && !self.selected.is_empty() { scene.models.retain(|m| self.selected.contains(&m.id)); }
+            self.export_status = match model_export::export(&scene, std::path::Path::new(&self.export_file), &self.export_options) {
+              Ok(()) => format!("Exported {} models.", scene.models.len()),
+              Err(e) => {
+                do_log!("[Kuplung] Cannot export {}: {}", self.export_file, e);
+                format!("Cannot export: {}", e)
+              }
+            };
+          }
+          if ui.button("Close").clicked() { self.show_export = false; }
+        });
+        if !self.export_status.is_empty() { ui.label(&self.export_status); }
+      });
+    self.show_export &= open;
+  }
+
+  fn render_menu(&mut self, ui: &mut egui::Ui, frame: &eframe::Frame) {
+    egui::menu::bar(ui, |ui| {
+      ui.menu_button("Add", |ui| {
+        ui.menu_button("Shape", |ui| {
+          let mut add = None;
+          for primitive in self.primitives.iter_mut() {
+            let title = primitive.as_ref().to_string();
+            ui.menu_button(title, |ui| {
+              render_primitive_parameters(ui, primitive);
+              if ui.button("Add").clicked() {
+                add = Some(*primitive);
+                ui.close_menu();
+              }
+            });
+          }
+          if let Some(primitive) = add { self.add_primitive(frame, primitive); }
+        });
+      });
+    });
+  }
+
+  /// The skin of the scene, the cel shading and the wireframe of all models and the solid light.
+  fn render_view_toolbar(&mut self, ui: &mut egui::Ui) {
+    let Ok(rendering_simple) = &self.rendering_simple else { return; };
+    let mut rendering_simple = rendering_simple.lock();
+    ui.horizontal(|ui| {
+      for option in ViewModelSkin::iter() {
+        if ui.selectable_value(&mut self.view_skin, option, option.icon()).on_hover_text(option.as_ref()).changed() {
+          rendering_simple.view_skin = self.view_skin;
+        }
+      }
+      ui.separator();
+      let models = &mut rendering_simple.models;
+      let mut cel_shading = models.iter().all(|m| m.view.cel_shading);
+      if ui.checkbox(&mut cel_shading, "Cel shading").on_hover_text("For all models").changed() {
+        for model in models.iter_mut() { model.view.cel_shading = cel_shading; }
+      }
+      let mut wireframe = models.iter().all(|m| m.view.wireframe);
+      if ui.checkbox(&mut wireframe, "Wireframe").on_hover_text("For all models").changed() {
+        for model in models.iter_mut() { model.view.wireframe = wireframe; }
+      }
+      ui.menu_button("Solid light", |ui| render_solid_light(ui, &mut rendering_simple.solid_light));
+    });
+  }
+
+  /// Plays, pauses and loops the animations, the slider moves through them.
+  fn render_timeline(&mut self, ui: &mut egui::Ui) {
+    let Ok(rendering_simple) = &self.rendering_simple else { return; };
+    let mut rendering_simple = rendering_simple.lock();
+    let timeline = &mut rendering_simple.timeline;
+    timeline.advance(ui.input(|i| i.stable_dt));
+    if timeline.playing { ui.ctx().request_repaint(); }
+    ui.horizontal(|ui| {
+      let (icon, hover) = if timeline.playing { ("⏸", "Pause") } else { ("▶", "Play") };
+      if ui.button(icon).on_hover_text(hover).clicked() { timeline.toggle(); }
+      if ui.button("⏹").on_hover_text("Stop and go back to the start").clicked() { timeline.stop(); }
+      ui.toggle_value(&mut timeline.looping, "🔁").on_hover_text("Loop");
+      let duration = timeline.duration;
+      ui.add(egui::Slider::new(&mut timeline.time, 0.0..=duration).suffix(" s").max_decimals(2));
+      ui.label("of");
+      if ui.add(egui::DragValue::new(&mut timeline.duration).range(0.1..=600.0).speed(0.1).suffix(" s")).changed() { timeline.time = timeline.time.min(timeline.duration); }
+      ui.add(egui::DragValue::new(&mut timeline.speed).range(0.1..=10.0).speed(0.01).prefix("×")).on_hover_text("Playback speed");
+    });
+  }
+
+  fn add_primitive(&mut self, frame: &eframe::Frame, primitive: Primitive) {
+    let (Some(gl), Ok(rendering_simple)) = (frame.gl(), &self.rendering_simple) else { return; };
+    let mut rendering_simple = rendering_simple.lock();
+    rendering_simple.add_scene(gl, Scene { models: vec![primitive.mesh()], ..Default::default() });
+    if let Some(bounds) = rendering_simple.bounds() { self.camera.frame(bounds); }
+    do_log!("[Kuplung] Added a {} to the scene.", primitive.as_ref());
+  }
+
+  /// Picks up the settings changed in the options.
+  pub fn apply_settings(&mut self) {
+    let (settings, mut rendering) = (kuplung_settings::get(), kuplung_settings::get_rendering());
+    if rendering.general.selected_view_model_skin != self.view_skin.index() {
+      rendering.general.selected_view_model_skin = self.view_skin.index();
+      if let Err(e) = kuplung_settings::set_rendering(rendering.clone()) { do_log!("[Kuplung] Cannot save the rendering settings: {}", e); }
+    }
+    self.view_ratio = rendering.general.ratio_height / rendering.general.ratio_width;
+    if let Ok(triangler) = &self.triangler { triangler.lock().apply_settings(&settings); }
+    if let Ok(rendering_simple) = &self.rendering_simple { rendering_simple.lock().apply_settings(&settings, &rendering); }
+  }
+
+  /// Builds the renderers again when a retry of their failed programs was requested from the Shaders panel or their files changed.
+  fn retry_failed_programs(&mut self, frame: &eframe::Frame) {
+    let Some(gl) = frame.gl() else { return; };
+    if self.triangler.is_err() && shader_registry::retry_pending("Triangler") {
+      self.triangler = Triangler::new(gl).map(|t| Arc::new(Mutex::new(t)));
+    }
+    if self.rendering_simple.is_err() && shader_registry::retry_pending("Model") {
+      self.rendering_simple = RenderingSimple::new(gl).map(|r| Arc::new(Mutex::new(r)));
+    }
+  }
+
+  /// Uploads the meshes of a finished import - the GL work has to happen here, on the UI thread.
+  fn poll_import(&mut self, ctx: &egui::Context, frame: &eframe::Frame) {
+    let Some(job) = self.import_job.as_mut() else { return; };
+    let Some(result) = job.poll() else {
+      ctx.request_repaint();
+      return;
+    };
+    let seconds = job.started.elapsed().as_secs_f32();
+    let file = std::mem::take(&mut job.file);
+    self.import_job = None;
+    match result {
+      Ok(scene) => {
+        recent_files::add(if self.import_replaces { RecentList::Scenes } else { RecentList::Imported }, &file);
+        self.add_scene(frame, scene, seconds);
+      }
+      Err(e) => self.import_status = e,
+    }
+  }
+
+  fn add_scene(&mut self, frame: &eframe::Frame, scene: Scene, seconds: f32) {
+    let (Some(gl), Ok(rendering_simple)) = (frame.gl(), &self.rendering_simple) else {
+      self.import_status = "Cannot upload the models - no OpenGL renderer.".to_string();
+      return;
+    };
+    let count = scene.models.len();
+    let mut rendering_simple = rendering_simple.lock();
+    if self.import_replaces {
+      rendering_simple.clear(gl);
+      self.mesh_reports.clear();
+      self.selected.clear();
+    }
+    let added = rendering_simple.add_scene(gl, scene);
+    if let Some(bounds) = rendering_simple.bounds() { self.camera.frame(bounds); }
+    self.import_status = format!("Imported {} of {} meshes in {:.2}s.", added, count, seconds);
+    // the dialog stays when something went wrong
+    if added == count { self.show_import = false; }
+  }
+
+  fn render_import(&mut self, ctx: &egui::Context) {
+    let mut open = self.show_import;
+    egui::Window::new(if self.import_replaces { "Open Scene" } else { "Import Model" })
+      .id(egui::Id::new("window_import_model"))
+      .open(&mut open)
+      .resizable(false)
+      .collapsible(false)
+      .show(ctx, |ui| {
+        ui.label(&self.import_file);
+        ui.separator();
+        match &self.import_job {
+          Some(job) => {
+            let progress = job.progress();
+            ui.label(progress.stage());
+            ui.add(egui::ProgressBar::new(progress.fraction()).show_percentage().desired_width(300.0));
+            ui.label(format!("{:.1}s", job.started.elapsed().as_secs_f32()));
+            if ui.button("Cancel").clicked() { job.cancel(); }
+          }
+          None => {
+            let options = &mut self.import_options;
+            egui::Grid::new("import_model_grid").num_columns(2).show(ui, |ui| {
+              ui.label("Scale:");
+              ui.add(egui::DragValue::new(&mut options.scale).range(0.001..=1000.0).speed(0.01));
+              ui.end_row();
+              ui.label("Units:");
+              unit_combo_box(ui, "import_model_unit", &mut options.unit);
+              ui.end_row();
+              ui.label("Up axis:");
+              egui::ComboBox::from_id_source("import_model_up_axis")
+                .selected_text(format!("{} up", options.up_axis.as_ref()))
+                .show_ui(ui, |ui| {
+                  for axis in UpAxis::iter() {
+                    ui.selectable_value(&mut options.up_axis, axis, format!("{} up", axis.as_ref()));
+                  }
+                });
+              ui.end_row();
+            });
+            ui.checkbox(&mut options.flip_uvs, "Flip texture coordinates");
+            ui.checkbox(&mut options.recompute_normals, "Recompute normals");
+            ui.checkbox(&mut options.merge_by_material, "Merge meshes by material");
+            ui.separator();
+            ui.horizontal(|ui| {
+              if ui.button("Import").clicked() {
+                match ModelImportJob::start(&self.import_file, &self.import_options) {
+                  Ok(job) => {
+                    self.import_status = "".to_string();
+                    self.import_job = Some(job);
+                  }
+                  Err(e) => self.import_status = e,
+                }
+              }
+              if ui.button("Close").clicked() { self.show_import = false; }
+            });
+          }
+        }
+        if !self.import_status.is_empty() { ui.label(&self.import_status); }
+      });
+    // closing the window cancels a running import
+    if !open || !self.show_import {
+      if let Some(job) = &self.import_job { job.cancel(); }
+    }
+    self.show_import &= open;
+  }
+
+  fn render_models(&mut self, ui: &mut egui::Ui, frame: &eframe::Frame) {
+    let (Some(gl), Ok(rendering_simple)) = (frame.gl(), &self.rendering_simple) else { return; };
+    let mut rendering_simple = rendering_simple.lock();
+    let time = rendering_simple.timeline.time;
+    ui.collapsing(format!("Models ({})", rendering_simple.models.len()), |ui| {
+      let mut remove = None;
+      let mut process = None;
+      let mut animate = None;
+      let mut pick_texture = None;
+      egui::Grid::new("viewer_models_grid").num_columns(4).striped(true).show(ui, |ui| {
+        for model in rendering_simple.models.iter_mut() {
+          let mesh = &model.mesh;
+          let mut selected = self.selected.contains(&mesh.id);
+          if ui.checkbox(&mut selected, "").on_hover_text("Select for exporting").changed() {
+            if selected { self.selected.insert(mesh.id); } else { self.selected.remove(&mesh.id); }
+          }
+          let info = ui.collapsing(&mesh.model_title, |ui| {
+            let report = self.mesh_reports.entry(mesh.id).or_insert_with(|| mesh_analysis::analyze(mesh));
+            render_mesh_info(ui, mesh, report);
+            if ui.button("Diffuse texture...").on_hover_text("Pick the image of the diffuse texture").clicked() { pick_texture = Some(mesh.id); }
+            ui.separator();
+            render_model_view(ui, mesh.id, &mut model.view);
+            ui.collapsing("Animation", |ui| {
+              let mut animation = mesh.animation.clone();
+              if render_model_animation(ui, mesh, &mut animation, time) { animate = Some((mesh.id, animation)); }
+            });
+            ui.separator();
+            if let Some(operation) = render_mesh_processing(ui, mesh.id, &mut self.processing) { process = Some((mesh.id, operation)); }
+          });
+          info.header_response.on_hover_text(&mesh.file_path);
+          ui.label(format!("{} triangles", mesh.count_triangles()));
+          if ui.small_button("🗙").on_hover_text("Remove from the scene").clicked() { remove = Some(mesh.id); }
+          ui.end_row();
+        }
+      });
+      if let Some(id) = remove {
+        rendering_simple.remove_model(gl, id);
+        self.mesh_reports.remove(&id);
+        self.selected.remove(&id);
+      }
+      if let Some(id) = pick_texture {
+        self.texture_model = Some(id);
+        self.browser_texture.open();
+      }
+      if let Some((id, animation)) = animate {
+        if let Some(model) = rendering_simple.models.iter_mut().find(|m| m.mesh.id == id) { model.mesh.animation = animation; }
+      }
+      if let Some((id, operation)) = process {
+        if let Some(model) = rendering_simple.models.iter_mut().find(|m| m.mesh.id == id) {
+          let mesh = mesh_processing::apply(&model.mesh, operation, &self.processing);
+          do_log!("[Kuplung] {} of {}: {} to {} triangles.", operation.as_ref(), mesh.model_title, model.mesh.count_triangles(), mesh.count_triangles());
+          if let Err(e) = model.set_mesh(gl, mesh) { do_log!("[Kuplung] [Rendering] Cannot upload {}: {}", model.mesh.model_title, e); }
+        }
+        self.mesh_reports.remove(&id);
+      }
+      ui.horizontal(|ui| {
+        if ui.button("Frame all").clicked() {
+          if let Some(bounds) = rendering_simple.bounds() { self.camera.frame(bounds); }
+        }
+        if ui.button("Clear scene").clicked() {
+          rendering_simple.clear(gl);
+          self.mesh_reports.clear();
+          self.selected.clear();
+        }
+      });
+    });
+    if !rendering_simple.cameras.is_empty() {
+      ui.collapsing(format!("Cameras ({})", rendering_simple.cameras.len()), |ui| {
+        for camera in &rendering_simple.cameras {
+          ui.horizontal(|ui| {
+            ui.label(&camera.name).on_hover_text(format!("Field of view {:.1}°", camera.yfov.to_degrees()));
+            if ui.small_button("Look through").clicked() { self.camera.look_along(&camera.position, &camera.direction); }
+          });
+        }
+      });
+    }
+    if !rendering_simple.lights.is_empty() {
+      ui.collapsing(format!("Lights ({})", rendering_simple.lights.len()), |ui| {
+        egui::Grid::new("viewer_lights_grid").num_columns(3).striped(true).show(ui, |ui| {
+          for light in &rendering_simple.lights {
+            ui.label(&light.name);
+            ui.label(light.light_type.as_ref());
+            let [r, g, b] = [light.color.x, light.color.y, light.color.z].map(|c| (c.clamp(0.0, 1.0) * 255.0) as u8);
+            ui.colored_label(egui::Color32::from_rgb(r, g, b), format!("⏺ {:.1}", light.intensity));
+            ui.end_row();
+          }
+        });
+        ui.label("The first directional light lights the scene.");
+      });
+    }
+  }
+
+  fn set_diffuse_texture(&mut self, frame: &eframe::Frame, id: usize, file: &std::path::Path) {
+    let (Some(gl), Ok(rendering_simple)) = (frame.gl(), &self.rendering_simple) else { return; };
+    let mut rendering_simple = rendering_simple.lock();
+    let Some(model) = rendering_simple.models.iter_mut().find(|m| m.mesh.id == id) else { return; };
+    let pixels = match image::open(file) {
+      Ok(image) => image.to_rgba8(),
+      Err(e) => {
+        do_log!("[Kuplung] Cannot load texture {}: {}", file.display(), e);
+        return;
+      }
+    };
+    let texture = MeshMaterialTextureImage {
+      filename: file.file_name().map_or("".to_string(), |f| f.to_string_lossy().to_string()),
+      image: file.display().to_string(),
+      width: pixels.width(),
+      height: pixels.height(),
+      use_texture: true,
+      pixels: Some(Arc::new(pixels)),
+      encoded: None,
+    };
+    match model.set_diffuse_texture(gl, texture) {
+      Ok(()) => do_log!("[Kuplung] {} uses the texture {}.", model.mesh.model_title, file.display()),
+      Err(e) => do_log!("[Kuplung] [Rendering] Cannot upload the texture of {}: {}", model.mesh.model_title, e),
+    }
+  }
+
+  fn paint_scene(&mut self, ui: &mut egui::Ui) {
+    let rendering_simple = match &self.rendering_simple {
+      Ok(rendering_simple) => rendering_simple.clone(),
+      Err(e) => return error_panel::render_error_panel(ui, "Models", e),
+    };
+    let width = ui.available_width().max(300.0);
+    let size = egui::vec2(width, width * self.view_ratio);
+    let (rect, response) = ui.allocate_exact_size(size, egui::Sense::drag());
+    self.camera.orbit(response.drag_delta());
+    if response.hovered() { self.camera.zoom(ui.input(|i| i.smooth_scroll_delta.y)); }
+    let scene_view = SceneView {
+      projection: self.camera.projection_matrix(rect.aspect_ratio()),
+      view: self.camera.view_matrix(),
+      camera_position: self.camera.position(),
+    };
+    let cb = egui_glow::CallbackFn::new(move |_info, painter| {
+      rendering_simple.lock().paint(painter.gl(), &scene_view);
+    });
+    let callback = egui::PaintCallback {
+      rect,
+      callback: Arc::new(cb),
+    };
+    ui.painter().add(callback);
   }
 
   fn paint_triangler(&mut self, ui: &mut egui::Ui) {
+    let rotating_triangle = match &self.triangler {
+      Ok(triangler) => triangler.clone(),
+      Err(e) => return error_panel::render_error_panel(ui, "Triangler", e),
+    };
     let (rect, response) = ui.allocate_exact_size(egui::Vec2::splat(300.0), egui::Sense::drag());
     self.angle += response.drag_motion().x * 0.01;
     let angle = self.angle;
-    let rotating_triangle = self.triangler.clone();
     let cb = egui_glow::CallbackFn::new(move |_info, painter| {
       rotating_triangle.lock().paint(painter.gl(), angle);
     });
@@ -42,10 +554,16 @@ impl RenderingManager {
     };
     ui.painter().add(callback);
   }
+
+  fn has_models(&self) -> bool {
+    self.rendering_simple.as_ref().is_ok_and(|r| !r.lock().models.is_empty())
+  }
 }
 
 impl eframe::App for RenderingManager {
-  fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+  fn update(&mut self, ctx: &egui::Context, frame: &mut eframe::Frame) {
+    self.retry_failed_programs(frame);
+    self.poll_import(ctx, frame);
     egui::Window::new("Viewer")
       .id(egui::Id::new("window_viewer"))
       .resizable(true)
@@ -55,6 +573,15 @@ impl eframe::App for RenderingManager {
       .hscroll(true)
       .vscroll(true)
       .show(ctx, |ui| {
+        self.render_menu(ui, frame);
+        if self.has_models() {
+          self.render_view_toolbar(ui);
+          self.render_timeline(ui);
+          self.render_models(ui, frame);
+          egui::Frame::canvas(ui.style()).show(ui, |ui| self.paint_scene(ui));
+          ui.label("Drag to rotate, scroll to zoom.");
+          return;
+        }
         ui.horizontal(|ui| {
           ui.spacing_mut().item_spacing.x = 0.0;
           ui.label("The triangler is being painted using ");
@@ -66,13 +593,266 @@ impl eframe::App for RenderingManager {
         egui::Frame::canvas(ui.style()).show(ui, |ui| {
           if self.show_triangler { self.paint_triangler(ui); }
         });
-        ui.label("Drag to rotate!");
+        ui.label("Drag to rotate! Models imported from File > Import... or added from Add > Shape replace the triangler.");
       });
+    if self.show_import { self.render_import(ctx); }
+    if self.show_export { self.render_export(ctx); }
+    if let Some(file) = self.browser_texture.show(ctx) {
+      if let Some(id) = self.texture_model.take() { self.set_diffuse_texture(frame, id, &file); }
+    }
   }
 
   fn on_exit(&mut self, gl: Option<&glow::Context>) {
     if let Some(gl) = gl {
-      self.triangler.lock().destroy(gl);
+      if let Ok(triangler) = &self.triangler { triangler.lock().destroy(gl); }
+      if let Ok(rendering_simple) = &self.rendering_simple { rendering_simple.lock().destroy(gl); }
     }
   }
+}
+
+fn unit_combo_box(ui: &mut egui::Ui, id: &str, unit: &mut ModelUnit) {
+  egui::ComboBox::from_id_source(id)
+    .selected_text(unit.as_ref())
+    .show_ui(ui, |ui| {
+      for option in ModelUnit::iter() {
+        ui.selectable_value(unit, option, option.as_ref());
+      }
+    });
+}
+
+fn render_primitive_parameters(ui: &mut egui::Ui, primitive: &mut Primitive) {
+  let length = |ui: &mut egui::Ui, label: &str, value: &mut f32| {
+    ui.label(label);
+    ui.add(egui::DragValue::new(value).speed(0.05).range(0.01..=100.0));
+    ui.end_row();
+  };
+  let count = |ui: &mut egui::Ui, label: &str, value: &mut u32, min: u32| {
+    ui.label(label);
+    ui.add(egui::DragValue::new(value).range(min..=256));
+    ui.end_row();
+  };
+  egui::Grid::new(format!("viewer_primitive_{}", primitive.as_ref())).num_columns(2).show(ui, |ui| {
+    match primitive {
+      Primitive::Cube { size, segments } | Primitive::Plane { size, segments } => {
+        length(ui, "Size", size);
+        count(ui, "Segments", segments, 1);
+      }
+      Primitive::Sphere { radius, segments, rings } => {
+        length(ui, "Radius", radius);
+        count(ui, "Segments", segments, 3);
+        count(ui, "Rings", rings, 2);
+      }
+      Primitive::IcoSphere { radius, subdivisions } => {
+        length(ui, "Radius", radius);
+        ui.label("Subdivisions");
+        ui.add(egui::DragValue::new(subdivisions).range(0..=primitives::MAX_ICO_SUBDIVISIONS));
+        ui.end_row();
+      }
+      Primitive::Torus { radius, tube_radius, segments, tube_segments } => {
+        length(ui, "Radius", radius);
+        length(ui, "Tube radius", tube_radius);
+        count(ui, "Segments", segments, 3);
+        count(ui, "Tube segments", tube_segments, 3);
+      }
+      Primitive::Cylinder { radius, height, segments } | Primitive::Cone { radius, height, segments } => {
+        length(ui, "Radius", radius);
+        length(ui, "Height", height);
+        count(ui, "Segments", segments, 3);
+      }
+      Primitive::Tube { outer, inner, height, segments } => {
+        length(ui, "Outer radius", outer);
+        length(ui, "Inner radius", inner);
+        length(ui, "Height", height);
+        count(ui, "Segments", segments, 3);
+      }
+    }
+  });
+}
+
+/// The skin, the cel shading and the wireframe of one model.
+fn render_model_view(ui: &mut egui::Ui, id: usize, view: &mut ModelViewSettings) {
+  ui.horizontal(|ui| {
+    ui.label("View");
+    egui::ComboBox::from_id_source(format!("viewer_model_skin_{}", id))
+      .selected_text(view.skin.map_or("Scene".to_string(), |skin| format!("{} {}", skin.icon(), skin.as_ref())))
+      .show_ui(ui, |ui| {
+        ui.selectable_value(&mut view.skin, None, "Scene");
+        for skin in ViewModelSkin::iter() {
+          ui.selectable_value(&mut view.skin, Some(skin), format!("{} {}", skin.icon(), skin.as_ref()));
+        }
+      });
+    ui.checkbox(&mut view.cel_shading, "Cel shading");
+    ui.checkbox(&mut view.wireframe, "Wireframe");
+  });
+}
+
+/// Turns on the motion of the properties of a model and edits their keyframes, returns whether something changed.
+fn render_model_animation(ui: &mut egui::Ui, mesh: &MeshModel, animation: &mut ModelAnimation, time: f32) -> bool {
+  let mut changed = false;
+  egui::Grid::new(format!("viewer_model_animation_{}", mesh.id)).num_columns(5).striped(true).show(ui, |ui| {
+    ui.label("Property");
+    ui.label("Animate");
+    ui.label("Speed");
+    ui.label("Range");
+    ui.label("Keyframes");
+    ui.end_row();
+    for property in AnimatedProperty::iter() {
+      ui.label(property.as_ref());
+      let mut animate = animation.motion(property).is_some_and(|m| m.animate);
+      if ui.checkbox(&mut animate, "").changed() {
+        animation.set_animate(property, animate);
+        changed = true;
+      }
+      match animation.motions.iter_mut().find(|m| m.property == property) {
+        Some(motion) => {
+          changed |= ui.add(egui::DragValue::new(&mut motion.speed).range(0.0..=10.0).speed(0.01).suffix(" /s")).changed();
+          let range = ui.add(egui::DragValue::new(&mut motion.range).range(0.0..=1000.0).speed(0.01));
+          changed |= if property.is_rotation() { range.on_hover_text("0 turns all the way around") } else { range }.changed();
+        }
+        None => {
+          ui.label("");
+          ui.label("");
+        }
+      }
+      ui.horizontal(|ui| {
+        ui.label(animation.track(property).map_or(0, |t| t.keyframes.len()).to_string());
+        if ui.small_button("◆").on_hover_text("Add a keyframe at the current time").clicked() {
+          let value = animation.track(property).and_then(|t| t.value(time)).unwrap_or_else(|| property.value(mesh));
+          animation.set_keyframe(property, time, value);
+          changed = true;
+        }
+      });
+      ui.end_row();
+    }
+  });
+  let mut remove = None;
+  for (t, track) in animation.tracks.iter_mut().enumerate() {
+    ui.collapsing(format!("{} keyframes", track.property.as_ref()), |ui| {
+      egui::Grid::new(format!("viewer_model_keyframes_{}_{}", mesh.id, t)).num_columns(4).show(ui, |ui| {
+        for (k, keyframe) in track.keyframes.iter_mut().enumerate() {
+          changed |= ui.add(egui::DragValue::new(&mut keyframe.time).range(0.0..=600.0).speed(0.01).suffix(" s")).changed();
+          changed |= ui.add(egui::DragValue::new(&mut keyframe.value).speed(0.01)).changed();
+          egui::ComboBox::from_id_source(format!("viewer_model_keyframe_{}_{}_{}", mesh.id, t, k))
+            .selected_text(keyframe.interpolation.as_ref())
+            .show_ui(ui, |ui| {
+              for interpolation in Interpolation::iter() {
+                changed |= ui.selectable_value(&mut keyframe.interpolation, interpolation, interpolation.as_ref()).changed();
+              }
+            });
+          if ui.small_button("🗙").on_hover_text("Remove the keyframe").clicked() { remove = Some((t, k)); }
+          ui.end_row();
+        }
+      });
+    });
+  }
+  if let Some((t, k)) = remove {
+    animation.tracks[t].keyframes.remove(k);
+    changed = true;
+  }
+  if changed { animation.sort_keyframes(); }
+  changed
+}
+
+/// The colors and the strengths of the light in the Solid, Material and Texture skins.
+fn render_solid_light(ui: &mut egui::Ui, light: &mut SolidLightSkin) {
+  let color = |ui: &mut egui::Ui, label: &str, value: &mut nalgebra_glm::Vec3| {
+    ui.label(label);
+    let mut rgb = [value.x, value.y, value.z];
+    if ui.color_edit_button_rgb(&mut rgb).changed() { *value = nalgebra_glm::make_vec3(&rgb); }
+    ui.end_row();
+  };
+  egui::Grid::new("viewer_solid_light_grid").num_columns(2).show(ui, |ui| {
+    color(ui, "Model", &mut light.material_color);
+    color(ui, "Ambient", &mut light.ambient);
+    color(ui, "Diffuse", &mut light.diffuse);
+    color(ui, "Specular", &mut light.specular);
+    ui.label("Ambient strength");
+    ui.add(egui::Slider::new(&mut light.ambient_strength, 0.0..=1.0));
+    ui.end_row();
+    ui.label("Diffuse strength");
+    ui.add(egui::Slider::new(&mut light.diffuse_strength, 0.0..=1.0));
+    ui.end_row();
+    ui.label("Specular strength");
+    ui.add(egui::Slider::new(&mut light.specular_strength, 0.0..=1.0));
+    ui.end_row();
+  });
+  if ui.button("Reset").clicked() { *light = SolidLightSkin::default(); }
+}
+
+/// A button per geometry operation with its parameters, returns the clicked one.
+fn render_mesh_processing(ui: &mut egui::Ui, id: usize, options: &mut ProcessingOptions) -> Option<MeshOperation> {
+  let mut clicked = None;
+  egui::Grid::new(format!("viewer_mesh_processing_{}", id)).num_columns(2).show(ui, |ui| {
+    for operation in MeshOperation::iter() {
+      if ui.button(operation.as_ref()).clicked() { clicked = Some(operation); }
+      match operation {
+        MeshOperation::SmoothNormals => ui.add(egui::DragValue::new(&mut options.smoothing_angle).range(0.0..=180.0).suffix("°")).on_hover_text("Faces further apart keep a hard edge"),
+        MeshOperation::Weld => ui.add(egui::DragValue::new(&mut options.weld_distance).speed(0.0001).range(0.0..=1.0).max_decimals(5)).on_hover_text("Vertices closer than this are joined"),
+        MeshOperation::Decimate => ui.add(egui::Slider::new(&mut options.decimate_ratio, 0.05..=1.0).text("of the triangles")),
+        _ => ui.label(""),
+      };
+      ui.end_row();
+    }
+  });
+  clicked
+}
+
+/// Geometry counts, the printability report and the material of a mesh.
+fn render_mesh_info(ui: &mut egui::Ui, mesh: &MeshModel, report: &MeshReport) {
+  let material = &mesh.model_material;
+  let vector = |c: &nalgebra_glm::Vec3| format!("{:.2} {:.2} {:.2}", c.x, c.y, c.z);
+  egui::Grid::new(format!("viewer_mesh_info_{}", mesh.id)).num_columns(2).show(ui, |ui| {
+    ui.label("File");
+    ui.label(&mesh.file);
+    ui.end_row();
+    ui.label("Vertices");
+    ui.label(format!("{} ({} UVs, {} normals, {} tangents, {} colors)", mesh.vertices.len(), mesh.texture_coordinates.len(), mesh.normals.len(), mesh.tangents.len(), mesh.colors.len()));
+    ui.end_row();
+    if let Some((min, max)) = mesh.world_bounds() {
+      ui.label("Size");
+      ui.label(format!("{} m", vector(&(max - min))));
+      ui.end_row();
+    }
+    ui.label("Watertight");
+    let (color, verdict) = if report.is_watertight() { (egui::Color32::GREEN, "Yes") } else { (egui::Color32::LIGHT_RED, "No") };
+    ui.colored_label(color, verdict).on_hover_text(format!("{} vertices and {} edges, with corners at the same position welded", report.vertices, report.edges));
+    ui.end_row();
+    let problems = [
+      ("Open edges", report.boundary_edges, "Edges with a single triangle - holes in the surface"),
+      ("Non-manifold edges", report.non_manifold_edges, "Edges shared by more than two triangles"),
+      ("Flipped edges", report.flipped_edges, "Edges whose triangles are wound the same way - a triangle faces the wrong side"),
+      ("Degenerate triangles", report.degenerate_triangles, "Triangles with two corners at the same position"),
+    ];
+    for (label, count, hint) in problems {
+      ui.label(label).on_hover_text(hint);
+      if count == 0 { ui.label("0"); } else { ui.colored_label(egui::Color32::LIGHT_RED, count.to_string()); }
+      ui.end_row();
+    }
+    ui.label("Material");
+    ui.label(&material.material_title);
+    ui.end_row();
+    ui.label("Ambient / Diffuse");
+    ui.label(format!("{} / {}", vector(&material.ambient_color), vector(&material.diffuse_color)));
+    ui.end_row();
+    ui.label("Specular / Emission");
+    ui.label(format!("{} / {}", vector(&material.specular_color), vector(&material.emission_color)));
+    ui.end_row();
+    ui.label("Ns / d / Ni / illum");
+    ui.label(format!("{:.1} / {:.2} / {:.2} / {}", material.specular_exp, material.transparency, material.optical_density, material.illumination_mode));
+    ui.end_row();
+    ui.label("Metallic / Roughness");
+    ui.label(format!("{:.2} / {:.2}", material.metallic, material.roughness));
+    ui.end_row();
+    let transform = &mesh.transform;
+    ui.label("Position / Rotation / Scale");
+    ui.label(format!("{} / {} / {}", vector(&transform.position), vector(&transform.rotation), vector(&transform.scale)));
+    ui.end_row();
+    for (slot, texture) in material.textures().iter().filter(|(_, t)| !t.filename.is_empty()) {
+      ui.label(*slot);
+      let label = if texture.width > 0 { format!("{} ({}x{})", texture.filename, texture.width, texture.height) } else { texture.filename.clone() };
+      let label = ui.label(label);
+      if texture.use_texture { label.on_hover_text(&texture.image); } else { label.on_hover_text("The file was not found"); }
+      ui.end_row();
+    }
+  });
 }
\ No newline at end of file
@@ -0,0 +1,183 @@
+#![allow(non_snake_case)]
+
+use eframe::egui_glow;
+use eframe::glow::HasContext;
+use egui_glow::glow;
+use nalgebra_glm as glm;
+use crate::do_log;
+use crate::meshes::animation::Timeline;
+use crate::meshes::mesh_model::MeshModel;
+use crate::meshes::scene::{LightType, Scene, SceneCamera, SceneLight};
+use crate::rendering::gl_error::GLError;
+use crate::rendering::model_face::ModelFace;
+use crate::rendering::shader_program::ShaderProgram;
+use crate::rendering::view_skin::{SolidLightSkin, ViewModelSkin};
+use crate::settings::kuplung_logger;
+use crate::settings::kuplung_settings::{self, KuplungSettings, RenderingSettings};
+
+/// What the scene is seen from, computed on the UI thread and handed to the paint callback.
+#[derive(Clone, Copy)]
+pub struct SceneView {
+  pub projection: glm::Mat4,
+  pub view: glm::Mat4,
+  pub camera_position: glm::Vec3,
+}
+
+/// Forward renderer for the imported models with a single directional light - the first one the scene brought in, if any.
+pub struct RenderingSimple {
+  gl_Program: ShaderProgram,
+  pub models: Vec<ModelFace>,
+  pub cameras: Vec<SceneCamera>,
+  pub lights: Vec<SceneLight>,
+  pub solid_light: SolidLightSkin,
+  pub timeline: Timeline,
+  /// The skin of the models that don't have one of their own.
+  pub view_skin: ViewModelSkin,
+  gamma: f32,
+  clear_color: [f32; 4],
+}
+
+#[allow(unsafe_code)]
+impl RenderingSimple {
+  pub fn new(gl: &glow::Context) -> Result<Self, GLError> {
+    let gl_Program = ShaderProgram::from_files(gl, "Model", &[
+      (glow::VERTEX_SHADER, "assets/shaders/viewer/model.vert"),
+      (glow::FRAGMENT_SHADER, "assets/shaders/viewer/model.frag"),
+    ])?;
+    let mut this = Self {
+      gl_Program,
+      models: Vec::new(),
+      cameras: Vec::new(),
+      lights: Vec::new(),
+      solid_light: SolidLightSkin::default(),
+      timeline: Timeline::default(),
+      view_skin: ViewModelSkin::default(),
+      gamma: 1.0,
+      clear_color: [0.0; 4],
+    };
+    this.apply_settings(&kuplung_settings::get(), &kuplung_settings::get_rendering());
+    Ok(this)
+  }
+
+  pub fn apply_settings(&mut self, settings: &KuplungSettings, rendering: &RenderingSettings) {
+    self.view_skin = ViewModelSkin::from_index(rendering.general.selected_view_model_skin);
+    self.gamma = rendering.general.gamma;
+    self.clear_color = settings.clear_color();
+  }
+
+  /// Adds the models, cameras and lights of an imported scene, the timeline comes along when the models are animated.
+  /// Returns how many models were added.
+  pub fn add_scene(&mut self, gl: &glow::Context, scene: Scene) -> usize {
+    if scene.models.iter().any(|m| !m.animation.is_empty()) { self.timeline = scene.timeline; }
+    self.cameras.extend(scene.cameras);
+    self.lights.extend(scene.lights);
+    self.add_models(gl, scene.models)
+  }
+
+  /// Uploads the meshes, the ones that fail are logged and skipped. Returns how many were added.
+  fn add_models(&mut self, gl: &glow::Context, meshes: Vec<MeshModel>) -> usize {
+    let mut added = 0;
+    let mut next_id = self.models.iter().map(|m| m.mesh.id + 1).max().unwrap_or(0);
+    for mut mesh in meshes {
+      mesh.id = next_id;
+      let title = mesh.model_title.clone();
+      match ModelFace::new(gl, mesh) {
+        Ok(model) => {
+          self.models.push(model);
+          next_id += 1;
+          added += 1;
+        }
+        Err(e) => do_log!("[Kuplung] [Rendering] Cannot upload {}: {}", title, e),
+      }
+    }
+    added
+  }
+
+  pub fn remove_model(&mut self, gl: &glow::Context, id: usize) {
+    if let Some(index) = self.models.iter().position(|m| m.mesh.id == id) {
+      self.models.remove(index).destroy(gl);
+    }
+  }
+
+  pub fn clear(&mut self, gl: &glow::Context) {
+    for model in self.models.drain(..) { model.destroy(gl); }
+    self.cameras.clear();
+    self.lights.clear();
+    self.timeline.stop();
+  }
+
+  /// The models with their animations, the cameras, the lights and the timeline, for exporting.
+  pub fn scene(&self) -> Scene {
+    Scene { models: self.models.iter().map(|m| m.mesh.clone()).collect(), cameras: self.cameras.clone(), lights: self.lights.clone(), timeline: self.timeline }
+  }
+
+  /// The (min, max) corners around all models.
+  pub fn bounds(&self) -> Option<(glm::Vec3, glm::Vec3)> {
+    self.models.iter().filter_map(|m| m.mesh.world_bounds()).reduce(|(min_a, max_a), (min_b, max_b)| (glm::min2(&min_a, &min_b), glm::max2(&max_a, &max_b)))
+  }
+
+  /// The distances from the camera to the nearest and the farthest point around the models, for the Depth skin.
+  fn depth_range(&self, camera_position: &glm::Vec3) -> glm::Vec2 {
+    match self.bounds() {
+      Some((min, max)) => {
+        let center = (min + max) * 0.5;
+        let radius = glm::distance(&center, &max);
+        let distance = glm::distance(&center, camera_position);
+        glm::vec2((distance - radius).max(0.0), distance + radius)
+      }
+      None => glm::vec2(0.0, 1.0),
+    }
+  }
+
+  pub fn paint(&mut self, gl: &glow::Context, scene_view: &SceneView) {
+    self.gl_Program.reload_if_changed(gl);
+    let scene_skin = self.view_skin;
+    let sun = self.lights.iter().find(|l| l.light_type == LightType::Directional);
+    let (light_direction, light_color) = sun.map_or((glm::vec3(-0.4, -1.0, -0.6), glm::vec3(1.0, 1.0, 1.0)), |l| (l.direction, l.color));
+    unsafe {
+      let [r, g, b, a] = self.clear_color;
+      gl.clear_color(r, g, b, a);
+      gl.clear(glow::COLOR_BUFFER_BIT | glow::DEPTH_BUFFER_BIT);
+      gl.enable(glow::DEPTH_TEST);
+      gl.depth_func(glow::LESS);
+      gl.enable(glow::BLEND);
+      gl.blend_func(glow::SRC_ALPHA, glow::ONE_MINUS_SRC_ALPHA);
+
+      self.gl_Program.begin_frame(gl);
+      self.gl_Program.set_vec3(gl, "u_cameraPosition", &scene_view.camera_position);
+      self.gl_Program.set_vec3(gl, "u_lightDirection", &light_direction);
+      self.gl_Program.set_vec3(gl, "u_lightColor", &light_color);
+      self.gl_Program.set_f32(gl, "u_gamma", self.gamma);
+      self.gl_Program.set_vec2(gl, "u_depthRange", &self.depth_range(&scene_view.camera_position));
+      let solid_light = &self.solid_light;
+      self.gl_Program.set_vec3(gl, "u_solidLight.materialColor", &solid_light.material_color);
+      self.gl_Program.set_vec3(gl, "u_solidLight.ambient", &solid_light.ambient);
+      self.gl_Program.set_vec3(gl, "u_solidLight.diffuse", &solid_light.diffuse);
+      self.gl_Program.set_vec3(gl, "u_solidLight.specular", &solid_light.specular);
+      self.gl_Program.set_f32(gl, "u_solidLight.strengthAmbient", solid_light.ambient_strength);
+      self.gl_Program.set_f32(gl, "u_solidLight.strengthDiffuse", solid_light.diffuse_strength);
+      self.gl_Program.set_f32(gl, "u_solidLight.strengthSpecular", solid_light.specular_strength);
+      for model in &self.models {
+        let pose = model.mesh.animation.pose(&model.mesh, self.timeline.time);
+        let world = pose.transform.matrix();
+        self.gl_Program.set_mat4(gl, "u_MVPMatrix", &(scene_view.projection * scene_view.view * world));
+        self.gl_Program.set_mat4(gl, "u_WorldMatrix", &world);
+        self.gl_Program.set_mat3(gl, "u_NormalMatrix", &glm::transpose(&glm::inverse(&glm::mat4_to_mat3(&world))));
+        self.gl_Program.set_i32(gl, "u_viewSkin", model.view.skin(scene_skin).index() as i32);
+        self.gl_Program.set_bool(gl, "u_celShading", model.view.cel_shading);
+        let wireframe = model.view.is_wireframe(scene_skin);
+        if wireframe { gl.polygon_mode(glow::FRONT_AND_BACK, glow::LINE); }
+        model.paint(gl, &self.gl_Program, &pose);
+        if wireframe { gl.polygon_mode(glow::FRONT_AND_BACK, glow::FILL); }
+      }
+      self.gl_Program.end_frame();
+
+      gl.disable(glow::DEPTH_TEST);
+    }
+  }
+
+  pub fn destroy(&mut self, gl: &glow::Context) {
+    self.clear(gl);
+    self.gl_Program.destroy(gl);
+  }
+}
@@ -0,0 +1,300 @@
+use std::collections::HashSet;
+use std::error::Error;
+use std::path::{Path, PathBuf};
+use crate::settings::configuration;
+use crate::utils::{asset_locator, asset_vfs};
+
+// Shader preprocessing done before handing the source to GL:
+//   #include "file.frag"  - relative to the including file, then to the shader folders; every file is included once
+//   #version              - hoisted to the very top, wherever it appears
+//   #pragma once          - accepted and dropped, files are always guarded
+// Defines set from Rust are injected right after #version. The output carries #line directives
+// (source string number = index into `files`), so compiler logs point back to the original files and lines.
+
+type ShaderLoader = Box<dyn Fn(&Path) -> Result<String, Box<dyn Error>>>;
+
+/// A preprocessed shader - the final code and the files its #line source string numbers refer to.
+#[derive(Clone, Debug, Default)]
+pub struct ShaderSource {
+  pub code: String,
+  pub files: Vec<String>,
+}
+
+impl ShaderSource {
+  /// Rewrites the `<source>:<line>` / `<source>(<line>)` locations in a compiler log to `file:line`.
+  pub fn map_log(&self, log: &str) -> String {
+    log.lines().map(|line| self.map_log_line(line)).collect::<Vec<_>>().join("\n")
+  }
+
+  fn map_log_line(&self, line: &str) -> String {
+    // NVIDIA: "0(12) : error ...", Mesa: "0:12(5): error ...", AMD: "ERROR: 0:12: ..."
+    let prefix_len = ["ERROR: ", "WARNING: "].iter().find(|p| line.starts_with(*p)).map_or(0, |p| p.len());
+    let rest = &line[prefix_len..];
+    let digits = rest.bytes().take_while(|b| b.is_ascii_digit()).count();
+    if digits == 0 { return line.to_string(); }
+    let after = &rest[digits..];
+    let Some(separator) = after.chars().next().filter(|c| *c == ':' || *c == '(') else { return line.to_string(); };
+    let line_digits = after[1..].bytes().take_while(|b| b.is_ascii_digit()).count();
+    if line_digits == 0 { return line.to_string(); }
+    let (Ok(file), Ok(number)) = (rest[..digits].parse::<usize>(), after[1..1 + line_digits].parse::<usize>()) else { return line.to_string(); };
+    let Some(file_name) = self.files.get(file) else { return line.to_string(); };
+    let mut remainder = &after[1 + line_digits..];
+    if separator == '(' { remainder = remainder.strip_prefix(')').unwrap_or(remainder); }
+    format!("{}{}:{}{}", &line[..prefix_len], file_name, number, remainder)
+  }
+}
+
+pub struct ShaderPreprocessor {
+  include_folders: Vec<PathBuf>,
+  defines: Vec<(String, String)>,
+  loader: ShaderLoader,
+}
+
+impl Default for ShaderPreprocessor {
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
+impl ShaderPreprocessor {
+  pub fn new() -> Self {
+    Self {
+      include_folders: vec![asset_locator::locate(configuration::SHADERS_FOLDER)],
+      defines: Vec::new(),
+      loader: Box::new(|path| Ok(asset_vfs::read_to_string(path)?)),
+    }
+  }
+
+  pub fn include_folder(mut self, folder: &str) -> Self {
+    self.include_folders.push(asset_locator::locate(folder));
+    self
+  }
+
+  pub fn define(mut self, name: &str, value: impl ToString) -> Self {
+    self.defines.retain(|(n, _)| n != name);
+    self.defines.push((name.to_string(), value.to_string()));
+    self
+  }
+
+  /// Preprocesses a shader file - asset paths ("assets/shaders/...") are looked up in the asset folders, then in the embedded assets.
+  pub fn process_file(&self, file: &str) -> Result<ShaderSource, Box<dyn Error>> {
+    let path = asset_locator::resolve(file).unwrap_or_else(|_| PathBuf::from(file));
+    let source = (self.loader)(&path).map_err(|e| format!("Cannot read shader {}: {}", file, e))?;
+    self.process(&path.to_string_lossy(), &source)
+  }
+
+  /// Preprocesses source text, `name` being the file it came from (used for includes and error locations).
+  pub fn process(&self, name: &str, source: &str) -> Result<ShaderSource, Box<dyn Error>> {
+    self.process_sources(&[(name, source)])
+  }
+
+  /// Preprocesses several named sources as one shader, each keeping its own line numbers in the compiler logs.
+  pub fn process_sources(&self, sources: &[(&str, &str)]) -> Result<ShaderSource, Box<dyn Error>> {
+    let mut state = ProcessState::default();
+    for (name, _) in sources {
+      state.included.insert(normalize(Path::new(name)));
+    }
+    for (name, source) in sources {
+      self.process_into(&mut state, Path::new(name), source, &mut Vec::new())?;
+    }
+
+    let mut result = ShaderSource { files: state.files, ..Default::default() };
+    if let Some((version, _, _)) = &state.version {
+      result.code.push_str(version);
+      result.code.push('\n');
+    }
+    for (name, value) in &self.defines {
+      result.code.push_str(&format!("#define {} {}\n", name, value));
+    }
+    result.code.push_str(&state.code);
+    Ok(result)
+  }
+
+  fn process_into(&self, state: &mut ProcessState, path: &Path, source: &str, stack: &mut Vec<PathBuf>) -> Result<(), Box<dyn Error>> {
+    let file = state.files.len();
+    state.files.push(path.to_string_lossy().to_string());
+    stack.push(normalize(path));
+    state.emit_line_directive(1, file);
+
+    for (index, line) in source.lines().enumerate() {
+      let number = index + 1;
+      let directive = line.trim_start();
+      if let Some(version) = directive.strip_prefix("#version") {
+        match &state.version {
+          None => state.version = Some((format!("#version {}", version.trim()), file, number)),
+          Some((existing, _, _)) if existing.split_whitespace().eq(line.split_whitespace()) => {}
+          Some((existing, version_file, version_line)) => {
+            return Err(format!("{}:{}: '{}' conflicts with '{}' from {}:{}", path.display(), number, directive, existing, state.files[*version_file], version_line).into());
+          }
+        }
+        state.emit_line_directive(number + 1, file);
+      }
+      else if directive.starts_with("#pragma") && directive.split_whitespace().nth(1) == Some("once") {
+        state.emit_line_directive(number + 1, file);
+      }
+      else if let Some(include) = directive.strip_prefix("#include") {
+        let include = include.trim();
+        let name = include.strip_prefix('"').and_then(|i| i.strip_suffix('"'))
+          .or_else(|| include.strip_prefix('<').and_then(|i| i.strip_suffix('>')))
+          .ok_or_else(|| format!("{}:{}: malformed #include {}", path.display(), number, include))?;
+        let (include_path, include_source) = self.load_include(path, name).map_err(|e| format!("{}:{}: {}", path.display(), number, e))?;
+        let key = normalize(&include_path);
+        if stack.contains(&key) {
+          return Err(format!("{}:{}: circular #include of {}", path.display(), number, name).into());
+        }
+        if state.included.insert(key) {
+          self.process_into(state, &include_path, &include_source, stack)?;
+        }
+        state.emit_line_directive(number + 1, file);
+      }
+      else {
+        state.code.push_str(line);
+        state.code.push('\n');
+      }
+    }
+    stack.pop();
+    Ok(())
+  }
+
+  /// Looks for an include next to the including file first, then in the include folders.
+  fn load_include(&self, from: &Path, name: &str) -> Result<(PathBuf, String), Box<dyn Error>> {
+    let mut candidates = vec![from.parent().unwrap_or(Path::new("")).join(name)];
+    candidates.extend(self.include_folders.iter().map(|f| f.join(name)));
+    for candidate in &candidates {
+      if let Ok(source) = (self.loader)(candidate) {
+        return Ok((candidate.clone(), source));
+      }
+    }
+    Err(format!("cannot find #include {} (searched {})", name, candidates.iter().map(|c| c.display().to_string()).collect::<Vec<_>>().join(", ")).into())
+  }
+}
+
+#[derive(Default)]
+struct ProcessState {
+  code: String,
+  files: Vec<String>,
+  version: Option<(String, usize, usize)>,
+  included: HashSet<PathBuf>,
+}
+
+impl ProcessState {
+  fn emit_line_directive(&mut self, line: usize, file: usize) {
+    self.code.push_str(&format!("#line {} {}\n", line, file));
+  }
+}
+
+/// Resolves `.` and `..` so that the same file reached through different paths is included once.
+fn normalize(path: &Path) -> PathBuf {
+  let mut normalized = PathBuf::new();
+  for component in path.components() {
+    match component {
+      std::path::Component::CurDir => {}
+      std::path::Component::ParentDir if normalized.file_name().is_some() => { normalized.pop(); }
+      c => normalized.push(c),
+    }
+  }
+  normalized
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+  use super::*;
+  use std::collections::HashMap;
+
+  fn preprocessor(files: &[(&str, &str)]) -> ShaderPreprocessor {
+    let files: HashMap<PathBuf, String> = files.iter().map(|(n, s)| (PathBuf::from(n), s.to_string())).collect();
+    ShaderPreprocessor {
+      loader: Box::new(move |path| files.get(&normalize(path)).cloned().ok_or_else(|| "not found".into())),
+      ..ShaderPreprocessor::new()
+    }
+  }
+
+  fn code_lines(source: &ShaderSource) -> Vec<&str> {
+    source.code.lines().filter(|l| !l.starts_with("#line")).collect()
+  }
+
+  #[test]
+  fn includes_hoists_version_and_injects_defines() {
+    let shaders = preprocessor(&[
+      ("shaders/main.frag", "#include \"vars.frag\"\nvoid main() {}\n"),
+      ("shaders/vars.frag", "#version 410 core\nuniform float x;\n"),
+    ]).define("NR_LIGHTS", 4);
+    let source = shaders.process_file("shaders/main.frag").unwrap();
+    assert!(source.code.starts_with("#version 410 core\n#define NR_LIGHTS 4\n"));
+    assert_eq!(code_lines(&source), vec!["#version 410 core", "#define NR_LIGHTS 4", "uniform float x;", "void main() {}"]);
+  }
+
+  #[test]
+  fn includes_each_file_once() {
+    let shaders = preprocessor(&[
+      ("main.frag", "#include \"a.glsl\"\n#include \"b.glsl\"\n"),
+      ("a.glsl", "#pragma once\n#include \"common.glsl\"\nfloat a;\n"),
+      ("b.glsl", "#include \"./common.glsl\"\nfloat b;\n"),
+      ("common.glsl", "float common;\n"),
+    ]);
+    let source = shaders.process_file("main.frag").unwrap();
+    assert_eq!(code_lines(&source), vec!["float common;", "float a;", "float b;"]);
+  }
+
+  #[test]
+  fn falls_back_to_include_folders() {
+    let shaders = preprocessor(&[
+      ("toys/main.frag", "#include <lib/noise.glsl>\n"),
+      ("extra/lib/noise.glsl", "float noise;\n"),
+    ]).include_folder("extra");
+    assert_eq!(code_lines(&shaders.process_file("toys/main.frag").unwrap()), vec!["float noise;"]);
+    let missing = preprocessor(&[("main.frag", "\n#include \"missing.glsl\"\n")]).process_file("main.frag").unwrap_err().to_string();
+    assert!(missing.starts_with("main.frag:2: cannot find #include missing.glsl"), "{}", missing);
+  }
+
+  #[test]
+  fn reports_cycles_and_version_conflicts() {
+    let cycle = preprocessor(&[("a.glsl", "#include \"b.glsl\"\n"), ("b.glsl", "#include \"a.glsl\"\n")]).process_file("a.glsl");
+    assert!(cycle.unwrap_err().to_string().contains("circular #include of a.glsl"));
+    let conflict = preprocessor(&[("a.glsl", "#version 410 core\n#include \"b.glsl\"\n"), ("b.glsl", "#version 330\n")]).process_file("a.glsl");
+    assert!(conflict.unwrap_err().to_string().contains("conflicts with '#version 410 core'"));
+  }
+
+  /// Where the driver thinks a line of the final code comes from, following the #line directives.
+  pub(crate) fn driver_location(source: &ShaderSource, needle: &str) -> (String, usize) {
+    let (mut line, mut file) = (0, 0);
+    for code in source.code.lines() {
+      match code.strip_prefix("#line ") {
+        Some(directive) => {
+          let mut parts = directive.split_whitespace().map(|p| p.parse::<usize>().unwrap());
+          (line, file) = (parts.next().unwrap() - 1, parts.next().unwrap());
+        }
+        None => line += 1,
+      }
+      if code.contains(needle) { break; }
+    }
+    (source.files[file].clone(), line)
+  }
+
+  #[test]
+  fn maps_lines_back_to_the_original_files() {
+    let shaders = preprocessor(&[
+      ("main.frag", "#version 410 core\n#include \"lights.frag\"\nvoid main() {\n  broken;\n}\n"),
+      ("lights.frag", "// lights\nuniform vec3 light;\n"),
+    ]).define("X", 1);
+    let source = shaders.process_file("main.frag").unwrap();
+    assert_eq!(driver_location(&source, "broken"), ("main.frag".to_string(), 4));
+    assert_eq!(driver_location(&source, "uniform vec3 light"), ("lights.frag".to_string(), 2));
+
+    assert_eq!(source.map_log("0(4) : error C1008: undefined variable \"broken\""), "main.frag:4 : error C1008: undefined variable \"broken\"");
+    assert_eq!(source.map_log("0:4(3): error: `broken' undeclared"), "main.frag:4(3): error: `broken' undeclared");
+    assert_eq!(source.map_log("ERROR: 1:2: 'light' : redefinition"), "ERROR: lights.frag:2: 'light' : redefinition");
+    assert_eq!(source.map_log("some other message"), "some other message");
+  }
+
+  #[test]
+  fn model_face_fragment_shader_is_complete() {
+    let source = ShaderPreprocessor::new().process_file("assets/shaders/rendering/model_face.frag").unwrap();
+    assert!(source.code.starts_with("#version 410 core\n"));
+    assert_eq!(source.code.matches("#version").count(), 1);
+    for piece in ["vars", "lights", "mapping", "effects", "pbr", "shadow_mapping", "misc"] {
+      assert!(source.files.iter().any(|f| f.ends_with(&format!("model_face_{}.frag", piece))), "missing {}", piece);
+    }
+    assert!(source.code.contains("void main(void)"));
+  }
+}
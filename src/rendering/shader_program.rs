@@ -0,0 +1,273 @@
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+use eframe::glow;
+use eframe::glow::HasContext;
+use nalgebra_glm as glm;
+use crate::do_log;
+use crate::rendering::gl_error::GLError;
+use crate::rendering::gl_utils;
+use crate::rendering::shader_preprocessor::ShaderPreprocessor;
+use crate::rendering::shader_registry;
+use crate::settings::kuplung_logger;
+
+const SAMPLER_TYPES: [u32; 8] = [
+  glow::SAMPLER_1D, glow::SAMPLER_2D, glow::SAMPLER_3D, glow::SAMPLER_CUBE,
+  glow::SAMPLER_2D_SHADOW, glow::SAMPLER_CUBE_SHADOW, glow::SAMPLER_2D_ARRAY, glow::SAMPLER_2D_MULTISAMPLE,
+];
+
+/// An active uniform as reflected after linking - arrays are a single entry named `name[0]`.
+pub struct ShaderUniform {
+  pub name: String,
+  pub utype: u32,
+}
+
+/// Linked program with the locations of its active uniforms looked up once.
+///
+/// Uniforms are set by the name used in GLSL - `u_zoomSize`, `lights[2]` or `directionalLights[i].ambient` built with format!.
+/// Array uniforms can also be set whole by their base name. Unknown names and type mismatches are logged once per name,
+/// and `end_frame` logs the uniforms that were never set since `begin_frame`.
+///
+/// Programs built from files are in the shader registry - `reload_if_changed` rebuilds them after their sources change.
+pub struct ShaderProgram {
+  name: String,
+  program: glow::Program,
+  uniforms: Vec<ShaderUniform>,
+  locations: HashMap<String, (glow::UniformLocation, usize)>,
+  set_uniforms: RefCell<Vec<bool>>,
+  warned: RefCell<HashSet<String>>,
+  shader_files: Vec<(u32, String)>,
+  registry_id: Option<usize>,
+  revision: u64,
+}
+
+#[allow(unsafe_code)]
+impl ShaderProgram {
+  /// Builds the program from shader files run through the shader preprocessor, and registers it for hot reload.
+  pub fn from_files(gl: &glow::Context, name: &str, shader_files: &[(u32, &str)]) -> Result<Self, GLError> {
+    let registry_id = shader_registry::register(name, shader_files);
+    let shader_files: Vec<(u32, String)> = shader_files.iter().map(|(t, f)| (*t, f.to_string())).collect();
+    match Self::build(gl, name, &shader_files) {
+      Ok((program, source_files)) => {
+        shader_registry::set_built(registry_id, source_files);
+        Ok(Self {
+          shader_files,
+          registry_id: Some(registry_id),
+          revision: shader_registry::revision(registry_id),
+          ..Self::new(gl, name, program)
+        })
+      }
+      Err(e) => {
+        shader_registry::set_failed(registry_id, &e, false);
+        Err(e)
+      }
+    }
+  }
+
+  /// Takes ownership of a linked program.
+  pub fn new(gl: &glow::Context, name: &str, program: glow::Program) -> Self {
+    let (uniforms, locations) = Self::reflect(gl, program);
+    do_log!("[Kuplung] [ShaderProgram] Program {} has {} active uniforms.", name, uniforms.len());
+    Self {
+      name: name.to_string(),
+      program,
+      set_uniforms: RefCell::new(vec![false; uniforms.len()]),
+      uniforms,
+      locations,
+      warned: RefCell::new(HashSet::new()),
+      shader_files: Vec::new(),
+      registry_id: None,
+      revision: 0,
+    }
+  }
+
+  /// Takes ownership of a program linked from generated sources and lists it in the shader registry.
+  /// The owner rebuilds it when `take_reload_request` says so.
+  pub fn registered(gl: &glow::Context, name: &str, program: glow::Program, source_files: Vec<String>) -> Self {
+    let registry_id = shader_registry::register(name, &[]);
+    shader_registry::set_built(registry_id, source_files);
+    Self {
+      registry_id: Some(registry_id),
+      revision: shader_registry::revision(registry_id),
+      ..Self::new(gl, name, program)
+    }
+  }
+
+  /// Whether a rebuild of a registered program was requested since the last call.
+  pub fn take_reload_request(&mut self) -> bool {
+    let Some(registry_id) = self.registry_id else { return false; };
+    let revision = shader_registry::revision(registry_id);
+    if revision == self.revision { return false; }
+    self.revision = revision;
+    true
+  }
+
+  /// A rebuild failed, the program keeps running.
+  pub fn set_failed(&self, error: &GLError) {
+    if let Some(registry_id) = self.registry_id { shader_registry::set_failed(registry_id, error, true); }
+  }
+
+  /// Rebuilds the program when the registry has a newer revision. A program that fails to build keeps the current one.
+  pub fn reload_if_changed(&mut self, gl: &glow::Context) {
+    if self.shader_files.is_empty() { return; }
+    let Some(registry_id) = self.registry_id else { return; };
+    let revision = shader_registry::revision(registry_id);
+    if revision == self.revision { return; }
+    self.revision = revision;
+    match Self::build(gl, &self.name, &self.shader_files) {
+      Ok((program, source_files)) => {
+        unsafe { gl.delete_program(self.program); }
+        let (uniforms, locations) = Self::reflect(gl, program);
+        self.program = program;
+        self.set_uniforms = RefCell::new(vec![false; uniforms.len()]);
+        self.uniforms = uniforms;
+        self.locations = locations;
+        self.warned.borrow_mut().clear();
+        shader_registry::set_built(registry_id, source_files);
+        do_log!("[Kuplung] [ShaderProgram] Program {} reloaded.", self.name);
+      }
+      Err(e) => {
+        do_log!("[Kuplung] [ShaderProgram] Program {} cannot be reloaded, keeping the last good one. {}", self.name, e);
+        shader_registry::set_failed(registry_id, &e, true);
+      }
+    }
+  }
+
+  /// The linked program and all the files it was built from.
+  fn build(gl: &glow::Context, name: &str, shader_files: &[(u32, String)]) -> Result<(glow::Program, Vec<String>), GLError> {
+    let shader_files: Vec<(u32, &str)> = shader_files.iter().map(|(t, f)| (*t, f.as_str())).collect();
+    let sources = gl_utils::load_shader_sources(&shader_files, &ShaderPreprocessor::new())?;
+    let mut source_files: Vec<String> = sources.iter().flat_map(|(_, _, source)| source.files.iter().cloned()).collect();
+    source_files.sort();
+    source_files.dedup();
+    let program = unsafe { gl_utils::create_program_from_sources(gl, name, &sources)? };
+    Ok((program, source_files))
+  }
+
+  fn reflect(gl: &glow::Context, program: glow::Program) -> (Vec<ShaderUniform>, HashMap<String, (glow::UniformLocation, usize)>) {
+    let mut uniforms = Vec::new();
+    let mut locations = HashMap::new();
+    unsafe {
+      for index in 0..gl.get_active_uniforms(program) {
+        let Some(active) = gl.get_active_uniform(program, index) else { continue; };
+        // uniform blocks members have no location
+        let Some(location) = gl.get_uniform_location(program, &active.name) else { continue; };
+        let entry = uniforms.len();
+        if let Some(base) = active.name.strip_suffix("[0]") {
+          locations.insert(base.to_string(), (location, entry));
+          for element in 1..active.size {
+            let element_name = format!("{}[{}]", base, element);
+            if let Some(element_location) = gl.get_uniform_location(program, &element_name) {
+              locations.insert(element_name, (element_location, entry));
+            }
+          }
+        }
+        locations.insert(active.name.clone(), (location, entry));
+        uniforms.push(ShaderUniform { name: active.name, utype: active.utype });
+      }
+    }
+    (uniforms, locations)
+  }
+
+  pub fn program(&self) -> glow::Program {
+    self.program
+  }
+
+  pub fn uniforms(&self) -> &[ShaderUniform] {
+    &self.uniforms
+  }
+
+  pub fn has_uniform(&self, name: &str) -> bool {
+    self.locations.contains_key(name)
+  }
+
+  /// Binds the program and starts tracking which uniforms get set.
+  pub fn begin_frame(&self, gl: &glow::Context) {
+    unsafe { gl.use_program(Some(self.program)); }
+    self.set_uniforms.borrow_mut().fill(false);
+  }
+
+  /// Logs (once per name) the active uniforms that were not set since `begin_frame` and returns them.
+  pub fn end_frame(&self) -> Vec<String> {
+    let unset: Vec<String> = self.uniforms.iter().zip(self.set_uniforms.borrow().iter())
+      .filter(|(_, set)| !**set)
+      .map(|(uniform, _)| uniform.name.clone())
+      .collect();
+    for name in &unset {
+      self.warn_once(format!("unset {}", name), || format!("Uniform {} was not set this frame.", name));
+    }
+    unset
+  }
+
+  pub fn set_f32(&self, gl: &glow::Context, name: &str, value: f32) {
+    if let Some(location) = self.location(name, &[glow::FLOAT]) { unsafe { gl.uniform_1_f32(Some(location), value); } }
+  }
+
+  pub fn set_i32(&self, gl: &glow::Context, name: &str, value: i32) {
+    if let Some(location) = self.location(name, &[glow::INT]) { unsafe { gl.uniform_1_i32(Some(location), value); } }
+  }
+
+  pub fn set_bool(&self, gl: &glow::Context, name: &str, value: bool) {
+    if let Some(location) = self.location(name, &[glow::BOOL, glow::INT]) { unsafe { gl.uniform_1_i32(Some(location), value as i32); } }
+  }
+
+  /// Points a sampler to a texture unit.
+  pub fn set_sampler(&self, gl: &glow::Context, name: &str, unit: i32) {
+    if let Some(location) = self.location(name, &SAMPLER_TYPES) { unsafe { gl.uniform_1_i32(Some(location), unit); } }
+  }
+
+  pub fn set_vec2(&self, gl: &glow::Context, name: &str, value: &glm::Vec2) {
+    if let Some(location) = self.location(name, &[glow::FLOAT_VEC2]) { unsafe { gl.uniform_2_f32_slice(Some(location), value.as_slice()); } }
+  }
+
+  pub fn set_vec3(&self, gl: &glow::Context, name: &str, value: &glm::Vec3) {
+    if let Some(location) = self.location(name, &[glow::FLOAT_VEC3]) { unsafe { gl.uniform_3_f32_slice(Some(location), value.as_slice()); } }
+  }
+
+  pub fn set_vec4(&self, gl: &glow::Context, name: &str, value: &glm::Vec4) {
+    if let Some(location) = self.location(name, &[glow::FLOAT_VEC4]) { unsafe { gl.uniform_4_f32_slice(Some(location), value.as_slice()); } }
+  }
+
+  pub fn set_mat3(&self, gl: &glow::Context, name: &str, value: &glm::Mat3) {
+    if let Some(location) = self.location(name, &[glow::FLOAT_MAT3]) { unsafe { gl.uniform_matrix_3_f32_slice(Some(location), false, value.as_slice()); } }
+  }
+
+  pub fn set_mat4(&self, gl: &glow::Context, name: &str, value: &glm::Mat4) {
+    if let Some(location) = self.location(name, &[glow::FLOAT_MAT4]) { unsafe { gl.uniform_matrix_4_f32_slice(Some(location), false, value.as_slice()); } }
+  }
+
+  /// Sets consecutive array elements, starting at the named one (`weights` or `weights[2]`).
+  pub fn set_f32_array(&self, gl: &glow::Context, name: &str, values: &[f32]) {
+    if let Some(location) = self.location(name, &[glow::FLOAT]) { unsafe { gl.uniform_1_f32_slice(Some(location), values); } }
+  }
+
+  pub fn set_vec3_array(&self, gl: &glow::Context, name: &str, values: &[glm::Vec3]) {
+    let flat: Vec<f32> = values.iter().flat_map(|v| v.iter().copied()).collect();
+    if let Some(location) = self.location(name, &[glow::FLOAT_VEC3]) { unsafe { gl.uniform_3_f32_slice(Some(location), &flat); } }
+  }
+
+  pub fn destroy(&self, gl: &glow::Context) {
+    unsafe { gl.delete_program(self.program); }
+    if let Some(registry_id) = self.registry_id { shader_registry::unregister(registry_id); }
+  }
+
+  /// Location of a uniform of one of the expected types, marking it as set.
+  fn location(&self, name: &str, expected: &[u32]) -> Option<&glow::UniformLocation> {
+    let Some((location, entry)) = self.locations.get(name) else {
+      self.warn_once(name.to_string(), || format!("Program has no active uniform {} - it's unknown or optimized out.", name));
+      return None;
+    };
+    let uniform = &self.uniforms[*entry];
+    if !expected.contains(&uniform.utype) {
+      self.warn_once(name.to_string(), || format!("Uniform {} is of GL type 0x{:X}, set with the wrong setter.", name, uniform.utype));
+      return None;
+    }
+    self.set_uniforms.borrow_mut()[*entry] = true;
+    Some(location)
+  }
+
+  fn warn_once(&self, key: String, message: impl FnOnce() -> String) {
+    if self.warned.borrow_mut().insert(key) {
+      do_log!("[Kuplung] [ShaderProgram] [{}] {}", self.name, message());
+    }
+  }
+}
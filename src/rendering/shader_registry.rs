@@ -0,0 +1,133 @@
+use std::path::PathBuf;
+use std::sync::Mutex;
+use chrono::Local;
+use strum_macros::AsRefStr;
+use crate::rendering::gl_error::GLError;
+
+/// Every built-in program built from shader files, so they can be rebuilt when the files change.
+/// The programs themselves live with their owners - they pick up a newer revision the next time they are painted.
+static SHADER_REGISTRY: Mutex<ShaderRegistry> = Mutex::new(ShaderRegistry { entries: Vec::new(), next_id: 0 });
+
+struct ShaderRegistry {
+  entries: Vec<ShaderRegistryEntry>,
+  next_id: usize,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, AsRefStr)]
+pub enum ShaderStatus {
+  Building,
+  Ok,
+  #[strum(serialize = "Reload pending")]
+  Pending,
+  #[strum(serialize = "Failed - running the last good program")]
+  FailedKeepingLast,
+  Failed,
+}
+
+#[derive(Clone)]
+pub struct ShaderRegistryEntry {
+  pub id: usize,
+  pub name: String,
+  /// Every file the program is built from, includes too.
+  pub source_files: Vec<String>,
+  pub status: ShaderStatus,
+  pub last_error: String,
+  pub last_build: String,
+  pub revision: u64,
+  /// False until the first build succeeds - the owner has nothing to run and builds the program again on a retry.
+  pub has_program: bool,
+}
+
+/// Adds a program, watching its stage files until the first build reports all its sources.
+/// A program that never built keeps its entry, so building it again reuses it.
+pub fn register(name: &str, shader_files: &[(u32, &str)]) -> usize {
+  let mut registry = SHADER_REGISTRY.lock().unwrap();
+  if let Some(entry) = registry.entries.iter_mut().find(|e| e.name == name && !e.has_program) {
+    entry.status = ShaderStatus::Building;
+    return entry.id;
+  }
+  let id = registry.next_id;
+  registry.next_id += 1;
+  registry.entries.push(ShaderRegistryEntry {
+    id,
+    name: name.to_string(),
+    source_files: shader_files.iter().map(|(_, file)| file.to_string()).collect(),
+    status: ShaderStatus::Building,
+    last_error: "".to_string(),
+    last_build: "".to_string(),
+    revision: 0,
+    has_program: false,
+  });
+  id
+}
+
+pub fn unregister(id: usize) {
+  SHADER_REGISTRY.lock().unwrap().entries.retain(|e| e.id != id);
+}
+
+pub fn set_built(id: usize, source_files: Vec<String>) {
+  update(id, |entry| {
+    entry.source_files = source_files;
+    entry.status = ShaderStatus::Ok;
+    entry.has_program = true;
+    entry.last_error = "".to_string();
+    entry.last_build = Local::now().format("%H:%M:%S").to_string();
+  });
+}
+
+/// A failed build - the source files stay the last known ones, so fixing them triggers another build.
+pub fn set_failed(id: usize, error: &GLError, has_program: bool) {
+  update(id, |entry| {
+    entry.status = if has_program { ShaderStatus::FailedKeepingLast } else { ShaderStatus::Failed };
+    entry.has_program = has_program;
+    entry.last_error = error.to_string();
+  });
+}
+
+/// Incremented whenever the program should be rebuilt.
+pub fn revision(id: usize) -> u64 {
+  SHADER_REGISTRY.lock().unwrap().entries.iter().find(|e| e.id == id).map_or(0, |e| e.revision)
+}
+
+/// Also retries the programs which failed to build - see `retry_pending`.
+pub fn request_reload(id: usize) {
+  update(id, |entry| {
+    entry.revision += 1;
+    entry.status = ShaderStatus::Pending;
+  });
+}
+
+/// Requests a rebuild of the programs using any of the changed files. Returns their names.
+pub fn files_changed(changed: &[PathBuf]) -> Vec<String> {
+  let mut registry = SHADER_REGISTRY.lock().unwrap();
+  let mut names = Vec::new();
+  for entry in registry.entries.iter_mut().filter(|e| e.source_files.iter().any(|f| changed.contains(&PathBuf::from(f)))) {
+    entry.revision += 1;
+    entry.status = ShaderStatus::Pending;
+    names.push(entry.name.clone());
+  }
+  names
+}
+
+/// Whether the program which failed to build should be built again by its owner.
+pub fn retry_pending(name: &str) -> bool {
+  SHADER_REGISTRY.lock().unwrap().entries.iter().any(|e| e.name == name && !e.has_program && e.status == ShaderStatus::Pending)
+}
+
+pub fn watched_files() -> Vec<String> {
+  let registry = SHADER_REGISTRY.lock().unwrap();
+  let mut files: Vec<String> = registry.entries.iter().flat_map(|e| e.source_files.iter().cloned()).collect();
+  files.sort();
+  files.dedup();
+  files
+}
+
+pub fn entries() -> Vec<ShaderRegistryEntry> {
+  SHADER_REGISTRY.lock().unwrap().entries.clone()
+}
+
+fn update(id: usize, f: impl FnOnce(&mut ShaderRegistryEntry)) {
+  if let Some(entry) = SHADER_REGISTRY.lock().unwrap().entries.iter_mut().find(|e| e.id == id) {
+    f(entry);
+  }
+}
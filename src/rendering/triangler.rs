@@ -3,9 +3,9 @@
 use eframe::egui_glow;
 use eframe::glow::HasContext;
 use egui_glow::glow;
-use log::error;
-use crate::rendering::gl_utils;
-use crate::settings::configuration;
+use crate::rendering::gl_error::GLError;
+use crate::rendering::shader_program::ShaderProgram;
+use crate::settings::kuplung_settings::{self, KuplungSettings};
 
 #[rustfmt::skip]
 pub static TRIANGLER_VERTICES:[f32; 18] = [
@@ -18,41 +18,31 @@ pub static TRIANGLER_VERTICES:[f32; 18] = [
 pub static TRIANGLER_INDICES: [i32; 3] = [ 0, 1, 2 ];
 
 pub struct Triangler {
-  gl_Program: glow::Program,
+  gl_Program: ShaderProgram,
   gl_VAO: glow::VertexArray,
   vbo_Vertices: glow::Buffer,
   vbo_Indices: glow::Buffer,
+  clear_color: [f32; 4],
 }
 
 #[allow(unsafe_code)]
 impl Triangler {
-  pub fn new(gl: &glow::Context) -> Option<Self> {
+  pub fn new(gl: &glow::Context) -> Result<Self, GLError> {
     use glow::HasContext as _;
     unsafe {
-      let gl_Program = gl.create_program().expect("[Kuplung] [Triangler] Cannot create program!");
+      let gl_Program = ShaderProgram::from_files(gl, "Triangler", &[
+        (glow::VERTEX_SHADER, "assets/shaders/viewer/triangle.vert"),
+        (glow::FRAGMENT_SHADER, "assets/shaders/viewer/triangle.frag"),
+      ])?;
 
-      let shader_vertex = gl_utils::create_shader(&gl_Program, &gl, glow::VERTEX_SHADER, "assets/shaders/viewer/triangle.vert");
-      let shader_fragment = gl_utils::create_shader(&gl_Program, &gl, glow::FRAGMENT_SHADER, "assets/shaders/viewer/triangle.frag");
-
-      gl.link_program(gl_Program);
-      if !gl.get_program_link_status(gl_Program) {
-        error!("[Kuplung] [Triangler] Program cannot be linked! {}", gl.get_program_info_log(gl_Program));
-        panic!("[Kuplung] [Triangler] Program cannot be linked! {}", gl.get_program_info_log(gl_Program));
-      }
-
-      gl.detach_shader(gl_Program, shader_vertex);
-      gl.delete_shader(shader_vertex);
-      gl.detach_shader(gl_Program, shader_fragment);
-      gl.delete_shader(shader_fragment);
-
-      let gl_VAO = gl.create_vertex_array().expect("[Kuplung] [Triangler] Cannot create vertex array!");
+      let gl_VAO = gl.create_vertex_array().map_err(GLError::resource("vertex array"))?;
       gl.bind_vertex_array(Some(gl_VAO));
 
-      let vbo_Vertices = gl.create_buffer().expect("[Kuplung] [Triangler] Cannot create vertex buffer!");
+      let vbo_Vertices = gl.create_buffer().map_err(GLError::resource("vertex buffer"))?;
       gl.bind_buffer(glow::ARRAY_BUFFER, Some(vbo_Vertices));
       gl.buffer_data_u8_slice(glow::ARRAY_BUFFER, bytemuck::cast_slice(&TRIANGLER_VERTICES[..]), glow::STATIC_DRAW);
 
-      let vbo_Indices = gl.create_buffer().expect("[Kuplung] [Triangler] Cannot create indices buffer!");
+      let vbo_Indices = gl.create_buffer().map_err(GLError::resource("indices buffer"))?;
       gl.bind_buffer(glow::ELEMENT_ARRAY_BUFFER, Some(vbo_Indices));
       gl.buffer_data_u8_slice(glow::ELEMENT_ARRAY_BUFFER, bytemuck::cast_slice(&TRIANGLER_INDICES[..]), glow::STATIC_DRAW);
 
@@ -64,11 +54,12 @@ impl Triangler {
 
       gl.bind_vertex_array(None);
 
-      Some(Self {
+      Ok(Self {
         gl_Program,
         gl_VAO,
         vbo_Vertices,
-        vbo_Indices
+        vbo_Indices,
+        clear_color: kuplung_settings::get().clear_color(),
       })
     }
   }
@@ -76,22 +67,30 @@ impl Triangler {
   pub fn destroy(&self, gl: &glow::Context) {
     use glow::HasContext as _;
     unsafe {
-      gl.delete_program(self.gl_Program);
+      self.gl_Program.destroy(gl);
       gl.delete_vertex_array(self.gl_VAO);
       gl.delete_buffer(self.vbo_Vertices);
       gl.delete_buffer(self.vbo_Indices);
     }
   }
 
-  pub fn paint(&self, gl: &glow::Context, angle: f32) {
+  /// Picks up the settings changed in the options.
+  pub fn apply_settings(&mut self, settings: &KuplungSettings) {
+    self.clear_color = settings.clear_color();
+  }
+
+  pub fn paint(&mut self, gl: &glow::Context, angle: f32) {
+    self.gl_Program.reload_if_changed(gl);
     unsafe {
-      gl.clear_color(configuration::GL_CLEAR_COLOR_R, configuration::GL_CLEAR_COLOR_G, configuration::GL_CLEAR_COLOR_B, configuration::GL_CLEAR_COLOR_A);
+      let [r, g, b, a] = self.clear_color;
+      gl.clear_color(r, g, b, a);
       gl.clear(glow::COLOR_BUFFER_BIT);
 
-      gl.use_program(Some(self.gl_Program));
+      self.gl_Program.begin_frame(gl);
       gl.bind_vertex_array(Some(self.gl_VAO));
-      gl.uniform_1_f32(gl.get_uniform_location(self.gl_Program, "vs_angle").as_ref(), angle);
+      self.gl_Program.set_f32(gl, "vs_angle", angle);
       gl.draw_elements(glow::TRIANGLES, 3, glow::UNSIGNED_INT, 0);
+      self.gl_Program.end_frame();
       gl.bind_vertex_array(None);
     }
   }
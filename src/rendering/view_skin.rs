@@ -0,0 +1,126 @@
+use nalgebra_glm as glm;
+use strum_macros::{AsRefStr, EnumIter};
+
+// How the Viewer draws the models. Naming follows ViewModelSkin, the solidLightSkin_* fields
+// and the Setting_* view fields of the MeshModel message in assets/protobuf/KuplungAppScene.proto.
+
+/// The numbers are the ones in the settings (General.SelectedViewModelSkin) and in the model shader.
+#[derive(Clone, Copy, Debug, Default, PartialEq, EnumIter, AsRefStr)]
+pub enum ViewModelSkin {
+  /// One color lit by the solid light.
+  Solid = 0,
+  /// The material colors lit by the solid light.
+  Material = 1,
+  /// The diffuse texture lit by the solid light.
+  Texture = 2,
+  Wireframe = 3,
+  /// Material, textures and vertex colors lit by the scene light.
+  #[default]
+  Rendered = 4,
+  Normals = 5,
+  /// Near is bright, far is dark.
+  Depth = 6,
+}
+
+impl ViewModelSkin {
+  pub fn from_index(index: u32) -> Self {
+    match index {
+      0 => ViewModelSkin::Solid,
+      1 => ViewModelSkin::Material,
+      2 => ViewModelSkin::Texture,
+      3 => ViewModelSkin::Wireframe,
+      5 => ViewModelSkin::Normals,
+      6 => ViewModelSkin::Depth,
+      _ => ViewModelSkin::Rendered,
+    }
+  }
+
+  pub fn index(&self) -> u32 {
+    *self as u32
+  }
+
+  /// An icon for the Viewer toolbar.
+  pub fn icon(&self) -> &'static str {
+    match self {
+      ViewModelSkin::Solid => "⏺",
+      ViewModelSkin::Material => "🎨",
+      ViewModelSkin::Texture => "🖼",
+      ViewModelSkin::Wireframe => "⬚",
+      ViewModelSkin::Rendered => "☀",
+      ViewModelSkin::Normals => "⬈",
+      ViewModelSkin::Depth => "◐",
+    }
+  }
+}
+
+/// The light of the Solid, Material and Texture skins, it comes from the camera.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SolidLightSkin {
+  /// The color of every model in the Solid skin.
+  pub material_color: glm::Vec3,
+  pub ambient: glm::Vec3,
+  pub diffuse: glm::Vec3,
+  pub specular: glm::Vec3,
+  pub ambient_strength: f32,
+  pub diffuse_strength: f32,
+  pub specular_strength: f32,
+}
+
+impl Default for SolidLightSkin {
+  fn default() -> Self {
+    Self {
+      material_color: glm::vec3(0.7, 0.7, 0.7),
+      ambient: glm::vec3(1.0, 1.0, 1.0),
+      diffuse: glm::vec3(1.0, 1.0, 1.0),
+      specular: glm::vec3(1.0, 1.0, 1.0),
+      ambient_strength: 0.3,
+      diffuse_strength: 1.0,
+      specular_strength: 0.2,
+    }
+  }
+}
+
+/// How one model is drawn, over the skin of the scene.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct ModelViewSettings {
+  /// None follows the skin of the scene.
+  pub skin: Option<ViewModelSkin>,
+  /// The light in a few flat bands with a hard highlight.
+  pub cel_shading: bool,
+  /// Draws the edges of the triangles only, in the colors of the skin.
+  pub wireframe: bool,
+}
+
+impl ModelViewSettings {
+  pub fn skin(&self, scene: ViewModelSkin) -> ViewModelSkin {
+    self.skin.unwrap_or(scene)
+  }
+
+  pub fn is_wireframe(&self, scene: ViewModelSkin) -> bool {
+    self.wireframe || self.skin(scene) == ViewModelSkin::Wireframe
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use strum::IntoEnumIterator;
+
+  #[test]
+  fn skin_indices_round_trip() {
+    for skin in ViewModelSkin::iter() {
+      assert_eq!(ViewModelSkin::from_index(skin.index()), skin);
+    }
+    assert_eq!(ViewModelSkin::from_index(42), ViewModelSkin::Rendered);
+  }
+
+  #[test]
+  fn model_view_follows_the_scene() {
+    let mut view = ModelViewSettings::default();
+    assert_eq!(view.skin(ViewModelSkin::Depth), ViewModelSkin::Depth);
+    assert!(view.is_wireframe(ViewModelSkin::Wireframe));
+    view.skin = Some(ViewModelSkin::Solid);
+    assert_eq!(view.skin(ViewModelSkin::Depth), ViewModelSkin::Solid);
+    assert!(!view.is_wireframe(ViewModelSkin::Wireframe));
+  }
+}
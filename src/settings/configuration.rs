@@ -13,12 +13,44 @@ pub const WINDOW_HEIGHT_SHADERTOY: f32 = 500.0;
 
 pub const COMPONENT_LOG_WIDTH: f32 = 600.0;
 pub const COMPONENT_LOG_HEIGHT: f32 = 200.0;
+pub const COMPONENT_SHADERS_WIDTH: f32 = 500.0;
+pub const COMPONENT_SHADERS_HEIGHT: f32 = 300.0;
 
 pub const KUPLUNG_LOG_LEVEL: &str = "KUPLUNG_LOG_LEVEL";
 pub const KUPLUNG_LOG_LEVEL_VALUE: &str = "trace";
 pub const KUPLUNG_LOG_STYLE: &str = "KUPLUNG_LOG_STYLE";
 pub const KUPLUNG_LOG_STYLE_VALUE: &str = "always";
 
+// Assets
+pub const KUPLUNG_ASSETS_FOLDER: &str = "KUPLUNG_ASSETS_FOLDER";
+pub const ASSETS_FOLDER: &str = "assets";
+pub const SETTINGS_FILE: &str = "Kuplung_Settings.yaml";
+pub const RENDERING_SETTINGS_FILE: &str = "Kuplung_RenderingSettings.yaml";
+pub const RECENT_FILES_FILE: &str = "Kuplung_RecentFiles.ini";
+pub const RECENT_FILES_IMPORTED_FILE: &str = "Kuplung_RecentFilesImported.ini";
+pub const RECENT_FILES_MAX: usize = 10;
+
+// Settings, stored per user
+pub const KUPLUNG_CONFIG_FOLDER: &str = "KUPLUNG_CONFIG_FOLDER";
+pub const CONFIG_FOLDER_NAME: &str = "Kuplung";
+pub const APP_VERSION: &str = "1.0 d";
+pub const RENDERER_TYPE: i32 = 1;
+pub const FILE_BROWSER_WIDTH: f32 = 500.0;
+pub const FILE_BROWSER_HEIGHT: f32 = 300.0;
+pub const FRAMES_PER_SECOND: f32 = 30.0;
+pub const SHOW_GL_ERRORS: bool = true;
+pub const CONSUMPTION_INTERVAL_CPU: u32 = 5;
+pub const CONSUMPTION_INTERVAL_MEMORY: u32 = 5;
+
+// Rendering settings
+pub const RENDERING_FOV: f32 = 45.0;
+pub const RENDERING_RATIO_WIDTH: f32 = 4.0;
+pub const RENDERING_RATIO_HEIGHT: f32 = 3.0;
+pub const RENDERING_PLANE_CLOSE: f32 = 1.0;
+pub const RENDERING_PLANE_FAR: f32 = 1000.0;
+pub const RENDERING_GAMMA: f32 = 1.0;
+pub const RENDERING_GRID_SIZE: i32 = 30;
+
 // OpenGL settings
 pub const GL_CLEAR_COLOR_R: f32 = 70.0 / 255.0;
 pub const GL_CLEAR_COLOR_G: f32 = 70.0 / 255.0;
@@ -28,4 +60,18 @@ pub const GL_CLEAR_COLOR_A: f32 = 255.0 / 255.0;
 pub const GL_DEPTH_SIZE: u8 = 24;
 pub const GL_MULTISAMPLING: u16 = 4;
 pub const GL_STENCIL_SIZE: u8 = 4;
-pub const GL_HARDWARE_ACCELERATED: bool = true;
\ No newline at end of file
+pub const GL_HARDWARE_ACCELERATED: bool = true;
+
+pub const SHADERS_FOLDER: &str = "assets/shaders";
+
+// ShaderToy settings
+pub const SHADERTOY_LIBRARY_FOLDER: &str = "assets/shaders/shadertoy";
+pub const SHADERTOY_LIBRARY_INDEX: &str = "library.json";
+pub const SHADERTOY_PARAMS_FILE: &str = "shadertoy_params.json";
+pub const SHADERTOY_TEXTURES_FOLDER: &str = "assets/shadertoy";
+pub const SHADERTOY_DEFAULT_LICENSE: &str = "CC BY-NC-SA 3.0";
+pub const SHADERTOY_EXPORT_FOLDER: &str = "shadertoy_export";
+pub const SHADERTOY_EXPORT_MAX_TILE_SIZE: u32 = 4096;
+// File watching
+pub const FILE_WATCHER_POLL_INTERVAL_MS: u64 = 250;
+pub const FILE_WATCHER_DEBOUNCE_MS: u64 = 300;
\ No newline at end of file
@@ -0,0 +1,591 @@
+use std::error::Error;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_yaml::Value;
+use crate::do_log;
+use crate::settings::{configuration, kuplung_logger};
+
+// Runtime settings, kept in Kuplung_Settings.yaml and Kuplung_RenderingSettings.yaml in the user config folder.
+// Files are merged over the defaults key by key: missing keys get the default, unknown keys are dropped
+// and values of the wrong type keep the default. Migrated files are written back.
+
+static APP_SETTINGS: Mutex<Option<KuplungSettings>> = Mutex::new(None);
+static RENDERING_SETTINGS: Mutex<Option<RenderingSettings>> = Mutex::new(None);
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AppSettings {
+  #[serde(rename = "appVersion")]
+  pub app_version: String,
+  #[serde(rename = "currentFolder")]
+  pub current_folder: String,
+  #[serde(rename = "appFolder")]
+  pub app_folder: String,
+  #[serde(rename = "RendererType")]
+  pub renderer_type: i32,
+  #[serde(rename = "showLog")]
+  pub show_log: bool,
+}
+
+impl Default for AppSettings {
+  fn default() -> Self {
+    Self {
+      app_version: configuration::APP_VERSION.to_string(),
+      current_folder: "".to_string(),
+      app_folder: "".to_string(),
+      renderer_type: configuration::RENDERER_TYPE,
+      show_log: true,
+    }
+  }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AppWindowSettings {
+  #[serde(rename = "SDL_Window_Width")]
+  pub window_width: f32,
+  #[serde(rename = "SDL_Window_Height")]
+  pub window_height: f32,
+  #[serde(rename = "LogWidth")]
+  pub log_width: f32,
+  #[serde(rename = "LogHeight")]
+  pub log_height: f32,
+  #[serde(rename = "FileBrowserWidth")]
+  pub file_browser_width: f32,
+  #[serde(rename = "FileBrowserHeight")]
+  pub file_browser_height: f32,
+}
+
+impl Default for AppWindowSettings {
+  fn default() -> Self {
+    Self {
+      window_width: configuration::WINDOW_WIDTH,
+      window_height: configuration::WINDOW_HEIGHT,
+      log_width: configuration::COMPONENT_LOG_WIDTH,
+      log_height: configuration::COMPONENT_LOG_HEIGHT,
+      file_browser_width: configuration::FILE_BROWSER_WIDTH,
+      file_browser_height: configuration::FILE_BROWSER_HEIGHT,
+    }
+  }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AppRenderingSettings {
+  #[serde(rename = "FramesPerSecond")]
+  pub frames_per_second: f32,
+  #[serde(rename = "ShowGLErrors")]
+  pub show_gl_errors: bool,
+}
+
+impl Default for AppRenderingSettings {
+  fn default() -> Self {
+    Self {
+      frames_per_second: configuration::FRAMES_PER_SECOND,
+      show_gl_errors: configuration::SHOW_GL_ERRORS,
+    }
+  }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AppGuiSettings {
+  /// RGBA, 0 - 255
+  #[serde(rename = "guiClearColor")]
+  pub clear_color: [f32; 4],
+  #[serde(rename = "darkMode")]
+  pub dark_mode: bool,
+  #[serde(rename = "uiScale")]
+  pub ui_scale: f32,
+}
+
+impl Default for AppGuiSettings {
+  fn default() -> Self {
+    Self {
+      clear_color: [
+        configuration::GL_CLEAR_COLOR_R * 255.0,
+        configuration::GL_CLEAR_COLOR_G * 255.0,
+        configuration::GL_CLEAR_COLOR_B * 255.0,
+        configuration::GL_CLEAR_COLOR_A * 255.0,
+      ],
+      dark_mode: false,
+      ui_scale: 1.0,
+    }
+  }
+}
+
+/// Refresh intervals in seconds.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ConsumptionSettings {
+  #[serde(rename = "Consumption_Interval_CPU")]
+  pub interval_cpu: u32,
+  #[serde(rename = "Consumption_Interval_Memory")]
+  pub interval_memory: u32,
+}
+
+impl Default for ConsumptionSettings {
+  fn default() -> Self {
+    Self {
+      interval_cpu: configuration::CONSUMPTION_INTERVAL_CPU,
+      interval_memory: configuration::CONSUMPTION_INTERVAL_MEMORY,
+    }
+  }
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FileBrowserSettings {
+  #[serde(rename = "Bookmarks")]
+  pub bookmarks: Vec<String>,
+  #[serde(rename = "ShowHidden")]
+  pub show_hidden: bool,
+}
+
+/// Main menu shortcuts, as "Ctrl+Shift+N" - see ui::keybindings.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct KeybindingsSettings {
+  #[serde(rename = "Quit")]
+  pub quit: String,
+  #[serde(rename = "New")]
+  pub new: String,
+  #[serde(rename = "Open")]
+  pub open: String,
+  #[serde(rename = "Save")]
+  pub save: String,
+  #[serde(rename = "Backend")]
+  pub backend: String,
+  #[serde(rename = "About")]
+  pub about: String,
+  #[serde(rename = "Viewer")]
+  pub viewer: String,
+  #[serde(rename = "Fractals")]
+  pub fractals: String,
+  #[serde(rename = "ShaderToy")]
+  pub shadertoy: String,
+  #[serde(rename = "Log")]
+  pub log: String,
+}
+
+impl Default for KeybindingsSettings {
+  fn default() -> Self {
+    Self {
+      quit: "Escape".to_string(),
+      new: "Ctrl+N".to_string(),
+      open: "Ctrl+O".to_string(),
+      save: "Ctrl+S".to_string(),
+      backend: "Ctrl+Shift+Alt+B".to_string(),
+      about: "F1".to_string(),
+      viewer: "Shift+V".to_string(),
+      fractals: "Shift+F".to_string(),
+      shadertoy: "Shift+S".to_string(),
+      log: "Shift+L".to_string(),
+    }
+  }
+}
+
+impl KeybindingsSettings {
+  /// Action names with their shortcuts, in menu order.
+  pub fn entries(&self) -> [(&'static str, &String); 10] {
+    [
+      ("New scene", &self.new),
+      ("Open scene", &self.open),
+      ("Save scene", &self.save),
+      ("Quit", &self.quit),
+      ("Viewer", &self.viewer),
+      ("Fractals", &self.fractals),
+      ("ShaderToy", &self.shadertoy),
+      ("Log", &self.log),
+      ("Backend", &self.backend),
+      ("About", &self.about),
+    ]
+  }
+
+  pub fn entries_mut(&mut self) -> [(&'static str, &mut String); 10] {
+    [
+      ("New scene", &mut self.new),
+      ("Open scene", &mut self.open),
+      ("Save scene", &mut self.save),
+      ("Quit", &mut self.quit),
+      ("Viewer", &mut self.viewer),
+      ("Fractals", &mut self.fractals),
+      ("ShaderToy", &mut self.shadertoy),
+      ("Log", &mut self.log),
+      ("Backend", &mut self.backend),
+      ("About", &mut self.about),
+    ]
+  }
+}
+
+/// Kuplung_Settings.yaml
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct KuplungSettings {
+  #[serde(rename = "App")]
+  pub app: AppSettings,
+  #[serde(rename = "AppWindow")]
+  pub app_window: AppWindowSettings,
+  #[serde(rename = "Rendering")]
+  pub rendering: AppRenderingSettings,
+  #[serde(rename = "AppGui")]
+  pub app_gui: AppGuiSettings,
+  #[serde(rename = "Consumption")]
+  pub consumption: ConsumptionSettings,
+  #[serde(rename = "Keybindings")]
+  pub keybindings: KeybindingsSettings,
+  #[serde(rename = "FileBrowser")]
+  pub file_browser: FileBrowserSettings,
+}
+
+impl KuplungSettings {
+  /// Clamps out of range values, returns what was corrected.
+  pub fn validate(&mut self) -> Vec<String> {
+    let mut notes = Vec::new();
+    let window = &mut self.app_window;
+    clamp(&mut notes, "AppWindow.SDL_Window_Width", &mut window.window_width, 320.0, 16384.0);
+    clamp(&mut notes, "AppWindow.SDL_Window_Height", &mut window.window_height, 240.0, 16384.0);
+    clamp(&mut notes, "AppWindow.LogWidth", &mut window.log_width, 100.0, 16384.0);
+    clamp(&mut notes, "AppWindow.LogHeight", &mut window.log_height, 50.0, 16384.0);
+    clamp(&mut notes, "AppWindow.FileBrowserWidth", &mut window.file_browser_width, 200.0, 16384.0);
+    clamp(&mut notes, "AppWindow.FileBrowserHeight", &mut window.file_browser_height, 150.0, 16384.0);
+    clamp(&mut notes, "Rendering.FramesPerSecond", &mut self.rendering.frames_per_second, 1.0, 240.0);
+    clamp(&mut notes, "App.RendererType", &mut self.app.renderer_type, 0, 2);
+    clamp(&mut notes, "AppGui.uiScale", &mut self.app_gui.ui_scale, 0.5, 3.0);
+    for (index, component) in self.app_gui.clear_color.iter_mut().enumerate() {
+      clamp(&mut notes, &format!("AppGui.guiClearColor[{}]", index), component, 0.0, 255.0);
+    }
+    clamp(&mut notes, "Consumption.Consumption_Interval_CPU", &mut self.consumption.interval_cpu, 1, 3600);
+    clamp(&mut notes, "Consumption.Consumption_Interval_Memory", &mut self.consumption.interval_memory, 1, 3600);
+    notes
+  }
+
+  /// The clear color as GL expects it.
+  pub fn clear_color(&self) -> [f32; 4] {
+    self.app_gui.clear_color.map(|c| c / 255.0)
+  }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RenderingGeneralSettings {
+  #[serde(rename = "ShowCube")]
+  pub show_cube: bool,
+  #[serde(rename = "Fov")]
+  pub fov: f32,
+  #[serde(rename = "RatioWidth")]
+  pub ratio_width: f32,
+  #[serde(rename = "RatioHeight")]
+  pub ratio_height: f32,
+  #[serde(rename = "PlaneClose")]
+  pub plane_close: f32,
+  #[serde(rename = "PlaneFar")]
+  pub plane_far: f32,
+  #[serde(rename = "GammaCoeficient")]
+  pub gamma: f32,
+  #[serde(rename = "ShowPickRays")]
+  pub show_pick_rays: bool,
+  #[serde(rename = "ShowPickRaysSingle")]
+  pub show_pick_rays_single: bool,
+  #[serde(rename = "RayAnimate")]
+  pub ray_animate: bool,
+  #[serde(rename = "RayOriginX")]
+  pub ray_origin_x: f32,
+  #[serde(rename = "RayOriginY")]
+  pub ray_origin_y: f32,
+  #[serde(rename = "RayOriginZ")]
+  pub ray_origin_z: f32,
+  #[serde(rename = "RayDraw")]
+  pub ray_draw: bool,
+  #[serde(rename = "RayDirectionX")]
+  pub ray_direction_x: f32,
+  #[serde(rename = "RayDirectionY")]
+  pub ray_direction_y: f32,
+  #[serde(rename = "RayDirectionZ")]
+  pub ray_direction_z: f32,
+  #[serde(rename = "UseOcclusionCulling")]
+  pub use_occlusion_culling: bool,
+  /// How the Viewer draws the models, see ViewModelSkin.
+  #[serde(rename = "SelectedViewModelSkin")]
+  pub selected_view_model_skin: u32,
+}
+
+impl Default for RenderingGeneralSettings {
+  fn default() -> Self {
+    Self {
+      show_cube: false,
+      fov: configuration::RENDERING_FOV,
+      ratio_width: configuration::RENDERING_RATIO_WIDTH,
+      ratio_height: configuration::RENDERING_RATIO_HEIGHT,
+      plane_close: configuration::RENDERING_PLANE_CLOSE,
+      plane_far: configuration::RENDERING_PLANE_FAR,
+      gamma: configuration::RENDERING_GAMMA,
+      show_pick_rays: false,
+      show_pick_rays_single: true,
+      ray_animate: false,
+      ray_origin_x: 0.0,
+      ray_origin_y: 0.0,
+      ray_origin_z: 0.0,
+      ray_draw: false,
+      ray_direction_x: 0.0,
+      ray_direction_y: 0.0,
+      ray_direction_z: 0.0,
+      use_occlusion_culling: true,
+      selected_view_model_skin: 4,
+    }
+  }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RenderingAxisSettings {
+  #[serde(rename = "ShowAxisHelpers")]
+  pub show_axis_helpers: bool,
+  #[serde(rename = "ShowZAxis")]
+  pub show_z_axis: bool,
+}
+
+impl Default for RenderingAxisSettings {
+  fn default() -> Self {
+    Self { show_axis_helpers: true, show_z_axis: true }
+  }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RenderingGridSettings {
+  #[serde(rename = "WorldGridSizeSquares")]
+  pub grid_size: i32,
+  #[serde(rename = "WorldGridFixedWithWorld")]
+  pub grid_fixed_with_world: bool,
+  #[serde(rename = "ShowGrid")]
+  pub show_grid: bool,
+  #[serde(rename = "ActAsMirror")]
+  pub act_as_mirror: bool,
+}
+
+impl Default for RenderingGridSettings {
+  fn default() -> Self {
+    Self {
+      grid_size: configuration::RENDERING_GRID_SIZE,
+      grid_fixed_with_world: true,
+      show_grid: true,
+      act_as_mirror: false,
+    }
+  }
+}
+
+/// Kuplung_RenderingSettings.yaml
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RenderingSettings {
+  #[serde(rename = "General")]
+  pub general: RenderingGeneralSettings,
+  #[serde(rename = "Axis")]
+  pub axis: RenderingAxisSettings,
+  #[serde(rename = "Grid")]
+  pub grid: RenderingGridSettings,
+}
+
+impl RenderingSettings {
+  /// Clamps out of range values, returns what was corrected.
+  pub fn validate(&mut self) -> Vec<String> {
+    let mut notes = Vec::new();
+    let general = &mut self.general;
+    clamp(&mut notes, "General.Fov", &mut general.fov, 1.0, 179.0);
+    clamp(&mut notes, "General.RatioWidth", &mut general.ratio_width, 0.1, 100.0);
+    clamp(&mut notes, "General.RatioHeight", &mut general.ratio_height, 0.1, 100.0);
+    clamp(&mut notes, "General.PlaneClose", &mut general.plane_close, 0.001, 1000.0);
+    clamp(&mut notes, "General.PlaneFar", &mut general.plane_far, general.plane_close + 1.0, 1000000.0);
+    clamp(&mut notes, "General.GammaCoeficient", &mut general.gamma, 0.1, 5.0);
+    clamp(&mut notes, "General.SelectedViewModelSkin", &mut general.selected_view_model_skin, 0, 6);
+    clamp(&mut notes, "Grid.WorldGridSizeSquares", &mut self.grid.grid_size, 1, 1000);
+    notes
+  }
+}
+
+trait SettingsFile: Serialize + DeserializeOwned + Default {
+  const FILE_NAME: &'static str;
+  const HEADER: &'static str;
+  fn validate(&mut self) -> Vec<String>;
+}
+
+impl SettingsFile for KuplungSettings {
+  const FILE_NAME: &'static str = configuration::SETTINGS_FILE;
+  const HEADER: &'static str = "# Kuplung Settings file";
+  fn validate(&mut self) -> Vec<String> { KuplungSettings::validate(self) }
+}
+
+impl SettingsFile for RenderingSettings {
+  const FILE_NAME: &'static str = configuration::RENDERING_SETTINGS_FILE;
+  const HEADER: &'static str = "# Kuplung Rendering Settings file";
+  fn validate(&mut self) -> Vec<String> { RenderingSettings::validate(self) }
+}
+
+/// Per-user folder for the settings - KUPLUNG_CONFIG_FOLDER if set, otherwise the platform config folder.
+pub fn config_folder() -> PathBuf {
+  if let Some(folder) = std::env::var_os(configuration::KUPLUNG_CONFIG_FOLDER).filter(|f| !f.is_empty()) {
+    return PathBuf::from(folder);
+  }
+  #[cfg(target_os = "windows")]
+  let base = std::env::var_os("APPDATA").map(PathBuf::from);
+  #[cfg(target_os = "macos")]
+  let base = std::env::var_os("HOME").map(|home| PathBuf::from(home).join("Library/Application Support"));
+  #[cfg(not(any(target_os = "windows", target_os = "macos")))]
+  let base = std::env::var_os("XDG_CONFIG_HOME").filter(|f| !f.is_empty()).map(PathBuf::from)
+    .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")));
+  base.unwrap_or_else(|| PathBuf::from(".")).join(configuration::CONFIG_FOLDER_NAME)
+}
+
+/// Loads (creating or migrating as needed) both settings files.
+pub fn load() {
+  let settings = load_file::<KuplungSettings>();
+  *APP_SETTINGS.lock().unwrap() = Some(settings);
+  let rendering = load_file::<RenderingSettings>();
+  *RENDERING_SETTINGS.lock().unwrap() = Some(rendering);
+}
+
+pub fn get() -> KuplungSettings {
+  APP_SETTINGS.lock().unwrap().get_or_insert_with(load_file::<KuplungSettings>).clone()
+}
+
+pub fn get_rendering() -> RenderingSettings {
+  RENDERING_SETTINGS.lock().unwrap().get_or_insert_with(load_file::<RenderingSettings>).clone()
+}
+
+/// Replaces the settings and writes them to the user's settings file.
+pub fn set(settings: KuplungSettings) -> Result<(), Box<dyn Error>> {
+  save_file(&settings)?;
+  *APP_SETTINGS.lock().unwrap() = Some(settings);
+  Ok(())
+}
+
+/// Replaces the rendering settings and writes them to the user's rendering settings file.
+pub fn set_rendering(rendering: RenderingSettings) -> Result<(), Box<dyn Error>> {
+  save_file(&rendering)?;
+  *RENDERING_SETTINGS.lock().unwrap() = Some(rendering);
+  Ok(())
+}
+
+fn load_file<T: SettingsFile>() -> T {
+  let file = config_folder().join(T::FILE_NAME);
+  let (mut settings, mut notes) = match std::fs::read_to_string(&file) {
+    Ok(text) => migrate::<T>(&text),
+    Err(_) => {
+      do_log!("[Kuplung] [Settings] No settings in {}, using the defaults.", file.display());
+      (T::default(), vec!["created".to_string()])
+    }
+  };
+  notes.extend(settings.validate());
+  if notes.is_empty() {
+    do_log!("[Kuplung] [Settings] Loaded {}.", file.display());
+    return settings;
+  }
+  for note in &notes {
+    do_log!("[Kuplung] [Settings] {}: {}", T::FILE_NAME, note);
+  }
+  if notes.iter().any(|n| n.starts_with("cannot parse")) {
+    let backup = file.with_extension("yaml.bak");
+    if std::fs::copy(&file, &backup).is_ok() {
+      do_log!("[Kuplung] [Settings] Unreadable settings backed up to {}.", backup.display());
+    }
+  }
+  if let Err(e) = save_file(&settings) {
+    do_log!("[Kuplung] [Settings] Cannot save {}: {}", file.display(), e);
+  }
+  settings
+}
+
+fn save_file<T: SettingsFile>(settings: &T) -> Result<(), Box<dyn Error>> {
+  let folder = config_folder();
+  std::fs::create_dir_all(&folder)?;
+  let yaml = serde_yaml::to_string(settings)?;
+  std::fs::write(folder.join(T::FILE_NAME), format!("{}\n\n{}", T::HEADER, yaml))?;
+  Ok(())
+}
+
+/// Settings from the file contents merged over the defaults, with notes on everything that was migrated.
+fn migrate<T: SettingsFile>(text: &str) -> (T, Vec<String>) {
+  let mut notes = Vec::new();
+  let file: Value = match serde_yaml::from_str(text) {
+    Ok(file) => file,
+    Err(e) => return (T::default(), vec![format!("cannot parse ({}), using the defaults", e)]),
+  };
+  let mut merged = serde_yaml::to_value(T::default()).unwrap_or(Value::Null);
+  merge(&mut merged, &file, "", &mut notes);
+  match serde_yaml::from_value(merged) {
+    Ok(settings) => (settings, notes),
+    Err(e) => (T::default(), vec![format!("cannot parse ({}), using the defaults", e)]),
+  }
+}
+
+fn merge(defaults: &mut Value, file: &Value, key: &str, notes: &mut Vec<String>) {
+  match (defaults, file) {
+    (Value::Mapping(defaults), Value::Mapping(file)) => {
+      let prefix = if key.is_empty() { "".to_string() } else { format!("{}.", key) };
+      for (name, value) in file {
+        let name_text = format!("{}{}", prefix, name.as_str().unwrap_or("?"));
+        match defaults.get_mut(name) {
+          Some(default) => merge(default, value, &name_text, notes),
+          None => notes.push(format!("unknown key {} dropped", name_text)),
+        }
+      }
+      for name in defaults.keys().filter(|name| !file.contains_key(*name)) {
+        notes.push(format!("missing key {}{} set to the default", prefix, name.as_str().unwrap_or("?")));
+      }
+    }
+    // an empty value keeps the default
+    (_, Value::Null) => {}
+    (default, value) if same_kind(default, value) => *default = value.clone(),
+    (_, value) => notes.push(format!("{} has an invalid value {:?}, using the default", key, value)),
+  }
+}
+
+fn same_kind(default: &Value, value: &Value) -> bool {
+  match (default, value) {
+    (Value::Bool(_), Value::Bool(_)) | (Value::String(_), Value::String(_)) => true,
+    (Value::Number(d), Value::Number(v)) => d.is_f64() || !v.is_f64(),
+    // an empty default is a list of paths of any length, otherwise a tuple like a color
+    (Value::Sequence(d), Value::Sequence(v)) if d.is_empty() => v.iter().all(|v| matches!(v, Value::String(_))),
+    (Value::Sequence(d), Value::Sequence(v)) => d.len() == v.len() && d.iter().zip(v).all(|(d, v)| same_kind(d, v)),
+    _ => false,
+  }
+}
+
+fn clamp<T: PartialOrd + Copy + std::fmt::Display>(notes: &mut Vec<String>, key: &str, value: &mut T, min: T, max: T) {
+  let clamped = if *value < min { min } else if *value > max { max } else { *value };
+  if clamped != *value {
+    notes.push(format!("{} = {} is out of range, set to {}", key, value, clamped));
+    *value = clamped;
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn bundled_settings_load_without_migration() {
+    let (settings, notes) = migrate::<KuplungSettings>(&std::fs::read_to_string("assets/Kuplung_Settings.yaml").unwrap());
+    assert!(notes.is_empty(), "{:?}", notes);
+    assert_eq!(settings.app_window.window_width, 1600.0);
+    assert_eq!(settings.clear_color(), [70.0 / 255.0, 70.0 / 255.0, 70.0 / 255.0, 1.0]);
+    let (rendering, notes) = migrate::<RenderingSettings>(&std::fs::read_to_string("assets/Kuplung_RenderingSettings.yaml").unwrap());
+    assert!(notes.is_empty(), "{:?}", notes);
+    assert_eq!(rendering.grid.grid_size, 30);
+  }
+
+  #[test]
+  fn migrates_unknown_missing_and_invalid_keys() {
+    let (mut settings, notes) = migrate::<KuplungSettings>("App:\n  showLog: false\n  oldKey: 1\nAppWindow:\n  LogWidth: wide\n  SDL_Window_Width: 100\n");
+    assert!(!settings.app.show_log);
+    assert_eq!(settings.app_window.log_width, configuration::COMPONENT_LOG_WIDTH);
+    assert!(notes.contains(&"unknown key App.oldKey dropped".to_string()));
+    assert!(notes.iter().any(|n| n.starts_with("AppWindow.LogWidth has an invalid value")));
+    assert!(notes.contains(&"missing key Consumption set to the default".to_string()));
+    assert_eq!(settings.validate(), vec!["AppWindow.SDL_Window_Width = 100 is out of range, set to 320".to_string()]);
+    assert_eq!(settings.app_window.window_width, 320.0);
+  }
+}
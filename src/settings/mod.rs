@@ -1,2 +1,4 @@
 pub mod configuration;
 pub mod kuplung_logger;
+pub mod kuplung_settings;
+pub mod recent_files;
\ No newline at end of file
@@ -0,0 +1,208 @@
+use std::error::Error;
+use std::path::Path;
+use std::sync::Mutex;
+use chrono::Local;
+use crate::do_log;
+use crate::settings::{configuration, kuplung_logger, kuplung_settings};
+
+// Recently opened scenes and imported models, kept in the user config folder as INI files:
+//   [File1]
+//   path=/home/user/scenes/car.kuplung
+//   title=car.kuplung
+//   pinned=false
+//   opened=2024-08-10 12:00:00
+// Pinned files come first and stay when the list is cleared or trimmed.
+
+static RECENT_SCENES: Mutex<Option<Vec<RecentFile>>> = Mutex::new(None);
+static RECENT_IMPORTED: Mutex<Option<Vec<RecentFile>>> = Mutex::new(None);
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum RecentList {
+  Scenes,
+  Imported,
+}
+
+impl RecentList {
+  fn file_name(self) -> &'static str {
+    match self {
+      Self::Scenes => configuration::RECENT_FILES_FILE,
+      Self::Imported => configuration::RECENT_FILES_IMPORTED_FILE,
+    }
+  }
+
+  fn storage(self) -> &'static Mutex<Option<Vec<RecentFile>>> {
+    match self {
+      Self::Scenes => &RECENT_SCENES,
+      Self::Imported => &RECENT_IMPORTED,
+    }
+  }
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct RecentFile {
+  pub path: String,
+  pub title: String,
+  pub pinned: bool,
+  pub opened: String,
+}
+
+impl RecentFile {
+  pub fn exists(&self) -> bool {
+    Path::new(&self.path).is_file()
+  }
+}
+
+pub fn list(kind: RecentList) -> Vec<RecentFile> {
+  with_list(kind, |files| files.clone())
+}
+
+/// Moves the file to the top of the unpinned files (or refreshes a pinned one), trimming the list.
+pub fn add(kind: RecentList, path: &str) {
+  update(kind, |files| {
+    let pinned = files.iter().any(|f| f.path == path && f.pinned);
+    files.retain(|f| f.path != path);
+    let file = RecentFile {
+      path: path.to_string(),
+      title: Path::new(path).file_name().map_or(path.to_string(), |f| f.to_string_lossy().to_string()),
+      pinned,
+      opened: Local::now().format("%Y-%m-%d %H:%M:%S").to_string(),
+    };
+    let position = if pinned { 0 } else { files.iter().filter(|f| f.pinned).count() };
+    files.insert(position, file);
+    trim(files);
+  });
+}
+
+pub fn set_pinned(kind: RecentList, path: &str, pinned: bool) {
+  update(kind, |files| {
+    if let Some(file) = files.iter_mut().find(|f| f.path == path) { file.pinned = pinned; }
+    sort(files);
+  });
+}
+
+pub fn remove(kind: RecentList, path: &str) {
+  update(kind, |files| files.retain(|f| f.path != path));
+}
+
+/// Removes the files which are no longer on disk, pinned ones too. Returns how many were removed.
+pub fn remove_missing(kind: RecentList) -> usize {
+  let mut removed = 0;
+  update(kind, |files| {
+    let count = files.len();
+    files.retain(|f| f.exists());
+    removed = count - files.len();
+  });
+  removed
+}
+
+/// Clears the list, keeping the pinned files.
+pub fn clear(kind: RecentList) {
+  update(kind, |files| files.retain(|f| f.pinned));
+}
+
+fn with_list<T>(kind: RecentList, f: impl FnOnce(&mut Vec<RecentFile>) -> T) -> T {
+  let mut storage = kind.storage().lock().unwrap();
+  f(storage.get_or_insert_with(|| load(kind)))
+}
+
+fn update(kind: RecentList, f: impl FnOnce(&mut Vec<RecentFile>)) {
+  let files = with_list(kind, |files| {
+    f(files);
+    files.clone()
+  });
+  if let Err(e) = save(kind, &files) {
+    do_log!("[Kuplung] [Settings] Cannot save {}: {}", kind.file_name(), e);
+  }
+}
+
+fn load(kind: RecentList) -> Vec<RecentFile> {
+  let file = kuplung_settings::config_folder().join(kind.file_name());
+  match std::fs::read_to_string(&file) {
+    Ok(ini) => {
+      let mut files = parse_ini(&ini);
+      sort(&mut files);
+      files
+    }
+    Err(_) => Vec::new(),
+  }
+}
+
+fn save(kind: RecentList, files: &[RecentFile]) -> Result<(), Box<dyn Error>> {
+  let folder = kuplung_settings::config_folder();
+  std::fs::create_dir_all(&folder)?;
+  std::fs::write(folder.join(kind.file_name()), format_ini(files))?;
+  Ok(())
+}
+
+/// Pinned files first, the order otherwise kept.
+fn sort(files: &mut [RecentFile]) {
+  files.sort_by_key(|f| !f.pinned);
+}
+
+fn trim(files: &mut Vec<RecentFile>) {
+  let mut unpinned = 0;
+  files.retain(|f| {
+    if f.pinned { return true; }
+    unpinned += 1;
+    unpinned <= configuration::RECENT_FILES_MAX
+  });
+}
+
+fn parse_ini(ini: &str) -> Vec<RecentFile> {
+  let mut files: Vec<RecentFile> = Vec::new();
+  for line in ini.lines().map(|l| l.trim()) {
+    if line.is_empty() || line.starts_with('#') || line.starts_with(';') { continue; }
+    if line.starts_with('[') && line.ends_with(']') {
+      files.push(RecentFile::default());
+      continue;
+    }
+    let (Some(file), Some((key, value))) = (files.last_mut(), line.split_once('=')) else { continue; };
+    let value = value.trim().to_string();
+    match key.trim() {
+      "path" => file.path = value,
+      "title" => file.title = value,
+      "pinned" => file.pinned = value == "true",
+      "opened" => file.opened = value,
+      _ => {}
+    }
+  }
+  files.retain(|f| !f.path.is_empty());
+  for file in files.iter_mut().filter(|f| f.title.is_empty()) {
+    file.title = Path::new(&file.path).file_name().map_or(file.path.clone(), |f| f.to_string_lossy().to_string());
+  }
+  files
+}
+
+fn format_ini(files: &[RecentFile]) -> String {
+  let mut ini = String::from("# Kuplung Recent Files file\n");
+  for (index, file) in files.iter().enumerate() {
+    ini.push_str(&format!("\n[File{}]\npath={}\ntitle={}\npinned={}\nopened={}\n", index + 1, file.path, file.title, file.pinned, file.opened));
+  }
+  ini
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn ini_round_trip_keeps_pinned_first() {
+    let ini = "# Kuplung Recent Files file\n\n[File1]\npath=/scenes/a.kuplung\nopened=2024-08-10 12:00:00\n\n[File2]\npath=/scenes/b.kuplung\ntitle=B\npinned=true\n\n[File3]\ntitle=no path\n";
+    let mut files = parse_ini(ini);
+    sort(&mut files);
+    assert_eq!(files.iter().map(|f| f.title.as_str()).collect::<Vec<_>>(), vec!["B", "a.kuplung"]);
+    assert_eq!(parse_ini(&format_ini(&files)), files);
+    assert_eq!(parse_ini(include_str!("../../assets/Kuplung_RecentFiles.ini")), vec![]);
+  }
+
+  #[test]
+  fn trimming_keeps_pinned_files() {
+    let mut files: Vec<RecentFile> = (0..configuration::RECENT_FILES_MAX + 3)
+      .map(|i| RecentFile { path: format!("/f{}", i), pinned: i == configuration::RECENT_FILES_MAX + 2, ..Default::default() })
+      .collect();
+    sort(&mut files);
+    trim(&mut files);
+    assert_eq!(files.len(), configuration::RECENT_FILES_MAX + 1);
+    assert!(files[0].pinned);
+  }
+}
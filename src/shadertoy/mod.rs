@@ -1,2 +1,8 @@
 pub mod shadertoy_manager;
-mod shadertoy_engine;
\ No newline at end of file
+mod shadertoy_audio;
+mod shadertoy_channels;
+mod shadertoy_engine;
+mod shadertoy_export;
+mod shadertoy_keyboard;
+pub mod shadertoy_library;
+mod shadertoy_params;
\ No newline at end of file
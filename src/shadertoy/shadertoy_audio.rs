@@ -0,0 +1,235 @@
+use std::error::Error;
+use std::io::{Read, Seek};
+use std::path::Path;
+use crate::do_log;
+use crate::settings::kuplung_logger;
+use crate::utils::asset_vfs;
+
+pub const AUDIO_TEXTURE_WIDTH: usize = 512;
+pub const AUDIO_TEXTURE_HEIGHT: usize = 2;
+const FFT_SIZE: usize = AUDIO_TEXTURE_WIDTH * 2;
+
+// WebAudio AnalyserNode defaults, which ShaderToy uses to fill its audio textures
+const MIN_DECIBELS: f32 = -100.0;
+const MAX_DECIBELS: f32 = -30.0;
+
+/// A decoded audio file, down-mixed to mono, driving an audio iChannel.
+pub struct ShaderToyAudio {
+  pub file: String,
+  pub sample_rate: u32,
+  samples: Vec<f32>,
+}
+
+impl ShaderToyAudio {
+  pub fn load(file: &str) -> Result<Self, Box<dyn Error>> {
+    let reader = std::io::Cursor::new(asset_vfs::read(file)?);
+    let extension = Path::new(file).extension().and_then(|e| e.to_str()).unwrap_or_default().to_lowercase();
+    let (samples, sample_rate) = match extension.as_str() {
+      "wav" => decode_wav(reader)?,
+      "ogg" => decode_ogg(reader)?,
+      _ => return Err(format!("Unsupported audio file {} - only WAV and OGG are supported.", file).into()),
+    };
+    if samples.is_empty() || sample_rate == 0 {
+      return Err(format!("Audio file {} has no samples!", file).into());
+    }
+    do_log!("[Kuplung] [ShaderToy] [Audio] Loaded {} ({} Hz, {:.1}s).", file, sample_rate, samples.len() as f32 / sample_rate as f32);
+    Ok(Self {
+      file: file.to_string(),
+      sample_rate,
+      samples,
+    })
+  }
+
+  /// Texture contents at the given ShaderToy time - the first row is the spectrum, the second the waveform. The audio loops.
+  pub fn texture_data(&self, time: f32) -> Vec<u8> {
+    let window = self.window_at(time);
+    let mut data = spectrum_bytes(&window);
+    data.extend(window[FFT_SIZE - AUDIO_TEXTURE_WIDTH..].iter().map(|s| (128.0 + s * 128.0).clamp(0.0, 255.0) as u8));
+    data
+  }
+
+  /// The FFT_SIZE samples ending at the given time.
+  fn window_at(&self, time: f32) -> Vec<f32> {
+    let length = self.samples.len() as i64;
+    let end = (time.max(0.0) as f64 * self.sample_rate as f64) as i64;
+    (end - FFT_SIZE as i64..end).map(|i| self.samples[i.rem_euclid(length) as usize]).collect()
+  }
+}
+
+pub fn decode_wav<R: Read>(reader: R) -> Result<(Vec<f32>, u32), Box<dyn Error>> {
+  let mut wav = hound::WavReader::new(reader)?;
+  let spec = wav.spec();
+  let interleaved: Vec<f32> = match spec.sample_format {
+    hound::SampleFormat::Float => wav.samples::<f32>().collect::<Result<_, _>>()?,
+    hound::SampleFormat::Int => {
+      let scale = (1i64 << (spec.bits_per_sample - 1)) as f32;
+      wav.samples::<i32>().map(|s| s.map(|s| s as f32 / scale)).collect::<Result<_, _>>()?
+    }
+  };
+  Ok((downmix(&interleaved, spec.channels as usize), spec.sample_rate))
+}
+
+pub fn decode_ogg<R: Read + Seek>(reader: R) -> Result<(Vec<f32>, u32), Box<dyn Error>> {
+  let mut ogg = lewton::inside_ogg::OggStreamReader::new(reader)?;
+  let channels = ogg.ident_hdr.audio_channels as usize;
+  let sample_rate = ogg.ident_hdr.audio_sample_rate;
+  let mut interleaved: Vec<f32> = Vec::new();
+  while let Some(packet) = ogg.read_dec_packet_itl()? {
+    interleaved.extend(packet.iter().map(|s| *s as f32 / 32768.0));
+  }
+  Ok((downmix(&interleaved, channels), sample_rate))
+}
+
+fn downmix(interleaved: &[f32], channels: usize) -> Vec<f32> {
+  if channels <= 1 { return interleaved.to_vec(); }
+  interleaved.chunks_exact(channels).map(|frame| frame.iter().sum::<f32>() / channels as f32).collect()
+}
+
+/// In-place iterative radix-2 FFT. The length must be a power of two.
+pub fn fft(re: &mut [f32], im: &mut [f32]) {
+  let n = re.len();
+  debug_assert!(n.is_power_of_two() && im.len() == n);
+  let mut j = 0;
+  for i in 1..n {
+    let mut bit = n >> 1;
+    while j & bit != 0 {
+      j ^= bit;
+      bit >>= 1;
+    }
+    j |= bit;
+    if i < j {
+      re.swap(i, j);
+      im.swap(i, j);
+    }
+  }
+  let mut length = 2;
+  while length <= n {
+    let angle = -2.0 * std::f32::consts::PI / length as f32;
+    for start in (0..n).step_by(length) {
+      for k in 0..length / 2 {
+        let (w_im, w_re) = (angle * k as f32).sin_cos();
+        let (a, b) = (start + k, start + k + length / 2);
+        let t_re = re[b] * w_re - im[b] * w_im;
+        let t_im = re[b] * w_im + im[b] * w_re;
+        re[b] = re[a] - t_re;
+        im[b] = im[a] - t_im;
+        re[a] += t_re;
+        im[a] += t_im;
+      }
+    }
+    length <<= 1;
+  }
+}
+
+/// Magnitudes of the first half of the spectrum of a Blackman-windowed block, normalized like WebAudio.
+pub fn spectrum(samples: &[f32]) -> Vec<f32> {
+  let n = samples.len();
+  let mut re: Vec<f32> = samples.iter().enumerate().map(|(i, s)| {
+    let x = 2.0 * std::f32::consts::PI * i as f32 / n as f32;
+    s * (0.42 - 0.5 * x.cos() + 0.08 * (2.0 * x).cos())
+  }).collect();
+  let mut im = vec![0.0; n];
+  fft(&mut re, &mut im);
+  (0..n / 2).map(|k| (re[k] * re[k] + im[k] * im[k]).sqrt() / n as f32).collect()
+}
+
+fn spectrum_bytes(samples: &[f32]) -> Vec<u8> {
+  spectrum(samples).iter().map(|magnitude| {
+    let db = 20.0 * magnitude.max(1e-12).log10();
+    (255.0 * (db - MIN_DECIBELS) / (MAX_DECIBELS - MIN_DECIBELS)).clamp(0.0, 255.0) as u8
+  }).collect()
+}
+
+#[cfg(feature = "audio_playback")]
+pub struct ShaderToyAudioPlayer {
+  _stream: rodio::OutputStream,
+  sink: rodio::Sink,
+}
+
+#[cfg(feature = "audio_playback")]
+impl ShaderToyAudioPlayer {
+  /// Starts looping playback from the given ShaderToy time.
+  pub fn play(audio: &ShaderToyAudio, time: f32) -> Result<Self, Box<dyn Error>> {
+    use rodio::Source;
+    let (stream, handle) = rodio::OutputStream::try_default()?;
+    let sink = rodio::Sink::try_new(&handle)?;
+    let duration = audio.samples.len() as f32 / audio.sample_rate as f32;
+    let offset = std::time::Duration::from_secs_f32(time.max(0.0) % duration);
+    let source = rodio::buffer::SamplesBuffer::new(1, audio.sample_rate, audio.samples.clone());
+    sink.append(source.clone().skip_duration(offset).chain(source.repeat_infinite()));
+    Ok(Self { _stream: stream, sink })
+  }
+
+  pub fn stop(&self) {
+    self.sink.stop();
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  const SAMPLE_RATE: u32 = 44100;
+
+  fn sine(frequency: f32, seconds: f32) -> Vec<f32> {
+    (0..(SAMPLE_RATE as f32 * seconds) as usize).map(|i| (2.0 * std::f32::consts::PI * frequency * i as f32 / SAMPLE_RATE as f32).sin() * 0.5).collect()
+  }
+
+  fn to_wav(samples: &[f32], channels: u16) -> Vec<u8> {
+    let spec = hound::WavSpec { channels, sample_rate: SAMPLE_RATE, bits_per_sample: 16, sample_format: hound::SampleFormat::Int };
+    let mut cursor = std::io::Cursor::new(Vec::new());
+    let mut writer = hound::WavWriter::new(&mut cursor, spec).unwrap();
+    for s in samples {
+      for _ in 0..channels {
+        writer.write_sample((s * i16::MAX as f32) as i16).unwrap();
+      }
+    }
+    writer.finalize().unwrap();
+    cursor.into_inner()
+  }
+
+  #[test]
+  fn fft_finds_tone_bin() {
+    let bin = 40;
+    let frequency = bin as f32 * SAMPLE_RATE as f32 / FFT_SIZE as f32;
+    let magnitudes = spectrum(&sine(frequency, 1.0)[..FFT_SIZE]);
+    let peak = magnitudes.iter().enumerate().max_by(|a, b| a.1.total_cmp(b.1)).unwrap().0;
+    assert_eq!(peak, bin);
+    assert!(magnitudes[bin] > 100.0 * magnitudes[bin + 20]);
+  }
+
+  #[test]
+  fn fft_of_impulse_is_flat() {
+    let mut re = vec![0.0; 16];
+    let mut im = vec![0.0; 16];
+    re[0] = 1.0;
+    fft(&mut re, &mut im);
+    assert!(re.iter().all(|v| (v - 1.0).abs() < 1e-6));
+    assert!(im.iter().all(|v| v.abs() < 1e-6));
+  }
+
+  #[test]
+  fn wav_decodes_and_downmixes() {
+    let tone = sine(440.0, 0.5);
+    let (samples, sample_rate) = decode_wav(std::io::Cursor::new(to_wav(&tone, 2))).unwrap();
+    assert_eq!(sample_rate, SAMPLE_RATE);
+    assert_eq!(samples.len(), tone.len());
+    assert!(samples.iter().zip(tone.iter()).all(|(a, b)| (a - b).abs() < 1e-3));
+  }
+
+  #[test]
+  fn texture_rows() {
+    let silence = ShaderToyAudio { file: "".to_string(), sample_rate: SAMPLE_RATE, samples: vec![0.0; SAMPLE_RATE as usize] };
+    let data = silence.texture_data(0.5);
+    assert_eq!(data.len(), AUDIO_TEXTURE_WIDTH * AUDIO_TEXTURE_HEIGHT);
+    assert!(data[..AUDIO_TEXTURE_WIDTH].iter().all(|b| *b == 0));
+    assert!(data[AUDIO_TEXTURE_WIDTH..].iter().all(|b| *b == 128));
+
+    let bin = 100;
+    let tone = ShaderToyAudio { file: "".to_string(), sample_rate: SAMPLE_RATE, samples: sine(bin as f32 * SAMPLE_RATE as f32 / FFT_SIZE as f32, 1.0) };
+    let data = tone.texture_data(0.5);
+    assert_eq!(data[bin], 255);
+    assert!(data[..AUDIO_TEXTURE_WIDTH].iter().enumerate().filter(|(i, _)| i.abs_diff(bin) > 8).all(|(_, b)| *b < 128));
+    assert!(data[AUDIO_TEXTURE_WIDTH..].iter().any(|b| *b > 180) && data[AUDIO_TEXTURE_WIDTH..].iter().any(|b| *b < 76));
+  }
+}
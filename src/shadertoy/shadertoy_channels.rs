@@ -0,0 +1,40 @@
+use std::path::Path;
+use crate::settings::configuration;
+use crate::utils::asset_vfs;
+
+/// Input bound to an iChannel slot.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub enum ShaderToyChannel {
+  #[default]
+  None,
+  Texture(String),
+  Keyboard,
+  Audio(String),
+}
+
+impl ShaderToyChannel {
+  pub fn label(&self) -> String {
+    match self {
+      Self::None => "None".to_string(),
+      Self::Texture(file) | Self::Audio(file) => Path::new(file).file_name().unwrap_or_default().to_string_lossy().to_string(),
+      Self::Keyboard => "Keyboard".to_string(),
+    }
+  }
+}
+
+/// The ShaderToy preset textures available locally.
+pub fn list_textures() -> Vec<String> {
+  list_media(|file| file.starts_with("tex"))
+}
+
+/// The audio files available locally.
+pub fn list_audio() -> Vec<String> {
+  list_media(|file| file.ends_with(".wav") || file.ends_with(".ogg"))
+}
+
+fn list_media(filter: impl Fn(&str) -> bool) -> Vec<String> {
+  asset_vfs::list(configuration::SHADERTOY_TEXTURES_FOLDER).into_iter()
+    .filter(|p| p.file_name().and_then(|f| f.to_str()).is_some_and(|f| filter(&f.to_lowercase())))
+    .map(|p| p.to_string_lossy().to_string())
+    .collect()
+}
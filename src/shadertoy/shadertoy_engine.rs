@@ -1,12 +1,21 @@
 #![allow(non_snake_case)]
 
+use std::error::Error;
 use eframe::egui_glow;
 use eframe::glow::HasContext;
 use egui_glow::glow;
-use log::error;
-use crate::{do_log, utils};
-use crate::settings::kuplung_logger;
+use nalgebra_glm as glm;
+use crate::do_log;
+use crate::settings::{configuration, kuplung_logger};
+use crate::rendering::gl_error::GLError;
 use crate::rendering::gl_utils;
+use crate::rendering::shader_program::ShaderProgram;
+use crate::rendering::shader_preprocessor::{ShaderPreprocessor, ShaderSource};
+use crate::shadertoy::shadertoy_audio::{AUDIO_TEXTURE_HEIGHT, AUDIO_TEXTURE_WIDTH};
+use crate::shadertoy::shadertoy_channels::ShaderToyChannel;
+use crate::shadertoy::shadertoy_keyboard::{KEYBOARD_TEXTURE_HEIGHT, KEYBOARD_TEXTURE_WIDTH};
+use crate::shadertoy::shadertoy_params::{ShaderToyParam, ShaderToyParamValue};
+use crate::utils::asset_vfs;
 
 #[rustfmt::skip]
 pub static SHADERTOY_VERTICES:[f32; 18] = [
@@ -18,6 +27,65 @@ pub static SHADERTOY_VERTICES:[f32; 18] = [
   -1.0, -1.0, 0.0
 ];
 
+/// Uniforms and defines of the ShaderToy environment, in front of the toy.
+const SHADERTOY_HEADER: &str = r#"#version 410 core
+
+in vec4 glFragCoord;
+out vec4 outFragmentColor;
+uniform vec3 iResolution;
+uniform float iGlobalTime;
+uniform float iTimeDelta;
+uniform int iFrame;
+uniform int iFrameRate;
+uniform float iChannelTime[4];
+uniform vec3 iChannelResolution[4];
+uniform vec4 iMouse;
+uniform vec4 iDate;
+
+uniform sampler2D iChannel0;
+//uniform samplerCube iChannel0;
+uniform sampler2D iChannel1;
+//uniform samplerCube iChannel1;
+uniform sampler2D iChannel2;
+//uniform samplerCube iChannel2;
+uniform sampler2D iChannel3;
+//uniform samplerCube iChannel3;
+
+#define texture2D texture
+#define textureCube texture
+#define iTime iGlobalTime
+"#;
+
+/// Shown when there is no toy selected.
+const SHADERTOY_DEFAULT_IMAGE: &str = r#"
+void mainImage(out vec4 fragColor, in vec2 fragCoord) {
+   vec2 uv = fragCoord.xy / iResolution.xy;
+   fragColor = vec4(uv, 0.5 + 0.5 * sin(iGlobalTime), 1.0);
+}
+"#;
+
+/// Calls the mainImage() of the toy, after it.
+const SHADERTOY_MAIN: &str = r#"
+void glslOptimizerFix() {
+  float f = iTimeDelta * iChannelTime[0] * iChannelTime[1] * iChannelTime[2] * iChannelTime[3];
+  vec3 v3 = iChannelResolution[0] * iChannelResolution[1] * iChannelResolution[2] * iChannelResolution[3];
+  int i = iFrame * iFrameRate;
+  vec4 v4 = iMouse * iDate;
+
+  vec2 s1 = texture2D(iChannel0,vec2(0,0)).xy;
+  vec2 s2 = texture2D(iChannel1,vec2(0,0)).xy;
+  vec2 s3 = texture2D(iChannel2,vec2(0,0)).xy;
+  vec2 s4 = texture2D(iChannel3,vec2(0,0)).xy;
+}
+
+void main() {
+    glslOptimizerFix();
+    vec4 color = vec4(0.0, 0.0, 0.0, 1.0);
+    mainImage(color, glFragCoord.xy);
+    outFragmentColor = color;
+}
+"#;
+
 pub struct ShaderToyEngine {
   pub iChannel0_Image: String,
   pub iChannel1_Image: String,
@@ -27,110 +95,78 @@ pub struct ShaderToyEngine {
   pub iChannel1_CubeImage: String,
   pub iChannel2_CubeImage: String,
   pub iChannel3_CubeImage: String,
+  iChannelTextures: [Option<glow::Texture>; 4],
+  iChannels: [ShaderToyChannel; 4],
+  params: Vec<ShaderToyParam>,
   pub textureWidth: i32,
   pub textureHeight: i32,
-  shaderProgram: glow::Program,
+  shaderProgram: ShaderProgram,
   glVAO: glow::VertexArray,
   vboVertices: glow::Buffer,
   tFBO: glow::Framebuffer,
   tRBO: glow::Renderbuffer,
-  vs_InFBO: glow::UniformLocation,
-  vs_ScreenResolution: glow::UniformLocation,
-  iChannelResolution0: [f32; 2],
-  iChannelResolution1: [f32; 2],
-  iChannelResolution2: [f32; 2],
-  iChannelResolution3: [f32; 2],
-  iResolution: glow::UniformLocation,
-  iGlobalTime: glow::UniformLocation,
-  iTimeDelta: glow::UniformLocation,
-  iFrame: glow::UniformLocation,
-  iFrameRate: glow::UniformLocation,
-  /*iChannelTime: [glow::UniformLocation; 4],
-  iChannelResolution: [glow::UniformLocation; 4],*/
-  iMouse: glow::UniformLocation,
-  iDate: glow::UniformLocation,
+  iChannelResolution: [glm::Vec3; 4],
 }
 
 #[allow(unsafe_code)]
 impl ShaderToyEngine {
-  pub fn new(gl: &glow::Context) -> Option<Self> {
-    Self::compile_shadertoy(gl, "".to_string())
+  pub fn new(gl: &glow::Context) -> Result<Self, GLError> {
+    Self::compile_shadertoy(gl, "", "", Default::default())
   }
 
-  pub fn compile_shadertoy(gl: &glow::Context, stoy: String) -> Option<Self> {
+  pub fn compile_shadertoy(gl: &glow::Context, common: &str, stoy: &str, channels: [ShaderToyChannel; 4]) -> Result<Self, GLError> {
     use glow::HasContext as _;
     unsafe {
-      let shaderProgram = gl.create_program().expect("[Kuplung] [ShaderToy-Engine] Cannot create program!");
-
-      let shader_vertex = gl_utils::create_shader(&shaderProgram, &gl, glow::VERTEX_SHADER, "assets/shaders/shadertoy/shadertoy.vert");
+      let vertex_file = "assets/shaders/shadertoy/shadertoy.vert";
+      let vertex_source = ShaderPreprocessor::new().process_file(vertex_file).map_err(|e| GLError::ShaderLoad { file: vertex_file.to_string(), reason: e.to_string() })?;
+      let fragment_source = Self::fragment_source(common, stoy).map_err(|e| GLError::ShaderLoad { file: "shadertoy".to_string(), reason: e.to_string() })?;
+      let source_files = vertex_source.files.iter().chain(fragment_source.files.iter()).cloned().collect();
+      let shaderProgram = gl_utils::create_program_from_sources(gl, "ShaderToy", &[
+        (glow::VERTEX_SHADER, vertex_file.to_string(), vertex_source),
+        (glow::FRAGMENT_SHADER, "shadertoy".to_string(), fragment_source),
+      ])?;
 
-      let shader_fragment = gl_utils::create_shader_from_string(&shaderProgram, &gl, glow::FRAGMENT_SHADER, Self::get_stoy(stoy).as_ref());
-
-      gl.link_program(shaderProgram);
-      if !gl.get_program_link_status(shaderProgram) {
-        error!("[Kuplung] [ShaderToy-Engine] Program cannot be linked! {}", gl.get_program_info_log(shaderProgram));
-        panic!("[Kuplung] [ShaderToy-Engine] Program cannot be linked! {}", gl.get_program_info_log(shaderProgram));
+      let shaderProgram = ShaderProgram::registered(gl, "ShaderToy", shaderProgram, source_files);
+      if !shaderProgram.has_uniform("vs_screenResolution") {
+        shaderProgram.destroy(gl);
+        return Err(GLError::MissingUniform { program: "ShaderToy".to_string(), uniform: "vs_screenResolution".to_string() });
       }
 
-      gl.detach_shader(shaderProgram, shader_vertex);
-      gl.delete_shader(shader_vertex);
-      gl.detach_shader(shaderProgram, shader_fragment);
-      gl.delete_shader(shader_fragment);
-
-      let vs_InFBO = gl_utils::get_uniform_no_warning(&shaderProgram, &gl, "vs_inFBO");
-      let vs_ScreenResolution = gl_utils::get_uniform_no_warning(&shaderProgram, &gl, "vs_screenResolution");
-
-      let iResolution = gl_utils::get_uniform_no_warning(&shaderProgram, &gl, "iResolution");
-      let iGlobalTime = gl_utils::get_uniform_no_warning(&shaderProgram, &gl, "iGlobalTime");
-      let iTimeDelta = gl_utils::get_uniform_no_warning(&shaderProgram, &gl, "iTimeDelta");
-      let iFrameRate = gl_utils::get_uniform_no_warning(&shaderProgram, &gl, "iFrameRate");
-      let iFrame = gl_utils::get_uniform_no_warning(&shaderProgram, &gl, "iFrame");
-      /*let iChannelTime: [glow::UniformLocation; 4] = [
-        gl_utils::get_uniform_no_warning(&shaderProgram, &gl, "iChannelTime[0]"),
-        gl_utils::get_uniform_no_warning(&shaderProgram, &gl, "iChannelTime[1]"),
-        gl_utils::get_uniform_no_warning(&shaderProgram, &gl, "iChannelTime[2]"),
-        gl_utils::get_uniform_no_warning(&shaderProgram, &gl, "iChannelTime[3]"),
-      ];
-      let iChannelResolution: [glow::UniformLocation; 4] = [
-        gl_utils::get_uniform_no_warning(&shaderProgram, &gl, "iChannelResolution[0]"),
-        gl_utils::get_uniform_no_warning(&shaderProgram, &gl, "iChannelResolution[1]"),
-        gl_utils::get_uniform_no_warning(&shaderProgram, &gl, "iChannelResolution[2]"),
-        gl_utils::get_uniform_no_warning(&shaderProgram, &gl, "iChannelResolution[3]"),
-      ];*/
-      let iMouse = gl_utils::get_uniform_no_warning(&shaderProgram, &gl, "iMouse");
-      let iDate = gl_utils::get_uniform_no_warning(&shaderProgram, &gl, "iDate");
-
-      let glVAO = gl.create_vertex_array().expect("[Kuplung] [ShaderToy-Engine] Cannot create VAO!");
+      let glVAO = gl.create_vertex_array().map_err(GLError::resource("vertex array"))?;
       gl.bind_vertex_array(Some(glVAO));
 
-      let vboVertices = gl.create_buffer().expect("[Kuplung] [ShaderToy-Engine] Cannot create VBO!");
+      let vboVertices = gl.create_buffer().map_err(GLError::resource("vertex buffer"))?;
       gl.bind_buffer(glow::ARRAY_BUFFER, Some(vboVertices));
       gl.buffer_data_u8_slice(glow::ARRAY_BUFFER, bytemuck::cast_slice(&SHADERTOY_VERTICES[..]), glow::STATIC_DRAW);
 
       gl.vertex_attrib_pointer_f32(0, 3, glow::FLOAT, false, 0, 0);
       gl.enable_vertex_attrib_array(0);
 
-      let tFBO = gl.create_framebuffer().expect("[Kuplung] [ShaderToy-Engine] Cannot create FBO!");
-      let tRBO = gl.create_renderbuffer().expect("[Kuplung] [ShaderToy-Engine] Cannot create RBO!");
+      let tFBO = gl.create_framebuffer().map_err(GLError::resource("framebuffer"))?;
+      let tRBO = gl.create_renderbuffer().map_err(GLError::resource("renderbuffer"))?;
 
       gl.bind_vertex_array(None);
 
-      let iChannel0_Image = "".to_string();
-      let iChannel1_Image = "".to_string();
-      let iChannel2_Image = "".to_string();
-      let iChannel3_Image = "".to_string();
+      let [iChannel0_Image, iChannel1_Image, iChannel2_Image, iChannel3_Image] = channels.clone().map(|c| match c {
+        ShaderToyChannel::Texture(file) => file,
+        _ => "".to_string(),
+      });
+      let iChannelTextures = channels.clone().map(|c| match c {
+        ShaderToyChannel::None => None,
+        ShaderToyChannel::Texture(file) => Self::load_channel_texture(gl, &file),
+        ShaderToyChannel::Keyboard => Self::create_data_texture(gl, KEYBOARD_TEXTURE_WIDTH as i32, KEYBOARD_TEXTURE_HEIGHT as i32, glow::NEAREST),
+        ShaderToyChannel::Audio(_) => Self::create_data_texture(gl, AUDIO_TEXTURE_WIDTH as i32, AUDIO_TEXTURE_HEIGHT as i32, glow::LINEAR),
+      });
       let iChannel0_CubeImage = "".to_string();
       let iChannel1_CubeImage = "".to_string();
       let iChannel2_CubeImage = "".to_string();
       let iChannel3_CubeImage = "".to_string();
       let textureWidth = 0;
       let textureHeight = 0;
-      let iChannelResolution0 = [0.0, 0.0];
-      let iChannelResolution1 = [0.0, 0.0];
-      let iChannelResolution2 = [0.0, 0.0];
-      let iChannelResolution3 = [0.0, 0.0];
+      let iChannelResolution = iChannelTextures.map(|t| t.map_or(glm::vec3(0.0, 0.0, 0.0), |(_, w, h)| glm::vec3(w as f32, h as f32, 1.0)));
+      let iChannelTextures = iChannelTextures.map(|t| t.map(|(texture, _, _)| texture));
 
-      Some(Self {
+      Ok(Self {
         iChannel0_Image,
         iChannel1_Image,
         iChannel2_Image,
@@ -139,6 +175,9 @@ impl ShaderToyEngine {
         iChannel1_CubeImage,
         iChannel2_CubeImage,
         iChannel3_CubeImage,
+        iChannelTextures,
+        iChannels: channels,
+        params: Vec::new(),
         textureWidth,
         textureHeight,
         shaderProgram,
@@ -146,121 +185,283 @@ impl ShaderToyEngine {
         vboVertices,
         tFBO,
         tRBO,
-        vs_InFBO,
-        vs_ScreenResolution,
-        iChannelResolution0,
-        iChannelResolution1,
-        iChannelResolution2,
-        iChannelResolution3,
-        iResolution,
-        iGlobalTime,
-        iTimeDelta,
-        iFrame,
-        iFrameRate,
-        /*iChannelTime,
-        iChannelResolution,*/
-        iMouse,
-        iDate,
+        iChannelResolution,
       })
     }
   }
 
-  pub fn get_stoy(stoy: String) -> String {
-    let mut shaderFragmentSource = String::from(r#"#version 410 core
+  /// The fragment shader of a toy - the header, the common pass, the image pass and main() go in as separate sources,
+  /// so the compiler errors point at the lines of the passes.
+  pub fn fragment_source(common: &str, stoy: &str) -> Result<ShaderSource, Box<dyn Error>> {
+    let mut sources = vec![("shadertoy", SHADERTOY_HEADER)];
+    if !common.is_empty() { sources.push(("common", common)); }
+    sources.push(("image", if stoy.is_empty() { SHADERTOY_DEFAULT_IMAGE } else { stoy }));
+    sources.push(("shadertoy_main", SHADERTOY_MAIN));
+    // toys can #include shared snippets from the library folder
+    ShaderPreprocessor::new()
+      .include_folder(configuration::SHADERTOY_LIBRARY_FOLDER)
+      .define("HW_PERFORMANCE", 1)
+      .process_sources(&sources)
+  }
 
-out vec4 outFragmentColor;
-uniform vec3 iResolution;
-uniform float iGlobalTime;
-uniform float iTimeDelta;
-uniform int iFrame;
-uniform int iFrameRate;
-uniform float iChannelTime[4];
-uniform vec3 iChannelResolution[4];
-uniform vec4 iMouse;
-uniform vec4 iDate;
+  /// Whether the Shaders panel asked for a rebuild or the vertex shader changed since the last call.
+  pub fn take_reload_request(&mut self) -> bool {
+    self.shaderProgram.take_reload_request()
+  }
 
-uniform sampler2D iChannel0;
-//uniform samplerCube iChannel0;
-uniform sampler2D iChannel1;
-//uniform samplerCube iChannel1;
-uniform sampler2D iChannel2;
-//uniform samplerCube iChannel2;
-uniform sampler2D iChannel3;
-//uniform samplerCube iChannel3;
+  /// Swaps in the new program - on failure the previous one keeps running and the error is returned.
+  pub fn reload_shadertoy(&mut self, common: &str, stoy: &str, channels: [ShaderToyChannel; 4], gl: &glow::Context) -> Result<(), GLError> {
+    let engine = Self::compile_shadertoy(gl, common, stoy, channels).inspect_err(|e| self.shaderProgram.set_failed(e))?;
+    self.destroy(gl);
+    *self = engine;
+    Ok(())
+  }
 
-#define texture2D texture
-#define textureCube texture
-"#);
-    if stoy.is_empty() {
-      shaderFragmentSource.push_str(r#"
-void mainImage(out vec4 fragColor, in vec2 fragCoord) {
-   vec2 uv = fragCoord.xy / iResolution.xy;
-   fragColor = vec4(uv, 0.5 + 0.5 * sin(iGlobalTime), 1.0);
-}
-"#);
+  fn load_channel_texture(gl: &glow::Context, image_file: &str) -> Option<(glow::Texture, i32, i32)> {
+    if image_file.is_empty() { return None; }
+    let image = match asset_vfs::read(image_file).map_err(image::ImageError::IoError).and_then(|data| image::load_from_memory(&data)) {
+      Ok(image) => image.flipv().to_rgba8(),
+      Err(e) => {
+        do_log!("[Kuplung] [ShaderToy-Engine] Cannot load channel image {}: {}", image_file, e);
+        return None;
+      }
+    };
+    let (width, height) = (image.width() as i32, image.height() as i32);
+    unsafe {
+      let texture = gl.create_texture().ok()?;
+      gl.bind_texture(glow::TEXTURE_2D, Some(texture));
+      gl.tex_parameter_i32(glow::TEXTURE_2D, glow::TEXTURE_WRAP_S, glow::REPEAT as i32);
+      gl.tex_parameter_i32(glow::TEXTURE_2D, glow::TEXTURE_WRAP_T, glow::REPEAT as i32);
+      gl.tex_parameter_i32(glow::TEXTURE_2D, glow::TEXTURE_MIN_FILTER, glow::LINEAR_MIPMAP_LINEAR as i32);
+      gl.tex_parameter_i32(glow::TEXTURE_2D, glow::TEXTURE_MAG_FILTER, glow::LINEAR as i32);
+      gl.tex_image_2d(glow::TEXTURE_2D, 0, glow::RGBA as i32, width, height, 0, glow::RGBA, glow::UNSIGNED_BYTE, Some(image.as_raw()));
+      gl.generate_mipmap(glow::TEXTURE_2D);
+      gl.bind_texture(glow::TEXTURE_2D, None);
+      do_log!("[Kuplung] [ShaderToy-Engine] Loaded channel image {} ({}x{}).", image_file, width, height);
+      Some((texture, width, height))
     }
-    else {
-      let shader_source = utils::file_io::read_shadertoy_shader(format!("{}.stoy", stoy).as_str());
-      shaderFragmentSource.push_str(shader_source.unwrap().as_str());
+  }
+
+  /// Single channel texture filled from the CPU every frame (keyboard state, audio spectrum).
+  fn create_data_texture(gl: &glow::Context, width: i32, height: i32, filter: u32) -> Option<(glow::Texture, i32, i32)> {
+    unsafe {
+      let texture = gl.create_texture().ok()?;
+      gl.bind_texture(glow::TEXTURE_2D, Some(texture));
+      gl.tex_parameter_i32(glow::TEXTURE_2D, glow::TEXTURE_WRAP_S, glow::CLAMP_TO_EDGE as i32);
+      gl.tex_parameter_i32(glow::TEXTURE_2D, glow::TEXTURE_WRAP_T, glow::CLAMP_TO_EDGE as i32);
+      gl.tex_parameter_i32(glow::TEXTURE_2D, glow::TEXTURE_MIN_FILTER, filter as i32);
+      gl.tex_parameter_i32(glow::TEXTURE_2D, glow::TEXTURE_MAG_FILTER, filter as i32);
+      gl.pixel_store_i32(glow::UNPACK_ALIGNMENT, 1);
+      gl.tex_image_2d(glow::TEXTURE_2D, 0, glow::R8 as i32, width, height, 0, glow::RED, glow::UNSIGNED_BYTE, Some(&vec![0u8; (width * height) as usize]));
+      gl.pixel_store_i32(glow::UNPACK_ALIGNMENT, 4);
+      gl.bind_texture(glow::TEXTURE_2D, None);
+      Some((texture, width, height))
     }
-    shaderFragmentSource.push_str(r#"
-void glslOptimizerFix() {
-  float f = iTimeDelta * iChannelTime[0] * iChannelTime[1] * iChannelTime[2] * iChannelTime[3];
-  vec3 v3 = iChannelResolution[0] * iChannelResolution[1] * iChannelResolution[2] * iChannelResolution[3];
-  int i = iFrame * iFrameRate;
-  vec4 v4 = iMouse * iDate;
+  }
 
-  vec2 s1 = texture2D(iChannel0,vec2(0,0)).xy;
-  vec2 s2 = texture2D(iChannel1,vec2(0,0)).xy;
-  vec2 s3 = texture2D(iChannel2,vec2(0,0)).xy;
-  vec2 s4 = texture2D(iChannel3,vec2(0,0)).xy;
-}
+  /// Uploads the keyboard state to every channel with a keyboard input.
+  pub fn update_keyboard(&self, gl: &glow::Context, keyboard_data: &[u8]) {
+    for (channel, texture) in self.iChannels.iter().zip(self.iChannelTextures.iter()) {
+      if let (ShaderToyChannel::Keyboard, Some(texture)) = (channel, texture) {
+        Self::update_data_texture(gl, *texture, KEYBOARD_TEXTURE_WIDTH as i32, KEYBOARD_TEXTURE_HEIGHT as i32, keyboard_data);
+      }
+    }
+  }
 
-void main() {
-    glslOptimizerFix();
-    vec4 color = vec4(0.0, 0.0, 0.0, 1.0);
-    mainImage(color, gl_FragCoord.xy);
-    outFragmentColor = color;
-}
-"#);
-    shaderFragmentSource
+  /// Uploads the spectrum and waveform rows to an audio channel.
+  pub fn update_audio(&self, gl: &glow::Context, channel: usize, audio_data: &[u8]) {
+    if let (ShaderToyChannel::Audio(_), Some(texture)) = (&self.iChannels[channel], self.iChannelTextures[channel]) {
+      Self::update_data_texture(gl, texture, AUDIO_TEXTURE_WIDTH as i32, AUDIO_TEXTURE_HEIGHT as i32, audio_data);
+    }
+  }
+
+  fn update_data_texture(gl: &glow::Context, texture: glow::Texture, width: i32, height: i32, data: &[u8]) {
+    unsafe {
+      gl.bind_texture(glow::TEXTURE_2D, Some(texture));
+      gl.pixel_store_i32(glow::UNPACK_ALIGNMENT, 1);
+      gl.tex_sub_image_2d(glow::TEXTURE_2D, 0, 0, 0, width, height, glow::RED, glow::UNSIGNED_BYTE, glow::PixelUnpackData::Slice(data));
+      gl.pixel_store_i32(glow::UNPACK_ALIGNMENT, 4);
+      gl.bind_texture(glow::TEXTURE_2D, None);
+    }
+  }
+
+  /// Whether the program has an active uniform with this name and GL type (i.e. it was not optimized out).
+  pub fn has_uniform(&self, name: &str, gl_type: u32) -> bool {
+    self.shaderProgram.uniforms().iter().any(|u| u.name == name && u.utype == gl_type)
   }
 
-  pub fn reload_shadertoy(&mut self, stoy: &str, gl: &glow::Context) {
-    Self::compile_shadertoy(gl, stoy.to_string());
+  pub fn set_params(&mut self, params: Vec<ShaderToyParam>) {
+    self.params = params;
   }
 
-  pub fn setup_fbo(&self, gl: &glow::Context, screen_width: f32, screen_height: f32) {
+  pub fn setup_fbo(&self, gl: &glow::Context, screen_width: i32, screen_height: i32) {
     unsafe {
-      gl.bind_renderbuffer(0, Some(self.tRBO));
-      gl.renderbuffer_storage(0, glow::DEPTH_COMPONENT, screen_width as i32, screen_height as i32);
-      gl.bind_renderbuffer(0, None);
+      gl.bind_renderbuffer(glow::RENDERBUFFER, Some(self.tRBO));
+      gl.renderbuffer_storage(glow::RENDERBUFFER, glow::DEPTH_COMPONENT24, screen_width, screen_height);
+      gl.bind_renderbuffer(glow::RENDERBUFFER, None);
     }
   }
 
-  pub fn paint(&self, gl: &glow::Context, screen_width: f32, screen_height: f32) {
+  pub fn paint(&self, gl: &glow::Context, screen_width: f32, screen_height: f32, time: f32, time_delta: f32, frame: i32) {
     unsafe {
-      gl.use_program(Some(self.shaderProgram));
+      let program = &self.shaderProgram;
+      program.begin_frame(gl);
       gl.bind_vertex_array(Some(self.glVAO));
 
-      gl.uniform_2_f32(Option::from(&self.vs_ScreenResolution), screen_width, screen_height);
-      gl.uniform_3_f32(Option::from(&self.iResolution), screen_width, screen_height, 1.0);
-      gl.uniform_1_f32(Option::from(&self.iGlobalTime), 1.0);
-      gl.uniform_1_f32(Option::from(&self.iTimeDelta), 1.0);
+      program.set_vec2(gl, "vs_screenResolution", &glm::vec2(screen_width, screen_height));
+      program.set_vec3(gl, "iResolution", &glm::vec3(screen_width, screen_height, 1.0));
+      program.set_f32(gl, "iGlobalTime", time);
+      program.set_f32(gl, "iTimeDelta", time_delta);
+      program.set_i32(gl, "iFrame", frame);
+      if time_delta > 0.0 {
+        program.set_i32(gl, "iFrameRate", (1.0 / time_delta).round() as i32);
+      }
+      program.set_f32_array(gl, "iChannelTime", &[time; 4]);
+      program.set_vec3_array(gl, "iChannelResolution", &self.iChannelResolution);
+
+      for param in &self.params {
+        if !self.has_uniform(&param.name, param.value.gl_type()) { continue; }
+        match param.value {
+          ShaderToyParamValue::Float(v) => program.set_f32(gl, &param.name, v),
+          ShaderToyParamValue::Int(v) => program.set_i32(gl, &param.name, v),
+          ShaderToyParamValue::Bool(v) => program.set_bool(gl, &param.name, v),
+          ShaderToyParamValue::Vec2(v) => program.set_vec2(gl, &param.name, &v.into()),
+          ShaderToyParamValue::Vec3(v) => program.set_vec3(gl, &param.name, &v.into()),
+          ShaderToyParamValue::Vec4(v) => program.set_vec4(gl, &param.name, &v.into()),
+        }
+      }
+
+      for (unit, texture) in self.iChannelTextures.iter().enumerate() {
+        if let Some(texture) = texture {
+          gl.active_texture(glow::TEXTURE0 + unit as u32);
+          gl.bind_texture(glow::TEXTURE_2D, Some(*texture));
+          program.set_sampler(gl, &format!("iChannel{}", unit), unit as i32);
+        }
+      }
 
       gl.draw_arrays(glow::TRIANGLES, 0, 6);
 
       gl.bind_vertex_array(None);
+      gl.active_texture(glow::TEXTURE0);
+    }
+  }
+
+  /// Renders a single frame into the FBO and reads it back.
+  /// Resolutions beyond the maximum renderbuffer size are rendered in tiles using the vertex shader TileMatrix.
+  pub fn render_offscreen(&self, gl: &glow::Context, width: u32, height: u32, time: f32, time_delta: f32, frame: i32) -> Option<image::RgbaImage> {
+    unsafe {
+      let max_size = gl.get_parameter_i32(glow::MAX_RENDERBUFFER_SIZE).min(gl.get_parameter_i32(glow::MAX_TEXTURE_SIZE));
+      let tile_size = (max_size.max(1) as u32).min(configuration::SHADERTOY_EXPORT_MAX_TILE_SIZE);
+      let tile_width = width.min(tile_size);
+      let tile_height = height.min(tile_size);
+
+      let mut previous_viewport = [0; 4];
+      gl.get_parameter_i32_slice(glow::VIEWPORT, &mut previous_viewport);
+      let previous_fbo = std::num::NonZeroU32::new(gl.get_parameter_i32(glow::FRAMEBUFFER_BINDING) as u32).map(glow::NativeFramebuffer);
+
+      let color_texture = gl.create_texture().ok()?;
+      gl.bind_texture(glow::TEXTURE_2D, Some(color_texture));
+      gl.tex_parameter_i32(glow::TEXTURE_2D, glow::TEXTURE_MIN_FILTER, glow::NEAREST as i32);
+      gl.tex_parameter_i32(glow::TEXTURE_2D, glow::TEXTURE_MAG_FILTER, glow::NEAREST as i32);
+      gl.tex_image_2d(glow::TEXTURE_2D, 0, glow::RGBA8 as i32, tile_width as i32, tile_height as i32, 0, glow::RGBA, glow::UNSIGNED_BYTE, None);
+      gl.bind_texture(glow::TEXTURE_2D, None);
+      self.setup_fbo(gl, tile_width as i32, tile_height as i32);
+
+      gl.bind_framebuffer(glow::FRAMEBUFFER, Some(self.tFBO));
+      gl.framebuffer_texture_2d(glow::FRAMEBUFFER, glow::COLOR_ATTACHMENT0, glow::TEXTURE_2D, Some(color_texture), 0);
+      gl.framebuffer_renderbuffer(glow::FRAMEBUFFER, glow::DEPTH_ATTACHMENT, glow::RENDERBUFFER, Some(self.tRBO));
+      let mut result = None;
+      if gl.check_framebuffer_status(glow::FRAMEBUFFER) != glow::FRAMEBUFFER_COMPLETE {
+        do_log!("[Kuplung] [ShaderToy-Engine] Offscreen framebuffer is incomplete!");
+      }
+      else {
+        let mut image = image::RgbaImage::new(width, height);
+        let mut pixels = vec![0u8; (tile_width * tile_height * 4) as usize];
+        let mut y0 = 0;
+        while y0 < height {
+          let th = tile_height.min(height - y0);
+          let mut x0 = 0;
+          while x0 < width {
+            let tw = tile_width.min(width - x0);
+            gl.viewport(0, 0, tw as i32, th as i32);
+            gl.clear_color(0.0, 0.0, 0.0, 1.0);
+            gl.clear(glow::COLOR_BUFFER_BIT | glow::DEPTH_BUFFER_BIT);
+            gl.use_program(Some(self.shaderProgram.program()));
+            self.shaderProgram.set_mat4(gl, "TileMatrix", &Self::tile_matrix(x0, y0, tw, th, width, height));
+            self.shaderProgram.set_bool(gl, "vs_inFBO", false);
+            self.paint(gl, width as f32, height as f32, time, time_delta, frame);
+            gl.read_pixels(0, 0, tw as i32, th as i32, glow::RGBA, glow::UNSIGNED_BYTE, glow::PixelPackData::Slice(&mut pixels[..(tw * th * 4) as usize]));
+            // GL rows are bottom-up
+            for row in 0..th {
+              for col in 0..tw {
+                let i = ((row * tw + col) * 4) as usize;
+                image.put_pixel(x0 + col, height - 1 - (y0 + row), image::Rgba([pixels[i], pixels[i + 1], pixels[i + 2], 255]));
+              }
+            }
+            x0 += tw;
+          }
+          y0 += th;
+        }
+        result = Some(image);
+      }
+
+      gl.use_program(Some(self.shaderProgram.program()));
+      self.shaderProgram.set_mat4(gl, "TileMatrix", &Self::tile_matrix(0, 0, 1, 1, 1, 1));
+      gl.framebuffer_texture_2d(glow::FRAMEBUFFER, glow::COLOR_ATTACHMENT0, glow::TEXTURE_2D, None, 0);
+      gl.bind_framebuffer(glow::FRAMEBUFFER, previous_fbo);
+      gl.viewport(previous_viewport[0], previous_viewport[1], previous_viewport[2], previous_viewport[3]);
+      gl.delete_texture(color_texture);
+      result
     }
   }
 
+  /// Maps the full-screen quad onto the tile at (x0, y0) of size (tw, th) within a (width, height) image.
+  fn tile_matrix(x0: u32, y0: u32, tw: u32, th: u32, width: u32, height: u32) -> glm::Mat4 {
+    let (width, height) = (width as f32, height as f32);
+    let scale_x = tw as f32 / width;
+    let scale_y = th as f32 / height;
+    let offset_x = (2.0 * x0 as f32 + tw as f32) / width - 1.0;
+    let offset_y = (2.0 * y0 as f32 + th as f32) / height - 1.0;
+    glm::Mat4::new(
+      scale_x, 0.0, 0.0, offset_x,
+      0.0, scale_y, 0.0, offset_y,
+      0.0, 0.0, 1.0, 0.0,
+      0.0, 0.0, 0.0, 1.0,
+    )
+  }
+
   pub fn destroy(&self, gl: &glow::Context) {
     do_log!("[Kuplung] [ShaderToy-Engine] DESTROY!");
     use glow::HasContext as _;
     unsafe {
-      gl.delete_program(self.shaderProgram);
+      self.shaderProgram.destroy(gl);
       gl.delete_vertex_array(self.glVAO);
       gl.delete_buffer(self.vboVertices);
+      gl.delete_framebuffer(self.tFBO);
+      gl.delete_renderbuffer(self.tRBO);
+      for texture in self.iChannelTextures.iter().flatten() {
+        gl.delete_texture(*texture);
+      }
     }
   }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::rendering::shader_preprocessor::tests::driver_location;
+
+  #[test]
+  fn shadertoy_errors_point_at_the_lines_of_the_passes() {
+    let common = "// common\nfloat shared() { return 1.0; }\n";
+    let image = "// image\nvoid mainImage(out vec4 fragColor, in vec2 fragCoord) {\n  fragColor = vec4(shared());\n  broken;\n}\n";
+    let source = ShaderToyEngine::fragment_source(common, image).unwrap();
+    assert!(source.code.starts_with("#version 410 core\n"));
+    assert_eq!(driver_location(&source, "broken"), ("image".to_string(), 4));
+    assert_eq!(driver_location(&source, "float shared()"), ("common".to_string(), 2));
+    assert!(driver_location(&source, "mainImage(color").0.starts_with("shadertoy"));
+
+    let image_file = source.files.iter().position(|f| f == "image").unwrap();
+    assert_eq!(source.map_log(&format!("{}:4(3): error: `broken' undeclared", image_file)), "image:4(3): error: `broken' undeclared");
+  }
+}
@@ -0,0 +1,151 @@
+use std::error::Error;
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::PathBuf;
+use image::codecs::gif::{GifEncoder, Repeat};
+use image::{Delay, Frame, RgbaImage};
+use strum_macros::{AsRefStr, EnumIter};
+use crate::do_log;
+use crate::settings::{configuration, kuplung_logger};
+
+#[derive(Clone, Copy, Debug, PartialEq, EnumIter, AsRefStr)]
+pub enum ShaderToyExportFormat {
+  PngSequence,
+  Gif,
+  Apng,
+}
+
+#[derive(Clone, Debug)]
+pub struct ShaderToyExportSettings {
+  pub width: u32,
+  pub height: u32,
+  pub frames: u32,
+  pub fps: f32,
+  pub start_time: f32,
+  pub format: ShaderToyExportFormat,
+  pub output_folder: String,
+}
+
+impl Default for ShaderToyExportSettings {
+  fn default() -> Self {
+    Self {
+      width: 1280,
+      height: 720,
+      frames: 60,
+      fps: 30.0,
+      start_time: 0.0,
+      format: ShaderToyExportFormat::PngSequence,
+      output_folder: configuration::SHADERTOY_EXPORT_FOLDER.to_string(),
+    }
+  }
+}
+
+enum ShaderToyExportWriter {
+  PngSequence,
+  Gif(Box<GifEncoder<BufWriter<File>>>),
+  Apng(Option<png::Writer<BufWriter<File>>>),
+}
+
+/// A running export - the engine renders one frame per egui frame into it, so the UI stays responsive.
+pub struct ShaderToyExportJob {
+  pub settings: ShaderToyExportSettings,
+  pub frame: u32,
+  pub finished: bool,
+  pub status: String,
+  toy: String,
+  writer: ShaderToyExportWriter,
+}
+
+impl ShaderToyExportJob {
+  pub fn new(settings: ShaderToyExportSettings, toy: &str) -> Result<Self, Box<dyn Error>> {
+    if settings.width == 0 || settings.height == 0 || settings.frames == 0 || settings.fps <= 0.0 {
+      return Err("Export resolution, frame count and FPS must be positive!".into());
+    }
+    std::fs::create_dir_all(&settings.output_folder)?;
+    let toy = if toy.is_empty() { "shadertoy".to_string() } else { toy.to_string() };
+    let writer = match settings.format {
+      ShaderToyExportFormat::PngSequence => ShaderToyExportWriter::PngSequence,
+      ShaderToyExportFormat::Gif => {
+        let file = BufWriter::new(File::create(Self::output_file(&settings, &toy, "gif"))?);
+        let mut encoder = GifEncoder::new(file);
+        encoder.set_repeat(Repeat::Infinite)?;
+        ShaderToyExportWriter::Gif(Box::new(encoder))
+      }
+      ShaderToyExportFormat::Apng => {
+        let file = BufWriter::new(File::create(Self::output_file(&settings, &toy, "png"))?);
+        let mut encoder = png::Encoder::new(file, settings.width, settings.height);
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+        encoder.set_animated(settings.frames, 0)?;
+        encoder.set_frame_delay((1000.0 / settings.fps).round() as u16, 1000)?;
+        ShaderToyExportWriter::Apng(Some(encoder.write_header()?))
+      }
+    };
+    do_log!("[Kuplung] [ShaderToy] [Export] Exporting {} frames of {} at {}x{} as {}...", settings.frames, toy, settings.width, settings.height, settings.format.as_ref());
+    Ok(Self {
+      settings,
+      frame: 0,
+      finished: false,
+      status: "".to_string(),
+      toy,
+      writer,
+    })
+  }
+
+  /// Time, time delta and frame number of the next frame to render.
+  pub fn next_frame(&self) -> (f32, f32, i32) {
+    let delta = 1.0 / self.settings.fps;
+    (self.settings.start_time + self.frame as f32 * delta, delta, self.frame as i32)
+  }
+
+  pub fn progress(&self) -> f32 {
+    self.frame as f32 / self.settings.frames as f32
+  }
+
+  pub fn push_frame(&mut self, image: RgbaImage) -> Result<(), Box<dyn Error>> {
+    match &mut self.writer {
+      ShaderToyExportWriter::PngSequence => {
+        image.save(PathBuf::from(&self.settings.output_folder).join(format!("{}_{:05}.png", self.toy, self.frame)))?;
+      }
+      ShaderToyExportWriter::Gif(encoder) => {
+        let delay = Delay::from_numer_denom_ms(1000, self.settings.fps.round().max(1.0) as u32);
+        encoder.encode_frame(Frame::from_parts(image, 0, 0, delay))?;
+      }
+      ShaderToyExportWriter::Apng(writer) => {
+        if let Some(writer) = writer {
+          writer.write_image_data(image.as_raw())?;
+        }
+      }
+    }
+    self.frame += 1;
+    self.status = format!("Frame {} / {}", self.frame, self.settings.frames);
+    if self.frame >= self.settings.frames {
+      self.finish()?;
+    }
+    Ok(())
+  }
+
+  pub fn cancel(&mut self) {
+    if !self.finished {
+      do_log!("[Kuplung] [ShaderToy] [Export] Export cancelled at frame {}.", self.frame);
+      self.finished = true;
+      self.status = format!("Cancelled at frame {}.", self.frame);
+    }
+  }
+
+  fn finish(&mut self) -> Result<(), Box<dyn Error>> {
+    self.finished = true;
+    if let ShaderToyExportWriter::Apng(writer) = &mut self.writer {
+      if let Some(writer) = writer.take() {
+        writer.finish()?;
+      }
+    }
+    self.status = format!("Exported {} frames to {}.", self.frame, self.settings.output_folder);
+    do_log!("[Kuplung] [ShaderToy] [Export] {}", self.status);
+    Ok(())
+  }
+
+  fn output_file(settings: &ShaderToyExportSettings, toy: &str, extension: &str) -> PathBuf {
+    PathBuf::from(&settings.output_folder).join(format!("{}.{}", toy, extension))
+  }
+}
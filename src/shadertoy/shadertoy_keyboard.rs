@@ -0,0 +1,144 @@
+use egui::Key;
+
+pub const KEYBOARD_TEXTURE_WIDTH: usize = 256;
+pub const KEYBOARD_TEXTURE_HEIGHT: usize = 3;
+
+/// ShaderToy keyboard state - row 0 is keys held down, row 1 keys pressed this frame and row 2 toggled keys, indexed by JavaScript key code.
+pub struct ShaderToyKeyboard {
+  down: [bool; KEYBOARD_TEXTURE_WIDTH],
+  pressed: [bool; KEYBOARD_TEXTURE_WIDTH],
+  toggled: [bool; KEYBOARD_TEXTURE_WIDTH],
+}
+
+impl Default for ShaderToyKeyboard {
+  fn default() -> Self {
+    Self {
+      down: [false; KEYBOARD_TEXTURE_WIDTH],
+      pressed: [false; KEYBOARD_TEXTURE_WIDTH],
+      toggled: [false; KEYBOARD_TEXTURE_WIDTH],
+    }
+  }
+}
+
+impl ShaderToyKeyboard {
+  /// Clears the one-frame pressed state, called once per rendered frame.
+  pub fn begin_frame(&mut self) {
+    self.pressed = [false; KEYBOARD_TEXTURE_WIDTH];
+  }
+
+  pub fn handle_events(&mut self, events: &[egui::Event], modifiers: egui::Modifiers) {
+    for event in events {
+      if let egui::Event::Key { key, pressed, repeat, .. } = event {
+        if let Some(code) = key_code(*key) {
+          self.set_key(code, *pressed, *repeat);
+        }
+      }
+    }
+    // egui doesn't send key events for the modifiers themselves
+    self.set_key(16, modifiers.shift, false);
+    self.set_key(17, modifiers.ctrl, false);
+    self.set_key(18, modifiers.alt, false);
+  }
+
+  fn set_key(&mut self, code: usize, pressed: bool, repeat: bool) {
+    if pressed && !repeat && !self.down[code] {
+      self.pressed[code] = true;
+      self.toggled[code] = !self.toggled[code];
+    }
+    self.down[code] = pressed;
+  }
+
+  /// Releases all held keys, e.g. when the canvas loses focus.
+  pub fn release_all(&mut self) {
+    self.down = [false; KEYBOARD_TEXTURE_WIDTH];
+  }
+
+  pub fn texture_data(&self) -> Vec<u8> {
+    [self.down, self.pressed, self.toggled]
+      .iter()
+      .flat_map(|row| row.iter().map(|state| if *state { 255 } else { 0 }))
+      .collect()
+  }
+}
+
+/// JavaScript key codes, as used by ShaderToy.
+fn key_code(key: Key) -> Option<usize> {
+  let code = match key {
+    Key::Backspace => 8,
+    Key::Tab => 9,
+    Key::Enter => 13,
+    Key::Escape => 27,
+    Key::Space => 32,
+    Key::PageUp => 33,
+    Key::PageDown => 34,
+    Key::End => 35,
+    Key::Home => 36,
+    Key::ArrowLeft => 37,
+    Key::ArrowUp => 38,
+    Key::ArrowRight => 39,
+    Key::ArrowDown => 40,
+    Key::Insert => 45,
+    Key::Delete => 46,
+    Key::Num0 => 48,
+    Key::Num1 => 49,
+    Key::Num2 => 50,
+    Key::Num3 => 51,
+    Key::Num4 => 52,
+    Key::Num5 => 53,
+    Key::Num6 => 54,
+    Key::Num7 => 55,
+    Key::Num8 => 56,
+    Key::Num9 => 57,
+    Key::A => 65,
+    Key::B => 66,
+    Key::C => 67,
+    Key::D => 68,
+    Key::E => 69,
+    Key::F => 70,
+    Key::G => 71,
+    Key::H => 72,
+    Key::I => 73,
+    Key::J => 74,
+    Key::K => 75,
+    Key::L => 76,
+    Key::M => 77,
+    Key::N => 78,
+    Key::O => 79,
+    Key::P => 80,
+    Key::Q => 81,
+    Key::R => 82,
+    Key::S => 83,
+    Key::T => 84,
+    Key::U => 85,
+    Key::V => 86,
+    Key::W => 87,
+    Key::X => 88,
+    Key::Y => 89,
+    Key::Z => 90,
+    Key::F1 => 112,
+    Key::F2 => 113,
+    Key::F3 => 114,
+    Key::F4 => 115,
+    Key::F5 => 116,
+    Key::F6 => 117,
+    Key::F7 => 118,
+    Key::F8 => 119,
+    Key::F9 => 120,
+    Key::F10 => 121,
+    Key::F11 => 122,
+    Key::F12 => 123,
+    Key::Semicolon => 186,
+    Key::Equals | Key::Plus => 187,
+    Key::Comma => 188,
+    Key::Minus => 189,
+    Key::Period => 190,
+    Key::Slash => 191,
+    Key::Backtick => 192,
+    Key::OpenBracket => 219,
+    Key::Backslash => 220,
+    Key::CloseBracket => 221,
+    Key::Quote => 222,
+    _ => return None,
+  };
+  Some(code)
+}
@@ -0,0 +1,478 @@
+use std::error::Error;
+use std::path::{Path, PathBuf};
+use serde::{Deserialize, Serialize};
+use crate::{do_log, utils};
+use crate::shadertoy::shadertoy_channels::ShaderToyChannel;
+use crate::settings::{configuration, kuplung_logger};
+use crate::utils::{asset_locator, asset_vfs};
+
+// ShaderToy export format (https://www.shadertoy.com/howto)
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ShaderToyInfo {
+  pub id: String,
+  pub name: String,
+  pub username: String,
+  pub description: String,
+  pub tags: Vec<String>,
+  #[serde(skip_serializing_if = "String::is_empty")]
+  pub license: String,
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ShaderToySampler {
+  pub filter: String,
+  pub wrap: String,
+  pub vflip: String,
+  pub srgb: String,
+  pub internal: String,
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ShaderToyInput {
+  pub id: serde_json::Value,
+  #[serde(alias = "filepath")]
+  pub src: String,
+  pub ctype: String,
+  pub channel: usize,
+  pub sampler: ShaderToySampler,
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ShaderToyOutput {
+  pub id: serde_json::Value,
+  pub channel: usize,
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ShaderToyRenderPass {
+  pub inputs: Vec<ShaderToyInput>,
+  pub outputs: Vec<ShaderToyOutput>,
+  pub code: String,
+  pub name: String,
+  pub description: String,
+  #[serde(rename = "type")]
+  pub pass_type: String,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ShaderToyShader {
+  #[serde(default)]
+  pub ver: String,
+  #[serde(default)]
+  pub info: ShaderToyInfo,
+  pub renderpass: Vec<ShaderToyRenderPass>,
+}
+
+#[derive(Deserialize)]
+struct ShaderToyWrapped {
+  #[serde(rename = "Shader")]
+  shader: ShaderToyShader,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum ShaderToyExport {
+  Wrapped(ShaderToyWrapped),
+  WrappedList(Vec<ShaderToyWrapped>),
+  List(Vec<ShaderToyShader>),
+  Single(ShaderToyShader),
+}
+
+impl ShaderToyShader {
+  pub fn pass(&self, pass_type: &str) -> Option<&ShaderToyRenderPass> {
+    self.renderpass.iter().find(|p| p.pass_type == pass_type)
+  }
+
+  /// Fragment source of the image pass.
+  pub fn image_source(&self) -> Option<String> {
+    self.pass("image").map(|p| p.code.clone())
+  }
+
+  /// Number of the Buffer A-D passes, only the image pass is rendered.
+  pub fn buffer_passes(&self) -> usize {
+    self.renderpass.iter().filter(|p| p.pass_type == "buffer").count()
+  }
+
+  /// Code shared by all passes, compiled in front of the image pass.
+  pub fn common_source(&self) -> String {
+    self.pass("common").map(|p| p.code.clone()).unwrap_or_default()
+  }
+}
+
+/// Parses any of the shapes ShaderToy exports come in - a single shader, `{"Shader": ...}` or a list of those.
+pub fn parse_shadertoy_export(json: &str) -> Result<Vec<ShaderToyShader>, Box<dyn Error>> {
+  let shaders = match serde_json::from_str::<ShaderToyExport>(json)? {
+    ShaderToyExport::Wrapped(w) => vec![w.shader],
+    ShaderToyExport::WrappedList(l) => l.into_iter().map(|w| w.shader).collect(),
+    ShaderToyExport::List(l) => l,
+    ShaderToyExport::Single(s) => vec![s],
+  };
+  for shader in &shaders {
+    if shader.pass("image").is_none() {
+      return Err(format!("Shader '{}' has no image pass!", shader.info.name).into());
+    }
+  }
+  Ok(shaders)
+}
+
+// Library
+
+/// Metadata for the plain `.stoy` toys, kept in the library index file.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ShaderToyIndexEntry {
+  pub id: String,
+  pub title: String,
+  pub author: String,
+  pub license: String,
+  pub description: String,
+}
+
+#[derive(Clone, Debug)]
+pub enum ShaderToySource {
+  Stoy,
+  Export(ShaderToyShader),
+}
+
+#[derive(Clone, Debug)]
+pub struct ShaderToyEntry {
+  pub id: String,
+  pub title: String,
+  pub author: String,
+  pub license: String,
+  pub description: String,
+  pub tags: Vec<String>,
+  pub path: PathBuf,
+  pub source: ShaderToySource,
+}
+
+impl ShaderToyEntry {
+  pub fn matches(&self, query: &str) -> bool {
+    let query = query.trim().to_lowercase();
+    if query.is_empty() { return true; }
+    self.id.to_lowercase().contains(&query)
+      || self.title.to_lowercase().contains(&query)
+      || self.author.to_lowercase().contains(&query)
+      || self.tags.iter().any(|t| t.to_lowercase().contains(&query))
+  }
+
+  /// Inputs of the image pass channels, indexed by iChannel slot.
+  pub fn channels(&self) -> [ShaderToyChannel; 4] {
+    let mut channels: [ShaderToyChannel; 4] = Default::default();
+    if let ShaderToySource::Export(shader) = &self.source {
+      if let Some(image) = shader.pass("image") {
+        for input in image.inputs.iter().filter(|i| i.channel < 4) {
+          match input.ctype.as_str() {
+            "texture" => match Self::local_input(&input.src) {
+              Some(local) => channels[input.channel] = ShaderToyChannel::Texture(local),
+              None => do_log!("[Kuplung] [ShaderToy] [Library] Toy {} uses texture {} which is not available locally.", self.id, input.src),
+            },
+            "music" | "musicstream" => match Self::local_input(&input.src) {
+              Some(local) => channels[input.channel] = ShaderToyChannel::Audio(local),
+              None => do_log!("[Kuplung] [ShaderToy] [Library] Toy {} uses audio {} which is not available locally.", self.id, input.src),
+            },
+            "keyboard" => channels[input.channel] = ShaderToyChannel::Keyboard,
+            ctype => do_log!("[Kuplung] [ShaderToy] [Library] Toy {} uses unsupported {} input on iChannel{}.", self.id, ctype, input.channel),
+          }
+        }
+      }
+    }
+    channels
+  }
+
+  /// Why the toy will not look like on ShaderToy, if it uses passes that cannot be rendered.
+  pub fn unsupported_passes(&self) -> Option<String> {
+    let ShaderToySource::Export(shader) = &self.source else { return None; };
+    let buffers = shader.buffer_passes();
+    (buffers > 0).then(|| format!("Toy {} uses {} buffer passes which are not supported, only the image pass is rendered.", self.id, buffers))
+  }
+
+  /// The local copy of a ShaderToy media file, looked up by file name.
+  fn local_input(src: &str) -> Option<String> {
+    let file_name = Path::new(src).file_name()?;
+    let local = asset_locator::locate(configuration::SHADERTOY_TEXTURES_FOLDER).join(file_name);
+    asset_vfs::exists(&local).then(|| local.to_string_lossy().to_string())
+  }
+}
+
+pub struct ShaderToyLibrary {
+  folder: PathBuf,
+  pub entries: Vec<ShaderToyEntry>,
+}
+
+impl ShaderToyLibrary {
+  pub fn new(folder: PathBuf) -> Self {
+    let mut this = Self {
+      folder,
+      entries: Vec::new(),
+    };
+    this.rescan();
+    this
+  }
+
+  pub fn rescan(&mut self) {
+    do_log!("[Kuplung] [ShaderToy] [Library] Scanning {}...", self.folder.display());
+    self.entries.clear();
+    let index = self.read_index();
+
+    let files = asset_vfs::list(&self.folder);
+    if files.is_empty() {
+      do_log!("[Kuplung] [ShaderToy] [Library] Library folder {} is empty or cannot be read.", self.folder.display());
+      return;
+    }
+
+    for path in files {
+      let extension = path.extension().and_then(|e| e.to_str()).unwrap_or_default();
+      let stem = path.file_stem().and_then(|e| e.to_str()).unwrap_or_default().to_string();
+      if extension == "stoy" {
+        let meta = index.iter().find(|m| m.id == stem).cloned().unwrap_or_default();
+        self.entries.push(ShaderToyEntry {
+          title: if meta.title.is_empty() { stem.clone() } else { meta.title },
+          id: stem,
+          author: meta.author,
+          license: meta.license,
+          description: meta.description,
+          tags: Vec::new(),
+          path,
+          source: ShaderToySource::Stoy,
+        });
+      }
+      else if extension == "json" && !Self::is_reserved_file(&path) {
+        match asset_vfs::read_to_string(&path).map_err(|e| e.into()).and_then(|json| parse_shadertoy_export(&json)) {
+          Ok(shaders) => {
+            for shader in shaders {
+              self.entries.push(Self::entry_from_export(shader, &path));
+            }
+          }
+          Err(e) => do_log!("[Kuplung] [ShaderToy] [Library] Skipping {}: {}", path.display(), e),
+        }
+      }
+    }
+    self.entries.sort_by_key(|e| e.title.to_lowercase());
+    do_log!("[Kuplung] [ShaderToy] [Library] Found {} toys.", self.entries.len());
+  }
+
+  pub fn find(&self, id: &str) -> Option<&ShaderToyEntry> {
+    self.entries.iter().find(|e| e.id == id)
+  }
+
+  pub fn search(&self, query: &str) -> Vec<&ShaderToyEntry> {
+    self.entries.iter().filter(|e| e.matches(query)).collect()
+  }
+
+  pub fn load_source(&self, entry: &ShaderToyEntry) -> Result<String, Box<dyn Error>> {
+    match &entry.source {
+      ShaderToySource::Stoy => utils::file_io::read_shadertoy_shader(format!("{}.stoy", entry.id).as_str()),
+      ShaderToySource::Export(shader) => shader.image_source().ok_or_else(|| format!("Toy {} has no image pass!", entry.id).into()),
+    }
+  }
+
+  /// The common pass of the toy, empty for toys without one.
+  pub fn load_common(&self, entry: &ShaderToyEntry) -> String {
+    match &entry.source {
+      ShaderToySource::Stoy => String::new(),
+      ShaderToySource::Export(shader) => shader.common_source(),
+    }
+  }
+
+  /// Re-reads the toy from disk after it was changed outside of Kuplung and returns its image source.
+  pub fn reload_source(&mut self, id: &str) -> Result<String, Box<dyn Error>> {
+    let entry = self.entries.iter_mut().find(|e| e.id == id).ok_or_else(|| format!("Toy {} is not in the library!", id))?;
+    if let ShaderToySource::Export(_) = entry.source {
+      let json = asset_vfs::read_to_string(&entry.path)?;
+      let shader = parse_shadertoy_export(&json)?.into_iter().find(|s| s.info.id == id).ok_or_else(|| format!("Toy {} is no longer in {}!", id, entry.path.display()))?;
+      *entry = Self::entry_from_export(shader, &entry.path.clone());
+    }
+    let entry = self.find(id).ok_or_else(|| format!("Toy {} is not in the library!", id))?;
+    self.load_source(entry)
+  }
+
+  /// Copies the shaders from a ShaderToy JSON export into the library. Returns the ids of the imported toys.
+  pub fn import_file(&mut self, file: &str) -> Result<Vec<String>, Box<dyn Error>> {
+    do_log!("[Kuplung] [ShaderToy] [Library] Importing {}...", file);
+    let json = std::fs::read_to_string(file)?;
+    let shaders = parse_shadertoy_export(&json)?;
+    let mut ids: Vec<String> = Vec::new();
+    for mut shader in shaders {
+      // the id is the file name, anything that could leave the library folder or clash gets a new one
+      let id = &shader.info.id;
+      if !Self::is_valid_id(id) || self.find(id).is_some() || ids.contains(id) {
+        shader.info.id = self.generate_id();
+      }
+      self.write_export(&shader)?;
+      ids.push(shader.info.id);
+    }
+    self.rescan();
+    do_log!("[Kuplung] [ShaderToy] [Library] Imported {} toys.", ids.len());
+    Ok(ids)
+  }
+
+  /// Stores the given common and image pass sources as a new toy in the library. Returns the id of the new toy.
+  pub fn save_as_new(&mut self, title: &str, author: &str, license: &str, description: &str, common: &str, code: &str) -> Result<String, Box<dyn Error>> {
+    if title.trim().is_empty() {
+      return Err("Toy title cannot be empty!".into());
+    }
+    let mut shader = ShaderToyShader {
+      ver: "0.1".to_string(),
+      info: ShaderToyInfo {
+        id: self.generate_id(),
+        name: title.trim().to_string(),
+        username: author.trim().to_string(),
+        description: description.to_string(),
+        tags: Vec::new(),
+        license: license.trim().to_string(),
+      },
+      renderpass: vec![ShaderToyRenderPass {
+        code: code.to_string(),
+        name: "Image".to_string(),
+        pass_type: "image".to_string(),
+        outputs: vec![ShaderToyOutput { id: serde_json::Value::from(37), channel: 0 }],
+        ..Default::default()
+      }],
+    };
+    if !common.is_empty() {
+      shader.renderpass.push(ShaderToyRenderPass {
+        code: common.to_string(),
+        name: "Common".to_string(),
+        pass_type: "common".to_string(),
+        ..Default::default()
+      });
+    }
+    self.write_export(&shader)?;
+    self.rescan();
+    do_log!("[Kuplung] [ShaderToy] [Library] Saved new toy {} ({}).", shader.info.name, shader.info.id);
+    Ok(shader.info.id)
+  }
+
+  fn write_export(&self, shader: &ShaderToyShader) -> Result<(), Box<dyn Error>> {
+    if !Self::is_valid_id(&shader.info.id) { return Err(format!("Toy id '{}' cannot be used as a file name!", shader.info.id).into()); }
+    let path = self.folder.join(format!("{}.json", shader.info.id));
+    let json = serde_json::to_string_pretty(&serde_json::json!({ "Shader": shader }))?;
+    std::fs::write(path, json)?;
+    Ok(())
+  }
+
+  /// Ids made of letters, digits, '_' and '-' only, as ShaderToy gives them.
+  fn is_valid_id(id: &str) -> bool {
+    !id.is_empty() && id.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+  }
+
+  /// Library files which are not toy exports.
+  fn is_reserved_file(path: &Path) -> bool {
+    let file_name = path.file_name().and_then(|f| f.to_str()).unwrap_or_default();
+    file_name == configuration::SHADERTOY_LIBRARY_INDEX
+  }
+
+  fn read_index(&self) -> Vec<ShaderToyIndexEntry> {
+    let path = self.folder.join(configuration::SHADERTOY_LIBRARY_INDEX);
+    match asset_vfs::read_to_string(&path) {
+      Ok(json) => serde_json::from_str(&json).unwrap_or_else(|e| {
+        do_log!("[Kuplung] [ShaderToy] [Library] Cannot parse library index {}: {}", path.display(), e);
+        Vec::new()
+      }),
+      Err(_) => Vec::new(),
+    }
+  }
+
+  /// ShaderToy-like 6 character id, unique within the library.
+  fn generate_id(&self) -> String {
+    const ALPHABET: &[u8] = b"0123456789abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ";
+    let mut seed = chrono::Utc::now().timestamp_millis() as u64;
+    loop {
+      let mut value = seed;
+      let id: String = (0..6).map(|_| {
+        let c = ALPHABET[(value % ALPHABET.len() as u64) as usize] as char;
+        value /= ALPHABET.len() as u64;
+        c
+      }).collect();
+      if self.find(&id).is_none() && !asset_vfs::exists(self.folder.join(format!("{}.json", id))) {
+        return id;
+      }
+      seed = seed.wrapping_add(1);
+    }
+  }
+
+  fn entry_from_export(shader: ShaderToyShader, path: &Path) -> ShaderToyEntry {
+    let info = shader.info.clone();
+    ShaderToyEntry {
+      id: info.id,
+      title: if info.name.is_empty() { path.file_stem().unwrap_or_default().to_string_lossy().to_string() } else { info.name },
+      author: info.username,
+      license: if info.license.is_empty() { configuration::SHADERTOY_DEFAULT_LICENSE.to_string() } else { info.license },
+      description: info.description,
+      tags: info.tags,
+      path: path.to_path_buf(),
+      source: ShaderToySource::Export(shader),
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  const SHADER: &str = r#"{
+    "ver": "0.1",
+    "info": { "id": "abc123", "name": "Waves", "username": "kuplung" },
+    "renderpass": [
+      { "type": "common", "name": "Common", "code": "float wave(float x) { return sin(x); }" },
+      { "type": "buffer", "name": "Buffer A", "code": "void mainImage(out vec4 c, in vec2 p) { c = vec4(0.0); }" },
+      { "type": "image", "name": "Image", "code": "void mainImage(out vec4 c, in vec2 p) { c = vec4(wave(p.x)); }" }
+    ]
+  }"#;
+
+  fn names(json: &str) -> Vec<String> {
+    parse_shadertoy_export(json).unwrap().into_iter().map(|s| s.info.name).collect()
+  }
+
+  #[test]
+  fn parses_every_export_shape() {
+    assert_eq!(names(SHADER), vec!["Waves"]);
+    assert_eq!(names(&format!(r#"{{"Shader": {}}}"#, SHADER)), vec!["Waves"]);
+    assert_eq!(names(&format!(r#"[{{"Shader": {}}}, {{"Shader": {}}}]"#, SHADER, SHADER)), vec!["Waves", "Waves"]);
+    assert_eq!(names(&format!("[{}, {}]", SHADER, SHADER)), vec!["Waves", "Waves"]);
+  }
+
+  #[test]
+  fn rejects_exports_without_an_image_pass() {
+    let error = parse_shadertoy_export(r#"{"info": {"name": "Empty"}, "renderpass": [{"type": "buffer", "code": ""}]}"#).unwrap_err();
+    assert!(error.to_string().contains("'Empty' has no image pass"));
+    assert!(parse_shadertoy_export(r#"{"Shader": 42}"#).is_err());
+  }
+
+  #[test]
+  fn keeps_the_passes_apart() {
+    let shader = parse_shadertoy_export(SHADER).unwrap().remove(0);
+    assert_eq!(shader.common_source(), "float wave(float x) { return sin(x); }");
+    assert!(shader.image_source().unwrap().contains("wave(p.x)"));
+    assert_eq!(shader.buffer_passes(), 1);
+    let entry = ShaderToyLibrary::entry_from_export(shader, Path::new("waves.json"));
+    assert!(entry.unsupported_passes().unwrap().contains("uses 1 buffer passes which are not supported"));
+  }
+
+  #[test]
+  fn imports_keep_the_toys_in_the_library_folder() {
+    let folder = std::env::temp_dir().join(format!("kuplung_shadertoy_{}", std::process::id()));
+    std::fs::create_dir_all(&folder).unwrap();
+    let escaping = SHADER.replace("abc123", "../../escaped");
+    let file = folder.join("import.txt");
+    std::fs::write(&file, format!("[{}, {}, {}]", SHADER, SHADER, escaping)).unwrap();
+    let mut library = ShaderToyLibrary::new(folder.clone());
+    let ids = library.import_file(&file.to_string_lossy()).unwrap();
+    let files: Vec<String> = std::fs::read_dir(&folder).unwrap().map(|f| f.unwrap().file_name().to_string_lossy().to_string()).collect();
+    std::fs::remove_dir_all(&folder).unwrap();
+
+    assert_eq!(ids.len(), 3);
+    assert_eq!(ids[0], "abc123");
+    assert!(ids.iter().all(|id| ShaderToyLibrary::is_valid_id(id)));
+    assert!(ids[1] != ids[0] && ids[2] != ids[1] && ids[2] != ids[0]);
+    for id in ids.iter() { assert!(files.contains(&format!("{}.json", id))); }
+    assert_eq!(library.entries.len(), 3);
+  }
+}
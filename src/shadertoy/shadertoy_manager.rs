@@ -5,47 +5,615 @@ use egui::mutex::Mutex;
 use egui::Ui;
 use egui_glow::glow;
 
+use strum::IntoEnumIterator;
+
+use crate::shadertoy::shadertoy_audio::ShaderToyAudio;
+#[cfg(feature = "audio_playback")]
+use crate::shadertoy::shadertoy_audio::ShaderToyAudioPlayer;
+use crate::shadertoy::shadertoy_channels::{self, ShaderToyChannel};
 use crate::shadertoy::shadertoy_engine::ShaderToyEngine;
+use crate::shadertoy::shadertoy_export::{ShaderToyExportFormat, ShaderToyExportJob, ShaderToyExportSettings};
+use crate::shadertoy::shadertoy_keyboard::ShaderToyKeyboard;
+use crate::shadertoy::shadertoy_library::ShaderToyLibrary;
+use crate::shadertoy::shadertoy_params::{self, ShaderToyParam, ShaderToyParamStore};
 use crate::do_log;
-use crate::settings::{configuration, kuplung_logger};
+use crate::rendering::gl_error::GLError;
+use crate::settings::{configuration, kuplung_logger, kuplung_settings};
+use crate::ui::components::code_editor::CodeEditor;
+use crate::ui::components::error_panel;
+use crate::ui::components::file_browser::{FileBrowser, FileBrowserMode};
+use crate::ui::components::glsl_syntax::{self, GlslToken};
+use crate::utils::asset_locator;
+use crate::utils::file_watcher::FileWatcher;
 
 pub struct ShaderToy {
   pub show_shadertoy: bool,
-  previous_toy: String,
   current_toy: String,
   current_source: String,
-  shader_toy_engine: Arc<Mutex<ShaderToyEngine>>
+  /// Common pass of the toy, compiled in front of the edited image pass.
+  current_common: String,
+  /// Image pass as it was loaded from the library, the editor has unsaved edits when it differs.
+  loaded_source: String,
+  /// Image pass changed on disk while there were unsaved edits, waiting for the user to reload or keep the edits.
+  disk_source: Option<String>,
+  /// Parts of the toy which cannot be rendered, shown above the editor.
+  toy_warning: String,
+  code_editor: CodeEditor,
+  current_channels: [ShaderToyChannel; 4],
+  channel_textures: Vec<String>,
+  channel_audio_files: Vec<String>,
+  channel_audio: [Option<Arc<ShaderToyAudio>>; 4],
+  audio_file: String,
+  audio_slot: usize,
+  #[cfg(feature = "audio_playback")]
+  play_audio: bool,
+  #[cfg(feature = "audio_playback")]
+  audio_player: Option<ShaderToyAudioPlayer>,
+  keyboard: ShaderToyKeyboard,
+  pending_compile: bool,
+  /// The running toy reads the time, the date, the audio or the keyboard, so it is painted every frame.
+  animating: bool,
+  compile_error: Arc<Mutex<String>>,
+  hot_reload: bool,
+  watcher: FileWatcher,
+  params: Vec<ShaderToyParam>,
+  params_store: ShaderToyParamStore,
+  params_dirty: bool,
+  library: ShaderToyLibrary,
+  library_search: String,
+  show_import: bool,
+  import_file: String,
+  browser_import: FileBrowser,
+  browser_audio: FileBrowser,
+  browser_export: FileBrowser,
+  show_save_as: bool,
+  save_title: String,
+  save_author: String,
+  save_license: String,
+  save_description: String,
+  library_status: String,
+  time_start: std::time::Instant,
+  time_last: f32,
+  frame: i32,
+  show_export: bool,
+  export_settings: ShaderToyExportSettings,
+  export_job: Arc<Mutex<Option<ShaderToyExportJob>>>,
+  export_status: String,
+  shader_toy_engine: Result<Arc<Mutex<ShaderToyEngine>>, GLError>
 }
 
 impl ShaderToy {
-  pub fn new<'a>(cc: &'a eframe::CreationContext<'a>) -> Option<Self> {
+  pub fn new<'a>(cc: &'a eframe::CreationContext<'a>) -> Result<Self, GLError> {
     do_log!("[Kuplung] [ShaderToy] Initializing...");
 
-    let gl = cc.gl.as_ref()?;
+    let gl = cc.gl.as_ref().ok_or(GLError::NoContext)?;
     let this = Self {
       show_shadertoy: false,
-      previous_toy: "".to_string(),
       current_toy: "".to_string(),
       current_source: "".into(),
-      shader_toy_engine: Arc::new(Mutex::new(ShaderToyEngine::new(gl)?))
+      current_common: "".into(),
+      loaded_source: "".into(),
+      disk_source: None,
+      toy_warning: "".into(),
+      code_editor: CodeEditor::new("shadertoy_editor"),
+      current_channels: Default::default(),
+      channel_textures: shadertoy_channels::list_textures(),
+      channel_audio_files: shadertoy_channels::list_audio(),
+      channel_audio: Default::default(),
+      audio_file: "".to_string(),
+      audio_slot: 0,
+      #[cfg(feature = "audio_playback")]
+      play_audio: false,
+      #[cfg(feature = "audio_playback")]
+      audio_player: None,
+      keyboard: ShaderToyKeyboard::default(),
+      pending_compile: false,
+      animating: true,
+      compile_error: Arc::new(Mutex::new("".to_string())),
+      hot_reload: true,
+      watcher: FileWatcher::default(),
+      params: Vec::new(),
+      params_store: ShaderToyParamStore::load(kuplung_settings::config_folder().join(configuration::SHADERTOY_PARAMS_FILE)),
+      params_dirty: false,
+      library: ShaderToyLibrary::new(asset_locator::locate(configuration::SHADERTOY_LIBRARY_FOLDER)),
+      library_search: "".to_string(),
+      show_import: false,
+      import_file: "".to_string(),
+      browser_import: FileBrowser::new("browser_shadertoy_import", "Import ShaderToy JSON").filter("ShaderToy export", &["json"]),
+      browser_audio: FileBrowser::new("browser_shadertoy_audio", "Audio File").filter("Audio", &["wav", "ogg"]),
+      browser_export: FileBrowser::new("browser_shadertoy_export", "Export Folder").mode(FileBrowserMode::Folder),
+      show_save_as: false,
+      save_title: "".to_string(),
+      save_author: "".to_string(),
+      save_license: configuration::SHADERTOY_DEFAULT_LICENSE.to_string(),
+      save_description: "".to_string(),
+      library_status: "".to_string(),
+      time_start: std::time::Instant::now(),
+      time_last: 0.0,
+      frame: 0,
+      show_export: false,
+      export_settings: ShaderToyExportSettings::default(),
+      export_job: Arc::new(Mutex::new(None)),
+      export_status: "".to_string(),
+      shader_toy_engine: ShaderToyEngine::new(gl).map(|e| Arc::new(Mutex::new(e)))
     };
     do_log!("[Kuplung] [ShaderToy] Initialized.");
-    Some(this)
+    Ok(this)
+  }
+
+  fn select_toy(&mut self, id: &str) {
+    let Some(entry) = self.library.find(id) else { return; };
+    match self.library.load_source(entry) {
+      Ok(source) => {
+        do_log!("[Kuplung] [ShaderToy] Selected toy {} ({}).", entry.title, entry.id);
+        self.current_channels = entry.channels();
+        self.current_toy = entry.id.clone();
+        self.current_common = self.library.load_common(entry);
+        self.loaded_source = source.clone();
+        self.current_source = source;
+        self.disk_source = None;
+        self.toy_warning = entry.unsupported_passes().unwrap_or_default();
+        if !self.toy_warning.is_empty() { do_log!("[Kuplung] [ShaderToy] {}", self.toy_warning); }
+        self.params = shadertoy_params::parse_params(&self.current_source);
+        self.params_store.apply(&self.current_toy, &mut self.params);
+        self.load_channel_audio();
+        self.update_watched_files();
+        self.pending_compile = true;
+      }
+      Err(e) => do_log!("[Kuplung] [ShaderToy] Cannot load toy {}: {}", entry.id, e),
+    }
+  }
+
+  /// Re-reads the parameter annotations after the source was edited, keeping the current values.
+  fn compile_source(&mut self) {
+    let mut params = shadertoy_params::parse_params(&self.current_source);
+    for param in params.iter_mut() {
+      if let Some(previous) = self.params.iter().find(|p| p.name == param.name && p.default == param.default) {
+        param.value = previous.value;
+      }
+    }
+    self.params = params;
+    self.pending_compile = true;
+  }
+
+  /// Watches the toy file (holding all of its passes) and the media files of its channels.
+  fn update_watched_files(&mut self) {
+    let mut files: Vec<std::path::PathBuf> = self.library.find(&self.current_toy).map(|e| e.path.clone()).into_iter().collect();
+    for channel in &self.current_channels {
+      if let ShaderToyChannel::Texture(file) | ShaderToyChannel::Audio(file) = channel {
+        files.push(file.into());
+      }
+    }
+    self.watcher.watch(files);
+  }
+
+  /// Picks up the changes made to the toy files by external editors.
+  fn check_watched_files(&mut self) {
+    if !self.hot_reload { return; }
+    let changed = self.watcher.poll();
+    if changed.is_empty() { return; }
+    let toy_changed = self.library.find(&self.current_toy).is_some_and(|e| changed.contains(&e.path));
+    if toy_changed {
+      match self.library.reload_source(&self.current_toy) {
+        Ok(source) if self.current_source != self.loaded_source && self.current_source != source => {
+          do_log!("[Kuplung] [ShaderToy] Toy {} changed on disk while it has unsaved edits.", self.current_toy);
+          self.disk_source = Some(source);
+        }
+        Ok(source) => {
+          do_log!("[Kuplung] [ShaderToy] Toy {} changed on disk, reloading...", self.current_toy);
+          self.apply_disk_source(source);
+        }
+        Err(e) => {
+          do_log!("[Kuplung] [ShaderToy] Cannot reload toy {}: {}", self.current_toy, e);
+          *self.compile_error.lock() = e.to_string();
+          return;
+        }
+      }
+    }
+    for (channel, audio) in self.current_channels.iter().zip(self.channel_audio.iter_mut()) {
+      if let ShaderToyChannel::Audio(file) = channel {
+        if changed.iter().any(|f| f.as_path() == std::path::Path::new(file)) { *audio = None; }
+      }
+    }
+    self.load_channel_audio();
+    // recompiling also reloads the channel textures
+    self.compile_source();
+  }
+
+  /// Replaces the editor contents with the toy as it is on disk.
+  fn apply_disk_source(&mut self, source: String) {
+    self.current_common = self.library.find(&self.current_toy).map(|e| self.library.load_common(e)).unwrap_or_default();
+    self.toy_warning = self.library.find(&self.current_toy).and_then(|e| e.unsupported_passes()).unwrap_or_default();
+    self.loaded_source = source.clone();
+    self.current_source = source;
+  }
+
+  /// Whether the current source changes from frame to frame - the inputs in comments do not count.
+  fn is_animating(&self) -> bool {
+    const TIME_INPUTS: [&str; 6] = ["iTime", "iGlobalTime", "iTimeDelta", "iFrame", "iDate", "iChannelTime"];
+    // without a toy the default one runs, which is animated
+    if self.current_source.trim().is_empty() { return true; }
+    let reads_time = [&self.current_common, &self.current_source].iter().any(|source| {
+      glsl_syntax::tokenize(source).into_iter().any(|(token, range)| token == GlslToken::ShaderToyUniform && TIME_INPUTS.contains(&&source[range]))
+    });
+    reads_time || self.current_channels.iter().any(|c| matches!(c, ShaderToyChannel::Audio(_) | ShaderToyChannel::Keyboard))
+  }
+
+  /// Decodes the audio files of the audio channels, dropping the channels that cannot be decoded.
+  fn load_channel_audio(&mut self) {
+    for (channel, audio) in self.current_channels.iter_mut().zip(self.channel_audio.iter_mut()) {
+      let ShaderToyChannel::Audio(file) = channel else {
+        *audio = None;
+        continue;
+      };
+      if audio.as_ref().is_some_and(|a| a.file == *file) { continue; }
+      match ShaderToyAudio::load(file) {
+        Ok(decoded) => *audio = Some(Arc::new(decoded)),
+        Err(e) => {
+          do_log!("[Kuplung] [ShaderToy] [Audio] Cannot load {}: {}", file, e);
+          *channel = ShaderToyChannel::None;
+          *audio = None;
+        }
+      }
+    }
+    self.restart_audio();
+  }
+
+  #[cfg(feature = "audio_playback")]
+  fn restart_audio(&mut self) {
+    if let Some(player) = self.audio_player.take() { player.stop(); }
+    if !self.play_audio { return; }
+    if let Some(audio) = self.channel_audio.iter().flatten().next() {
+      match ShaderToyAudioPlayer::play(audio, self.time_start.elapsed().as_secs_f32()) {
+        Ok(player) => self.audio_player = Some(player),
+        Err(e) => do_log!("[Kuplung] [ShaderToy] [Audio] Cannot play {}: {}", audio.file, e),
+      }
+    }
+  }
+
+  #[cfg(not(feature = "audio_playback"))]
+  fn restart_audio(&mut self) {}
+
+  fn render_channels(&mut self, ui: &mut Ui) {
+    let mut channels_changed = false;
+    ui.collapsing("Channels", |ui| {
+      egui::Grid::new("shadertoy_channels_grid").num_columns(2).show(ui, |ui| {
+        for (i, channel) in self.current_channels.iter_mut().enumerate() {
+          ui.label(format!("iChannel{}", i));
+          let previous = channel.clone();
+          egui::ComboBox::from_id_source(format!("shadertoy_channel_{}", i))
+            .selected_text(channel.label())
+            .show_ui(ui, |ui| {
+              ui.selectable_value(channel, ShaderToyChannel::None, "None");
+              ui.selectable_value(channel, ShaderToyChannel::Keyboard, "Keyboard");
+              for texture in &self.channel_textures {
+                let option = ShaderToyChannel::Texture(texture.clone());
+                let label = option.label();
+                ui.selectable_value(channel, option, label);
+              }
+              for audio_file in &self.channel_audio_files {
+                let option = ShaderToyChannel::Audio(audio_file.clone());
+                let label = format!("{} (audio)", option.label());
+                ui.selectable_value(channel, option, label);
+              }
+            });
+          if *channel != previous { channels_changed = true; }
+          ui.end_row();
+        }
+      });
+      ui.horizontal(|ui| {
+        ui.label("Audio file");
+        ui.add(egui::TextEdit::singleline(&mut self.audio_file).hint_text("path to a .wav or .ogg file"));
+        if ui.button("Browse...").clicked() { self.browser_audio.open(); }
+        egui::ComboBox::from_id_source("shadertoy_audio_slot")
+          .selected_text(format!("iChannel{}", self.audio_slot))
+          .show_ui(ui, |ui| {
+            for i in 0..4 {
+              ui.selectable_value(&mut self.audio_slot, i, format!("iChannel{}", i));
+            }
+          });
+        if ui.button("Assign").clicked() && !self.audio_file.is_empty() {
+          self.current_channels[self.audio_slot] = ShaderToyChannel::Audio(self.audio_file.clone());
+          channels_changed = true;
+        }
+      });
+      #[cfg(feature = "audio_playback")]
+      if ui.checkbox(&mut self.play_audio, "Play audio").changed() {
+        self.restart_audio();
+      }
+    });
+    if channels_changed {
+      self.load_channel_audio();
+      self.update_watched_files();
+      self.pending_compile = true;
+    }
+  }
+
+  fn render_params(&mut self, ui: &mut Ui) {
+    if self.params.is_empty() { return; }
+    let Ok(engine) = &self.shader_toy_engine else { return; };
+    let engine = engine.lock();
+    ui.collapsing("Parameters", |ui| {
+      egui::Grid::new("shadertoy_params_grid").num_columns(3).striped(true).show(ui, |ui| {
+        for param in self.params.iter_mut() {
+          if engine.has_uniform(&param.name, param.value.gl_type()) {
+            ui.label(&param.name);
+          }
+          else {
+            ui.label(&param.name).on_hover_text("Uniform is not active in the compiled program - it's unused or its type doesn't match.");
+          }
+          if shadertoy_params::draw_param(ui, param).changed() { self.params_dirty = true; }
+          if ui.add_enabled(param.value != param.default, egui::Button::new("Reset")).clicked() {
+            param.value = param.default;
+            self.params_dirty = true;
+          }
+          ui.end_row();
+        }
+      });
+      if ui.button("Reset all").clicked() {
+        self.params.iter_mut().for_each(|p| p.value = p.default);
+        self.params_dirty = true;
+      }
+    });
+    drop(engine);
+    if self.params_dirty && !ui.input(|i| i.pointer.any_down()) {
+      self.params_dirty = false;
+      self.params_store.store(&self.current_toy, &self.params);
+      if let Err(e) = self.params_store.save() {
+        do_log!("[Kuplung] [ShaderToy] [Params] Cannot save parameters: {}", e);
+      }
+    }
+  }
+
+  fn render_menu_toys(&mut self, ui: &mut Ui) {
+    ui.horizontal(|ui| {
+      ui.label("Search:");
+      ui.text_edit_singleline(&mut self.library_search);
+    });
+    ui.separator();
+    let mut selected: Option<String> = None;
+    egui::ScrollArea::vertical().max_height(300.0).show(ui, |ui| {
+      for entry in self.library.search(&self.library_search) {
+        let label = if entry.author.is_empty() { entry.title.clone() } else { format!("{} - {}", entry.title, entry.author) };
+        let hover = format!("{}\n{}\nLicense: {}\n{}", entry.id, entry.path.display(), entry.license, entry.description);
+        if ui.selectable_label(entry.id == self.current_toy, label).on_hover_text(hover).clicked() {
+          selected = Some(entry.id.clone());
+        }
+      }
+    });
+    if let Some(id) = selected {
+      self.select_toy(&id);
+      ui.close_menu();
+    }
+    ui.separator();
+    if ui.button("Import ShaderToy JSON...").clicked() {
+      self.show_import = true;
+      ui.close_menu();
+    }
+    if ui.button("Save as new toy...").clicked() {
+      self.show_save_as = true;
+      ui.close_menu();
+    }
+    if ui.button("Rescan library").clicked() {
+      self.library.rescan();
+      ui.close_menu();
+    }
+  }
+
+  fn render_import(&mut self, ctx: &egui::Context) {
+    let mut open = self.show_import;
+    egui::Window::new("Import ShaderToy JSON")
+      .id(egui::Id::new("window_shadertoy_import"))
+      .open(&mut open)
+      .resizable(false)
+      .show(ctx, |ui| {
+        ui.horizontal(|ui| {
+          ui.label("File:");
+          ui.text_edit_singleline(&mut self.import_file);
+          if ui.button("Browse...").clicked() { self.browser_import.open(); }
+        });
+        if ui.button("Import").clicked() {
+          match self.library.import_file(self.import_file.trim()) {
+            Ok(ids) => {
+              self.library_status = format!("Imported {} toy(s).", ids.len());
+              if let Some(id) = ids.first() { self.select_toy(id); }
+            }
+            Err(e) => self.library_status = format!("Import failed: {}", e),
+          }
+        }
+        if !self.library_status.is_empty() { ui.label(&self.library_status); }
+      });
+    self.show_import = open;
+  }
+
+  fn render_save_as(&mut self, ctx: &egui::Context) {
+    let mut open = self.show_save_as;
+    egui::Window::new("Save as new toy")
+      .id(egui::Id::new("window_shadertoy_save_as"))
+      .open(&mut open)
+      .resizable(false)
+      .show(ctx, |ui| {
+        egui::Grid::new("shadertoy_save_as_grid").num_columns(2).show(ui, |ui| {
+          ui.label("Title:");
+          ui.text_edit_singleline(&mut self.save_title);
+          ui.end_row();
+          ui.label("Author:");
+          ui.text_edit_singleline(&mut self.save_author);
+          ui.end_row();
+          ui.label("License:");
+          ui.text_edit_singleline(&mut self.save_license);
+          ui.end_row();
+          ui.label("Description:");
+          ui.text_edit_multiline(&mut self.save_description);
+          ui.end_row();
+        });
+        if ui.button("Save").clicked() {
+          match self.library.save_as_new(&self.save_title, &self.save_author, &self.save_license, &self.save_description, &self.current_common, &self.current_source) {
+            Ok(id) => {
+              self.library_status = format!("Saved as {}.", id);
+              self.current_toy = id;
+              self.loaded_source = self.current_source.clone();
+            }
+            Err(e) => self.library_status = format!("Save failed: {}", e),
+          }
+        }
+        if !self.library_status.is_empty() { ui.label(&self.library_status); }
+      });
+    self.show_save_as = open;
+  }
+
+  fn render_disk_reload(&mut self, ctx: &egui::Context) {
+    egui::Window::new("Toy changed on disk")
+      .id(egui::Id::new("window_shadertoy_disk_reload"))
+      .collapsible(false)
+      .resizable(false)
+      .show(ctx, |ui| {
+        ui.label(format!("Toy {} was changed by another editor, but it also has unsaved edits here.", self.current_toy));
+        ui.horizontal(|ui| {
+          if ui.button("Reload").on_hover_text("Discard the edits and load the toy from disk").clicked() {
+            if let Some(source) = self.disk_source.take() {
+              self.apply_disk_source(source);
+              self.compile_source();
+            }
+          }
+          if ui.button("Keep my edits").clicked() {
+            if let Some(source) = self.disk_source.take() { self.loaded_source = source; }
+          }
+        });
+      });
+  }
+
+  fn render_export(&mut self, ctx: &egui::Context) {
+    let mut open = self.show_export;
+    egui::Window::new("Export ShaderToy")
+      .id(egui::Id::new("window_shadertoy_export"))
+      .open(&mut open)
+      .resizable(false)
+      .show(ctx, |ui| {
+        let exporting = self.export_job.lock().as_ref().is_some_and(|job| !job.finished);
+        ui.add_enabled_ui(!exporting, |ui| {
+          let settings = &mut self.export_settings;
+          egui::Grid::new("shadertoy_export_grid").num_columns(2).show(ui, |ui| {
+            ui.label("Resolution:");
+            ui.horizontal(|ui| {
+              ui.add(egui::DragValue::new(&mut settings.width).range(1..=32768).suffix(" px"));
+              ui.label("x");
+              ui.add(egui::DragValue::new(&mut settings.height).range(1..=32768).suffix(" px"));
+            });
+            ui.end_row();
+            ui.label("Frames:");
+            ui.add(egui::DragValue::new(&mut settings.frames).range(1..=100000));
+            ui.end_row();
+            ui.label("FPS:");
+            ui.add(egui::DragValue::new(&mut settings.fps).range(1.0..=240.0).speed(0.1));
+            ui.end_row();
+            ui.label("Start time:");
+            ui.add(egui::DragValue::new(&mut settings.start_time).range(0.0..=f32::MAX).speed(0.1).suffix(" s"));
+            ui.end_row();
+            ui.label("Format:");
+            egui::ComboBox::from_id_source("shadertoy_export_format")
+              .selected_text(settings.format.as_ref())
+              .show_ui(ui, |ui| {
+                for format in ShaderToyExportFormat::iter() {
+                  ui.selectable_value(&mut settings.format, format, format.as_ref());
+                }
+              });
+            ui.end_row();
+            ui.label("Output folder:");
+            ui.horizontal(|ui| {
+              ui.text_edit_singleline(&mut settings.output_folder);
+              if ui.button("Browse...").clicked() { self.browser_export.open(); }
+            });
+            ui.end_row();
+          });
+        });
+        ui.separator();
+        ui.horizontal(|ui| {
+          if !exporting && ui.button("Export").clicked() {
+            match ShaderToyExportJob::new(self.export_settings.clone(), &self.current_toy) {
+              Ok(job) => *self.export_job.lock() = Some(job),
+              Err(e) => self.export_status = format!("Export failed: {}", e),
+            }
+          }
+          if exporting && ui.button("Cancel").clicked() {
+            if let Some(job) = self.export_job.lock().as_mut() { job.cancel(); }
+          }
+        });
+        if let Some(job) = self.export_job.lock().as_ref() {
+          ui.add(egui::ProgressBar::new(job.progress()).show_percentage());
+          self.export_status = job.status.clone();
+        }
+        if !self.export_status.is_empty() { ui.label(&self.export_status); }
+        if exporting { ctx.request_repaint(); }
+      });
+    self.show_export = open;
   }
 
   fn render_toy(&mut self, ui: &mut Ui) {
+    let shader_toy_engine = match &self.shader_toy_engine {
+      Ok(shader_toy_engine) => shader_toy_engine.clone(),
+      Err(e) => return error_panel::render_error_panel(ui, "ShaderToy", e),
+    };
     egui::Frame::canvas(ui.style()).show(ui, |ui| {
       let window_width: f32 = ui.available_width();
       let window_height: f32 = ui.available_height();
-      let (rect, _) = ui.allocate_exact_size(egui::Vec2::from([window_width, window_height]), egui::Sense::click_and_drag());
-      let shader_toy_engine = self.shader_toy_engine.clone();
-      let current_toy = self.current_toy.clone();
-      let mut recompile = false;
-      if self.current_toy.clone() != self.previous_toy { recompile = true; }
+      let (rect, response) = ui.allocate_exact_size(egui::Vec2::from([window_width, window_height]), egui::Sense::click_and_drag());
+      let uses_keyboard = self.current_channels.contains(&ShaderToyChannel::Keyboard);
+      if uses_keyboard && response.clicked() { response.request_focus(); }
+      self.keyboard.begin_frame();
+      if uses_keyboard && response.has_focus() {
+        ui.memory_mut(|m| m.set_focus_lock_filter(response.id, egui::EventFilter { tab: true, horizontal_arrows: true, vertical_arrows: true, escape: false }));
+        let (events, modifiers) = ui.input(|i| (i.events.clone(), i.modifiers));
+        self.keyboard.handle_events(&events, modifiers);
+        ui.painter().rect_stroke(rect, 0.0, ui.visuals().selection.stroke);
+      }
+      else {
+        self.keyboard.release_all();
+      }
+      let keyboard_data = self.keyboard.texture_data();
+      let recompile = std::mem::take(&mut self.pending_compile) | shader_toy_engine.lock().take_reload_request();
+      if recompile { self.animating = self.is_animating(); }
+      let current_source = self.current_source.clone();
+      let current_common = self.current_common.clone();
+      let current_channels = self.current_channels.clone();
+      let time = self.time_start.elapsed().as_secs_f32();
+      let time_delta = time - self.time_last;
+      let frame = self.frame;
+      self.time_last = time;
+      self.frame += 1;
+      let export_job = self.export_job.clone();
+      let params = self.params.clone();
+      let channel_audio = self.channel_audio.clone();
+      let compile_error = self.compile_error.clone();
       let cb = egui_glow::CallbackFn::new(move |_, painter| {
-        if recompile { shader_toy_engine.lock().reload_shadertoy(&current_toy, painter.gl()); }
-        shader_toy_engine.lock().setup_fbo(painter.gl(), window_width, window_height);
-        shader_toy_engine.lock().paint(painter.gl(), window_width, window_height);
+        if recompile {
+          *compile_error.lock() = match shader_toy_engine.lock().reload_shadertoy(&current_common, &current_source, current_channels.clone(), painter.gl()) {
+            Ok(()) => "".to_string(),
+            Err(e) => e.to_string(),
+          };
+        }
+        shader_toy_engine.lock().set_params(params.clone());
+        shader_toy_engine.lock().update_keyboard(painter.gl(), &keyboard_data);
+        if let Some(job) = export_job.lock().as_mut().filter(|job| !job.finished) {
+          let (export_time, export_delta, export_frame) = job.next_frame();
+          let (width, height) = (job.settings.width, job.settings.height);
+          for (i, audio) in channel_audio.iter().enumerate() {
+            if let Some(audio) = audio { shader_toy_engine.lock().update_audio(painter.gl(), i, &audio.texture_data(export_time)); }
+          }
+          let pushed = match shader_toy_engine.lock().render_offscreen(painter.gl(), width, height, export_time, export_delta, export_frame) {
+            Some(image) => job.push_frame(image).map_err(|e| e.to_string()),
+            None => Err("Offscreen rendering failed!".to_string()),
+          };
+          if let Err(e) = pushed {
+            do_log!("[Kuplung] [ShaderToy] [Export] {}", e);
+            job.cancel();
+            job.status = e;
+          }
+        }
+        for (i, audio) in channel_audio.iter().enumerate() {
+          if let Some(audio) = audio { shader_toy_engine.lock().update_audio(painter.gl(), i, &audio.texture_data(time)); }
+        }
+        shader_toy_engine.lock().paint(painter.gl(), window_width, window_height, time, time_delta, frame);
       });
       let callback = egui::PaintCallback {
         rect,
@@ -59,6 +627,7 @@ impl ShaderToy {
 impl eframe::App for ShaderToy {
   fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
     self.show_shadertoy = true;
+    self.check_watched_files();
     egui::Window::new("ShaderToy")
       .id(egui::Id::new("window_shadertoy"))
       .resizable(true)
@@ -72,68 +641,60 @@ impl eframe::App for ShaderToy {
             ui.close_menu();
             self.show_shadertoy = false;
           }
-          ui.menu_button("Toys", |ui| {
-            if ui.button("Artificial").clicked() {
-              self.current_toy = "4ljGW1".to_string();
-              ui.close_menu();
-            }
-            if ui.button("Combustible Voronoi Layers").clicked() {
-              self.current_toy = "4tlSzl".to_string();
-              ui.close_menu();
-            }
-            if ui.button("Seascape").clicked() {
-              self.current_toy = "Ms2SD1".to_string();
-              ui.close_menu();
-            }
-            if ui.button("Star Nest").clicked() {
-              self.current_toy = "XlfGRj".to_string();
-              ui.close_menu();
-            }
-            if ui.button("Sun Surface").clicked() {
-              self.current_toy = "XlSSzK".to_string();
-              ui.close_menu();
-            }
-          });
+          ui.menu_button("Toys", |ui| self.render_menu_toys(ui));
+          if ui.button("Export...").clicked() {
+            ui.close_menu();
+            self.show_export = true;
+          }
         });
         ui.separator();
-        if ui.button("Compile").clicked() {
-        }
-
-        let mut theme = egui_extras::syntax_highlighting::CodeTheme::from_memory(ui.ctx());
-        ui.collapsing("Theme", |ui| {
-          ui.group(|ui| {
-            theme.ui(ui);
-            theme.clone().store_in_memory(ui.ctx());
-          });
+        ui.horizontal(|ui| {
+          if ui.button("Compile").clicked() {
+            self.compile_source();
+          }
+          ui.checkbox(&mut self.hot_reload, "Hot reload").on_hover_text("Recompile when the toy or its channel files are saved from another editor");
         });
+        if !self.toy_warning.is_empty() {
+          ui.label(egui::RichText::new(&self.toy_warning).color(ui.visuals().warn_fg_color));
+        }
+        let compile_error = self.compile_error.lock().clone();
+        if !compile_error.is_empty() {
+          egui::CollapsingHeader::new(egui::RichText::new("Compile error - showing the last working version").color(ui.visuals().error_fg_color))
+            .id_source("shadertoy_compile_error")
+            .default_open(true)
+            .show(ui, |ui| {
+              egui::ScrollArea::vertical().max_height(120.0).show(ui, |ui| {
+                ui.label(egui::RichText::new(compile_error).monospace());
+              });
+            });
+        }
+        self.render_channels(ui);
+        self.render_params(ui);
 
-        let mut layouter = |ui: &egui::Ui, string: &str, wrap_width: f32| {
-          let mut layout_job = egui_extras::syntax_highlighting::highlight(ui.ctx(), &theme, string, "rs");
-          layout_job.wrap.max_width = wrap_width;
-          ui.fonts(|f| f.layout_job(layout_job))
-        };
-
-        let s_code = self.current_source.clone();
-        egui::ScrollArea::vertical().show(ui, |ui| {
-          ui.add(
-            egui::TextEdit::multiline(&mut s_code)
-              .font(egui::TextStyle::Monospace) // for cursor height
-              .code_editor()
-              .desired_rows(10)
-              .lock_focus(true)
-              .desired_width(f32::INFINITY)
-              .layouter(&mut layouter),
-          );
-        });
+        let max_height = ui.available_height() / 2.0;
+        self.code_editor.show(ui, &mut self.current_source, max_height);
 
         ui.separator();
         self.render_toy(ui);
       });
+    if self.show_import { self.render_import(ctx); }
+    if self.show_save_as { self.render_save_as(ctx); }
+    if self.show_export { self.render_export(ctx); }
+    if self.disk_source.is_some() { self.render_disk_reload(ctx); }
+    if let Some(file) = self.browser_import.show(ctx) { self.import_file = file.display().to_string(); }
+    if let Some(file) = self.browser_audio.show(ctx) { self.audio_file = file.display().to_string(); }
+    if let Some(folder) = self.browser_export.show(ctx) { self.export_settings.output_folder = folder.display().to_string(); }
+    let exporting = self.export_job.lock().as_ref().is_some_and(|job| !job.finished);
+    if self.animating || exporting { ctx.request_repaint(); }
+    // the files are still watched while nothing moves
+    else if self.hot_reload { ctx.request_repaint_after(std::time::Duration::from_millis(configuration::FILE_WATCHER_POLL_INTERVAL_MS)); }
   }
 
   fn on_exit(&mut self, gl: Option<&glow::Context>) {
+    #[cfg(feature = "audio_playback")]
+    if let Some(player) = self.audio_player.take() { player.stop(); }
     if let Some(gl) = gl {
-      self.shader_toy_engine.lock().destroy(gl);
+      if let Ok(shader_toy_engine) = &self.shader_toy_engine { shader_toy_engine.lock().destroy(gl); }
     }
   }
 }
\ No newline at end of file
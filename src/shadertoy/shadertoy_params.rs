@@ -0,0 +1,255 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::path::PathBuf;
+use eframe::glow;
+use serde::{Deserialize, Serialize};
+use crate::do_log;
+use crate::settings::kuplung_logger;
+
+// Custom toy parameters, declared in the toy source as annotated uniforms:
+//   uniform float SEA_HEIGHT; // @param slider min=0.0 max=2.0 default=0.6
+//   uniform vec3 SEA_BASE; // @param color default=0.1,0.19,0.22
+//   uniform bool DITHERING; // @param default=true
+
+const PARAM_ANNOTATION: &str = "@param";
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ShaderToyParamWidget {
+  Slider,
+  Drag,
+  Color,
+  Checkbox,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum ShaderToyParamValue {
+  Float(f32),
+  Int(i32),
+  Bool(bool),
+  Vec2([f32; 2]),
+  Vec3([f32; 3]),
+  Vec4([f32; 4]),
+}
+
+impl ShaderToyParamValue {
+  fn zero(glsl_type: &str) -> Option<Self> {
+    match glsl_type {
+      "float" => Some(Self::Float(0.0)),
+      "int" => Some(Self::Int(0)),
+      "bool" => Some(Self::Bool(false)),
+      "vec2" => Some(Self::Vec2([0.0; 2])),
+      "vec3" => Some(Self::Vec3([0.0; 3])),
+      "vec4" => Some(Self::Vec4([0.0; 4])),
+      _ => None,
+    }
+  }
+
+  fn parse(glsl_type: &str, text: &str) -> Option<Self> {
+    let floats: Vec<f32> = text.split(',').filter_map(|v| v.trim().parse::<f32>().ok()).collect();
+    match glsl_type {
+      "float" => floats.first().map(|v| Self::Float(*v)),
+      "int" => text.trim().parse::<i32>().ok().map(Self::Int),
+      "bool" => text.trim().parse::<bool>().ok().map(Self::Bool),
+      "vec2" if floats.len() == 2 => Some(Self::Vec2([floats[0], floats[1]])),
+      "vec3" if floats.len() == 3 => Some(Self::Vec3([floats[0], floats[1], floats[2]])),
+      "vec4" if floats.len() == 4 => Some(Self::Vec4([floats[0], floats[1], floats[2], floats[3]])),
+      _ => None,
+    }
+  }
+
+  /// GL type enum as reported by glGetActiveUniform.
+  pub fn gl_type(&self) -> u32 {
+    match self {
+      Self::Float(_) => glow::FLOAT,
+      Self::Int(_) => glow::INT,
+      Self::Bool(_) => glow::BOOL,
+      Self::Vec2(_) => glow::FLOAT_VEC2,
+      Self::Vec3(_) => glow::FLOAT_VEC3,
+      Self::Vec4(_) => glow::FLOAT_VEC4,
+    }
+  }
+
+  fn same_kind(&self, other: &Self) -> bool {
+    std::mem::discriminant(self) == std::mem::discriminant(other)
+  }
+}
+
+#[derive(Clone, Debug)]
+pub struct ShaderToyParam {
+  pub name: String,
+  pub widget: ShaderToyParamWidget,
+  pub min: Option<f32>,
+  pub max: Option<f32>,
+  pub default: ShaderToyParamValue,
+  pub value: ShaderToyParamValue,
+}
+
+/// Collects the annotated uniforms from a toy source.
+pub fn parse_params(source: &str) -> Vec<ShaderToyParam> {
+  let mut params = Vec::new();
+  for line in source.lines() {
+    let Some((declaration, annotation)) = line.split_once("//") else { continue; };
+    let Some(annotation) = annotation.trim().strip_prefix(PARAM_ANNOTATION) else { continue; };
+    let tokens: Vec<&str> = declaration.trim().trim_end_matches(';').split_whitespace().collect();
+    if tokens.len() != 3 || tokens[0] != "uniform" {
+      do_log!("[Kuplung] [ShaderToy] [Params] Ignoring annotation on '{}' - expected 'uniform <type> <name>;'.", line.trim());
+      continue;
+    }
+    let (glsl_type, name) = (tokens[1], tokens[2]);
+    let Some(zero) = ShaderToyParamValue::zero(glsl_type) else {
+      do_log!("[Kuplung] [ShaderToy] [Params] Unsupported parameter type {} for {}.", glsl_type, name);
+      continue;
+    };
+
+    let mut param = ShaderToyParam {
+      name: name.to_string(),
+      widget: match zero {
+        ShaderToyParamValue::Bool(_) => ShaderToyParamWidget::Checkbox,
+        ShaderToyParamValue::Float(_) | ShaderToyParamValue::Int(_) => ShaderToyParamWidget::Slider,
+        _ => ShaderToyParamWidget::Drag,
+      },
+      min: None,
+      max: None,
+      default: zero,
+      value: zero,
+    };
+    for token in annotation.split_whitespace() {
+      match token.split_once('=') {
+        Some(("min", v)) => param.min = v.parse().ok(),
+        Some(("max", v)) => param.max = v.parse().ok(),
+        Some(("default", v)) => match ShaderToyParamValue::parse(glsl_type, v) {
+          Some(value) => param.default = value,
+          None => do_log!("[Kuplung] [ShaderToy] [Params] Invalid default '{}' for {} {}.", v, glsl_type, name),
+        },
+        None if token == "slider" => param.widget = ShaderToyParamWidget::Slider,
+        None if token == "drag" => param.widget = ShaderToyParamWidget::Drag,
+        None if token == "color" => param.widget = ShaderToyParamWidget::Color,
+        None if token == "checkbox" => param.widget = ShaderToyParamWidget::Checkbox,
+        _ => do_log!("[Kuplung] [ShaderToy] [Params] Unknown annotation '{}' for {}.", token, name),
+      }
+    }
+    param.value = param.default;
+    params.push(param);
+  }
+  params
+}
+
+/// Per-toy parameter values, persisted as JSON in the config folder.
+#[derive(Default)]
+pub struct ShaderToyParamStore {
+  file: PathBuf,
+  values: HashMap<String, HashMap<String, ShaderToyParamValue>>,
+}
+
+impl ShaderToyParamStore {
+  pub fn load(file: PathBuf) -> Self {
+    let values = match std::fs::read_to_string(&file) {
+      Ok(json) => serde_json::from_str(&json).unwrap_or_else(|e| {
+        do_log!("[Kuplung] [ShaderToy] [Params] Cannot parse {}: {}", file.display(), e);
+        HashMap::new()
+      }),
+      Err(_) => HashMap::new(),
+    };
+    Self { file, values }
+  }
+
+  pub fn save(&self) -> Result<(), Box<dyn Error>> {
+    if let Some(folder) = self.file.parent() { std::fs::create_dir_all(folder)?; }
+    std::fs::write(&self.file, serde_json::to_string_pretty(&self.values)?)?;
+    Ok(())
+  }
+
+  /// Replaces the defaults with the stored values for the toy.
+  pub fn apply(&self, toy: &str, params: &mut [ShaderToyParam]) {
+    let Some(stored) = self.values.get(toy) else { return; };
+    for param in params.iter_mut() {
+      if let Some(value) = stored.get(&param.name).filter(|v| v.same_kind(&param.default)) {
+        param.value = *value;
+      }
+    }
+  }
+
+  pub fn store(&mut self, toy: &str, params: &[ShaderToyParam]) {
+    let stored = self.values.entry(toy.to_string()).or_default();
+    stored.clear();
+    for param in params.iter().filter(|p| p.value != p.default) {
+      stored.insert(param.name.clone(), param.value);
+    }
+    if stored.is_empty() {
+      self.values.remove(toy);
+    }
+  }
+}
+
+pub fn draw_param(ui: &mut egui::Ui, param: &mut ShaderToyParam) -> egui::Response {
+  let (min, max) = (param.min.unwrap_or(0.0), param.max.unwrap_or(1.0));
+  let range = param.min.unwrap_or(f32::MIN)..=param.max.unwrap_or(f32::MAX);
+  match (&mut param.value, param.widget) {
+    (ShaderToyParamValue::Float(v), ShaderToyParamWidget::Slider) => ui.add(egui::Slider::new(v, min..=max)),
+    (ShaderToyParamValue::Float(v), _) => ui.add(egui::DragValue::new(v).speed(0.01).range(range)),
+    (ShaderToyParamValue::Int(v), ShaderToyParamWidget::Slider) => ui.add(egui::Slider::new(v, min as i32..=param.max.unwrap_or(100.0) as i32)),
+    (ShaderToyParamValue::Int(v), _) => ui.add(egui::DragValue::new(v).range(range)),
+    (ShaderToyParamValue::Bool(v), _) => ui.checkbox(v, ""),
+    (ShaderToyParamValue::Vec3(v), ShaderToyParamWidget::Color) => ui.color_edit_button_rgb(v),
+    (ShaderToyParamValue::Vec4(v), ShaderToyParamWidget::Color) => ui.color_edit_button_rgba_unmultiplied(v),
+    (ShaderToyParamValue::Vec2(v), _) => draw_components(ui, v, range),
+    (ShaderToyParamValue::Vec3(v), _) => draw_components(ui, v, range),
+    (ShaderToyParamValue::Vec4(v), _) => draw_components(ui, v, range),
+  }
+}
+
+fn draw_components(ui: &mut egui::Ui, values: &mut [f32], range: std::ops::RangeInclusive<f32>) -> egui::Response {
+  ui.horizontal(|ui| {
+    let mut response: Option<egui::Response> = None;
+    for v in values.iter_mut() {
+      let r = ui.add(egui::DragValue::new(v).speed(0.01).range(range.clone()));
+      response = Some(match response { Some(prev) => prev.union(r), None => r });
+    }
+    response.unwrap_or_else(|| ui.label(""))
+  }).inner
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn parses_the_annotations() {
+    let params = parse_params("uniform float SEA_HEIGHT; // @param slider min=0.0 max=2.0 default=0.6\nuniform vec3 SEA_BASE; // @param color default=0.1,0.19,0.22\nuniform bool DITHERING; // @param default=true\n");
+    assert_eq!(params.len(), 3);
+    assert_eq!(params[0].name, "SEA_HEIGHT");
+    assert_eq!(params[0].widget, ShaderToyParamWidget::Slider);
+    assert_eq!((params[0].min, params[0].max), (Some(0.0), Some(2.0)));
+    assert_eq!(params[0].value, ShaderToyParamValue::Float(0.6));
+    assert_eq!(params[1].widget, ShaderToyParamWidget::Color);
+    assert_eq!(params[1].default, ShaderToyParamValue::Vec3([0.1, 0.19, 0.22]));
+    assert_eq!(params[2].widget, ShaderToyParamWidget::Checkbox);
+    assert_eq!(params[2].value, ShaderToyParamValue::Bool(true));
+  }
+
+  #[test]
+  fn defaults_to_zero_and_the_widget_of_the_type() {
+    let params = parse_params("uniform int STEPS; // @param\nuniform vec2 OFFSET; // @param\nuniform vec4 TINT; // @param default=1,2\n");
+    assert_eq!(params.len(), 3);
+    assert_eq!((params[0].widget, params[0].value), (ShaderToyParamWidget::Slider, ShaderToyParamValue::Int(0)));
+    assert_eq!((params[1].widget, params[1].value), (ShaderToyParamWidget::Drag, ShaderToyParamValue::Vec2([0.0; 2])));
+    // a default with the wrong number of components keeps zero
+    assert_eq!(params[2].value, ShaderToyParamValue::Vec4([0.0; 4]));
+    assert_eq!((params[0].min, params[0].max), (None, None));
+  }
+
+  #[test]
+  fn skips_malformed_lines() {
+    let params = parse_params(concat!(
+      "float plain; // @param\n",
+      "uniform mat4 MATRIX; // @param\n",
+      "uniform float A, B; // @param\n",
+      "uniform float NOTE; // just a comment\n",
+      "uniform float SPEED; // @param bogus min=oops default=x\n",
+    ));
+    assert_eq!(params.len(), 1);
+    assert_eq!(params[0].name, "SPEED");
+    assert_eq!(params[0].min, None);
+    assert_eq!(params[0].value, ShaderToyParamValue::Float(0.0));
+  }
+}
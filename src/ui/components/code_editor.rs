@@ -1,97 +1,259 @@
-// ----------------------------------------------------------------------------
+use egui::text::{CCursor, CCursorRange};
+use egui::{Key, Modifiers, TextEdit, Ui};
+use crate::ui::components::glsl_syntax::{self, GlslTheme, SHADERTOY_UNIFORMS};
 
-#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
-#[cfg_attr(feature = "serde", serde(default))]
+/// Uniform completion popup state - the word being completed starts at `start` (char index).
+struct Completion {
+  start: usize,
+  candidates: Vec<usize>,
+  selected: usize,
+}
+
+/// GLSL source editor with highlighting, line numbers, bracket matching, find/replace and ShaderToy uniform completion.
 pub struct CodeEditor {
-  language: String,
-  code: String,
+  id: egui::Id,
+  show_find: bool,
+  find_text: String,
+  replace_text: String,
+  match_case: bool,
+  find_status: String,
+  completion: Option<Completion>,
+  scroll_to_cursor: bool,
 }
 
-impl Default for CodeEditor {
-  fn default() -> Self {
+impl CodeEditor {
+  pub fn new(id: &str) -> Self {
     Self {
-      language: "rs".into(),
-      code: "// A very simple example\n\
-fn main() {\n\
-\tprintln!(\"Hello world!\");\n\
-}\n\
-"
-        .into(),
+      id: egui::Id::new(id),
+      show_find: false,
+      find_text: "".to_string(),
+      replace_text: "".to_string(),
+      match_case: false,
+      find_status: "".to_string(),
+      completion: None,
+      scroll_to_cursor: false,
     }
   }
-}
-
-impl crate::Demo for CodeEditor {
-  fn name(&self) -> &'static str {
-    "🖮 Code Editor"
-  }
-
-  fn show(&mut self, ctx: &egui::Context, open: &mut bool) {
-    use crate::View as _;
-    egui::Window::new(self.name())
-      .open(open)
-      .default_height(500.0)
-      .show(ctx, |ui| self.ui(ui));
-  }
-}
 
-impl crate::View for CodeEditor {
-  fn ui(&mut self, ui: &mut egui::Ui) {
-    let Self { language, code } = self;
-
-    ui.horizontal(|ui| {
-      ui.set_height(0.0);
-      ui.label("An example of syntax highlighting in a TextEdit.");
-      ui.add(crate::egui_github_link_file!());
-    });
-
-    if cfg!(feature = "syntect") {
-      ui.horizontal(|ui| {
-        ui.label("Language:");
-        ui.text_edit_singleline(language);
-      });
-      ui.horizontal_wrapped(|ui| {
-        ui.spacing_mut().item_spacing.x = 0.0;
-        ui.label("Syntax highlighting powered by ");
-        ui.hyperlink_to("syntect", "https://github.com/trishume/syntect");
-        ui.label(".");
-      });
-    } else {
-      ui.horizontal_wrapped(|ui| {
-        ui.spacing_mut().item_spacing.x = 0.0;
-        ui.label("Compile the demo with the ");
-        ui.code("syntax_highlighting");
-        ui.label(" feature to enable more accurate syntax highlighting using ");
-        ui.hyperlink_to("syntect", "https://github.com/trishume/syntect");
-        ui.label(".");
-      });
+  pub fn show(&mut self, ui: &mut Ui, code: &mut String, max_height: f32) -> egui::Response {
+    let text_id = self.id.with("text");
+    let has_focus = ui.memory(|m| m.has_focus(text_id));
+    if has_focus && ui.input_mut(|i| i.consume_key(Modifiers::COMMAND, Key::F)) {
+      self.show_find = true;
+    }
+    if self.show_find {
+      self.render_find(ui, code, text_id);
     }
 
-    let mut theme = egui_extras::syntax_highlighting::CodeTheme::from_memory(ui.ctx());
-    ui.collapsing("Theme", |ui| {
-      ui.group(|ui| {
-        theme.ui(ui);
-        theme.clone().store_in_memory(ui.ctx());
-      });
-    });
+    if has_focus { self.handle_completion_keys(ui, code, text_id); }
+    else { self.completion = None; }
 
-    let mut layouter = |ui: &egui::Ui, string: &str, wrap_width: f32| {
-      let mut layout_job =
-        egui_extras::syntax_highlighting::highlight(ui.ctx(), &theme, string, language);
-      layout_job.wrap.max_width = wrap_width;
+    let font_id = egui::TextStyle::Monospace.resolve(ui.style());
+    let theme = GlslTheme::from_visuals(ui.visuals());
+    let cursor = TextEdit::load_state(ui.ctx(), text_id)
+      .and_then(|s| s.cursor.char_range())
+      .map(|r| char_to_byte(code, r.primary.index));
+    let mut layouter = |ui: &Ui, text: &str, _wrap_width: f32| {
+      let layout_job = glsl_syntax::highlight(text, font_id.clone(), &theme, cursor);
       ui.fonts(|f| f.layout_job(layout_job))
     };
 
-    egui::ScrollArea::vertical().show(ui, |ui| {
-      ui.add(
-        egui::TextEdit::multiline(code)
+    let line_count = code.split('\n').count();
+    let mut line_numbers = (1..=line_count).map(|n| n.to_string()).collect::<Vec<_>>().join("\n");
+    let numbers_width = ui.fonts(|f| f.glyph_width(&font_id, '0')) * (line_count.to_string().len() as f32 + 1.0);
+
+    let scroll = egui::ScrollArea::both().id_source(self.id.with("scroll")).max_height(max_height).show(ui, |ui| {
+      ui.horizontal_top(|ui| {
+        ui.spacing_mut().item_spacing.x = 0.0;
+        ui.add(
+          TextEdit::multiline(&mut line_numbers)
+            .id(self.id.with("line_numbers"))
+            .font(egui::TextStyle::Monospace)
+            .interactive(false)
+            .frame(false)
+            .desired_rows(10)
+            .desired_width(numbers_width)
+            .text_color(ui.visuals().weak_text_color()),
+        );
+        TextEdit::multiline(code)
+          .id(text_id)
           .font(egui::TextStyle::Monospace) // for cursor height
           .code_editor()
+          .frame(false)
           .desired_rows(10)
           .lock_focus(true)
           .desired_width(f32::INFINITY)
-          .layouter(&mut layouter),
-      );
+          .layouter(&mut layouter)
+          .show(ui)
+      }).inner
     });
+    let output = scroll.inner;
+
+    let cursor = output.cursor_range.map(|r| r.primary.ccursor.index);
+    if self.scroll_to_cursor {
+      self.scroll_to_cursor = false;
+      if let Some(cursor) = cursor {
+        let rect = output.galley.pos_from_ccursor(CCursor::new(cursor)).translate(output.galley_pos.to_vec2());
+        ui.scroll_to_rect(rect.expand(20.0), Some(egui::Align::Center));
+      }
+    }
+
+    if output.response.changed() {
+      self.completion = cursor.and_then(|c| Self::completion_at(code, c));
+    }
+    else if let (Some(completion), Some(cursor)) = (&self.completion, cursor) {
+      if cursor < completion.start || code.chars().skip(completion.start).take(cursor - completion.start).any(|c| !is_word_char(c)) {
+        self.completion = None;
+      }
+    }
+    if let (Some(cursor), true) = (cursor, self.completion.is_some() && has_focus) {
+      let position = output.galley_pos + output.galley.pos_from_ccursor(CCursor::new(cursor)).left_bottom().to_vec2();
+      self.render_completion(ui, code, text_id, position);
+    }
+    output.response
   }
+
+  /// Proposes the ShaderToy uniforms when the word before the cursor starts like one.
+  fn completion_at(code: &str, cursor: usize) -> Option<Completion> {
+    let before: Vec<char> = code.chars().take(cursor).collect();
+    let start = before.iter().rposition(|c| !is_word_char(*c)).map_or(0, |p| p + 1);
+    let prefix: String = before[start..].iter().collect();
+    if !prefix.starts_with('i') { return None; }
+    let candidates: Vec<usize> = SHADERTOY_UNIFORMS.iter().enumerate()
+      .filter(|(_, (name, _, _))| name.starts_with(prefix.as_str()) && *name != prefix)
+      .map(|(i, _)| i)
+      .collect();
+    (!candidates.is_empty()).then_some(Completion { start, candidates, selected: 0 })
+  }
+
+  /// The popup keys have to be taken before the text edit sees them.
+  fn handle_completion_keys(&mut self, ui: &mut Ui, code: &mut String, text_id: egui::Id) {
+    let Some(completion) = &mut self.completion else { return; };
+    let count = completion.candidates.len();
+    if ui.input_mut(|i| i.consume_key(Modifiers::NONE, Key::ArrowDown)) { completion.selected = (completion.selected + 1) % count; }
+    if ui.input_mut(|i| i.consume_key(Modifiers::NONE, Key::ArrowUp)) { completion.selected = (completion.selected + count - 1) % count; }
+    if ui.input_mut(|i| i.consume_key(Modifiers::NONE, Key::Escape)) {
+      self.completion = None;
+      return;
+    }
+    if ui.input_mut(|i| i.consume_key(Modifiers::NONE, Key::Tab) || i.consume_key(Modifiers::NONE, Key::Enter)) {
+      let candidate = completion.candidates[completion.selected];
+      self.accept_completion(ui, code, text_id, candidate);
+    }
+  }
+
+  fn accept_completion(&mut self, ui: &Ui, code: &mut String, text_id: egui::Id, candidate: usize) {
+    let Some(completion) = self.completion.take() else { return; };
+    let Some(mut state) = TextEdit::load_state(ui.ctx(), text_id) else { return; };
+    let Some(cursor) = state.cursor.char_range().map(|r| r.primary.index) else { return; };
+    let name = SHADERTOY_UNIFORMS[candidate].0;
+    let (from, to) = (char_to_byte(code, completion.start), char_to_byte(code, cursor));
+    code.replace_range(from..to, name);
+    state.cursor.set_char_range(Some(CCursorRange::one(CCursor::new(completion.start + name.chars().count()))));
+    state.store(ui.ctx(), text_id);
+  }
+
+  fn render_completion(&mut self, ui: &Ui, code: &mut String, text_id: egui::Id, position: egui::Pos2) {
+    let Some(completion) = &self.completion else { return; };
+    let mut clicked = None;
+    egui::Area::new(self.id.with("completion"))
+      .order(egui::Order::Foreground)
+      .fixed_pos(position)
+      .show(ui.ctx(), |ui| {
+        egui::Frame::popup(ui.style()).show(ui, |ui| {
+          for (i, candidate) in completion.candidates.iter().enumerate() {
+            let (name, glsl_type, description) = SHADERTOY_UNIFORMS[*candidate];
+            let label = egui::RichText::new(format!("{:<20}{:<11}{}", name, glsl_type, description)).monospace();
+            if ui.selectable_label(i == completion.selected, label).clicked() { clicked = Some(*candidate); }
+          }
+        });
+      });
+    if let Some(candidate) = clicked {
+      self.accept_completion(ui, code, text_id, candidate);
+      ui.memory_mut(|m| m.request_focus(text_id));
+    }
+  }
+
+  fn render_find(&mut self, ui: &mut Ui, code: &mut String, text_id: egui::Id) {
+    ui.horizontal(|ui| {
+      ui.label("Find");
+      let find = ui.add(TextEdit::singleline(&mut self.find_text).desired_width(140.0));
+      if find.lost_focus() && ui.input(|i| i.key_pressed(Key::Enter)) {
+        self.find_next(ui, code, text_id);
+        find.request_focus();
+      }
+      ui.label("Replace");
+      ui.add(TextEdit::singleline(&mut self.replace_text).desired_width(140.0));
+      ui.toggle_value(&mut self.match_case, "Aa").on_hover_text("Match case");
+      if ui.button("Next").clicked() { self.find_next(ui, code, text_id); }
+      if ui.button("Replace").clicked() { self.replace(ui, code, text_id); }
+      if ui.button("Replace all").clicked() { self.replace_all(code); }
+      ui.label(&self.find_status);
+      if ui.button("✖").on_hover_text("Close").clicked() { self.show_find = false; }
+    });
+  }
+
+  fn find_from(&self, code: &str, from: usize) -> Option<usize> {
+    if self.find_text.is_empty() { return None; }
+    let (haystack, needle) = if self.match_case { (code.to_string(), self.find_text.clone()) } else { (code.to_ascii_lowercase(), self.find_text.to_ascii_lowercase()) };
+    haystack.get(from..).and_then(|h| h.find(&needle)).map(|p| p + from).or_else(|| haystack.find(&needle))
+  }
+
+  /// Selects the next occurrence after the cursor, wrapping around.
+  fn find_next(&mut self, ui: &Ui, code: &str, text_id: egui::Id) {
+    let mut state = TextEdit::load_state(ui.ctx(), text_id).unwrap_or_default();
+    let from = state.cursor.char_range().map_or(0, |r| char_to_byte(code, r.sorted()[1].index));
+    match self.find_from(code, from) {
+      Some(start) => {
+        let end = start + self.find_text.len();
+        state.cursor.set_char_range(Some(CCursorRange::two(CCursor::new(byte_to_char(code, start)), CCursor::new(byte_to_char(code, end)))));
+        state.store(ui.ctx(), text_id);
+        ui.memory_mut(|m| m.request_focus(text_id));
+        self.scroll_to_cursor = true;
+        self.find_status = "".to_string();
+      }
+      None => self.find_status = "Not found".to_string(),
+    }
+  }
+
+  /// Replaces the selection when it is an occurrence, then moves to the next one.
+  fn replace(&mut self, ui: &Ui, code: &mut String, text_id: egui::Id) {
+    if let Some(mut state) = TextEdit::load_state(ui.ctx(), text_id) {
+      if let Some([start, end]) = state.cursor.char_range().map(|r| r.sorted()) {
+        let (from, to) = (char_to_byte(code, start.index), char_to_byte(code, end.index));
+        let selected = &code[from..to];
+        let matches = if self.match_case { selected == self.find_text } else { selected.eq_ignore_ascii_case(&self.find_text) };
+        if matches && !self.find_text.is_empty() {
+          code.replace_range(from..to, &self.replace_text);
+          let after = CCursor::new(start.index + self.replace_text.chars().count());
+          state.cursor.set_char_range(Some(CCursorRange::one(after)));
+          state.store(ui.ctx(), text_id);
+        }
+      }
+    }
+    self.find_next(ui, code, text_id);
+  }
+
+  fn replace_all(&mut self, code: &mut String) {
+    let mut count = 0;
+    let mut from = 0;
+    while let Some(start) = self.find_from(code, from).filter(|s| *s >= from) {
+      code.replace_range(start..start + self.find_text.len(), &self.replace_text);
+      from = start + self.replace_text.len();
+      count += 1;
+    }
+    self.find_status = format!("Replaced {}", count);
+  }
+}
+
+fn is_word_char(c: char) -> bool {
+  c.is_ascii_alphanumeric() || c == '_'
+}
+
+fn char_to_byte(text: &str, char_index: usize) -> usize {
+  text.char_indices().nth(char_index).map_or(text.len(), |(b, _)| b)
+}
+
+fn byte_to_char(text: &str, byte_index: usize) -> usize {
+  text[..byte_index].chars().count()
 }
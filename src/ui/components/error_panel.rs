@@ -0,0 +1,26 @@
+use egui::{Context, RichText, Ui};
+use crate::rendering::gl_error::GLError;
+
+/// Shown in place of a subsystem that could not create its GL resources.
+pub fn render_error_panel(ui: &mut Ui, subsystem: &str, error: &GLError) {
+  egui::Frame::group(ui.style()).show(ui, |ui| {
+    ui.label(RichText::new(format!("{} is not available", subsystem)).strong().color(ui.visuals().error_fg_color));
+    ui.label("The rest of Kuplung keeps running - check the log for details.");
+    ui.separator();
+    egui::ScrollArea::vertical().id_source(format!("error_panel_{}", subsystem)).max_height(200.0).show(ui, |ui| {
+      ui.label(RichText::new(error.to_string()).monospace());
+    });
+  });
+}
+
+/// Window for a subsystem that failed before it could open its own. Returns false when closed.
+pub fn render_error_window(ctx: &Context, subsystem: &str, error: &GLError) -> bool {
+  let mut open = true;
+  egui::Window::new(subsystem)
+    .id(egui::Id::new(format!("window_error_{}", subsystem)))
+    .open(&mut open)
+    .resizable(true)
+    .default_pos([80.0, 80.0])
+    .show(ctx, |ui| render_error_panel(ui, subsystem, error));
+  open
+}
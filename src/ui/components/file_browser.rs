@@ -0,0 +1,607 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::thread::JoinHandle;
+use std::time::Duration;
+use chrono::{DateTime, Local};
+use egui::{Context, Key, Ui};
+use crate::do_log;
+use crate::settings::{configuration, kuplung_logger, kuplung_settings};
+use crate::shadertoy::shadertoy_library;
+use crate::utils::asset_locator;
+
+const PREVIEW_SIZE: u32 = 256;
+const PREVIEW_MAX_FILE_SIZE: u64 = 64 * 1024 * 1024;
+const PREVIEW_TEXT_LINES: usize = 30;
+pub const IMAGE_EXTENSIONS: [&str; 7] = ["png", "jpg", "jpeg", "bmp", "tga", "gif", "hdr"];
+const TEXT_EXTENSIONS: [&str; 12] = ["stoy", "glsl", "frag", "vert", "geom", "tcs", "tes", "mtl", "txt", "yaml", "ini", "json"];
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum FileBrowserMode {
+  Open,
+  Save,
+  Folder,
+}
+
+struct FileFilter {
+  name: String,
+  /// Lowercase, without the dot. Empty for all files.
+  extensions: Vec<String>,
+}
+
+impl FileFilter {
+  fn matches(&self, path: &Path) -> bool {
+    self.extensions.is_empty() || extension(path).is_some_and(|e| self.extensions.contains(&e))
+  }
+}
+
+#[derive(Clone)]
+struct FileEntry {
+  path: PathBuf,
+  name: String,
+  is_folder: bool,
+  size: u64,
+  modified: String,
+}
+
+enum PreviewContent {
+  Image(egui::TextureHandle),
+  Text(String),
+  Nothing,
+}
+
+struct FilePreview {
+  info: Vec<String>,
+  content: PreviewContent,
+}
+
+/// What the preview thread reads - the image becomes a texture on the UI thread.
+enum PreviewData {
+  Image(egui::ColorImage),
+  Text(String),
+  Nothing,
+}
+
+struct PreviewJob {
+  path: PathBuf,
+  handle: JoinHandle<(Vec<String>, PreviewData)>,
+}
+
+/// A file picker window - folders, bookmarks, extension filters and a preview of the selected file.
+///
+/// Keep one per use, open it with `open` and call `show` every frame - it returns the picked path once.
+/// Arrow keys move the selection, Enter opens a folder or picks the file and Backspace goes to the parent folder.
+/// Previews are read on threads of their own and kept until the folder is left.
+pub struct FileBrowser {
+  id: String,
+  title: String,
+  mode: FileBrowserMode,
+  filters: Vec<FileFilter>,
+  filter: usize,
+  is_open: bool,
+  folder: PathBuf,
+  folder_text: String,
+  entries: Vec<FileEntry>,
+  selected: Option<usize>,
+  scroll_to_selected: bool,
+  file_name: String,
+  history: Vec<PathBuf>,
+  error: String,
+  preview: Option<PathBuf>,
+  previews: HashMap<PathBuf, FilePreview>,
+  preview_jobs: Vec<PreviewJob>,
+  bookmarks: Vec<String>,
+  show_hidden: bool,
+  window_size: [f32; 2],
+}
+
+impl FileBrowser {
+  pub fn new(id: &str, title: &str) -> Self {
+    Self {
+      id: id.to_string(),
+      title: title.to_string(),
+      mode: FileBrowserMode::Open,
+      filters: Vec::new(),
+      filter: 0,
+      is_open: false,
+      folder: PathBuf::new(),
+      folder_text: "".to_string(),
+      entries: Vec::new(),
+      selected: None,
+      scroll_to_selected: false,
+      file_name: "".to_string(),
+      history: Vec::new(),
+      error: "".to_string(),
+      preview: None,
+      previews: HashMap::new(),
+      preview_jobs: Vec::new(),
+      bookmarks: Vec::new(),
+      show_hidden: false,
+      window_size: [0.0, 0.0],
+    }
+  }
+
+  pub fn mode(mut self, mode: FileBrowserMode) -> Self {
+    self.mode = mode;
+    self
+  }
+
+  /// Adds an extension filter, the first one added is selected. An "All files" filter is always available.
+  pub fn filter(mut self, name: &str, extensions: &[&str]) -> Self {
+    self.filters.push(FileFilter { name: name.to_string(), extensions: extensions.iter().map(|e| e.to_lowercase()).collect() });
+    self
+  }
+
+  /// Opens the browser in the folder of the last picked file, the current folder from the settings or the working directory.
+  pub fn open(&mut self) {
+    if self.filters.last().is_none_or(|f| !f.extensions.is_empty()) {
+      self.filters.push(FileFilter { name: "All files".to_string(), extensions: Vec::new() });
+    }
+    let settings = kuplung_settings::get();
+    self.bookmarks = settings.file_browser.bookmarks;
+    self.show_hidden = settings.file_browser.show_hidden;
+    self.window_size = [settings.app_window.file_browser_width, settings.app_window.file_browser_height];
+    let current_folder = PathBuf::from(settings.app.current_folder);
+    let folder = [self.folder.clone(), current_folder].into_iter()
+      .find(|f| f.is_dir())
+      .unwrap_or_else(|| std::env::current_dir().unwrap_or_default());
+    self.is_open = true;
+    self.history.clear();
+    self.navigate(folder);
+  }
+
+  pub fn is_open(&self) -> bool {
+    self.is_open
+  }
+
+  pub fn close(&mut self) {
+    self.is_open = false;
+    self.preview = None;
+    self.previews.clear();
+  }
+
+  /// Shows the window when open. Returns the picked file (or folder) once.
+  pub fn show(&mut self, ctx: &Context) -> Option<PathBuf> {
+    if !self.is_open { return None; }
+    self.poll_previews(ctx);
+    let mut open = true;
+    let mut picked = None;
+    egui::Window::new(&self.title)
+      .id(egui::Id::new(&self.id))
+      .open(&mut open)
+      .resizable(true)
+      .collapsible(false)
+      .default_size(self.window_size)
+      .show(ctx, |ui| {
+        picked = self.handle_keys(ui);
+        self.render_address_bar(ui);
+        ui.separator();
+        let bottom_height = 60.0;
+        ui.horizontal_top(|ui| {
+          let height = (ui.available_height() - bottom_height).max(120.0);
+          ui.vertical(|ui| {
+            ui.set_width(120.0);
+            ui.set_height(height);
+            self.render_bookmarks(ui);
+          });
+          ui.separator();
+          ui.vertical(|ui| {
+            ui.set_width((ui.available_width() - PREVIEW_SIZE as f32 * 0.75 - 10.0).max(150.0));
+            ui.set_height(height);
+            if let Some(path) = self.render_entries(ui) { picked = Some(path); }
+          });
+          ui.separator();
+          ui.vertical(|ui| {
+            ui.set_height(height);
+            self.render_preview(ui);
+          });
+        });
+        ui.separator();
+        if let Some(path) = self.render_bottom_bar(ui) { picked = Some(path); }
+      });
+    if !open { self.close(); }
+    if let Some(path) = &picked {
+      do_log!("[Kuplung] [UI] [FileBrowser] Picked {}.", path.display());
+      self.remember_folder();
+      self.close();
+    }
+    picked
+  }
+
+  fn navigate(&mut self, folder: PathBuf) {
+    let folder = folder.canonicalize().unwrap_or(folder);
+    if self.folder != folder && self.folder.is_dir() { self.history.push(self.folder.clone()); }
+    self.folder_text = folder.display().to_string();
+    self.folder = folder;
+    self.refresh();
+  }
+
+  fn refresh(&mut self) {
+    self.entries.clear();
+    self.selected = None;
+    self.preview = None;
+    self.previews.clear();
+    self.error = "".to_string();
+    let dir = match std::fs::read_dir(&self.folder) {
+      Ok(dir) => dir,
+      Err(e) => {
+        self.error = format!("Cannot read {}: {}", self.folder.display(), e);
+        return;
+      }
+    };
+    for entry in dir.filter_map(|e| e.ok()) {
+      let path = entry.path();
+      let name = entry.file_name().to_string_lossy().to_string();
+      if !self.show_hidden && name.starts_with('.') { continue; }
+      let metadata = entry.metadata().ok();
+      let is_folder = path.is_dir();
+      if !is_folder && (self.mode == FileBrowserMode::Folder || !self.filters[self.filter].matches(&path)) { continue; }
+      self.entries.push(FileEntry {
+        path,
+        name,
+        is_folder,
+        size: metadata.as_ref().map_or(0, |m| m.len()),
+        modified: metadata.and_then(|m| m.modified().ok()).map_or("".to_string(), |t| DateTime::<Local>::from(t).format("%Y-%m-%d %H:%M").to_string()),
+      });
+    }
+    self.entries.sort_by_key(|e| (!e.is_folder, e.name.to_lowercase()));
+  }
+
+  fn select(&mut self, index: Option<usize>) {
+    self.selected = index;
+    self.scroll_to_selected = true;
+    let Some(entry) = index.and_then(|i| self.entries.get(i)) else { return; };
+    if !entry.is_folder && self.mode == FileBrowserMode::Save { self.file_name = entry.name.clone(); }
+    self.preview = Some(entry.path.clone());
+    if self.previews.contains_key(&entry.path) || self.preview_jobs.iter().any(|j| j.path == entry.path) { return; }
+    let job_entry = entry.clone();
+    match std::thread::Builder::new().name("kuplung-preview".to_string()).spawn(move || read_preview(&job_entry)) {
+      Ok(handle) => self.preview_jobs.push(PreviewJob { path: entry.path.clone(), handle }),
+      Err(e) => do_log!("[Kuplung] [UI] [FileBrowser] Cannot start the preview of {}: {}", entry.path.display(), e),
+    }
+  }
+
+  /// Keeps the previews of the finished threads, repaints until the others are done.
+  fn poll_previews(&mut self, ctx: &Context) {
+    if self.preview_jobs.is_empty() { return; }
+    let (finished, pending): (Vec<PreviewJob>, Vec<PreviewJob>) = std::mem::take(&mut self.preview_jobs).into_iter().partition(|j| j.handle.is_finished());
+    self.preview_jobs = pending;
+    for job in finished {
+      // the folder was left while the thread ran
+      if job.path.parent() != Some(self.folder.as_path()) { continue; }
+      let (info, data) = job.handle.join().unwrap_or_else(|_| (vec!["The preview thread crashed.".to_string()], PreviewData::Nothing));
+      let content = match data {
+        PreviewData::Image(image) => PreviewContent::Image(ctx.load_texture(format!("{}_thumbnail_{}", self.id, job.path.display()), image, egui::TextureOptions::LINEAR)),
+        PreviewData::Text(text) => PreviewContent::Text(text),
+        PreviewData::Nothing => PreviewContent::Nothing,
+      };
+      self.previews.insert(job.path, FilePreview { info, content });
+    }
+    if !self.preview_jobs.is_empty() { ctx.request_repaint_after(Duration::from_millis(50)); }
+  }
+
+  /// Enter on a folder opens it, on a file picks it.
+  fn activate(&mut self, index: usize) -> Option<PathBuf> {
+    let entry = self.entries.get(index)?;
+    if entry.is_folder {
+      let folder = entry.path.clone();
+      self.navigate(folder);
+      None
+    }
+    else if self.mode == FileBrowserMode::Open {
+      Some(entry.path.clone())
+    }
+    else {
+      self.confirm()
+    }
+  }
+
+  fn confirm(&mut self) -> Option<PathBuf> {
+    match self.mode {
+      FileBrowserMode::Open => self.selected.and_then(|i| self.entries.get(i)).filter(|e| !e.is_folder).map(|e| e.path.clone()),
+      FileBrowserMode::Folder => Some(self.selected.and_then(|i| self.entries.get(i)).filter(|e| e.is_folder).map_or(self.folder.clone(), |e| e.path.clone())),
+      FileBrowserMode::Save => {
+        let name = self.file_name.trim();
+        if name.is_empty() { return None; }
+        let mut path = self.folder.join(name);
+        let filter = &self.filters[self.filter];
+        if let Some(default_extension) = filter.extensions.first().filter(|_| !filter.matches(&path)) {
+          path.set_extension(default_extension);
+        }
+        Some(path)
+      }
+    }
+  }
+
+  fn handle_keys(&mut self, ui: &Ui) -> Option<PathBuf> {
+    // keys belong to the text fields while one is focused
+    if ui.ctx().memory(|m| m.focused().is_some()) || self.entries.is_empty() && !ui.input(|i| i.key_pressed(Key::Backspace)) { return None; }
+    let last = self.entries.len().saturating_sub(1);
+    let (up, down, home, end, enter, back) = ui.input_mut(|i| (
+      i.consume_key(egui::Modifiers::NONE, Key::ArrowUp),
+      i.consume_key(egui::Modifiers::NONE, Key::ArrowDown),
+      i.consume_key(egui::Modifiers::NONE, Key::Home),
+      i.consume_key(egui::Modifiers::NONE, Key::End),
+      i.consume_key(egui::Modifiers::NONE, Key::Enter),
+      i.consume_key(egui::Modifiers::NONE, Key::Backspace),
+    ));
+    if up { self.select(Some(self.selected.map_or(last, |s| s.saturating_sub(1)))); }
+    if down { self.select(Some(self.selected.map_or(0, |s| (s + 1).min(last)))); }
+    if home { self.select(Some(0)); }
+    if end { self.select(Some(last)); }
+    if back { self.go_up(); }
+    if enter {
+      return match self.selected {
+        Some(index) => self.activate(index),
+        None => self.confirm(),
+      };
+    }
+    None
+  }
+
+  fn go_up(&mut self) {
+    if let Some(parent) = self.folder.parent().map(|p| p.to_path_buf()) { self.navigate(parent); }
+  }
+
+  fn render_address_bar(&mut self, ui: &mut Ui) {
+    ui.horizontal(|ui| {
+      if ui.add_enabled(!self.history.is_empty(), egui::Button::new("⬅")).on_hover_text("Back").clicked() {
+        if let Some(folder) = self.history.pop() {
+          self.navigate(folder);
+          self.history.pop();
+        }
+      }
+      if ui.button("⬆").on_hover_text("Parent folder (Backspace)").clicked() { self.go_up(); }
+      if ui.button("⟳").on_hover_text("Refresh").clicked() { self.refresh(); }
+      let response = ui.add(egui::TextEdit::singleline(&mut self.folder_text).desired_width(f32::INFINITY));
+      if response.lost_focus() && ui.input(|i| i.key_pressed(Key::Enter)) {
+        let folder = PathBuf::from(self.folder_text.trim());
+        if folder.is_dir() { self.navigate(folder); }
+        else { self.error = format!("{} is not a folder.", folder.display()); }
+      }
+    });
+  }
+
+  fn render_bookmarks(&mut self, ui: &mut Ui) {
+    let mut places: Vec<(String, PathBuf)> = Vec::new();
+    if let Some(home) = std::env::var_os("HOME").or_else(|| std::env::var_os("USERPROFILE")) {
+      places.push(("🏠 Home".to_string(), PathBuf::from(home)));
+    }
+    if let Ok(cwd) = std::env::current_dir() { places.push(("🖥 Kuplung".to_string(), cwd)); }
+    if let Ok(assets) = asset_locator::resolve(configuration::ASSETS_FOLDER) { places.push(("📦 Assets".to_string(), assets)); }
+    let mut navigate_to = None;
+    egui::ScrollArea::vertical().id_source(format!("{}_bookmarks", self.id)).show(ui, |ui| {
+      for (name, folder) in &places {
+        if ui.selectable_label(self.folder == *folder, name).on_hover_text(folder.display().to_string()).clicked() { navigate_to = Some(folder.clone()); }
+      }
+      ui.separator();
+      let mut remove = None;
+      for bookmark in &self.bookmarks {
+        let folder = PathBuf::from(bookmark);
+        let name = folder.file_name().map_or(bookmark.clone(), |n| n.to_string_lossy().to_string());
+        let response = ui.selectable_label(self.folder == folder, format!("⭐ {}", name)).on_hover_text(bookmark);
+        if response.clicked() { navigate_to = Some(folder.clone()); }
+        response.context_menu(|ui| {
+          if ui.button("Remove bookmark").clicked() {
+            remove = Some(bookmark.clone());
+            ui.close_menu();
+          }
+        });
+      }
+      let current = self.folder.display().to_string();
+      let bookmarked = self.bookmarks.contains(&current);
+      let mut changed = false;
+      if ui.add_enabled(!bookmarked, egui::Button::new("☆ Add bookmark")).on_hover_text("Bookmark the current folder").clicked() {
+        self.bookmarks.push(current);
+        changed = true;
+      }
+      if let Some(bookmark) = remove {
+        self.bookmarks.retain(|b| *b != bookmark);
+        changed = true;
+      }
+      if ui.checkbox(&mut self.show_hidden, "Hidden files").changed() {
+        changed = true;
+        navigate_to = Some(self.folder.clone());
+      }
+      if changed { self.save_bookmarks(); }
+    });
+    if let Some(folder) = navigate_to {
+      if folder == self.folder { self.refresh(); } else { self.navigate(folder); }
+    }
+  }
+
+  fn render_entries(&mut self, ui: &mut Ui) -> Option<PathBuf> {
+    let mut clicked = None;
+    let mut activated = None;
+    egui::ScrollArea::vertical().id_source(format!("{}_entries", self.id)).auto_shrink([false, false]).show(ui, |ui| {
+      if !self.error.is_empty() { ui.colored_label(ui.visuals().error_fg_color, &self.error); }
+      egui::Grid::new(format!("{}_grid", self.id)).num_columns(3).striped(true).show(ui, |ui| {
+        for (index, entry) in self.entries.iter().enumerate() {
+          let icon = if entry.is_folder { "🗀" } else { "🗋" };
+          let response = ui.selectable_label(self.selected == Some(index), format!("{} {}", icon, entry.name));
+          if response.clicked() { clicked = Some(index); }
+          if response.double_clicked() { activated = Some(index); }
+          if self.scroll_to_selected && self.selected == Some(index) { response.scroll_to_me(None); }
+          ui.label(if entry.is_folder { "".to_string() } else { format_size(entry.size) });
+          ui.label(&entry.modified);
+          ui.end_row();
+        }
+      });
+    });
+    self.scroll_to_selected = false;
+    if let Some(index) = clicked { self.select(Some(index)); }
+    activated.and_then(|index| self.activate(index))
+  }
+
+  fn render_preview(&mut self, ui: &mut Ui) {
+    let Some(path) = &self.preview else {
+      ui.label("No preview");
+      return;
+    };
+    let Some(preview) = self.previews.get(path) else {
+      ui.label("Loading the preview...");
+      return;
+    };
+    egui::ScrollArea::vertical().id_source(format!("{}_preview", self.id)).show(ui, |ui| {
+      for line in &preview.info {
+        ui.label(line);
+      }
+      match &preview.content {
+        PreviewContent::Image(texture) => {
+          let size = texture.size_vec2();
+          let scale = (PREVIEW_SIZE as f32 * 0.75 / size.x.max(size.y)).min(1.0);
+          ui.image((texture.id(), size * scale));
+        }
+        PreviewContent::Text(text) => {
+          ui.add(egui::Label::new(egui::RichText::new(text).monospace().small()).wrap_mode(egui::TextWrapMode::Extend));
+        }
+        PreviewContent::Nothing => {}
+      }
+    });
+  }
+
+  fn render_bottom_bar(&mut self, ui: &mut Ui) -> Option<PathBuf> {
+    let mut picked = None;
+    ui.horizontal(|ui| {
+      if self.mode == FileBrowserMode::Save {
+        ui.label("File name");
+        ui.add(egui::TextEdit::singleline(&mut self.file_name).desired_width(200.0));
+      }
+      if self.mode != FileBrowserMode::Folder {
+        let previous = self.filter;
+        egui::ComboBox::from_id_source(format!("{}_filter", self.id))
+          .selected_text(filter_label(&self.filters[self.filter]))
+          .show_ui(ui, |ui| {
+            for (index, filter) in self.filters.iter().enumerate() {
+              ui.selectable_value(&mut self.filter, index, filter_label(filter));
+            }
+          });
+        if self.filter != previous { self.refresh(); }
+      }
+    });
+    ui.horizontal(|ui| {
+      let label = match self.mode {
+        FileBrowserMode::Open => "Open",
+        FileBrowserMode::Save => "Save",
+        FileBrowserMode::Folder => "Choose folder",
+      };
+      if ui.button(label).clicked() { picked = self.confirm(); }
+      if ui.button("Cancel").clicked() { self.close(); }
+    });
+    picked
+  }
+
+  /// The bookmarks and the hidden files switch are shared by every file browser.
+  fn save_bookmarks(&mut self) {
+    let mut settings = kuplung_settings::get();
+    settings.file_browser.bookmarks = self.bookmarks.clone();
+    settings.file_browser.show_hidden = self.show_hidden;
+    if let Err(e) = kuplung_settings::set(settings) { self.error = format!("Cannot save the bookmarks: {}", e); }
+  }
+
+  /// The folder of the picked file is where the next file browser starts.
+  fn remember_folder(&self) {
+    let mut settings = kuplung_settings::get();
+    let folder = self.folder.display().to_string();
+    if settings.app.current_folder == folder { return; }
+    settings.app.current_folder = folder;
+    if let Err(e) = kuplung_settings::set(settings) {
+      do_log!("[Kuplung] [UI] [FileBrowser] Cannot save the current folder: {}", e);
+    }
+  }
+}
+
+fn extension(path: &Path) -> Option<String> {
+  path.extension().map(|e| e.to_string_lossy().to_lowercase())
+}
+
+fn filter_label(filter: &FileFilter) -> String {
+  if filter.extensions.is_empty() { return filter.name.clone(); }
+  format!("{} ({})", filter.name, filter.extensions.iter().map(|e| format!("*.{}", e)).collect::<Vec<_>>().join(", "))
+}
+
+fn format_size(size: u64) -> String {
+  match size {
+    s if s >= 1024 * 1024 * 1024 => format!("{:.1} GB", s as f64 / (1024.0 * 1024.0 * 1024.0)),
+    s if s >= 1024 * 1024 => format!("{:.1} MB", s as f64 / (1024.0 * 1024.0)),
+    s if s >= 1024 => format!("{:.1} KB", s as f64 / 1024.0),
+    s => format!("{} B", s),
+  }
+}
+
+/// The lines about the file and its content, runs on a preview thread.
+fn read_preview(entry: &FileEntry) -> (Vec<String>, PreviewData) {
+  let mut info = vec![entry.name.clone()];
+  if entry.is_folder {
+    let count = std::fs::read_dir(&entry.path).map_or(0, |d| d.count());
+    info.push(format!("Folder, {} items", count));
+    return (info, PreviewData::Nothing);
+  }
+  info.push(format!("{}, modified {}", format_size(entry.size), entry.modified));
+  if entry.size > PREVIEW_MAX_FILE_SIZE { return (info, PreviewData::Nothing); }
+  let mut content = PreviewData::Nothing;
+  let extension = extension(&entry.path).unwrap_or_default();
+  if IMAGE_EXTENSIONS.contains(&extension.as_str()) {
+    match image::open(&entry.path) {
+      Ok(image) => {
+        info.push(format!("{} x {}", image.width(), image.height()));
+        let thumbnail = image.thumbnail(PREVIEW_SIZE, PREVIEW_SIZE).to_rgba8();
+        let size = [thumbnail.width() as usize, thumbnail.height() as usize];
+        content = PreviewData::Image(egui::ColorImage::from_rgba_unmultiplied(size, thumbnail.as_raw()));
+      }
+      Err(e) => info.push(format!("Cannot read the image: {}", e)),
+    }
+  }
+  else if extension == "obj" {
+    match std::fs::read_to_string(&entry.path) {
+      Ok(obj) => info.extend(obj_stats(&obj)),
+      Err(e) => info.push(format!("Cannot read the file: {}", e)),
+    }
+  }
+  else if TEXT_EXTENSIONS.contains(&extension.as_str()) {
+    match std::fs::read_to_string(&entry.path) {
+      Ok(text) => {
+        // ShaderToy exports show the toy name and its image pass
+        let toy = (extension == "json").then(|| shadertoy_library::parse_shadertoy_export(&text).ok()).flatten();
+        let source = match toy.as_ref().and_then(|t| t.first()) {
+          Some(shader) => {
+            info.push(format!("ShaderToy: {} by {}", shader.info.name, shader.info.username));
+            shader.image_source().unwrap_or_default()
+          }
+          None => text,
+        };
+        content = PreviewData::Text(source.lines().take(PREVIEW_TEXT_LINES).collect::<Vec<_>>().join("\n"));
+      }
+      Err(e) => info.push(format!("Cannot read the file: {}", e)),
+    }
+  }
+  (info, content)
+}
+
+/// Vertex, face and material counts of an OBJ file.
+fn obj_stats(obj: &str) -> Vec<String> {
+  let (mut vertices, mut uvs, mut normals, mut faces, mut objects) = (0, 0, 0, 0, 0);
+  let mut materials: Vec<&str> = Vec::new();
+  let mut libraries: Vec<&str> = Vec::new();
+  for line in obj.lines() {
+    let mut parts = line.split_whitespace();
+    match parts.next() {
+      Some("v") => vertices += 1,
+      Some("vt") => uvs += 1,
+      Some("vn") => normals += 1,
+      Some("f") => faces += 1,
+      Some("o") | Some("g") => objects += 1,
+      Some("usemtl") => if let Some(m) = parts.next() { if !materials.contains(&m) { materials.push(m); } },
+      Some("mtllib") => libraries.extend(parts),
+      _ => {}
+    }
+  }
+  let mut stats = vec![
+    format!("Vertices: {}", vertices),
+    format!("UVs: {}, normals: {}", uvs, normals),
+    format!("Faces: {}", faces),
+    format!("Objects / groups: {}", objects),
+    format!("Materials: {}", materials.len()),
+  ];
+  if !libraries.is_empty() { stats.push(format!("Material libraries: {}", libraries.join(", "))); }
+  stats
+}
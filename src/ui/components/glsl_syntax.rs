@@ -0,0 +1,305 @@
+use std::ops::Range;
+use egui::text::{LayoutJob, TextFormat};
+use egui::{Color32, FontId};
+
+const GLSL_KEYWORDS: &[&str] = &[
+  "attribute", "const", "uniform", "varying", "buffer", "shared", "layout", "centroid", "flat", "smooth", "noperspective",
+  "patch", "sample", "subroutine", "in", "out", "inout", "invariant", "precise", "precision", "highp", "mediump", "lowp",
+  "break", "continue", "do", "for", "while", "switch", "case", "default", "if", "else", "discard", "return", "struct",
+  "true", "false", "coherent", "volatile", "restrict", "readonly", "writeonly",
+];
+
+const GLSL_TYPES: &[&str] = &[
+  "void", "bool", "int", "uint", "float", "double",
+  "vec2", "vec3", "vec4", "ivec2", "ivec3", "ivec4", "uvec2", "uvec3", "uvec4", "bvec2", "bvec3", "bvec4", "dvec2", "dvec3", "dvec4",
+  "mat2", "mat3", "mat4", "mat2x2", "mat2x3", "mat2x4", "mat3x2", "mat3x3", "mat3x4", "mat4x2", "mat4x3", "mat4x4",
+  "sampler1D", "sampler2D", "sampler3D", "samplerCube", "sampler2DShadow", "samplerCubeShadow", "sampler2DArray", "sampler2DMS",
+  "isampler2D", "usampler2D", "image2D",
+];
+
+const GLSL_BUILTINS: &[&str] = &[
+  "radians", "degrees", "sin", "cos", "tan", "asin", "acos", "atan", "sinh", "cosh", "tanh", "asinh", "acosh", "atanh",
+  "pow", "exp", "log", "exp2", "log2", "sqrt", "inversesqrt", "abs", "sign", "floor", "trunc", "round", "roundEven", "ceil",
+  "fract", "mod", "modf", "min", "max", "clamp", "mix", "step", "smoothstep", "isnan", "isinf", "fma",
+  "length", "distance", "dot", "cross", "normalize", "faceforward", "reflect", "refract",
+  "matrixCompMult", "outerProduct", "transpose", "determinant", "inverse",
+  "lessThan", "lessThanEqual", "greaterThan", "greaterThanEqual", "equal", "notEqual", "any", "all", "not",
+  "texture", "textureLod", "textureProj", "textureGrad", "textureOffset", "textureSize", "texelFetch", "texture2D", "textureCube",
+  "dFdx", "dFdy", "fwidth", "floatBitsToInt", "floatBitsToUint", "intBitsToFloat", "uintBitsToFloat",
+  "packUnorm2x16", "unpackUnorm2x16", "packHalf2x16", "unpackHalf2x16",
+  "gl_FragCoord", "gl_FragColor", "gl_FragDepth", "gl_Position", "gl_VertexID", "gl_InstanceID", "gl_FrontFacing", "gl_PointCoord",
+];
+
+/// The inputs ShaderToy provides to every toy - name, GLSL type and description.
+pub const SHADERTOY_UNIFORMS: &[(&str, &str, &str)] = &[
+  ("iResolution", "vec3", "viewport resolution in pixels"),
+  ("iTime", "float", "playback time in seconds"),
+  ("iGlobalTime", "float", "playback time in seconds (legacy name)"),
+  ("iTimeDelta", "float", "render time of the last frame in seconds"),
+  ("iFrame", "int", "playback frame"),
+  ("iFrameRate", "int", "frames per second"),
+  ("iChannelTime", "float[4]", "channel playback time in seconds"),
+  ("iChannelResolution", "vec3[4]", "channel resolution in pixels"),
+  ("iMouse", "vec4", "mouse pixel coordinates - xy current, zw click"),
+  ("iDate", "vec4", "year, month, day, time in seconds"),
+  ("iChannel0", "sampler2D", "input channel 0"),
+  ("iChannel1", "sampler2D", "input channel 1"),
+  ("iChannel2", "sampler2D", "input channel 2"),
+  ("iChannel3", "sampler2D", "input channel 3"),
+];
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum GlslToken {
+  Comment,
+  Preprocessor,
+  Keyword,
+  Type,
+  Builtin,
+  ShaderToyUniform,
+  Number,
+  Punctuation,
+  Identifier,
+  Whitespace,
+}
+
+/// Splits GLSL source into tokens, as byte ranges into the source.
+pub fn tokenize(text: &str) -> Vec<(GlslToken, Range<usize>)> {
+  let bytes = text.as_bytes();
+  let mut tokens = Vec::new();
+  let mut line_start = true;
+  let mut i = 0;
+  while i < bytes.len() {
+    let start = i;
+    let c = bytes[i];
+    let token = if c == b'/' && bytes.get(i + 1) == Some(&b'/') {
+      i = find_line_end(bytes, i);
+      GlslToken::Comment
+    }
+    else if c == b'/' && bytes.get(i + 1) == Some(&b'*') {
+      i = text[i + 2..].find("*/").map_or(bytes.len(), |end| i + 2 + end + 2);
+      GlslToken::Comment
+    }
+    else if c == b'#' && line_start {
+      i = find_line_end(bytes, i);
+      GlslToken::Preprocessor
+    }
+    else if c.is_ascii_whitespace() {
+      while i < bytes.len() && bytes[i].is_ascii_whitespace() { i += 1; }
+      GlslToken::Whitespace
+    }
+    else if c.is_ascii_digit() || (c == b'.' && bytes.get(i + 1).is_some_and(|b| b.is_ascii_digit())) {
+      while i < bytes.len() && (bytes[i].is_ascii_alphanumeric() || bytes[i] == b'.' || ((bytes[i] == b'-' || bytes[i] == b'+') && matches!(bytes[i - 1], b'e' | b'E'))) { i += 1; }
+      GlslToken::Number
+    }
+    else if c.is_ascii_alphabetic() || c == b'_' {
+      while i < bytes.len() && (bytes[i].is_ascii_alphanumeric() || bytes[i] == b'_') { i += 1; }
+      classify_word(&text[start..i])
+    }
+    else {
+      // a single character, which might be multi-byte
+      i += text[i..].chars().next().map_or(1, |ch| ch.len_utf8());
+      GlslToken::Punctuation
+    };
+    if token == GlslToken::Whitespace {
+      if text[start..i].contains('\n') { line_start = true; }
+    }
+    else if token != GlslToken::Comment {
+      line_start = false;
+    }
+    tokens.push((token, start..i));
+  }
+  tokens
+}
+
+fn classify_word(word: &str) -> GlslToken {
+  if GLSL_KEYWORDS.contains(&word) { GlslToken::Keyword }
+  else if GLSL_TYPES.contains(&word) { GlslToken::Type }
+  else if GLSL_BUILTINS.contains(&word) { GlslToken::Builtin }
+  else if SHADERTOY_UNIFORMS.iter().any(|(name, _, _)| *name == word) { GlslToken::ShaderToyUniform }
+  else { GlslToken::Identifier }
+}
+
+/// End of the line, following backslash line continuations.
+fn find_line_end(bytes: &[u8], mut i: usize) -> usize {
+  while i < bytes.len() && bytes[i] != b'\n' {
+    if bytes[i] == b'\\' && bytes.get(i + 1) == Some(&b'\n') { i += 1; }
+    i += 1;
+  }
+  i
+}
+
+/// Byte positions of the bracket at (or just before) the cursor and its match, skipping comments and directives.
+pub fn matching_brackets(text: &str, tokens: &[(GlslToken, Range<usize>)], cursor: usize) -> Option<(usize, usize)> {
+  let brackets: Vec<(usize, u8)> = tokens.iter()
+    .filter(|(t, r)| *t == GlslToken::Punctuation && r.len() == 1 && b"()[]{}".contains(&text.as_bytes()[r.start]))
+    .map(|(_, r)| (r.start, text.as_bytes()[r.start]))
+    .collect();
+  let index = brackets.iter().position(|(p, _)| *p == cursor)
+    .or_else(|| brackets.iter().position(|(p, _)| *p + 1 == cursor))?;
+  let (position, bracket) = brackets[index];
+  let (open, close, forward) = match bracket {
+    b'(' => (b'(', b')', true),
+    b'[' => (b'[', b']', true),
+    b'{' => (b'{', b'}', true),
+    b')' => (b'(', b')', false),
+    b']' => (b'[', b']', false),
+    _ => (b'{', b'}', false),
+  };
+  let mut depth = 0;
+  let candidates: Box<dyn Iterator<Item = &(usize, u8)>> = if forward { Box::new(brackets[index..].iter()) } else { Box::new(brackets[..=index].iter().rev()) };
+  for (p, b) in candidates {
+    if *b == open { depth += if forward { 1 } else { -1 }; }
+    if *b == close { depth += if forward { -1 } else { 1 }; }
+    if depth == 0 { return Some((position, *p)); }
+  }
+  None
+}
+
+pub struct GlslTheme {
+  pub text: Color32,
+  pub comment: Color32,
+  pub preprocessor: Color32,
+  pub keyword: Color32,
+  pub glsl_type: Color32,
+  pub builtin: Color32,
+  pub uniform: Color32,
+  pub number: Color32,
+  pub punctuation: Color32,
+  pub bracket_match: Color32,
+}
+
+impl GlslTheme {
+  pub fn from_visuals(visuals: &egui::Visuals) -> Self {
+    if visuals.dark_mode {
+      Self {
+        text: Color32::from_rgb(212, 212, 212),
+        comment: Color32::from_rgb(106, 153, 85),
+        preprocessor: Color32::from_rgb(197, 134, 192),
+        keyword: Color32::from_rgb(86, 156, 214),
+        glsl_type: Color32::from_rgb(78, 201, 176),
+        builtin: Color32::from_rgb(220, 220, 170),
+        uniform: Color32::from_rgb(255, 160, 90),
+        number: Color32::from_rgb(181, 206, 168),
+        punctuation: Color32::from_rgb(170, 170, 170),
+        bracket_match: Color32::from_rgba_unmultiplied(120, 120, 120, 110),
+      }
+    }
+    else {
+      Self {
+        text: Color32::from_rgb(30, 30, 30),
+        comment: Color32::from_rgb(0, 128, 0),
+        preprocessor: Color32::from_rgb(175, 0, 219),
+        keyword: Color32::from_rgb(0, 0, 255),
+        glsl_type: Color32::from_rgb(38, 127, 153),
+        builtin: Color32::from_rgb(121, 94, 38),
+        uniform: Color32::from_rgb(200, 80, 0),
+        number: Color32::from_rgb(9, 134, 88),
+        punctuation: Color32::from_rgb(90, 90, 90),
+        bracket_match: Color32::from_rgba_unmultiplied(150, 150, 150, 90),
+      }
+    }
+  }
+
+  fn color(&self, token: GlslToken) -> Color32 {
+    match token {
+      GlslToken::Comment => self.comment,
+      GlslToken::Preprocessor => self.preprocessor,
+      GlslToken::Keyword => self.keyword,
+      GlslToken::Type => self.glsl_type,
+      GlslToken::Builtin => self.builtin,
+      GlslToken::ShaderToyUniform => self.uniform,
+      GlslToken::Number => self.number,
+      GlslToken::Punctuation => self.punctuation,
+      GlslToken::Identifier | GlslToken::Whitespace => self.text,
+    }
+  }
+}
+
+/// Colored layout of GLSL source, with the matching bracket pair at the cursor (byte index) highlighted.
+pub fn highlight(text: &str, font_id: FontId, theme: &GlslTheme, cursor: Option<usize>) -> LayoutJob {
+  let tokens = tokenize(text);
+  let brackets = cursor.and_then(|c| matching_brackets(text, &tokens, c));
+  let mut job = LayoutJob::default();
+  for (token, range) in tokens {
+    let mut format = TextFormat::simple(font_id.clone(), theme.color(token));
+    if brackets.is_some_and(|(a, b)| range.len() == 1 && (range.start == a || range.start == b)) {
+      format.background = theme.bracket_match;
+    }
+    job.append(&text[range], 0.0, format);
+  }
+  job
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  /// The tokens of the text without the whitespace.
+  fn tokens(text: &str) -> Vec<(GlslToken, &str)> {
+    tokenize(text).into_iter().filter(|(t, _)| *t != GlslToken::Whitespace).map(|(t, r)| (t, &text[r])).collect()
+  }
+
+  fn brackets(text: &str, cursor: usize) -> Option<(usize, usize)> {
+    matching_brackets(text, &tokenize(text), cursor)
+  }
+
+  #[test]
+  fn tokenizes_comments() {
+    assert_eq!(tokens("// line\nfloat x; /* block\n comment */ int"), vec![
+      (GlslToken::Comment, "// line"),
+      (GlslToken::Type, "float"),
+      (GlslToken::Identifier, "x"),
+      (GlslToken::Punctuation, ";"),
+      (GlslToken::Comment, "/* block\n comment */"),
+      (GlslToken::Type, "int"),
+    ]);
+    assert_eq!(tokens("x /* never closed\nvec3"), vec![(GlslToken::Identifier, "x"), (GlslToken::Comment, "/* never closed\nvec3")]);
+    // the ranges cover the whole text
+    let text = "void main() { // é\n  gl_FragColor = vec4(1.0); }";
+    assert_eq!(tokenize(text).into_iter().map(|(_, r)| &text[r]).collect::<String>(), text);
+  }
+
+  #[test]
+  fn tokenizes_preprocessor_lines() {
+    assert_eq!(tokens("#define A 1 \\\n  + 2\nvec2 v;"), vec![
+      (GlslToken::Preprocessor, "#define A 1 \\\n  + 2"),
+      (GlslToken::Type, "vec2"),
+      (GlslToken::Identifier, "v"),
+      (GlslToken::Punctuation, ";"),
+    ]);
+    // indented or after a comment it is still a directive, in the middle of a line it is not
+    assert_eq!(tokens("  #version 410 core")[0], (GlslToken::Preprocessor, "#version 410 core"));
+    assert_eq!(tokens("/* c */ #pragma once")[1], (GlslToken::Preprocessor, "#pragma once"));
+    assert_eq!(tokens("a # b")[1], (GlslToken::Punctuation, "#"));
+  }
+
+  #[test]
+  fn tokenizes_numbers_with_suffixes() {
+    for number in ["1", "1.0f", "2u", "0x1F", "1e-3", "2.5E+4", ".5", "3.lf"] {
+      assert_eq!(tokens(number), vec![(GlslToken::Number, number)], "{}", number);
+    }
+    assert_eq!(tokens("a-1"), vec![(GlslToken::Identifier, "a"), (GlslToken::Punctuation, "-"), (GlslToken::Number, "1")]);
+    assert_eq!(tokens("x1 iTime sin"), vec![(GlslToken::Identifier, "x1"), (GlslToken::ShaderToyUniform, "iTime"), (GlslToken::Builtin, "sin")]);
+  }
+
+  #[test]
+  fn matches_nested_brackets() {
+    let text = "f(a[1], {b})";
+    assert_eq!(brackets(text, 1), Some((1, 11)));
+    assert_eq!(brackets(text, 11), Some((11, 1)));
+    // just after the bracket
+    assert_eq!(brackets(text, 6), Some((5, 3)));
+    assert_eq!(brackets(text, 8), Some((8, 10)));
+    assert_eq!(brackets(text, 7), None);
+    // brackets in comments and directives do not count
+    assert_eq!(brackets("( // )\n#if (\n)", 0), Some((0, 13)));
+  }
+
+  #[test]
+  fn unbalanced_brackets_have_no_match() {
+    assert_eq!(brackets("f((a)", 1), None);
+    assert_eq!(brackets("f((a)", 2), Some((2, 4)));
+    assert_eq!(brackets("a)]", 1), None);
+    assert_eq!(brackets("(]", 0), None);
+  }
+}
@@ -1,7 +1,7 @@
 use clipboard::{ClipboardContext, ClipboardProvider};
 use egui::{Context, TextEdit};
 use crate::do_log;
-use crate::settings::{configuration, kuplung_logger};
+use crate::settings::{kuplung_logger, kuplung_settings};
 
 #[derive(Default)]
 pub struct ComponentLog {
@@ -21,13 +21,14 @@ impl ComponentLog {
 
   pub fn render_component_log(&mut self, ctx: &Context) {
     let screen_rect = ctx.screen_rect();
-    let posx: f32 = screen_rect.size().x / 2.0 - configuration::COMPONENT_LOG_WIDTH / 2.0;
-    let posy: f32 = screen_rect.size().y - (configuration::COMPONENT_LOG_HEIGHT + 80.0);
+    let window = kuplung_settings::get().app_window;
+    let posx: f32 = screen_rect.size().x / 2.0 - window.log_width / 2.0;
+    let posy: f32 = screen_rect.size().y - (window.log_height + 80.0);
     egui::Window::new("Log")
       .id(egui::Id::new("component_log"))
       .resizable(true)
       .enabled(true)
-      .default_size([configuration::COMPONENT_LOG_WIDTH, configuration::COMPONENT_LOG_HEIGHT + 10.0])
+      .default_size([window.log_width, window.log_height + 10.0])
       .default_pos([posx, posy])
       .show(ctx, |ui| {
         ui.horizontal(|ui| {
@@ -1,2 +1,7 @@
 pub mod log;
-mod code_editor;
\ No newline at end of file
+pub mod code_editor;
+pub mod error_panel;
+pub mod shaders;
+pub mod recent_files;
+pub mod file_browser;
+pub mod glsl_syntax;
\ No newline at end of file
@@ -0,0 +1,47 @@
+use egui::Ui;
+use crate::do_log;
+use crate::settings::kuplung_logger;
+use crate::settings::recent_files::{self, RecentList};
+
+/// Contents of a recent files submenu. Returns the file picked to be opened.
+pub fn render_recent_files_menu(ui: &mut Ui, kind: RecentList) -> Option<String> {
+  let files = recent_files::list(kind);
+  let mut picked = None;
+  if files.is_empty() {
+    ui.label("No recent files");
+  }
+  for file in &files {
+    ui.horizontal(|ui| {
+      if ui.selectable_label(file.pinned, "📌").on_hover_text(if file.pinned { "Unpin" } else { "Pin to the top of the list" }).clicked() {
+        recent_files::set_pinned(kind, &file.path, !file.pinned);
+      }
+      let exists = file.exists();
+      let title = if exists { file.title.clone() } else { format!("⚠ {}", file.title) };
+      let hover = if exists { format!("{}\nOpened {}", file.path, file.opened) } else { format!("{}\nThe file is missing", file.path) };
+      let response = ui.add_enabled(exists, egui::Button::new(title)).on_hover_text(&hover).on_disabled_hover_text(&hover);
+      if response.clicked() {
+        picked = Some(file.path.clone());
+        ui.close_menu();
+      }
+      response.context_menu(|ui| {
+        if ui.button("Remove from the list").clicked() {
+          recent_files::remove(kind, &file.path);
+          ui.close_menu();
+        }
+      });
+      if !exists && ui.small_button("🗙").on_hover_text("Remove from the list").clicked() {
+        recent_files::remove(kind, &file.path);
+      }
+    });
+  }
+  ui.separator();
+  if ui.add_enabled(files.iter().any(|f| !f.exists()), egui::Button::new("Remove missing files")).clicked() {
+    let removed = recent_files::remove_missing(kind);
+    do_log!("[Kuplung] [UI] Removed {} missing recent files.", removed);
+  }
+  if ui.add_enabled(files.iter().any(|f| !f.pinned), egui::Button::new("Clear list")).on_hover_text("Pinned files stay").clicked() {
+    recent_files::clear(kind);
+    ui.close_menu();
+  }
+  picked
+}
@@ -0,0 +1,84 @@
+use egui::{Context, RichText};
+use crate::do_log;
+use crate::rendering::shader_registry::{self, ShaderStatus};
+use crate::settings::{configuration, kuplung_logger};
+use crate::utils::file_watcher::FileWatcher;
+
+/// Lists the built-in shader programs and rebuilds them when their files change.
+pub struct ComponentShaders {
+  auto_reload: bool,
+  watcher: FileWatcher,
+}
+
+impl Default for ComponentShaders {
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
+impl ComponentShaders {
+  pub fn new() -> Self {
+    Self {
+      auto_reload: true,
+      watcher: FileWatcher::default(),
+    }
+  }
+
+  /// Called every frame, also when the panel is hidden.
+  pub fn check_files(&mut self, ctx: &Context) {
+    if !self.auto_reload { return; }
+    self.watcher.watch(shader_registry::watched_files());
+    let changed = self.watcher.poll();
+    if changed.is_empty() { return; }
+    let programs = shader_registry::files_changed(&changed);
+    if !programs.is_empty() {
+      do_log!("[Kuplung] [UI] [Component] Shader files changed, rebuilding {}.", programs.join(", "));
+      ctx.request_repaint();
+    }
+  }
+
+  pub fn render_component_shaders(&mut self, ctx: &Context, open: &mut bool) {
+    egui::Window::new("Shaders")
+      .id(egui::Id::new("component_shaders"))
+      .open(open)
+      .resizable(true)
+      .default_size([configuration::COMPONENT_SHADERS_WIDTH, configuration::COMPONENT_SHADERS_HEIGHT])
+      .show(ctx, |ui| {
+        let entries = shader_registry::entries();
+        ui.horizontal(|ui| {
+          ui.checkbox(&mut self.auto_reload, "Auto reload").on_hover_text("Rebuild the programs when their shader files are saved");
+          if ui.button("Reload all").clicked() {
+            entries.iter().for_each(|e| shader_registry::request_reload(e.id));
+            ctx.request_repaint();
+          }
+        });
+        ui.separator();
+        if entries.is_empty() { ui.label("No shader programs are loaded."); }
+        egui::ScrollArea::vertical().auto_shrink([false; 2]).show(ui, |ui| {
+          for entry in &entries {
+            let color = match entry.status {
+              ShaderStatus::Ok => ui.visuals().text_color(),
+              ShaderStatus::Building | ShaderStatus::Pending => ui.visuals().warn_fg_color,
+              ShaderStatus::FailedKeepingLast | ShaderStatus::Failed => ui.visuals().error_fg_color,
+            };
+            let title = RichText::new(format!("{} - {}", entry.name, entry.status.as_ref())).color(color);
+            egui::CollapsingHeader::new(title).id_source(("component_shaders", entry.id)).show(ui, |ui| {
+              ui.horizontal(|ui| {
+                if !entry.last_build.is_empty() { ui.label(format!("Built at {}", entry.last_build)); }
+                if ui.button(if entry.has_program { "Reload" } else { "Retry" }).clicked() {
+                  shader_registry::request_reload(entry.id);
+                  ctx.request_repaint();
+                }
+              });
+              for file in &entry.source_files {
+                ui.label(RichText::new(file).monospace());
+              }
+              if !entry.last_error.is_empty() {
+                ui.label(RichText::new(&entry.last_error).monospace().color(ui.visuals().error_fg_color));
+              }
+            });
+          }
+        });
+      });
+  }
+}
@@ -1,11 +1,325 @@
-use egui::Context;
+use egui::{Context, Ui};
+use strum::IntoEnumIterator;
+use strum_macros::{AsRefStr, EnumIter};
+use crate::do_log;
+use crate::settings::kuplung_logger;
+use crate::settings::kuplung_settings::{self, KuplungSettings, RenderingSettings};
+use crate::ui::keybindings;
 
-pub fn render_dialog_options(ctx: &Context) {
-  egui::Window::new("Options")
-    .id(egui::Id::new("dialog_options"))
-    .resizable(true)
-    .enabled(true)
-    .show(ctx, |ui| {
-      ui.label("Hello World!");
+const RENDERER_TYPES: [&str; 3] = ["Simple", "Forward", "Deferred"];
+
+#[derive(Clone, Copy, PartialEq, AsRefStr, EnumIter)]
+enum OptionsTab {
+  General,
+  Window,
+  Rendering,
+  Log,
+  Theme,
+  Keybindings,
+}
+
+/// Edits a copy of the runtime settings - nothing changes until Apply, which also writes the user's settings files.
+pub struct DialogOptions {
+  tab: OptionsTab,
+  settings: KuplungSettings,
+  rendering: RenderingSettings,
+  /// The runtime settings as of the last apply or revert, to tell the changes.
+  applied_settings: KuplungSettings,
+  applied_rendering: RenderingSettings,
+  /// Keybinding waiting for a key press.
+  recording: Option<usize>,
+  status: String,
+}
+
+impl Default for DialogOptions {
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
+impl DialogOptions {
+  pub fn new() -> Self {
+    let (settings, rendering) = (kuplung_settings::get(), kuplung_settings::get_rendering());
+    Self {
+      tab: OptionsTab::General,
+      applied_settings: settings.clone(),
+      applied_rendering: rendering.clone(),
+      settings,
+      rendering,
+      recording: None,
+      status: "".to_string(),
+    }
+  }
+
+  /// Drops the unapplied changes.
+  pub fn revert(&mut self) {
+    self.applied_settings = kuplung_settings::get();
+    self.applied_rendering = kuplung_settings::get_rendering();
+    self.settings = self.applied_settings.clone();
+    self.rendering = self.applied_rendering.clone();
+    self.recording = None;
+    self.status = "".to_string();
+  }
+
+  /// A keybinding is being recorded, the main menu shortcuts should not fire.
+  pub fn is_recording(&self) -> bool {
+    self.recording.is_some()
+  }
+
+  /// Returns true when the settings were applied this frame.
+  pub fn render_dialog_options(&mut self, ctx: &Context, open: &mut bool) -> bool {
+    let mut applied = false;
+    let mut close = false;
+    let errors = keybindings::validate(&self.settings.keybindings);
+    egui::Window::new("Options")
+      .id(egui::Id::new("dialog_options"))
+      .resizable(true)
+      .enabled(true)
+      .open(open)
+      .default_size([460.0, 420.0])
+      .show(ctx, |ui| {
+        ui.horizontal(|ui| {
+          for tab in OptionsTab::iter() {
+            ui.selectable_value(&mut self.tab, tab, tab.as_ref());
+          }
+        });
+        ui.separator();
+        egui::ScrollArea::vertical().max_height(ui.available_height() - 60.0).auto_shrink([false, true]).show(ui, |ui| {
+          match self.tab {
+            OptionsTab::General => self.render_general(ui),
+            OptionsTab::Window => self.render_window(ui),
+            OptionsTab::Rendering => self.render_rendering(ui),
+            OptionsTab::Log => self.render_log(ui),
+            OptionsTab::Theme => self.render_theme(ui),
+            OptionsTab::Keybindings => self.render_keybindings(ui),
+          }
+        });
+        ui.separator();
+        for error in &errors {
+          ui.colored_label(ui.visuals().error_fg_color, error);
+        }
+        let changed = self.settings != self.applied_settings || self.rendering != self.applied_rendering;
+        ui.horizontal(|ui| {
+          if ui.add_enabled(changed && errors.is_empty(), egui::Button::new("Apply")).on_hover_text("Use and save the settings").clicked() {
+            applied = self.apply();
+          }
+          if ui.add_enabled(changed, egui::Button::new("Revert")).on_hover_text("Drop the changes since the last apply").clicked() { self.revert(); }
+          if ui.button("Reset to defaults").on_hover_text("Default values for all settings, use Apply to keep them").clicked() {
+            self.settings = KuplungSettings::default();
+            self.rendering = RenderingSettings::default();
+            self.recording = None;
+          }
+          if ui.button("Close").clicked() { close = true; }
+        });
+        if !self.status.is_empty() { ui.label(&self.status); }
+      });
+    if close { *open = false; }
+    if !*open { self.revert(); }
+    applied
+  }
+
+  fn apply(&mut self) -> bool {
+    let mut notes = self.settings.validate();
+    notes.extend(self.rendering.validate());
+    for note in &notes {
+      do_log!("[Kuplung] [UI] [Options] {}", note);
+    }
+    match kuplung_settings::set(self.settings.clone()).and_then(|_| kuplung_settings::set_rendering(self.rendering.clone())) {
+      Ok(()) => {
+        self.applied_settings = self.settings.clone();
+        self.applied_rendering = self.rendering.clone();
+        self.status = format!("Saved to {}.", kuplung_settings::config_folder().display());
+        do_log!("[Kuplung] [UI] [Options] Settings applied.");
+        true
+      }
+      Err(e) => {
+        self.status = format!("Cannot save the settings: {}", e);
+        do_log!("[Kuplung] [UI] [Options] {}", self.status);
+        false
+      }
+    }
+  }
+
+  fn render_general(&mut self, ui: &mut Ui) {
+    let app = &mut self.settings.app;
+    egui::Grid::new("options_general").num_columns(2).striped(true).show(ui, |ui| {
+      ui.label("Renderer");
+      let selected = RENDERER_TYPES.get(app.renderer_type as usize).copied().unwrap_or("?");
+      egui::ComboBox::from_id_source("options_renderer").selected_text(selected).show_ui(ui, |ui| {
+        for (index, name) in RENDERER_TYPES.iter().enumerate() {
+          ui.selectable_value(&mut app.renderer_type, index as i32, *name);
+        }
+      });
+      ui.end_row();
+      ui.label("Application folder");
+      ui.text_edit_singleline(&mut app.app_folder).on_hover_text("Folder with the assets, used after a restart");
+      ui.end_row();
+      ui.label("Current folder");
+      ui.text_edit_singleline(&mut app.current_folder).on_hover_text("Starting folder for the file dialogs");
+      ui.end_row();
+      ui.label("CPU refresh (s)");
+      ui.add(egui::DragValue::new(&mut self.settings.consumption.interval_cpu).range(1..=3600));
+      ui.end_row();
+      ui.label("Memory refresh (s)");
+      ui.add(egui::DragValue::new(&mut self.settings.consumption.interval_memory).range(1..=3600));
+      ui.end_row();
+      ui.label("Settings folder");
+      ui.label(kuplung_settings::config_folder().display().to_string());
+      ui.end_row();
+    });
+  }
+
+  fn render_window(&mut self, ui: &mut Ui) {
+    let window = &mut self.settings.app_window;
+    egui::Grid::new("options_window").num_columns(2).striped(true).show(ui, |ui| {
+      ui.label("Window width");
+      ui.add(egui::DragValue::new(&mut window.window_width).range(320.0..=16384.0).speed(1.0));
+      ui.end_row();
+      ui.label("Window height");
+      ui.add(egui::DragValue::new(&mut window.window_height).range(240.0..=16384.0).speed(1.0));
+      ui.end_row();
+      ui.label("File browser width");
+      ui.add(egui::DragValue::new(&mut window.file_browser_width).range(200.0..=16384.0).speed(1.0));
+      ui.end_row();
+      ui.label("File browser height");
+      ui.add(egui::DragValue::new(&mut window.file_browser_height).range(150.0..=16384.0).speed(1.0));
+      ui.end_row();
+    });
+    ui.label("The window size is used on the next start.");
+  }
+
+  fn render_rendering(&mut self, ui: &mut Ui) {
+    let general = &mut self.rendering.general;
+    egui::Grid::new("options_rendering").num_columns(2).striped(true).show(ui, |ui| {
+      ui.label("Frames per second");
+      ui.add(egui::DragValue::new(&mut self.settings.rendering.frames_per_second).range(1.0..=240.0));
+      ui.end_row();
+      ui.label("Show GL errors");
+      ui.checkbox(&mut self.settings.rendering.show_gl_errors, "");
+      ui.end_row();
+      ui.label("Field of view");
+      ui.add(egui::Slider::new(&mut general.fov, 1.0..=179.0));
+      ui.end_row();
+      ui.label("Ratio");
+      ui.horizontal(|ui| {
+        ui.add(egui::DragValue::new(&mut general.ratio_width).range(0.1..=100.0).speed(0.1));
+        ui.label(":");
+        ui.add(egui::DragValue::new(&mut general.ratio_height).range(0.1..=100.0).speed(0.1));
+      });
+      ui.end_row();
+      ui.label("Near / far plane");
+      ui.horizontal(|ui| {
+        ui.add(egui::DragValue::new(&mut general.plane_close).range(0.001..=1000.0).speed(0.1));
+        ui.add(egui::DragValue::new(&mut general.plane_far).range(general.plane_close + 1.0..=1000000.0).speed(1.0));
+      });
+      ui.end_row();
+      ui.label("Gamma");
+      ui.add(egui::Slider::new(&mut general.gamma, 0.1..=5.0));
+      ui.end_row();
+      ui.label("Occlusion culling");
+      ui.checkbox(&mut general.use_occlusion_culling, "");
+      ui.end_row();
+      ui.label("Show cube");
+      ui.checkbox(&mut general.show_cube, "");
+      ui.end_row();
+      ui.label("Pick rays");
+      ui.horizontal(|ui| {
+        ui.checkbox(&mut general.show_pick_rays, "Show");
+        ui.checkbox(&mut general.show_pick_rays_single, "Single");
+        ui.checkbox(&mut general.ray_animate, "Animate");
+        ui.checkbox(&mut general.ray_draw, "Draw");
+      });
+      ui.end_row();
+      ui.label("Ray origin");
+      ui.horizontal(|ui| {
+        ui.add(egui::DragValue::new(&mut general.ray_origin_x).speed(0.1));
+        ui.add(egui::DragValue::new(&mut general.ray_origin_y).speed(0.1));
+        ui.add(egui::DragValue::new(&mut general.ray_origin_z).speed(0.1));
+      });
+      ui.end_row();
+      ui.label("Ray direction");
+      ui.horizontal(|ui| {
+        ui.add(egui::DragValue::new(&mut general.ray_direction_x).speed(0.1));
+        ui.add(egui::DragValue::new(&mut general.ray_direction_y).speed(0.1));
+        ui.add(egui::DragValue::new(&mut general.ray_direction_z).speed(0.1));
+      });
+      ui.end_row();
+      ui.label("Axis helpers");
+      ui.horizontal(|ui| {
+        ui.checkbox(&mut self.rendering.axis.show_axis_helpers, "Show");
+        ui.checkbox(&mut self.rendering.axis.show_z_axis, "Z axis");
+      });
+      ui.end_row();
+      let grid = &mut self.rendering.grid;
+      ui.label("Grid");
+      ui.horizontal(|ui| {
+        ui.checkbox(&mut grid.show_grid, "Show");
+        ui.checkbox(&mut grid.grid_fixed_with_world, "Fixed with world");
+        ui.checkbox(&mut grid.act_as_mirror, "Mirror");
+      });
+      ui.end_row();
+      ui.label("Grid size");
+      ui.add(egui::DragValue::new(&mut grid.grid_size).range(1..=1000));
+      ui.end_row();
+    });
+  }
+
+  fn render_log(&mut self, ui: &mut Ui) {
+    egui::Grid::new("options_log").num_columns(2).striped(true).show(ui, |ui| {
+      ui.label("Show on start");
+      ui.checkbox(&mut self.settings.app.show_log, "");
+      ui.end_row();
+      ui.label("Width");
+      ui.add(egui::DragValue::new(&mut self.settings.app_window.log_width).range(100.0..=16384.0).speed(1.0));
+      ui.end_row();
+      ui.label("Height");
+      ui.add(egui::DragValue::new(&mut self.settings.app_window.log_height).range(50.0..=16384.0).speed(1.0));
+      ui.end_row();
+    });
+  }
+
+  fn render_theme(&mut self, ui: &mut Ui) {
+    let gui = &mut self.settings.app_gui;
+    egui::Grid::new("options_theme").num_columns(2).striped(true).show(ui, |ui| {
+      ui.label("Dark mode");
+      ui.checkbox(&mut gui.dark_mode, "");
+      ui.end_row();
+      ui.label("UI scale");
+      ui.add(egui::Slider::new(&mut gui.ui_scale, 0.5..=3.0));
+      ui.end_row();
+      ui.label("Clear color");
+      let mut color = gui.clear_color.map(|c| c / 255.0);
+      if ui.color_edit_button_rgba_unmultiplied(&mut color).changed() {
+        gui.clear_color = color.map(|c| (c * 255.0).round());
+      }
+      ui.end_row();
+    });
+  }
+
+  fn render_keybindings(&mut self, ui: &mut Ui) {
+    if let Some(index) = self.recording {
+      let pressed = ui.input(|i| i.events.iter().find_map(|e| match e {
+        egui::Event::Key { key, pressed: true, modifiers, .. } => Some((*key, *modifiers)),
+        _ => None,
+      }));
+      if let Some((key, modifiers)) = pressed {
+        if let Some((_, shortcut)) = self.settings.keybindings.entries_mut().into_iter().nth(index) {
+          *shortcut = keybindings::format_shortcut(modifiers, key);
+        }
+        self.recording = None;
+      }
+    }
+    egui::Grid::new("options_keybindings").num_columns(3).striped(true).show(ui, |ui| {
+      for (index, (action, shortcut)) in self.settings.keybindings.entries_mut().into_iter().enumerate() {
+        ui.label(action);
+        ui.add(egui::TextEdit::singleline(shortcut).desired_width(140.0));
+        let recording = self.recording == Some(index);
+        if ui.selectable_label(recording, if recording { "Press a key..." } else { "⌨ Record" }).clicked() {
+          self.recording = if recording { None } else { Some(index) };
+        }
+        ui.end_row();
+      }
     });
-}
\ No newline at end of file
+    ui.label("Modifiers: Ctrl, Shift, Alt, Cmd - e.g. Ctrl+Shift+N.");
+  }
+}
@@ -0,0 +1,117 @@
+use egui::{Key, KeyboardShortcut, Modifiers};
+use crate::do_log;
+use crate::settings::kuplung_logger;
+use crate::settings::kuplung_settings::KeybindingsSettings;
+
+/// The main menu shortcuts, parsed from the settings.
+pub struct Keybindings {
+  pub quit: KeyboardShortcut,
+  pub new: KeyboardShortcut,
+  pub open: KeyboardShortcut,
+  pub save: KeyboardShortcut,
+  pub backend: KeyboardShortcut,
+  pub about: KeyboardShortcut,
+  pub viewer: KeyboardShortcut,
+  pub fractals: KeyboardShortcut,
+  pub shadertoy: KeyboardShortcut,
+  pub log: KeyboardShortcut,
+}
+
+impl Default for Keybindings {
+  fn default() -> Self {
+    Self::from_settings(&KeybindingsSettings::default())
+  }
+}
+
+impl Keybindings {
+  /// Shortcuts that cannot be parsed fall back to their defaults.
+  pub fn from_settings(settings: &KeybindingsSettings) -> Self {
+    let defaults = KeybindingsSettings::default();
+    let shortcut = |text: &str, default: &str| parse_shortcut(text).unwrap_or_else(|e| {
+      do_log!("[Kuplung] [UI] [Keybindings] {} Using {}.", e, default);
+      parse_shortcut(default).expect("default shortcut")
+    });
+    Self {
+      quit: shortcut(&settings.quit, &defaults.quit),
+      new: shortcut(&settings.new, &defaults.new),
+      open: shortcut(&settings.open, &defaults.open),
+      save: shortcut(&settings.save, &defaults.save),
+      backend: shortcut(&settings.backend, &defaults.backend),
+      about: shortcut(&settings.about, &defaults.about),
+      viewer: shortcut(&settings.viewer, &defaults.viewer),
+      fractals: shortcut(&settings.fractals, &defaults.fractals),
+      shadertoy: shortcut(&settings.shadertoy, &defaults.shadertoy),
+      log: shortcut(&settings.log, &defaults.log),
+    }
+  }
+}
+
+/// Parses "Ctrl+Shift+N", "Alt+F4", "Escape" - modifiers first, then a key name as egui knows it.
+pub fn parse_shortcut(text: &str) -> Result<KeyboardShortcut, String> {
+  let parts: Vec<&str> = text.split('+').map(|p| p.trim()).collect();
+  let (key_name, modifier_names) = parts.split_last().filter(|(k, _)| !k.is_empty()).ok_or_else(|| format!("Shortcut '{}' has no key.", text))?;
+  let mut modifiers = Modifiers::NONE;
+  for name in modifier_names {
+    modifiers = modifiers | match name.to_lowercase().as_str() {
+      "ctrl" | "control" => Modifiers::CTRL,
+      "shift" => Modifiers::SHIFT,
+      "alt" | "option" => Modifiers::ALT,
+      "cmd" | "command" => Modifiers::MAC_CMD,
+      _ => return Err(format!("Shortcut '{}' has an unknown modifier '{}'.", text, name)),
+    };
+  }
+  let key = Key::from_name(key_name).ok_or_else(|| format!("Shortcut '{}' has an unknown key '{}'.", text, key_name))?;
+  Ok(KeyboardShortcut::new(modifiers, key))
+}
+
+/// The settings text of a shortcut, the reverse of `parse_shortcut`.
+pub fn format_shortcut(modifiers: Modifiers, key: Key) -> String {
+  let mut parts = Vec::new();
+  if modifiers.ctrl { parts.push("Ctrl"); }
+  if modifiers.shift { parts.push("Shift"); }
+  if modifiers.alt { parts.push("Alt"); }
+  if modifiers.mac_cmd { parts.push("Cmd"); }
+  parts.push(key.name());
+  parts.join("+")
+}
+
+/// Problems with the shortcuts - ones that cannot be parsed and ones bound to more than one action.
+pub fn validate(settings: &KeybindingsSettings) -> Vec<String> {
+  let mut errors = Vec::new();
+  let mut parsed: Vec<(&str, KeyboardShortcut)> = Vec::new();
+  for (action, text) in settings.entries() {
+    match parse_shortcut(text) {
+      Ok(shortcut) => {
+        if let Some((other, _)) = parsed.iter().find(|(_, s)| *s == shortcut) {
+          errors.push(format!("{} uses the same shortcut as {}.", action, other));
+        }
+        parsed.push((action, shortcut));
+      }
+      Err(e) => errors.push(format!("{}: {}", action, e)),
+    }
+  }
+  errors
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn parses_formats_and_validates_shortcuts() {
+    let shortcut = parse_shortcut("Ctrl+Shift+Alt+B").unwrap();
+    assert_eq!(shortcut, KeyboardShortcut::new(Modifiers::CTRL | Modifiers::SHIFT | Modifiers::ALT, Key::B));
+    assert_eq!(format_shortcut(shortcut.modifiers, shortcut.logical_key), "Ctrl+Shift+Alt+B");
+    assert!(parse_shortcut("Hyper+B").is_err());
+    assert!(parse_shortcut("Ctrl+").is_err());
+
+    let mut settings = KeybindingsSettings::default();
+    assert!(validate(&settings).is_empty());
+    settings.log = "shift + v".to_string();
+    settings.about = "F99".to_string();
+    assert_eq!(validate(&settings), vec![
+      "Log uses the same shortcut as Viewer.".to_string(),
+      "About: Shortcut 'F99' has an unknown key 'F99'.".to_string(),
+    ]);
+  }
+}
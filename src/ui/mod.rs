@@ -1,4 +1,5 @@
 pub mod ui_manager;
 mod panel_backend;
 mod dialogs;
-mod components;
+mod keybindings;
+pub mod components;
@@ -1,9 +1,14 @@
-use egui::{Context, Modifiers, Ui};
+use egui::{Context, Ui};
 use crate::do_log;
-use crate::settings::kuplung_logger;
-use crate::ui::dialogs::options;
+use crate::settings::{kuplung_logger, kuplung_settings};
+use crate::ui::dialogs::options::DialogOptions;
+use crate::ui::keybindings::Keybindings;
 use crate::ui::panel_backend;
+use crate::settings::recent_files::RecentList;
+use crate::ui::components::file_browser::{FileBrowser, FileBrowserMode};
 use crate::ui::components::log::ComponentLog;
+use crate::ui::components::recent_files::render_recent_files_menu;
+use crate::ui::components::shaders::ComponentShaders;
 
 #[derive(Clone, Copy, Debug)]
 #[must_use]
@@ -12,34 +17,89 @@ enum Command {
   ResetEverything,
 }
 
-#[derive(Default)]
+/// A file picked in the menus, handled by the app.
+pub enum FileRequest {
+  OpenScene(String),
+  SaveScene(String),
+  ImportModel(String),
+  ExportScene(String),
+}
+
 pub struct UIManager {
   dark_mode: bool,
   show_backend: bool,
   panel_backend: panel_backend::PanelBackend,
   show_options: bool,
+  dialog_options: DialogOptions,
+  keybindings: Keybindings,
   show_about: bool,
   show_component_log: bool,
   component_log: ComponentLog,
+  show_component_shaders: bool,
+  component_shaders: ComponentShaders,
   pub show_viewer: bool,
   pub show_fractals: bool,
   pub show_shadertoy: bool,
+  file_request: Option<FileRequest>,
+  /// The options were applied, the other managers pick them up too.
+  settings_applied: bool,
+  browser_open_scene: FileBrowser,
+  browser_save_scene: FileBrowser,
+  browser_import_model: FileBrowser,
+  browser_export_scene: FileBrowser,
+  /// Shown in a window until dismissed.
+  error_message: Option<String>,
+}
+
+impl Default for UIManager {
+  fn default() -> Self {
+    Self::new()
+  }
 }
 
 impl UIManager {
   pub fn new() -> Self {
     do_log!("[Kuplung] [UI] Initializing UI...");
+    let settings = kuplung_settings::get();
     let this = Self {
-      dark_mode: false,
+      dark_mode: settings.app_gui.dark_mode,
       show_backend: false,
       panel_backend: panel_backend::PanelBackend::default(),
       show_options: false,
+      dialog_options: DialogOptions::new(),
+      keybindings: Keybindings::from_settings(&settings.keybindings),
       show_about: false,
-      show_component_log: true,
+      show_component_log: settings.app.show_log,
       component_log: ComponentLog::new(),
+      show_component_shaders: false,
+      component_shaders: ComponentShaders::new(),
       show_viewer: false,
       show_fractals: false,
       show_shadertoy: false,
+      file_request: None,
+      settings_applied: false,
+      browser_open_scene: FileBrowser::new("browser_open_scene", "Open Scene")
+        .filter("Scenes", &["gltf", "glb", "kuplung"])
+        .filter("glTF 2.0", &["gltf", "glb"])
+        .filter("Kuplung scenes", &["kuplung"]),
+      browser_save_scene: FileBrowser::new("browser_save_scene", "Save Scene")
+        .mode(FileBrowserMode::Save)
+        .filter("glTF binary", &["glb"])
+        .filter("glTF", &["gltf"]),
+      browser_import_model: FileBrowser::new("browser_import_model", "Import Model")
+        .filter("3D models", &["obj", "gltf", "glb", "stl", "ply"])
+        .filter("Wavefront OBJ", &["obj"])
+        .filter("glTF 2.0", &["gltf", "glb"])
+        .filter("STL", &["stl"])
+        .filter("PLY", &["ply"]),
+      browser_export_scene: FileBrowser::new("browser_export_scene", "Export Scene")
+        .mode(FileBrowserMode::Save)
+        .filter("glTF binary", &["glb"])
+        .filter("glTF", &["gltf"])
+        .filter("STL", &["stl"])
+        .filter("PLY", &["ply"])
+        .filter("OBJ", &["obj"]),
+      error_message: None,
     };
     do_log!("[Kuplung] [UI] UI initialized.");
     this
@@ -65,33 +125,40 @@ impl UIManager {
 
       if self.show_options { self.render_options(ctx); }
       if self.show_component_log { self.render_component_log(ctx); }
+      if self.show_component_shaders { self.render_component_shaders(ctx); }
       if self.show_about { self.render_about(ctx); }
+      if self.error_message.is_some() { self.render_error_message(ctx); }
+      self.render_file_browsers(ctx);
+      self.component_shaders.check_files(ctx);
     });
   }
 
   fn show_main_menu(&mut self, ui: &mut Ui) {
-    // shortcuts
-    let shortcut_quit = egui::KeyboardShortcut::new(Modifiers::NONE, egui::Key::Escape);
-    let shortcut_new = egui::KeyboardShortcut::new(Modifiers::CTRL, egui::Key::N);
-    let shortcut_open = egui::KeyboardShortcut::new(Modifiers::CTRL, egui::Key::O);
-    let shortcut_save = egui::KeyboardShortcut::new(Modifiers::CTRL, egui::Key::S);
-    let shortcut_backend = egui::KeyboardShortcut::new(Modifiers::SHIFT | Modifiers::CTRL | Modifiers::ALT, egui::Key::B);
-    let shortcut_about = egui::KeyboardShortcut::new(Modifiers::NONE, egui::Key::F1);
-    let shortcut_viewer = egui::KeyboardShortcut::new(Modifiers::SHIFT, egui::Key::V);
-    let shortcut_fractals = egui::KeyboardShortcut::new(Modifiers::SHIFT, egui::Key::F);
-    let shortcut_shadertoy = egui::KeyboardShortcut::new(Modifiers::SHIFT, egui::Key::S);
-    let shortcut_component_log = egui::KeyboardShortcut::new(Modifiers::SHIFT, egui::Key::L);
-
-    if ui.input_mut(|i| i.consume_shortcut(&shortcut_quit)) { self.handle_key_escape(ui) }
-    if ui.input_mut(|i| i.consume_shortcut(&shortcut_new)) { self.toggle_dialog_new(ui); }
-    if ui.input_mut(|i| i.consume_shortcut(&shortcut_open)) { self.toggle_dialog_open(ui); }
-    if ui.input_mut(|i| i.consume_shortcut(&shortcut_save)) { self.toggle_dialog_save(ui); }
-    if ui.input_mut(|i| i.consume_shortcut(&shortcut_backend)) { self.toggle_backend(ui); }
-    if ui.input_mut(|i| i.consume_shortcut(&shortcut_about)) { self.toggle_about(ui); }
-    if ui.input_mut(|i| i.consume_shortcut(&shortcut_viewer)) { self.toggle_window_viewer(ui); }
-    if ui.input_mut(|i| i.consume_shortcut(&shortcut_fractals)) { self.toggle_window_fractals(ui); }
-    if ui.input_mut(|i| i.consume_shortcut(&shortcut_shadertoy)) { self.toggle_window_shadertoy(ui); }
-    if ui.input_mut(|i| i.consume_shortcut(&shortcut_component_log)) { self.toggle_component_log(ui); }
+    // shortcuts, from the settings
+    let shortcut_quit = self.keybindings.quit;
+    let shortcut_new = self.keybindings.new;
+    let shortcut_open = self.keybindings.open;
+    let shortcut_save = self.keybindings.save;
+    let shortcut_backend = self.keybindings.backend;
+    let shortcut_about = self.keybindings.about;
+    let shortcut_viewer = self.keybindings.viewer;
+    let shortcut_fractals = self.keybindings.fractals;
+    let shortcut_shadertoy = self.keybindings.shadertoy;
+    let shortcut_component_log = self.keybindings.log;
+
+    // a keybinding being recorded in the options gets the key press instead
+    if !self.dialog_options.is_recording() {
+      if ui.input_mut(|i| i.consume_shortcut(&shortcut_quit)) { self.handle_key_escape(ui) }
+      if ui.input_mut(|i| i.consume_shortcut(&shortcut_new)) { self.toggle_dialog_new(ui); }
+      if ui.input_mut(|i| i.consume_shortcut(&shortcut_open)) { self.toggle_dialog_open(ui); }
+      if ui.input_mut(|i| i.consume_shortcut(&shortcut_save)) { self.toggle_dialog_save(ui); }
+      if ui.input_mut(|i| i.consume_shortcut(&shortcut_backend)) { self.toggle_backend(ui); }
+      if ui.input_mut(|i| i.consume_shortcut(&shortcut_about)) { self.toggle_about(ui); }
+      if ui.input_mut(|i| i.consume_shortcut(&shortcut_viewer)) { self.toggle_window_viewer(ui); }
+      if ui.input_mut(|i| i.consume_shortcut(&shortcut_fractals)) { self.toggle_window_fractals(ui); }
+      if ui.input_mut(|i| i.consume_shortcut(&shortcut_shadertoy)) { self.toggle_window_shadertoy(ui); }
+      if ui.input_mut(|i| i.consume_shortcut(&shortcut_component_log)) { self.toggle_component_log(ui); }
+    }
 
     // main menu
     egui::menu::bar(ui, |ui| {
@@ -99,9 +166,16 @@ impl UIManager {
       ui.separator();
       ui.menu_button("File", |ui| {
         if ui.add(egui::Button::new("🗋 New").shortcut_text(ui.ctx().format_shortcut(&shortcut_new))).on_hover_text("New scene").clicked() { self.toggle_dialog_new(ui); }
-        if ui.add(egui::Button::new("🗁 Open").shortcut_text(ui.ctx().format_shortcut(&shortcut_open))).on_hover_text("Open existing scene").clicked() { self.toggle_dialog_open(ui); }
-        if ui.button("🗐 Open Recent").on_hover_text("Open recent scene").clicked() { }
-        if ui.add(egui::Button::new("🖴 Save").shortcut_text(ui.ctx().format_shortcut(&shortcut_save))).on_hover_text("New Save scene to a file").clicked() { self.toggle_dialog_save(ui); }
+        if ui.add(egui::Button::new("🗁 Open").shortcut_text(ui.ctx().format_shortcut(&shortcut_open))).on_hover_text("Open a glTF scene, replacing the one in the Viewer").clicked() { self.toggle_dialog_open(ui); }
+        ui.menu_button("🗐 Open Recent", |ui| {
+          if let Some(file) = render_recent_files_menu(ui, RecentList::Scenes) { self.request_file(FileRequest::OpenScene(file)); }
+        }).response.on_hover_text("Open recent scene");
+        if ui.button("📥 Import...").on_hover_text("Import a 3D model").clicked() { self.toggle_dialog_import(ui); }
+        ui.menu_button("🗐 Recent Imports", |ui| {
+          if let Some(file) = render_recent_files_menu(ui, RecentList::Imported) { self.request_file(FileRequest::ImportModel(file)); }
+        }).response.on_hover_text("Import a recently imported model");
+        if ui.button("📤 Export...").on_hover_text("Export the Viewer scene to glTF, STL, PLY or OBJ").clicked() { self.toggle_dialog_export(ui); }
+        if ui.add(egui::Button::new("🖴 Save").shortcut_text(ui.ctx().format_shortcut(&shortcut_save))).on_hover_text("Save the Viewer scene with its animations to a glTF file").clicked() { self.toggle_dialog_save(ui); }
         ui.separator();
         if ui.add(egui::Button::new("🗙 Quit").shortcut_text(ui.ctx().format_shortcut(&shortcut_quit)), ).clicked() { self.exit_kuplung(ui); }
       });
@@ -113,6 +187,7 @@ impl UIManager {
       ui.menu_button("View", |ui| {
         if ui.button("🛠 Options").on_hover_text("Configure Kuplung options").clicked() { self.toggle_options(ui); }
         if ui.add(egui::Button::new("🖹 Log").shortcut_text(ui.ctx().format_shortcut(&shortcut_component_log))).on_hover_text("Toggle log window").clicked() { self.toggle_component_log(ui); }
+        if ui.button("🎨 Shaders").on_hover_text("Built-in shader programs and their status").clicked() { self.toggle_component_shaders(ui); }
         if ui.add(egui::Button::new("📺 Backend").shortcut_text(ui.ctx().format_shortcut(&shortcut_backend))).on_hover_text("View egui backend").clicked() { self.toggle_backend(ui); }
       });
       ui.menu_button("Help", |ui| {
@@ -124,7 +199,13 @@ impl UIManager {
   }
 
   fn handle_key_escape(&mut self, ui: &mut Ui) {
-    if self.show_about { self.show_about = false; }
+    if self.browser_open_scene.is_open() { self.browser_open_scene.close(); }
+    else if self.browser_save_scene.is_open() { self.browser_save_scene.close(); }
+    else if self.browser_import_model.is_open() { self.browser_import_model.close(); }
+    else if self.browser_export_scene.is_open() { self.browser_export_scene.close(); }
+    else if self.error_message.is_some() { self.error_message = None; }
+    else if self.show_about { self.show_about = false; }
+    else if self.show_options { self.show_options = false; self.dialog_options.revert(); }
     else { self.exit_kuplung(ui); }
   }
 
@@ -160,19 +241,53 @@ impl UIManager {
   fn show_theme(&mut self, ui: &mut Ui) {
     #![allow(clippy::collapsible_else_if)]
     if self.dark_mode {
-      if ui.button("☀").on_hover_text("Switch to light mode").clicked() {
-        self.dark_mode = false;
-        ui.ctx().set_visuals(egui::Visuals::light());
-      }
+      if ui.button("☀").on_hover_text("Switch to light mode").clicked() { self.set_dark_mode(ui.ctx(), false); }
     }
     else {
-      if ui.button("🌙").on_hover_text("Switch to dark mode").clicked() {
-        self.dark_mode = true;
-        ui.ctx().set_visuals(egui::Visuals::dark());
-      }
+      if ui.button("🌙").on_hover_text("Switch to dark mode").clicked() { self.set_dark_mode(ui.ctx(), true); }
     }
   }
 
+  fn set_dark_mode(&mut self, ctx: &Context, dark_mode: bool) {
+    self.dark_mode = dark_mode;
+    ctx.set_visuals(if dark_mode { egui::Visuals::dark() } else { egui::Visuals::light() });
+    let mut settings = kuplung_settings::get();
+    settings.app_gui.dark_mode = dark_mode;
+    if let Err(e) = kuplung_settings::set(settings) {
+      do_log!("[Kuplung] [UI] Cannot save the theme: {}", e);
+    }
+  }
+
+  /// Picks up the settings changed in the options.
+  fn apply_settings(&mut self, ctx: &Context) {
+    let settings = kuplung_settings::get();
+    self.dark_mode = settings.app_gui.dark_mode;
+    ctx.set_visuals(if self.dark_mode { egui::Visuals::dark() } else { egui::Visuals::light() });
+    ctx.set_zoom_factor(settings.app_gui.ui_scale);
+    self.keybindings = Keybindings::from_settings(&settings.keybindings);
+    self.settings_applied = true;
+  }
+
+  /// Whether the options were applied since the last call.
+  pub fn take_settings_applied(&mut self) -> bool {
+    std::mem::take(&mut self.settings_applied)
+  }
+
+  /// Tells the user what went wrong with something they asked for.
+  pub fn show_error(&mut self, message: String) {
+    self.error_message = Some(message);
+  }
+
+  /// The next file picked in the menus.
+  pub fn take_file_request(&mut self) -> Option<FileRequest> {
+    self.file_request.take()
+  }
+
+  /// The file goes on the recent lists once it was opened or imported, not when it is picked.
+  fn request_file(&mut self, request: FileRequest) {
+    self.file_request = Some(request);
+  }
+
   fn toggle_backend(&mut self, ui: &mut Ui) {
     self.show_backend = !self.show_backend;
     ui.close_menu();
@@ -184,10 +299,22 @@ impl UIManager {
 
   fn toggle_dialog_open(&mut self, ui: &mut Ui) {
     ui.close_menu();
+    if self.browser_open_scene.is_open() { self.browser_open_scene.close(); } else { self.browser_open_scene.open(); }
+  }
+
+  fn toggle_dialog_import(&mut self, ui: &mut Ui) {
+    ui.close_menu();
+    if self.browser_import_model.is_open() { self.browser_import_model.close(); } else { self.browser_import_model.open(); }
+  }
+
+  fn toggle_dialog_export(&mut self, ui: &mut Ui) {
+    ui.close_menu();
+    if self.browser_export_scene.is_open() { self.browser_export_scene.close(); } else { self.browser_export_scene.open(); }
   }
 
   fn toggle_dialog_save(&mut self, ui: &mut Ui) {
     ui.close_menu();
+    if self.browser_save_scene.is_open() { self.browser_save_scene.close(); } else { self.browser_save_scene.open(); }
   }
 
   fn toggle_window_viewer(&mut self, ui: &mut Ui) {
@@ -208,6 +335,7 @@ impl UIManager {
   fn toggle_options(&mut self, ui: &mut Ui) {
     ui.close_menu();
     self.show_options = !self.show_options;
+    if self.show_options { self.dialog_options.revert(); }
   }
 
   fn toggle_component_log(&mut self, ui: &mut Ui) {
@@ -215,19 +343,53 @@ impl UIManager {
     self.show_component_log = !self.show_component_log;
   }
 
+  fn toggle_component_shaders(&mut self, ui: &mut Ui) {
+    ui.close_menu();
+    self.show_component_shaders = !self.show_component_shaders;
+  }
+
   fn toggle_about(&mut self, ui: &mut Ui) {
     ui.close_menu();
     self.show_about = !self.show_about;
   }
 
   fn render_options(&mut self, ctx: &Context) {
-    options::render_dialog_options(ctx);
+    if self.dialog_options.render_dialog_options(ctx, &mut self.show_options) { self.apply_settings(ctx); }
+  }
+
+  fn render_file_browsers(&mut self, ctx: &Context) {
+    if let Some(file) = self.browser_open_scene.show(ctx) { self.request_file(FileRequest::OpenScene(file.display().to_string())); }
+    if let Some(file) = self.browser_save_scene.show(ctx) { self.request_file(FileRequest::SaveScene(file.display().to_string())); }
+    if let Some(file) = self.browser_import_model.show(ctx) { self.request_file(FileRequest::ImportModel(file.display().to_string())); }
+    if let Some(file) = self.browser_export_scene.show(ctx) { self.request_file(FileRequest::ExportScene(file.display().to_string())); }
+  }
+
+  fn render_error_message(&mut self, ctx: &Context) {
+    let Some(message) = &self.error_message else { return; };
+    let mut open = true;
+    let mut dismissed = false;
+    egui::Window::new("Error")
+      .id(egui::Id::new("window_error_message"))
+      .open(&mut open)
+      .resizable(false)
+      .collapsible(false)
+      .anchor(egui::Align2::CENTER_CENTER, egui::Vec2::ZERO)
+      .show(ctx, |ui| {
+        ui.label(egui::RichText::new(message).color(ui.visuals().error_fg_color));
+        ui.separator();
+        if ui.button("OK").clicked() { dismissed = true; }
+      });
+    if !open || dismissed { self.error_message = None; }
   }
 
   fn render_component_log(&mut self, ctx: &Context) {
     self.component_log.render_component_log(ctx);
   }
 
+  fn render_component_shaders(&mut self, ctx: &Context) {
+    self.component_shaders.render_component_shaders(ctx, &mut self.show_component_shaders);
+  }
+
   fn render_about(&mut self, ctx: &Context) {
     let screen_rect = ctx.screen_rect();
     egui::Window::new("About Kuplung")
@@ -0,0 +1,93 @@
+use std::error::Error;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use crate::do_log;
+use crate::settings::{configuration, kuplung_logger, kuplung_settings};
+
+// Assets are referred to by their path in the repository ("assets/shaders/..."),
+// the folders below stand in for the "assets" folder, so Kuplung runs from any working directory.
+static ASSET_FOLDERS: OnceLock<Vec<PathBuf>> = OnceLock::new();
+
+#[derive(Debug)]
+pub struct AssetNotFound {
+  pub asset: String,
+  pub searched: Vec<PathBuf>,
+}
+
+impl fmt::Display for AssetNotFound {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(f, "Cannot find asset {}, searched:", self.asset)?;
+    for path in &self.searched {
+      write!(f, "\n  {}", path.display())?;
+    }
+    Ok(())
+  }
+}
+
+impl Error for AssetNotFound {}
+
+/// The folders searched for assets, in order:
+/// the folder from the KUPLUNG_ASSETS_FOLDER environment variable, `appFolder` from Kuplung_Settings.yaml (the user's, then the bundled one),
+/// next to the executable (and Resources in the macOS app container), the working directory and the build output.
+pub fn asset_folders() -> &'static [PathBuf] {
+  ASSET_FOLDERS.get_or_init(|| {
+    let mut defaults = Vec::new();
+    if let Ok(exe) = std::env::current_exe() {
+      if let Some(exe_folder) = exe.parent() {
+        defaults.push(exe_folder.join(configuration::ASSETS_FOLDER));
+        defaults.push(exe_folder.join("../Resources"));
+      }
+    }
+    if let Ok(cwd) = std::env::current_dir() {
+      defaults.push(cwd.join(configuration::ASSETS_FOLDER));
+    }
+    defaults.push(Path::new(env!("OUT_DIR")).join(configuration::ASSETS_FOLDER));
+
+    let mut folders = Vec::new();
+    if let Some(folder) = std::env::var_os(configuration::KUPLUNG_ASSETS_FOLDER).filter(|f| !f.is_empty()) {
+      folders.push(PathBuf::from(folder));
+    }
+    let settings_files = std::iter::once(kuplung_settings::config_folder()).chain(defaults.iter().cloned()).map(|f| f.join(configuration::SETTINGS_FILE));
+    if let Some(app_folder) = settings_files.into_iter().find_map(|f| read_app_folder(&f)) {
+      folders.push(app_folder.join(configuration::ASSETS_FOLDER));
+      folders.push(app_folder);
+    }
+    folders.extend(defaults);
+
+    let mut existing: Vec<PathBuf> = Vec::new();
+    for folder in folders.into_iter().filter(|f| f.is_dir()) {
+      let folder = folder.canonicalize().unwrap_or(folder);
+      if !existing.contains(&folder) { existing.push(folder); }
+    }
+    do_log!("[Kuplung] [Assets] Asset folders: {}", existing.iter().map(|f| f.display().to_string()).collect::<Vec<_>>().join(", "));
+    existing
+  })
+}
+
+/// Finds an asset file or folder. Paths outside "assets" are looked up relative to the working directory only.
+pub fn resolve(asset: impl AsRef<Path>) -> Result<PathBuf, AssetNotFound> {
+  let asset = asset.as_ref();
+  let mut searched = Vec::new();
+  if let Ok(relative) = asset.strip_prefix(configuration::ASSETS_FOLDER) {
+    for folder in asset_folders() {
+      let candidate = folder.join(relative);
+      if candidate.exists() { return Ok(candidate); }
+      searched.push(candidate);
+    }
+  }
+  if asset.exists() { return Ok(asset.to_path_buf()); }
+  searched.push(asset.to_path_buf());
+  Err(AssetNotFound { asset: asset.display().to_string(), searched })
+}
+
+/// The resolved asset, or the path as given when it cannot be found (for the caller to report when opening it).
+pub fn locate(asset: impl AsRef<Path>) -> PathBuf {
+  resolve(&asset).unwrap_or_else(|_| asset.as_ref().to_path_buf())
+}
+
+fn read_app_folder(settings_file: &Path) -> Option<PathBuf> {
+  let yaml: serde_yaml::Value = serde_yaml::from_str(&std::fs::read_to_string(settings_file).ok()?).ok()?;
+  let app_folder = yaml.get("App")?.get("appFolder")?.as_str()?.trim();
+  (!app_folder.is_empty()).then(|| PathBuf::from(app_folder))
+}
@@ -0,0 +1,90 @@
+use std::io;
+use std::path::{Path, PathBuf};
+use crate::settings::configuration;
+use crate::utils::asset_locator;
+
+// Read-only view of the assets used by every loader. Files found on disk (see asset_locator) win,
+// so assets can be edited during development; with the `embedded_assets` feature everything else
+// comes from the bundle packed into the binary by build.rs.
+
+#[cfg(feature = "embedded_assets")]
+mod embedded {
+  include!(concat!(env!("OUT_DIR"), "/assets_index.rs"));
+}
+
+/// Reads an asset ("assets/shaders/...", or a path inside one of the asset folders).
+pub fn read(path: impl AsRef<Path>) -> io::Result<Vec<u8>> {
+  let path = path.as_ref();
+  match asset_locator::resolve(path) {
+    Ok(file) => std::fs::read(file),
+    Err(not_found) => read_embedded(path).ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, not_found.to_string()))?,
+  }
+}
+
+pub fn read_to_string(path: impl AsRef<Path>) -> io::Result<String> {
+  String::from_utf8(read(path)?).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+pub fn exists(path: impl AsRef<Path>) -> bool {
+  let path = path.as_ref();
+  asset_locator::resolve(path).is_ok() || embedded_key(path).is_some_and(|key| embedded_files().any(|name| name == key))
+}
+
+/// The files directly in an asset folder, from disk and the bundle. Embedded files come as "assets/..." paths.
+pub fn list(folder: impl AsRef<Path>) -> Vec<PathBuf> {
+  let folder = folder.as_ref();
+  let mut files: Vec<PathBuf> = match asset_locator::resolve(folder).map(std::fs::read_dir) {
+    Ok(Ok(dir)) => dir.filter_map(|e| e.ok()).map(|e| e.path()).filter(|p| p.is_file()).collect(),
+    _ => Vec::new(),
+  };
+  if let Some(key) = embedded_key(folder) {
+    let prefix = if key.is_empty() { key } else { format!("{}/", key) };
+    for name in embedded_files() {
+      let Some(file_name) = name.strip_prefix(&prefix).filter(|f| !f.contains('/')) else { continue; };
+      if !files.iter().any(|f| f.file_name().is_some_and(|n| n == file_name)) {
+        files.push(Path::new(configuration::ASSETS_FOLDER).join(name));
+      }
+    }
+  }
+  files.sort();
+  files
+}
+
+/// Name of the asset in the bundle - the path relative to the assets folder, with forward slashes.
+fn embedded_key(path: &Path) -> Option<String> {
+  let relative = path.strip_prefix(configuration::ASSETS_FOLDER).ok()
+    .or_else(|| asset_locator::asset_folders().iter().find_map(|f| path.strip_prefix(f).ok()))?;
+  let mut key = Vec::new();
+  for component in relative.components() {
+    match component {
+      std::path::Component::Normal(c) => key.push(c.to_string_lossy().to_string()),
+      std::path::Component::ParentDir => { key.pop()?; }
+      _ => {}
+    }
+  }
+  Some(key.join("/"))
+}
+
+#[cfg(feature = "embedded_assets")]
+fn embedded_files() -> impl Iterator<Item = &'static str> {
+  embedded::ASSETS_INDEX.iter().map(|entry| entry.0)
+}
+
+#[cfg(not(feature = "embedded_assets"))]
+fn embedded_files() -> impl Iterator<Item = &'static str> {
+  std::iter::empty()
+}
+
+#[cfg(feature = "embedded_assets")]
+fn read_embedded(path: &Path) -> Option<io::Result<Vec<u8>>> {
+  use std::io::Read;
+  let key = embedded_key(path)?;
+  let (_, offset, length, size) = embedded::ASSETS_INDEX.iter().find(|entry| entry.0 == key)?;
+  let mut data = Vec::with_capacity(*size);
+  Some(flate2::read::DeflateDecoder::new(&embedded::ASSETS_BUNDLE[*offset..*offset + *length]).read_to_end(&mut data).map(|_| data))
+}
+
+#[cfg(not(feature = "embedded_assets"))]
+fn read_embedded(_path: &Path) -> Option<io::Result<Vec<u8>>> {
+  None
+}
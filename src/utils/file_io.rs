@@ -1,6 +1,9 @@
 use std::error::Error;
+use std::path::Path;
+use crate::settings::configuration;
+use crate::utils::asset_vfs;
 
 pub fn read_shadertoy_shader(stoy: &str) -> Result<String, Box<dyn Error>> {
-  let shader_source: String = std::fs::read_to_string(format!("assets/shaders/shadertoy/{}", stoy))?;
+  let shader_source: String = asset_vfs::read_to_string(Path::new(configuration::SHADERTOY_LIBRARY_FOLDER).join(stoy))?;
   Ok(shader_source)
 }
\ No newline at end of file
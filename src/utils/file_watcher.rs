@@ -0,0 +1,64 @@
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime};
+use crate::settings::configuration;
+
+/// Polls the modification times of a set of files. Editors often write a file in several steps
+/// (truncate, write, rename), so changes are reported only once the files have been quiet for a while.
+pub struct FileWatcher {
+  files: HashMap<PathBuf, Option<SystemTime>>,
+  changed: HashSet<PathBuf>,
+  last_change: Option<Instant>,
+  last_poll: Instant,
+}
+
+impl Default for FileWatcher {
+  fn default() -> Self {
+    Self {
+      files: HashMap::new(),
+      changed: HashSet::new(),
+      last_change: None,
+      last_poll: Instant::now(),
+    }
+  }
+}
+
+impl FileWatcher {
+  /// Replaces the watched files, keeping the known state of the ones already watched.
+  pub fn watch<P: AsRef<Path>>(&mut self, files: impl IntoIterator<Item = P>) {
+    let mut watched = HashMap::new();
+    for file in files {
+      let file = file.as_ref().to_path_buf();
+      let modified = self.files.get(&file).copied().unwrap_or_else(|| Self::modified(&file));
+      watched.insert(file, modified);
+    }
+    self.changed.retain(|f| watched.contains_key(f));
+    self.files = watched;
+  }
+
+  /// The files changed since the last call, once the debounce period has passed.
+  pub fn poll(&mut self) -> Vec<PathBuf> {
+    if self.last_poll.elapsed() >= Duration::from_millis(configuration::FILE_WATCHER_POLL_INTERVAL_MS) {
+      self.last_poll = Instant::now();
+      for (file, modified) in self.files.iter_mut() {
+        let current = Self::modified(file);
+        if current != *modified {
+          *modified = current;
+          self.changed.insert(file.clone());
+          self.last_change = Some(Instant::now());
+        }
+      }
+    }
+    match self.last_change {
+      Some(last_change) if last_change.elapsed() >= Duration::from_millis(configuration::FILE_WATCHER_DEBOUNCE_MS) => {
+        self.last_change = None;
+        self.changed.drain().collect()
+      }
+      _ => Vec::new(),
+    }
+  }
+
+  fn modified(file: &Path) -> Option<SystemTime> {
+    std::fs::metadata(file).and_then(|m| m.modified()).ok()
+  }
+}
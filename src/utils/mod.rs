@@ -1 +1,4 @@
-pub mod file_io;
\ No newline at end of file
+pub mod asset_locator;
+pub mod asset_vfs;
+pub mod file_io;
+pub mod file_watcher;
\ No newline at end of file